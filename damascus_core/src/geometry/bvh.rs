@@ -0,0 +1,362 @@
+// Copyright (c) 2024, Owen Bulka
+// All rights reserved.
+// This source code is licensed under the BSD-style license found in the
+// LICENSE file in the root directory of this source tree.
+
+use crevice::std430::AsStd430;
+use glam::{BVec3, Mat3, Vec3};
+
+/// An axis aligned bounding box.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BoundingBox {
+    pub minimum: Vec3,
+    pub maximum: Vec3,
+}
+
+impl BoundingBox {
+    /// The box containing nothing, which is the identity of the union.
+    pub const EMPTY: Self = Self {
+        minimum: Vec3::splat(f32::MAX),
+        maximum: Vec3::splat(f32::MIN),
+    };
+
+    /// The box containing everything.
+    pub const INFINITE: Self = Self {
+        minimum: Vec3::splat(f32::MIN),
+        maximum: Vec3::splat(f32::MAX),
+    };
+
+    pub fn new(minimum: Vec3, maximum: Vec3) -> Self {
+        Self { minimum, maximum }
+    }
+
+    /// A box centred at the origin.
+    pub fn from_half_extents(half_extents: Vec3) -> Self {
+        Self::new(-half_extents.abs(), half_extents.abs())
+    }
+
+    pub fn centre(&self) -> Vec3 {
+        0.5 * (self.minimum + self.maximum)
+    }
+
+    pub fn half_extents(&self) -> Vec3 {
+        0.5 * (self.maximum - self.minimum)
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        Self::new(
+            self.minimum.min(other.minimum),
+            self.maximum.max(other.maximum),
+        )
+    }
+
+    /// Grow the box by an amount along each axis, in both directions.
+    pub fn expand(&self, amount: Vec3) -> Self {
+        Self::new(self.minimum - amount, self.maximum + amount)
+    }
+
+    pub fn translate(&self, translation: Vec3) -> Self {
+        Self::new(self.minimum + translation, self.maximum + translation)
+    }
+
+    /// The box containing this box after it has been transformed by a
+    /// linear transformation.
+    pub fn transform(&self, matrix: &Mat3) -> Self {
+        let absolute_matrix = Mat3::from_cols(
+            matrix.x_axis.abs(),
+            matrix.y_axis.abs(),
+            matrix.z_axis.abs(),
+        );
+        Self::from_half_extents(absolute_matrix * self.half_extents())
+            .translate(*matrix * self.centre())
+    }
+
+    /// The box containing this box and its reflection through the
+    /// origin, along the chosen axes.
+    pub fn symmetric(&self, axes: BVec3) -> Self {
+        let extent: Vec3 = self.minimum.abs().max(self.maximum.abs());
+        Self::new(
+            Vec3::select(axes, -extent, self.minimum),
+            Vec3::select(axes, extent, self.maximum),
+        )
+    }
+
+    /// The box containing this box after it has been scaled about the
+    /// origin by any factor within a range.
+    pub fn scale_between(&self, minimum_scale: f32, maximum_scale: f32) -> Self {
+        Self::new(
+            (self.minimum * minimum_scale).min(self.minimum * maximum_scale),
+            (self.maximum * minimum_scale).max(self.maximum * maximum_scale),
+        )
+    }
+
+    /// The distance from the centre to the furthest corner.
+    pub fn bounding_radius(&self) -> f32 {
+        self.minimum.abs().max(self.maximum.abs()).length()
+    }
+}
+
+/// A node of the bounding volume hierarchy, flattened in depth first
+/// order so that the first child of an interior node immediately
+/// follows it. Traversal moves to the escape index whenever a node is
+/// skipped, or a leaf has been evaluated, and ends at an escape index of
+/// zero, as the root is never skipped to.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, AsStd430)]
+pub struct GPUBVHNode {
+    minimum: Vec3,
+    escape_index: u32,
+    maximum: Vec3,
+    primitive_id: u32,
+}
+
+impl GPUBVHNode {
+    fn new(bounding_box: &BoundingBox, primitive_id: u32) -> Self {
+        Self {
+            minimum: bounding_box.minimum,
+            escape_index: 0,
+            maximum: bounding_box.maximum,
+            primitive_id,
+        }
+    }
+}
+
+/// Build a bounding volume hierarchy over the primitives at the given
+/// indices, where each leaf refers to a single primitive by its id (its
+/// index + 1) and interior nodes have an id of zero.
+///
+/// The leaves without bounds, such as planes, are placed in a chain
+/// before the root of the hierarchy with infinite bounds, so that they
+/// are always evaluated. The remaining leaves are split recursively at
+/// the median of their centres, along the axis the centres are most
+/// spread out on.
+pub fn build_bvh(leaves: Vec<(u32, Option<BoundingBox>)>) -> Vec<GPUBVHNode> {
+    let mut nodes: Vec<GPUBVHNode> = vec![];
+    let mut bounded_leaves: Vec<(u32, BoundingBox)> = vec![];
+    for (index, bounding_box) in leaves {
+        match bounding_box {
+            Some(bounding_box) => bounded_leaves.push((index, bounding_box)),
+            None => {
+                let mut node = GPUBVHNode::new(&BoundingBox::INFINITE, index + 1);
+                node.escape_index = nodes.len() as u32 + 1;
+                nodes.push(node);
+            }
+        }
+    }
+
+    if !bounded_leaves.is_empty() {
+        build_subtree(&mut bounded_leaves, &mut nodes);
+    }
+
+    // Escaping from the last subtree ends the traversal
+    let num_nodes = nodes.len() as u32;
+    for node in nodes.iter_mut() {
+        if node.escape_index == num_nodes {
+            node.escape_index = 0;
+        }
+    }
+
+    nodes
+}
+
+fn build_subtree(leaves: &mut [(u32, BoundingBox)], nodes: &mut Vec<GPUBVHNode>) {
+    let bounding_box: BoundingBox = leaves
+        .iter()
+        .fold(BoundingBox::EMPTY, |bounds, (_, leaf_bounds)| {
+            bounds.union(leaf_bounds)
+        });
+    let node_index: usize = nodes.len();
+
+    if leaves.len() == 1 {
+        nodes.push(GPUBVHNode::new(&bounding_box, leaves[0].0 + 1));
+    } else {
+        nodes.push(GPUBVHNode::new(&bounding_box, 0));
+
+        let centre_bounds: BoundingBox =
+            leaves
+                .iter()
+                .fold(BoundingBox::EMPTY, |bounds, (_, leaf_bounds)| {
+                    let centre: Vec3 = leaf_bounds.centre();
+                    bounds.union(&BoundingBox::new(centre, centre))
+                });
+        let spread: Vec3 = centre_bounds.half_extents();
+        let split_axis: usize = if spread.x >= spread.y && spread.x >= spread.z {
+            0
+        } else if spread.y >= spread.z {
+            1
+        } else {
+            2
+        };
+        leaves.sort_by(|(_, bounds_0), (_, bounds_1)| {
+            bounds_0.centre()[split_axis].total_cmp(&bounds_1.centre()[split_axis])
+        });
+
+        let (left, right) = leaves.split_at_mut(leaves.len() / 2);
+        build_subtree(left, nodes);
+        build_subtree(right, nodes);
+    }
+
+    nodes[node_index].escape_index = nodes.len() as u32;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box_at(centre: Vec3) -> BoundingBox {
+        BoundingBox::from_half_extents(Vec3::splat(0.5)).translate(centre)
+    }
+
+    fn contains(outer: &GPUBVHNode, inner: &GPUBVHNode) -> bool {
+        outer.minimum.cmple(inner.minimum).all() && outer.maximum.cmpge(inner.maximum).all()
+    }
+
+    /// The primitive ids of the leaves visited by following the escape
+    /// indices as the shader does, but skipping the nodes whose bounds
+    /// do not contain the position, rather than those further away than
+    /// the nearest distance found so far.
+    fn traverse(nodes: &[GPUBVHNode], position: Vec3) -> Vec<u32> {
+        let mut visited: Vec<u32> = vec![];
+        let mut node_index: usize = 0;
+        let mut steps: usize = 0;
+        loop {
+            steps += 1;
+            assert!(steps <= nodes.len(), "the traversal does not terminate");
+
+            let node: &GPUBVHNode = &nodes[node_index];
+            let inside: bool =
+                position.cmpge(node.minimum).all() && position.cmple(node.maximum).all();
+            if inside && node.primitive_id == 0 {
+                node_index += 1;
+                continue;
+            }
+            if inside {
+                visited.push(node.primitive_id);
+            }
+            if node.escape_index == 0 {
+                break;
+            }
+            node_index = node.escape_index as usize;
+        }
+        visited
+    }
+
+    #[test]
+    fn test_empty_scene() {
+        assert!(build_bvh(vec![]).is_empty());
+    }
+
+    #[test]
+    fn test_single_primitive() {
+        let bounding_box = unit_box_at(Vec3::new(1., 2., 3.));
+        let nodes = build_bvh(vec![(4, Some(bounding_box))]);
+
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].primitive_id, 5);
+        assert_eq!(nodes[0].escape_index, 0);
+        assert_eq!(nodes[0].minimum, bounding_box.minimum);
+        assert_eq!(nodes[0].maximum, bounding_box.maximum);
+    }
+
+    #[test]
+    fn test_unbounded_primitives() {
+        let nodes = build_bvh(vec![
+            (0, None),
+            (1, Some(unit_box_at(Vec3::ZERO))),
+            (2, None),
+        ]);
+
+        // The unbounded leaves are chained before the bounded ones
+        let primitive_ids: Vec<u32> = nodes.iter().map(|node| node.primitive_id).collect();
+        assert_eq!(primitive_ids, vec![1, 3, 2]);
+        let escape_indices: Vec<u32> = nodes.iter().map(|node| node.escape_index).collect();
+        assert_eq!(escape_indices, vec![1, 2, 0]);
+        assert_eq!(nodes[0].minimum, BoundingBox::INFINITE.minimum);
+        assert_eq!(nodes[1].maximum, BoundingBox::INFINITE.maximum);
+
+        // Unbounded leaves are visited from anywhere
+        assert_eq!(traverse(&nodes, Vec3::splat(1e6)), vec![1, 3]);
+        assert_eq!(traverse(&nodes, Vec3::ZERO), vec![1, 3, 2]);
+
+        let only_unbounded = build_bvh(vec![(0, None)]);
+        assert_eq!(only_unbounded.len(), 1);
+        assert_eq!(only_unbounded[0].escape_index, 0);
+    }
+
+    #[test]
+    fn test_flattened_layout() {
+        let nodes = build_bvh(vec![
+            (0, Some(unit_box_at(Vec3::ZERO))),
+            (2, Some(unit_box_at(Vec3::new(4., 0., 0.)))),
+            (1, Some(unit_box_at(Vec3::new(2., 0., 0.)))),
+        ]);
+
+        // The root, then the left leaf, then the right subtree
+        let primitive_ids: Vec<u32> = nodes.iter().map(|node| node.primitive_id).collect();
+        assert_eq!(primitive_ids, vec![0, 1, 0, 2, 3]);
+        let escape_indices: Vec<u32> = nodes.iter().map(|node| node.escape_index).collect();
+        assert_eq!(escape_indices, vec![0, 2, 0, 4, 0]);
+        assert_eq!(nodes[0].minimum, Vec3::splat(-0.5));
+        assert_eq!(nodes[0].maximum, Vec3::new(4.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn test_bounds_contain_descendants() {
+        let leaves: Vec<(u32, Option<BoundingBox>)> = (0..27)
+            .map(|index: u32| {
+                let centre = Vec3::new(
+                    (index % 3) as f32,
+                    (index / 3 % 3) as f32 * 1.5,
+                    (index / 9) as f32 * 0.75,
+                );
+                (
+                    index,
+                    Some(unit_box_at(centre).expand(Vec3::splat(0.1 * (index % 4) as f32))),
+                )
+            })
+            .collect();
+        let nodes = build_bvh(leaves.clone());
+
+        // Every leaf appears exactly once, and interior nodes are not leaves
+        let mut primitive_ids: Vec<u32> = nodes
+            .iter()
+            .map(|node| node.primitive_id)
+            .filter(|primitive_id| *primitive_id != 0)
+            .collect();
+        primitive_ids.sort();
+        assert_eq!(primitive_ids, (1..=27).collect::<Vec<u32>>());
+        assert_eq!(nodes.len(), 2 * 27 - 1);
+
+        // The descendants of a node are the nodes before its escape index
+        for (index, node) in nodes.iter().enumerate() {
+            let escape_index: usize = match node.escape_index {
+                0 => nodes.len(),
+                escape_index => escape_index as usize,
+            };
+            assert!(escape_index > index);
+            if node.primitive_id != 0 {
+                assert_eq!(escape_index, index + 1);
+            }
+            for descendant in &nodes[index + 1..escape_index] {
+                assert!(contains(node, descendant));
+            }
+        }
+
+        // The traversal finds exactly the leaves containing a position
+        for position in [Vec3::ZERO, Vec3::new(1.2, 1.5, 0.7), Vec3::new(2., 3., 1.5)] {
+            let mut visited: Vec<u32> = traverse(&nodes, position);
+            visited.sort();
+            let expected: Vec<u32> = leaves
+                .iter()
+                .filter(|(_, bounding_box)| {
+                    let bounding_box = bounding_box.unwrap();
+                    position.cmpge(bounding_box.minimum).all()
+                        && position.cmple(bounding_box.maximum).all()
+                })
+                .map(|(index, _)| index + 1)
+                .collect();
+            assert!(!expected.is_empty());
+            assert_eq!(visited, expected);
+        }
+    }
+}
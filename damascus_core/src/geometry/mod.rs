@@ -4,11 +4,18 @@
 // LICENSE file in the root directory of this source tree.
 
 use crevice::std430::AsStd430;
-use glam::{Mat3, Vec3};
+use glam::{Mat3, Mat4, Vec3};
 use strum::{Display, EnumCount, EnumIter, EnumString};
 
+pub mod bvh;
 pub mod camera;
 pub mod primitive;
+pub mod primitive_sdfs;
+pub mod scatter;
+
+// The smallest scale along any axis of a transform, below which the
+// scale and shear would not be invertible
+const MINIMUM_SCALE: f32 = 1e-4;
 
 #[repr(C)]
 #[derive(Debug, Default, Copy, Clone, AsStd430, serde::Serialize, serde::Deserialize)]
@@ -16,7 +23,90 @@ pub mod primitive;
 pub struct Transform {
     translation: Vec3,
     inverse_rotation: Mat3,
-    uniform_scale: f32,
+    inverse_scale_shear: Mat3,
+    minimum_scale: f32,
+}
+
+impl Transform {
+    /// Split an affine world matrix into a translation, a rotation, and
+    /// the remaining scale and shear, via a QR decomposition of its
+    /// linear part. The minimum scale is the smallest factor by which
+    /// the matrix shrinks any direction, which the distance to a
+    /// transformed primitive must be multiplied by to remain a
+    /// conservative bound.
+    ///
+    /// A matrix that flattens any axis is clamped to the minimum scale,
+    /// so that the primitive becomes very thin rather than the inverse
+    /// becoming infinite.
+    pub fn from_world_matrix(world_matrix: &Mat4) -> Self {
+        let linear = Mat3::from_mat4(*world_matrix);
+
+        let x_axis: Vec3 = linear.x_axis.normalize_or(Vec3::X);
+        let y_axis: Vec3 = (linear.y_axis - linear.y_axis.dot(x_axis) * x_axis)
+            .normalize_or(x_axis.any_orthonormal_vector());
+        let z_axis: Vec3 = x_axis.cross(y_axis);
+        let rotation = Mat3::from_cols(x_axis, y_axis, z_axis);
+
+        // The rotation is orthonormal so its transpose is its inverse
+        let inverse_rotation: Mat3 = rotation.transpose();
+        let mut scale_shear: Mat3 = inverse_rotation * linear;
+
+        // The scale and shear are upper triangular, so their determinant
+        // is the product of the scales along the diagonal
+        if scale_shear.determinant().abs() < f32::EPSILON {
+            let clamp_scale = |scale: f32| {
+                if scale.abs() < MINIMUM_SCALE {
+                    MINIMUM_SCALE.copysign(scale)
+                } else {
+                    scale
+                }
+            };
+            scale_shear.x_axis.x = clamp_scale(scale_shear.x_axis.x);
+            scale_shear.y_axis.y = clamp_scale(scale_shear.y_axis.y);
+            scale_shear.z_axis.z = clamp_scale(scale_shear.z_axis.z);
+        }
+
+        // The reciprocal of the Frobenius norm of the inverse is a lower
+        // bound on the minimum scale, which keeps it above zero where the
+        // eigenvalues are too small to be found in single precision
+        let inverse_scale_shear: Mat3 = scale_shear.inverse();
+        let inverse_norm: f32 = (inverse_scale_shear.x_axis.length_squared()
+            + inverse_scale_shear.y_axis.length_squared()
+            + inverse_scale_shear.z_axis.length_squared())
+        .sqrt();
+
+        Self {
+            translation: world_matrix.w_axis.truncate(),
+            inverse_rotation,
+            inverse_scale_shear,
+            minimum_scale: minimum_singular_value(&scale_shear).max(1. / inverse_norm),
+        }
+    }
+}
+
+/// The smallest singular value of a matrix, found from the smallest
+/// eigenvalue of the symmetric matrix `transpose(matrix) * matrix`.
+fn minimum_singular_value(matrix: &Mat3) -> f32 {
+    let symmetric: Mat3 = matrix.transpose() * *matrix;
+    let off_diagonal: f32 =
+        symmetric.y_axis.x.powi(2) + symmetric.z_axis.x.powi(2) + symmetric.z_axis.y.powi(2);
+    let mean: f32 = (symmetric.x_axis.x + symmetric.y_axis.y + symmetric.z_axis.z) / 3.;
+    let deviation: f32 = ((symmetric.x_axis.x - mean).powi(2)
+        + (symmetric.y_axis.y - mean).powi(2)
+        + (symmetric.z_axis.z - mean).powi(2)
+        + 2. * off_diagonal)
+        / 6.;
+    if deviation <= f32::EPSILON * mean * mean {
+        return mean.max(0.).sqrt();
+    }
+    let deviation: f32 = deviation.sqrt();
+    let half_determinant: f32 =
+        ((symmetric - Mat3::from_diagonal(Vec3::splat(mean))) * (1. / deviation)).determinant()
+            / 2.;
+    let angle: f32 = half_determinant.clamp(-1., 1.).acos() / 3.;
+    let minimum_eigenvalue: f32 =
+        mean + 2. * deviation * (angle + 2. * std::f32::consts::PI / 3.).cos();
+    minimum_eigenvalue.max(0.).sqrt()
 }
 
 #[derive(
@@ -39,6 +129,34 @@ pub enum BlendType {
     Union,
     Subtraction,
     Intersection,
+    ExclusiveOr,
+    Groove,
+    Tongue,
+    Engrave,
+}
+
+#[derive(
+    Debug,
+    Display,
+    Default,
+    Copy,
+    Clone,
+    EnumIter,
+    EnumString,
+    Eq,
+    PartialEq,
+    PartialOrd,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum BlendKernel {
+    #[default]
+    Polynomial,
+    Exponential,
+    Circular,
+    Chamfer,
+    Stairs,
+    Columns,
 }
 
 #[derive(
@@ -60,4 +178,62 @@ pub enum Repetition {
     None,
     Finite,
     Infinite,
+    Polar,
+}
+
+#[derive(
+    Debug,
+    Display,
+    Default,
+    Copy,
+    Clone,
+    EnumIter,
+    EnumString,
+    Eq,
+    PartialEq,
+    PartialOrd,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum Axis {
+    X,
+    #[default]
+    Y,
+    Z,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_world_matrix() {
+        let world_matrix = Mat4::from_scale_rotation_translation(
+            Vec3::new(2., 3., 0.5),
+            glam::Quat::from_rotation_y(1.),
+            Vec3::new(1., 2., 3.),
+        );
+        let transform = Transform::from_world_matrix(&world_matrix);
+        assert_eq!(transform.translation, Vec3::new(1., 2., 3.));
+        assert!((transform.minimum_scale - 0.5).abs() < 1e-4);
+        let position = Vec3::new(-1., 4., 2.);
+        assert!((transform.inverse_scale_shear
+            * (transform.inverse_rotation * (position - transform.translation)))
+            .abs_diff_eq(world_matrix.inverse().transform_point3(position), 1e-5));
+    }
+
+    #[test]
+    fn test_from_flattened_world_matrix() {
+        for scale in [
+            Vec3::new(1., 0., 1.),
+            Vec3::new(0., 0., 2.),
+            Vec3::new(1., 1., -0.),
+            Vec3::ZERO,
+        ] {
+            let transform = Transform::from_world_matrix(&Mat4::from_scale(scale));
+            assert!(transform.inverse_scale_shear.is_finite(), "{:?}", scale);
+            assert!(transform.minimum_scale > 0., "{:?}", scale);
+            assert!(transform.minimum_scale.is_finite(), "{:?}", scale);
+        }
+    }
 }
@@ -4,12 +4,17 @@
 // LICENSE file in the root directory of this source tree.
 
 use crevice::std430::AsStd430;
-use glam::{BVec3, Mat4, UVec3, Vec3, Vec4};
+use glam::{BVec3, EulerRot, Mat3, Mat4, UVec3, Vec2, Vec3, Vec3Swizzles, Vec4, Vec4Swizzles};
 use strum::{Display, EnumIter, EnumString};
 
-use super::{BlendType, Repetition, Transform};
+use super::{
+    bvh::BoundingBox, primitive_sdfs, Axis, BlendKernel, BlendType, Repetition, Transform,
+};
 use crate::{
-    materials::{GPUMaterial, Material},
+    materials::{
+        GPUMaterial, GPUProceduralTexture, Material, ProceduralTexture, ProceduralTextureType,
+        TextureBuffers,
+    },
     DualDevice,
 };
 
@@ -37,21 +42,32 @@ pub enum Shapes {
     Cylinder,
     DeathStar,
     Ellipsoid,
+    Gyroid,
     HexagonalPrism,
     HollowSphere,
+    HybridFractal,
     InfiniteCone,
     InfiniteCylinder,
+    KaleidoscopicIFS,
     Link,
     Mandelbox,
     Mandelbulb,
+    MengerSponge,
+    Neovius,
     Octahedron,
     Plane,
+    QuaternionJulia,
     RectangularPrism,
     RectangularPrismFrame,
     Rhombus,
     RoundedCone,
+    SchwarzD,
+    SchwarzP,
+    SierpinskiOctahedron,
+    SierpinskiTetrahedron,
     SolidAngle,
     Sphere,
+    Superellipsoid,
     Torus,
     TriangularPrism,
 }
@@ -60,6 +76,7 @@ pub enum Shapes {
 #[derive(Debug, Copy, Clone, AsStd430)]
 pub struct GPUPrimitive {
     pub id: u32,
+    cell_id: u32,
     shape: u32,
     transform: Transform,
     material: GPUMaterial,
@@ -67,15 +84,45 @@ pub struct GPUPrimitive {
     negative_repetitions: Vec3,
     positive_repetitions: Vec3,
     spacing: Vec3,
+    polar_segments: f32,
+    polar_axis: u32,
+    polar_radius: f32,
+    cell_scale_variation: f32,
+    cell_rotation_variation: Vec3,
+    cell_jitter: Vec3,
+    cell_hue_variation: f32,
+    cell_value_variation: f32,
+    cell_visibility: f32,
+    cell_transform_seeds: Vec3,
+    cell_material_seeds: Vec3,
+    cell_clearance: f32,
+    blend_type: u32,
+    blend_kernel: u32,
     blend_strength: f32,
+    blend_steps: f32,
+    blend_width: f32,
     wall_thickness: f32,
     edge_radius: f32,
     elongation: Vec3,
+    twist_axis: u32,
+    twist_rate: f32,
+    bend_axis: u32,
+    bend_radius: f32,
+    taper_axis: u32,
+    taper_rate: f32,
     num_descendants: u32,
     dimensional_data: Vec4,
+    extended_dimensional_data: Vec4,
+    fold_planes: Mat3,
+    fractal_rotation: Mat3,
+    fractal_offset: Vec3,
+    displacement_texture: GPUProceduralTexture,
+    displacement_amplitude: f32,
+    displacement_offset: f32,
+    lipschitz_bound: f32,
 }
 
-#[derive(Debug, Copy, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(default)]
 pub struct Primitive {
     pub shape: Shapes,
@@ -86,16 +133,53 @@ pub struct Primitive {
     pub negative_repetitions: UVec3,
     pub positive_repetitions: UVec3,
     pub spacing: Vec3,
+    pub polar_segments: u32,
+    pub polar_axis: Axis,
+    pub polar_radius: f32,
+    pub polar_mirror: bool,
+    pub cell_variation: bool,
+    pub cell_scale_variation: f32,
+    pub cell_scale_seed: u32,
+    pub cell_rotation_variation: Vec3,
+    pub cell_rotation_seed: u32,
+    pub cell_jitter: Vec3,
+    pub cell_jitter_seed: u32,
+    pub cell_hue_variation: f32,
+    pub cell_hue_seed: u32,
+    pub cell_value_variation: f32,
+    pub cell_value_seed: u32,
+    pub cell_visibility: f32,
+    pub cell_visibility_seed: u32,
     pub blend_type: BlendType,
+    pub blend_kernel: BlendKernel,
     pub blend_strength: f32,
+    pub blend_steps: u32,
+    pub blend_width: f32,
     pub mirror: BVec3,
     pub hollow: bool,
     pub wall_thickness: f32,
     pub elongate: bool,
     pub elongation: Vec3,
+    pub twist: bool,
+    pub twist_axis: Axis,
+    pub twist_rate: f32,
+    pub bend: bool,
+    pub bend_axis: Axis,
+    pub bend_radius: f32,
+    pub taper: bool,
+    pub taper_axis: Axis,
+    pub taper_rate: f32,
     pub bounding_volume: bool,
     pub num_descendants: u32,
     pub dimensional_data: Vec4,
+    pub extended_dimensional_data: Vec4,
+    pub fold_planes: Mat3,
+    pub fractal_rotation_angles: Vec3,
+    pub fractal_offset: Vec3,
+    pub displacement_texture: ProceduralTexture,
+    pub displacement_amplitude: f32,
+    pub displacement_offset: f32,
+    pub lipschitz_bound: f32,
 }
 
 impl Default for Primitive {
@@ -109,55 +193,875 @@ impl Default for Primitive {
             negative_repetitions: UVec3::ZERO,
             positive_repetitions: UVec3::ONE,
             blend_type: BlendType::Union,
+            blend_kernel: BlendKernel::Polynomial,
             blend_strength: 0.,
+            blend_steps: 4,
+            blend_width: 0.05,
             spacing: Vec3::ONE,
+            polar_segments: 6,
+            polar_axis: Axis::Y,
+            polar_radius: 1.,
+            polar_mirror: false,
+            cell_variation: false,
+            cell_scale_variation: 0.,
+            cell_scale_seed: 0,
+            cell_rotation_variation: Vec3::ZERO,
+            cell_rotation_seed: 0,
+            cell_jitter: Vec3::ZERO,
+            cell_jitter_seed: 0,
+            cell_hue_variation: 0.,
+            cell_hue_seed: 0,
+            cell_value_variation: 0.,
+            cell_value_seed: 0,
+            cell_visibility: 1.,
+            cell_visibility_seed: 0,
             mirror: BVec3::FALSE,
             hollow: false,
             wall_thickness: 0.01,
             elongate: false,
             elongation: Vec3::ZERO,
+            twist: false,
+            twist_axis: Axis::Y,
+            twist_rate: 90.,
+            bend: false,
+            bend_axis: Axis::Z,
+            bend_radius: 1.,
+            taper: false,
+            taper_axis: Axis::Y,
+            taper_rate: 0.5,
             bounding_volume: false,
             num_descendants: 0,
             dimensional_data: 0.5 * Vec4::X,
+            extended_dimensional_data: Vec4::ZERO,
+            fold_planes: Mat3::from_cols(
+                Vec3::new(1., 1., 0.),
+                Vec3::new(1., 0., 1.),
+                Vec3::new(0., 1., 1.),
+            ),
+            fractal_rotation_angles: Vec3::ZERO,
+            fractal_offset: Vec3::ONE,
+            displacement_texture: ProceduralTexture::default(),
+            displacement_amplitude: 0.,
+            displacement_offset: 0.,
+            lipschitz_bound: 1.,
         }
     }
 }
 
-impl Primitive {}
+impl Primitive {
+    /// The signed distance from a world space position to the primitive,
+    /// evaluated on the CPU. Elongation, mirroring, hollowing, and edge
+    /// rounding are applied, but repetition, deformations, displacement,
+    /// and children are not. Shapes without a CPU port return `None`.
+    pub fn signed_distance(&self, position: Vec3) -> Option<f32> {
+        let transform = Transform::from_world_matrix(&self.world_matrix);
+        let mut local_position: Vec3 =
+            transform.inverse_rotation * (position - transform.translation);
+        if self.elongate {
+            local_position -= local_position.clamp(-self.elongation, self.elongation);
+        }
+        local_position = Vec3::select(self.mirror, local_position.abs(), local_position);
+        local_position = transform.inverse_scale_shear * local_position;
 
-impl DualDevice<GPUPrimitive, Std430GPUPrimitive> for Primitive {
-    fn to_gpu(&self) -> GPUPrimitive {
-        let (scale, quaternion, translation) = self.world_matrix.to_scale_rotation_translation();
+        let data: Vec4 = self.dimensional_data;
+        let extended_data: Vec4 = self.extended_dimensional_data;
+        let distance: f32 = match self.shape {
+            Shapes::CappedTorus => primitive_sdfs::distance_to_capped_torus(
+                local_position,
+                data.x,
+                data.y,
+                data.z.to_radians(),
+            ),
+            Shapes::Capsule => {
+                primitive_sdfs::distance_to_capsule(local_position, data.x, data.y, data.z)
+            }
+            Shapes::Cylinder => {
+                primitive_sdfs::distance_to_cylinder(local_position, data.x, data.y)
+            }
+            Shapes::Ellipsoid => primitive_sdfs::distance_to_ellipsoid(local_position, data.xyz()),
+            Shapes::Gyroid => primitive_sdfs::distance_to_gyroid(local_position, data.x, data.y),
+            Shapes::HybridFractal => primitive_sdfs::distance_to_hybrid_fractal(
+                local_position,
+                data.x,
+                data.y as u32,
+                data.z,
+                data.w,
+                extended_data.x,
+                extended_data.y,
+                extended_data.z as u32,
+                extended_data.w as u32,
+            ),
+            Shapes::HexagonalPrism => {
+                primitive_sdfs::distance_to_hexagonal_prism(local_position, data.x, data.y)
+            }
+            Shapes::HollowSphere => {
+                primitive_sdfs::distance_to_hollow_sphere(local_position, data.x, data.y, data.z)
+            }
+            Shapes::InfiniteCylinder => {
+                primitive_sdfs::distance_to_infinite_cylinder(local_position, data.x)
+            }
+            Shapes::KaleidoscopicIFS => primitive_sdfs::distance_to_kaleidoscopic_ifs(
+                local_position,
+                data.x,
+                data.z,
+                data.y as u32,
+                self.normalised_fold_planes(),
+                self.fractal_rotation(),
+                self.fractal_offset,
+            ),
+            Shapes::Link => {
+                primitive_sdfs::distance_to_link(local_position, data.x, data.y, data.z)
+            }
+            Shapes::Mandelbox => primitive_sdfs::distance_to_mandelbox(
+                local_position,
+                data.x,
+                data.y as i32,
+                data.z,
+                data.w,
+            ),
+            Shapes::Mandelbulb => primitive_sdfs::distance_to_mandelbulb(
+                local_position,
+                data.x,
+                data.y as u32,
+                data.z,
+            ),
+            Shapes::MengerSponge => {
+                primitive_sdfs::distance_to_menger_sponge(local_position, data.x, data.y as u32)
+            }
+            Shapes::Neovius => primitive_sdfs::distance_to_neovius(local_position, data.x, data.y),
+            Shapes::Octahedron => primitive_sdfs::distance_to_octahedron(local_position, data.x),
+            Shapes::Plane => {
+                primitive_sdfs::distance_to_plane(local_position, data.xyz().normalize_or(Vec3::Y))
+            }
+            Shapes::QuaternionJulia => primitive_sdfs::distance_to_quaternion_julia(
+                local_position,
+                data,
+                extended_data.x as u32,
+                extended_data.y,
+            ),
+            Shapes::RectangularPrism => primitive_sdfs::distance_to_rectangular_prism(
+                local_position,
+                data.x,
+                data.y,
+                data.z,
+            ),
+            Shapes::RectangularPrismFrame => primitive_sdfs::distance_to_rectangular_prism_frame(
+                local_position,
+                data.x,
+                data.y,
+                data.z,
+                data.w,
+            ),
+            Shapes::Rhombus => {
+                primitive_sdfs::distance_to_rhombus(local_position, data.x, data.y, data.z, data.w)
+            }
+            Shapes::SchwarzD => {
+                primitive_sdfs::distance_to_schwarz_d(local_position, data.x, data.y)
+            }
+            Shapes::SchwarzP => {
+                primitive_sdfs::distance_to_schwarz_p(local_position, data.x, data.y)
+            }
+            Shapes::SierpinskiOctahedron => primitive_sdfs::distance_to_sierpinski_octahedron(
+                local_position,
+                data.x,
+                data.y as u32,
+            ),
+            Shapes::SierpinskiTetrahedron => primitive_sdfs::distance_to_sierpinski_tetrahedron(
+                local_position,
+                data.x,
+                data.y as u32,
+            ),
+            Shapes::Sphere => primitive_sdfs::distance_to_sphere(local_position, data.x),
+            Shapes::Superellipsoid => primitive_sdfs::distance_to_superellipsoid(
+                local_position,
+                data.xyz(),
+                extended_data.x,
+                extended_data.y,
+            ),
+            Shapes::Torus => primitive_sdfs::distance_to_torus(local_position, data.x, data.y),
+            Shapes::TriangularPrism => {
+                primitive_sdfs::distance_to_triangular_prism(local_position, data.x, data.y)
+            }
+            _ => return None,
+        } * transform.minimum_scale;
+
+        let distance: f32 = if self.hollow {
+            distance.abs() - self.wall_thickness
+        } else {
+            distance
+        };
+        Some(distance - self.edge_radius)
+    }
+
+    /// The planes that kaleidoscopic fractals fold across, with unit
+    /// normals, or zero normals for planes that are skipped.
+    fn normalised_fold_planes(&self) -> Mat3 {
+        Mat3::from_cols(
+            self.fold_planes.x_axis.normalize_or_zero(),
+            self.fold_planes.y_axis.normalize_or_zero(),
+            self.fold_planes.z_axis.normalize_or_zero(),
+        )
+    }
+
+    /// The rotation that fractals apply on each iteration.
+    fn fractal_rotation(&self) -> Mat3 {
+        let radian_fractal_rotation: Vec3 =
+            self.fractal_rotation_angles * std::f32::consts::PI / 180.;
+        Mat3::from_euler(
+            EulerRot::XYZ,
+            radian_fractal_rotation.x,
+            radian_fractal_rotation.y,
+            radian_fractal_rotation.z,
+        )
+    }
+
+    /// The surface normal of the primitive at a world space position,
+    /// estimated from the gradient of its CPU signed distance field.
+    pub fn normal(&self, position: Vec3, offset: f32) -> Option<Vec3> {
+        let x_offset = Vec3::new(offset, -offset, -offset);
+        let y_offset = Vec3::new(-offset, -offset, offset);
+        let z_offset = Vec3::new(-offset, offset, -offset);
+        let w_offset = Vec3::splat(offset);
+        Some(
+            (x_offset * self.signed_distance(position + x_offset)?
+                + y_offset * self.signed_distance(position + y_offset)?
+                + z_offset * self.signed_distance(position + z_offset)?
+                + w_offset * self.signed_distance(position + w_offset)?)
+            .normalize_or_zero(),
+        )
+    }
+
+    /// A world space box that conservatively bounds the primitive,
+    /// excluding its children. Returns `None` if the primitive is
+    /// infinite, or its shape has no known bounds (e.g. the fractals).
+    ///
+    /// The modifiers are undone in the reverse of the order in which
+    /// the shader applies them to the ray position. Textures can be
+    /// graded, blended, or remapped beyond the unit range, so primitives
+    /// that are displaced by one are unbounded.
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        if self.displacement_amplitude != 0.
+            && (self.displacement_texture.texture_type != ProceduralTextureType::None
+                || self.displacement_texture.is_composite())
+        {
+            return None;
+        }
+
+        let data: Vec4 = self.dimensional_data;
+        let shape_bounds: BoundingBox = match self.shape {
+            Shapes::CappedCone => {
+                let radius: f32 = data.y.abs().max(data.z.abs());
+                BoundingBox::from_half_extents(Vec3::new(radius, 0.5 * data.x, radius))
+            }
+            Shapes::CappedTorus | Shapes::Torus => {
+                let radius: f32 = data.x.abs() + data.y.abs();
+                BoundingBox::from_half_extents(Vec3::new(radius, radius, data.y))
+            }
+            Shapes::Capsule => {
+                let radius: f32 = data.x.abs();
+                BoundingBox::new(
+                    Vec3::new(-radius, -data.y.max(-data.z) - radius, -radius),
+                    Vec3::new(radius, data.z.max(-data.y) + radius, radius),
+                )
+            }
+            Shapes::Cone => {
+                let radius: f32 = (data.y * data.x.to_radians().tan()).abs();
+                BoundingBox::new(
+                    Vec3::new(-radius, data.y.min(0.), -radius),
+                    Vec3::new(radius, data.y.max(0.), radius),
+                )
+            }
+            Shapes::CutSphere
+            | Shapes::DeathStar
+            | Shapes::MengerSponge
+            | Shapes::Octahedron
+            | Shapes::SierpinskiOctahedron
+            | Shapes::SierpinskiTetrahedron
+            | Shapes::SolidAngle
+            | Shapes::Sphere => BoundingBox::from_half_extents(Vec3::splat(data.x)),
+            Shapes::Cylinder => {
+                BoundingBox::from_half_extents(Vec3::new(data.x, 0.5 * data.y, data.x))
+            }
+            Shapes::Ellipsoid | Shapes::Superellipsoid => {
+                BoundingBox::from_half_extents(data.xyz())
+            }
+            Shapes::HexagonalPrism => {
+                // The height is between the flat sides, so the corners
+                // are further out by 1 / cos(PI / 6)
+                let radius: f32 = 0.57735027 * data.x;
+                BoundingBox::from_half_extents(Vec3::new(radius, radius, 0.5 * data.y))
+            }
+            Shapes::HollowSphere => {
+                BoundingBox::from_half_extents(Vec3::splat(data.x.abs() + 0.5 * data.z.abs()))
+            }
+            Shapes::Link => {
+                let radius: f32 = data.x.abs() + data.y.abs();
+                BoundingBox::from_half_extents(Vec3::new(
+                    radius,
+                    0.5 * data.z.abs() + radius,
+                    data.y,
+                ))
+            }
+            Shapes::RectangularPrism | Shapes::RectangularPrismFrame => {
+                BoundingBox::from_half_extents(0.5 * data.xyz())
+            }
+            Shapes::Rhombus => BoundingBox::from_half_extents(Vec3::new(
+                0.5 * data.x.abs() + data.w.abs(),
+                0.5 * data.y.abs() + data.w.abs(),
+                0.5 * data.z,
+            )),
+            Shapes::RoundedCone => {
+                let radius: f32 = data.y.abs().max(data.z.abs());
+                BoundingBox::new(
+                    Vec3::new(-radius, (-radius).min(data.x - radius), -radius),
+                    Vec3::new(radius, radius.max(data.x + radius), radius),
+                )
+            }
+            Shapes::TriangularPrism => {
+                // The distance from the centroid to the corners
+                let radius: f32 = 0.57735027 * data.x;
+                BoundingBox::from_half_extents(Vec3::new(radius, radius, data.y))
+            }
+            _ => return None,
+        };
+
+        let transform = Transform::from_world_matrix(&self.world_matrix);
+
+        // Grow by the offsets that are subtracted from the distance
+        let surface_offset: f32 = self.edge_radius.max(0.)
+            + if self.hollow {
+                self.wall_thickness.abs()
+            } else {
+                0.
+            }
+            + (self.displacement_amplitude * (1. + self.displacement_offset)).abs();
+        let mut bounds: BoundingBox = shape_bounds
+            .expand(Vec3::splat(
+                surface_offset / transform.minimum_scale.max(f32::EPSILON),
+            ))
+            .transform(&transform.inverse_scale_shear.inverse());
+
+        if self.taper {
+            bounds = taper_bounds(&bounds, self.taper_axis, self.taper_rate);
+        }
+        if self.twist {
+            bounds = twist_bounds(&bounds, self.twist_axis);
+        }
+        if self.bend {
+            bounds = bend_bounds(&bounds, self.bend_axis, self.bend_radius);
+        }
+        if self.mirror.any() {
+            bounds = bounds.symmetric(self.mirror);
+        }
+        if self.elongate {
+            bounds = bounds.expand(self.elongation.abs());
+        }
+        if self.cell_variation && self.repetition != Repetition::None {
+            let scale_variation: f32 = self.cell_scale_variation.clamp(0., 0.99);
+            bounds = bounds.scale_between(1. - scale_variation, 1. + scale_variation);
+            if self.cell_rotation_variation != Vec3::ZERO {
+                bounds = BoundingBox::from_half_extents(Vec3::splat(bounds.bounding_radius()));
+            }
+            bounds = bounds.expand(self.cell_jitter.abs());
+        }
+        match self.repetition {
+            Repetition::Finite => {
+                let lower_offset: Vec3 = -self.spacing * self.negative_repetitions.as_vec3();
+                let upper_offset: Vec3 = self.spacing * self.positive_repetitions.as_vec3();
+                // Every other repetition is mirrored
+                let bounds_to_repeat: BoundingBox = bounds.symmetric(BVec3::TRUE);
+                bounds = BoundingBox::new(
+                    bounds_to_repeat.minimum + lower_offset.min(upper_offset),
+                    bounds_to_repeat.maximum + lower_offset.max(upper_offset),
+                );
+            }
+            Repetition::Infinite => return None,
+            Repetition::Polar => {
+                bounds = polar_repetition_bounds(&bounds, self.polar_axis, self.polar_radius);
+            }
+            Repetition::None => {}
+        }
+
+        // The transpose of a rotation is its inverse
+        Some(
+            bounds
+                .transform(&transform.inverse_rotation.transpose())
+                .translate(transform.translation),
+        )
+    }
+
+    /// Convert to the GPU representation, appending the data that the
+    /// procedural textures refer to by index to the texture buffers.
+    pub fn to_gpu_with_texture_buffers(
+        &self,
+        texture_buffers: &mut TextureBuffers,
+    ) -> GPUPrimitive {
         GPUPrimitive {
             id: 0,
+            cell_id: 0,
             shape: self.shape as u32,
-            transform: Transform {
-                translation: translation,
-                inverse_rotation: glam::Mat3::from_quat(quaternion).inverse(),
-                uniform_scale: scale.x,
-            },
-            material: self.material.to_gpu(),
-            modifiers: self.repetition as u32
-                | (self.elongate as u32) << 2
+            transform: Transform::from_world_matrix(&self.world_matrix),
+            material: self.material.to_gpu_with_texture_buffers(texture_buffers),
+            modifiers: if self.repetition == Repetition::Polar {
+                1 << 7 | (self.polar_mirror as u32) << 8
+            } else {
+                self.repetition as u32
+            } | (self.elongate as u32) << 2
                 | (self.mirror.x as u32) << 3
                 | (self.mirror.y as u32) << 4
                 | (self.mirror.z as u32) << 5
                 | (self.hollow as u32) << 6
-                | if self.blend_type > BlendType::Union && !self.bounding_volume {
-                    1 << self.blend_type as u32 + 6
-                } else {
-                    0
-                }
-                | (self.bounding_volume as u32) << 9,
+                | (self.bounding_volume as u32) << 9
+                | (self.twist as u32) << 10
+                | (self.bend as u32) << 11
+                | (self.taper as u32) << 12
+                | ((self.cell_variation && self.repetition != Repetition::None) as u32) << 13
+                | (self.material.uses_surface_normal() as u32) << 14
+                | ((self.displacement_amplitude != 0.
+                    && self.displacement_texture.uses_surface_normal()) as u32)
+                    << 15,
             negative_repetitions: self.negative_repetitions.as_vec3(),
             positive_repetitions: self.positive_repetitions.as_vec3(),
             spacing: self.spacing,
+            polar_segments: self.polar_segments.max(1) as f32,
+            polar_axis: self.polar_axis as u32,
+            polar_radius: self.polar_radius,
+            cell_scale_variation: self.cell_scale_variation.clamp(0., 0.99),
+            cell_rotation_variation: self.cell_rotation_variation * std::f32::consts::PI / 180.,
+            cell_jitter: self.cell_jitter,
+            cell_hue_variation: self.cell_hue_variation.clamp(0., 1.),
+            cell_value_variation: self.cell_value_variation.clamp(0., 1.),
+            cell_visibility: self.cell_visibility.clamp(0., 1.),
+            cell_transform_seeds: UVec3::new(
+                self.cell_scale_seed,
+                self.cell_rotation_seed,
+                self.cell_jitter_seed,
+            )
+            .as_vec3(),
+            cell_material_seeds: UVec3::new(
+                self.cell_hue_seed,
+                self.cell_value_seed,
+                self.cell_visibility_seed,
+            )
+            .as_vec3(),
+            cell_clearance: 0.,
+            blend_type: if self.bounding_volume {
+                BlendType::Union as u32
+            } else {
+                self.blend_type as u32
+            },
+            blend_kernel: self.blend_kernel as u32,
             blend_strength: self.blend_strength,
+            blend_steps: self.blend_steps.max(1) as f32,
+            blend_width: self.blend_width,
             wall_thickness: self.wall_thickness,
             edge_radius: self.edge_radius,
             elongation: self.elongation,
+            twist_axis: self.twist_axis as u32,
+            twist_rate: self.twist_rate * std::f32::consts::PI / 180.,
+            bend_axis: self.bend_axis as u32,
+            bend_radius: self.bend_radius,
+            taper_axis: self.taper_axis as u32,
+            taper_rate: self.taper_rate,
             num_descendants: self.num_descendants,
             dimensional_data: self.dimensional_data,
+            extended_dimensional_data: self.extended_dimensional_data,
+            fold_planes: self.normalised_fold_planes(),
+            fractal_rotation: self.fractal_rotation(),
+            fractal_offset: self.fractal_offset,
+            displacement_texture: self
+                .displacement_texture
+                .to_gpu_with_texture_buffers(texture_buffers),
+            displacement_amplitude: self.displacement_amplitude,
+            displacement_offset: self.displacement_offset,
+            lipschitz_bound: self.lipschitz_bound.max(1.),
+        }
+    }
+}
+
+impl DualDevice<GPUPrimitive, Std430GPUPrimitive> for Primitive {
+    fn to_gpu(&self) -> GPUPrimitive {
+        self.to_gpu_with_texture_buffers(&mut TextureBuffers::default())
+    }
+}
+
+/// Cyclically permute the components of a vector so that the chosen axis
+/// becomes the z-axis, as the shader does.
+fn to_axis_space(vector: Vec3, axis: Axis) -> Vec3 {
+    match axis {
+        Axis::X => vector.yzx(),
+        Axis::Y => vector.zxy(),
+        Axis::Z => vector,
+    }
+}
+
+/// Undo the permutation performed by `to_axis_space`.
+fn from_axis_space(vector: Vec3, axis: Axis) -> Vec3 {
+    match axis {
+        Axis::X => vector.zxy(),
+        Axis::Y => vector.yzx(),
+        Axis::Z => vector,
+    }
+}
+
+/// The bounds of the tapered space that maps into the given bounds.
+fn taper_bounds(bounds: &BoundingBox, axis: Axis, rate: f32) -> BoundingBox {
+    let minimum: Vec3 = to_axis_space(bounds.minimum, axis);
+    let maximum: Vec3 = to_axis_space(bounds.maximum, axis);
+    let lower_scale: f32 = (1. + rate * minimum.z).max(0.01);
+    let upper_scale: f32 = (1. + rate * maximum.z).max(0.01);
+    let scaled = BoundingBox::new(minimum, maximum)
+        .scale_between(lower_scale.min(upper_scale), lower_scale.max(upper_scale));
+    BoundingBox::new(
+        from_axis_space(Vec3::from((scaled.minimum.xy(), minimum.z)), axis),
+        from_axis_space(Vec3::from((scaled.maximum.xy(), maximum.z)), axis),
+    )
+}
+
+/// The bounds of the twisted space that maps into the given bounds,
+/// which can be rotated to any angle about the axis.
+fn twist_bounds(bounds: &BoundingBox, axis: Axis) -> BoundingBox {
+    let minimum: Vec3 = to_axis_space(bounds.minimum, axis);
+    let maximum: Vec3 = to_axis_space(bounds.maximum, axis);
+    let radius: f32 = minimum.xy().abs().max(maximum.xy().abs()).length();
+    BoundingBox::new(
+        from_axis_space(Vec3::new(-radius, -radius, minimum.z), axis),
+        from_axis_space(Vec3::new(radius, radius, maximum.z), axis),
+    )
+}
+
+/// The bounds of the bent space that maps into the given bounds. The
+/// first axis of the bent space is the arc length around the centre of
+/// the bend, and the second is the offset from the arc.
+fn bend_bounds(bounds: &BoundingBox, axis: Axis, bend_radius: f32) -> BoundingBox {
+    let minimum: Vec3 = to_axis_space(bounds.minimum, axis);
+    let maximum: Vec3 = to_axis_space(bounds.maximum, axis);
+    let radius: f32 = bend_radius.abs().max(1e-3);
+    let bend_sign: f32 = if bend_radius < 0. { -1. } else { 1. };
+
+    // The range of distances from, and angles around, the centre
+    let distance_bounds = Vec2::new(
+        (radius - bend_sign * minimum.y).min(radius - bend_sign * maximum.y),
+        (radius - bend_sign * minimum.y).max(radius - bend_sign * maximum.y),
+    )
+    .max(Vec2::ZERO);
+    let angle_bounds: Vec2 = (Vec2::new(minimum.x, maximum.x) / radius).clamp(
+        Vec2::splat(-std::f32::consts::PI),
+        Vec2::splat(std::f32::consts::PI),
+    );
+
+    // The sine and cosine are extremal at the ends of the range of
+    // angles, or at any multiple of PI / 2 within it
+    let mut sine_bounds = Vec2::new(f32::MAX, f32::MIN);
+    let mut cosine_bounds = Vec2::new(f32::MAX, f32::MIN);
+    let quarter_turn: f32 = std::f32::consts::FRAC_PI_2;
+    let extremal_angles = [angle_bounds.x, angle_bounds.y]
+        .into_iter()
+        .chain((-2..=2).map(|quarter_turns| quarter_turns as f32 * quarter_turn))
+        .filter(|angle| angle_bounds.x <= *angle && *angle <= angle_bounds.y);
+    for angle in extremal_angles {
+        sine_bounds = Vec2::new(
+            sine_bounds.x.min(angle.sin()),
+            sine_bounds.y.max(angle.sin()),
+        );
+        cosine_bounds = Vec2::new(
+            cosine_bounds.x.min(angle.cos()),
+            cosine_bounds.y.max(angle.cos()),
+        );
+    }
+
+    // The distances are positive, so the products are extremal at the
+    // ends of their ranges
+    let horizontal = BoundingBox::new(
+        Vec3::new(sine_bounds.x, cosine_bounds.x, 0.),
+        Vec3::new(sine_bounds.y, cosine_bounds.y, 0.),
+    )
+    .scale_between(distance_bounds.x, distance_bounds.y);
+    let vertical_bounds = Vec2::new(
+        bend_sign * (radius - horizontal.maximum.y),
+        bend_sign * (radius - horizontal.minimum.y),
+    );
+    BoundingBox::new(
+        from_axis_space(
+            Vec3::new(
+                horizontal.minimum.x,
+                vertical_bounds.min_element(),
+                minimum.z,
+            ),
+            axis,
+        ),
+        from_axis_space(
+            Vec3::new(
+                horizontal.maximum.x,
+                vertical_bounds.max_element(),
+                maximum.z,
+            ),
+            axis,
+        ),
+    )
+}
+
+/// The bounds of every segment of a polar repetition, which can be
+/// rotated to any angle about the axis after being offset from it.
+fn polar_repetition_bounds(bounds: &BoundingBox, axis: Axis, polar_radius: f32) -> BoundingBox {
+    let minimum: Vec3 = to_axis_space(bounds.minimum, axis);
+    let maximum: Vec3 = to_axis_space(bounds.maximum, axis);
+    let offset = Vec2::new(polar_radius, 0.);
+    let radius: f32 = (minimum.xy() + offset)
+        .abs()
+        .max((maximum.xy() + offset).abs())
+        .length();
+    BoundingBox::new(
+        from_axis_space(Vec3::new(-radius, -radius, minimum.z), axis),
+        from_axis_space(Vec3::new(radius, radius, maximum.z), axis),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world_matrix(scale: Vec3, rotation: Vec3, translation: Vec3) -> Mat4 {
+        Mat4::from_scale_rotation_translation(
+            scale,
+            glam::Quat::from_euler(
+                EulerRot::XYZ,
+                rotation.x.to_radians(),
+                rotation.y.to_radians(),
+                rotation.z.to_radians(),
+            ),
+            translation,
+        )
+    }
+
+    /// Check that every point on a grid around the primitive that is
+    /// inside it, according to its CPU distance field, is also inside its
+    /// bounding box.
+    fn assert_bounded(primitive: &Primitive) {
+        let bounding_box: BoundingBox = primitive.bounding_box().unwrap();
+        let half_width: Vec3 = 2. * bounding_box.half_extents() + 1.;
+        let steps: u32 = 24;
+        let mut points_inside: u32 = 0;
+        for x in 0..=steps {
+            for y in 0..=steps {
+                for z in 0..=steps {
+                    let position: Vec3 = bounding_box.centre()
+                        + half_width * (2. * UVec3::new(x, y, z).as_vec3() / steps as f32 - 1.);
+                    if primitive.signed_distance(position).unwrap() > 0. {
+                        continue;
+                    }
+                    points_inside += 1;
+                    assert!(
+                        position.cmpge(bounding_box.minimum - 1e-4).all()
+                            && position.cmple(bounding_box.maximum + 1e-4).all(),
+                        "{:?} is inside the {:?} but outside its bounds {:?}",
+                        position,
+                        primitive.shape,
+                        bounding_box,
+                    );
+                }
+            }
+        }
+        assert!(
+            points_inside > 0,
+            "no points are inside the {:?}",
+            primitive.shape
+        );
+    }
+
+    #[test]
+    fn test_signed_distance() {
+        let sphere = Primitive {
+            dimensional_data: Vec4::new(1., 0., 0., 0.),
+            world_matrix: world_matrix(Vec3::splat(2.), Vec3::new(0., 90., 0.), Vec3::X),
+            ..Default::default()
+        };
+        assert!((sphere.signed_distance(Vec3::new(4., 0., 0.)).unwrap() - 1.).abs() < 1e-5);
+        assert!((sphere.signed_distance(Vec3::X).unwrap() + 2.).abs() < 1e-5);
+
+        let hollow_sphere = Primitive {
+            hollow: true,
+            wall_thickness: 0.1,
+            edge_radius: 0.2,
+            ..sphere.clone()
+        };
+        assert!((hollow_sphere.signed_distance(Vec3::X).unwrap() - 1.7).abs() < 1e-5);
+
+        let normal: Vec3 = sphere.normal(Vec3::new(1., 2., 0.), 1e-3).unwrap();
+        assert!(normal.abs_diff_eq(Vec3::Y, 1e-3), "{:?}", normal);
+
+        let death_star = Primitive {
+            shape: Shapes::DeathStar,
+            ..Default::default()
+        };
+        assert!(death_star.signed_distance(Vec3::ZERO).is_none());
+        assert!(death_star.normal(Vec3::ZERO, 1e-3).is_none());
+    }
+
+    #[test]
+    fn test_bounding_box_contains_shapes() {
+        let shapes: [(Shapes, Vec4); 9] = [
+            (Shapes::Sphere, Vec4::new(0.5, 0., 0., 0.)),
+            (Shapes::Ellipsoid, Vec4::new(0.3, 0.6, 0.9, 0.)),
+            (Shapes::RectangularPrism, Vec4::new(0.5, 1., 1.5, 0.)),
+            (Shapes::Cylinder, Vec4::new(0.4, 1.2, 0., 0.)),
+            (Shapes::Capsule, Vec4::new(0.3, 0.4, 0.6, 0.)),
+            (Shapes::Torus, Vec4::new(0.6, 0.2, 0., 0.)),
+            (Shapes::HexagonalPrism, Vec4::new(0.8, 0.5, 0., 0.)),
+            (Shapes::TriangularPrism, Vec4::new(0.8, 0.5, 0., 0.)),
+            (Shapes::Octahedron, Vec4::new(0.7, 0., 0., 0.)),
+        ];
+        for (shape, dimensional_data) in shapes {
+            let primitive = Primitive {
+                shape,
+                dimensional_data,
+                world_matrix: world_matrix(
+                    Vec3::new(1., 2., 0.5),
+                    Vec3::new(30., 45., 60.),
+                    Vec3::new(1., -2., 3.),
+                ),
+                ..Default::default()
+            };
+            assert_bounded(&primitive);
+        }
+    }
+
+    #[test]
+    fn test_bounding_box_contains_modifiers() {
+        let sphere = Primitive {
+            dimensional_data: Vec4::new(0.5, 0., 0., 0.),
+            world_matrix: world_matrix(Vec3::splat(1.5), Vec3::new(0., 30., 0.), Vec3::X),
+            ..Default::default()
+        };
+        assert_bounded(&Primitive {
+            edge_radius: 0.2,
+            ..sphere.clone()
+        });
+        assert_bounded(&Primitive {
+            hollow: true,
+            wall_thickness: 0.1,
+            ..sphere.clone()
+        });
+        assert_bounded(&Primitive {
+            elongate: true,
+            elongation: Vec3::new(0.5, 0., 1.),
+            ..sphere.clone()
+        });
+        assert_bounded(&Primitive {
+            mirror: BVec3::new(true, false, true),
+            world_matrix: world_matrix(Vec3::ONE, Vec3::ZERO, Vec3::new(1., 0., 2.)),
+            ..sphere.clone()
+        });
+
+        // Shear the sphere into a slanted ellipsoid
+        let shear = Mat4::from_cols(Vec4::X, Vec4::new(0.8, 1., 0., 0.), Vec4::Z, Vec4::W);
+        assert_bounded(&Primitive {
+            world_matrix: sphere.world_matrix * shear,
+            ..sphere
+        });
+    }
+
+    #[test]
+    fn test_bounding_box_of_repetitions() {
+        let sphere = Primitive {
+            dimensional_data: Vec4::new(0.5, 0., 0., 0.),
+            ..Default::default()
+        };
+        assert!(Primitive {
+            repetition: Repetition::Infinite,
+            ..sphere.clone()
+        }
+        .bounding_box()
+        .is_none());
+
+        let bounding_box: BoundingBox = Primitive {
+            repetition: Repetition::Finite,
+            negative_repetitions: UVec3::new(1, 0, 0),
+            positive_repetitions: UVec3::new(2, 0, 1),
+            spacing: Vec3::splat(2.),
+            ..sphere.clone()
+        }
+        .bounding_box()
+        .unwrap();
+        assert!(bounding_box
+            .minimum
+            .cmple(Vec3::new(-2.5, -0.5, -0.5))
+            .all());
+        assert!(bounding_box.maximum.cmpge(Vec3::new(4.5, 0.5, 2.5)).all());
+
+        // Every segment of a polar repetition about the y-axis is within
+        // the ring of the polar radius
+        let bounding_box: BoundingBox = Primitive {
+            repetition: Repetition::Polar,
+            polar_axis: Axis::Y,
+            polar_radius: 2.,
+            ..sphere
+        }
+        .bounding_box()
+        .unwrap();
+        assert!(bounding_box
+            .minimum
+            .cmple(Vec3::new(-2.5, -0.5, -2.5))
+            .all());
+        assert!(bounding_box.maximum.cmpge(Vec3::new(2.5, 0.5, 2.5)).all());
+        assert!(bounding_box.maximum.y < 1.);
+    }
+
+    #[test]
+    fn test_bounding_box_of_displacement() {
+        let sphere = Primitive {
+            dimensional_data: Vec4::new(0.5, 0., 0., 0.),
+            displacement_amplitude: 0.25,
+            displacement_offset: 1.,
+            ..Default::default()
+        };
+
+        // Without a texture the surface is offset by a constant
+        let bounding_box: BoundingBox = sphere.bounding_box().unwrap();
+        assert!(bounding_box.maximum.cmpge(Vec3::splat(1.)).all());
+        assert!(bounding_box.minimum.cmple(Vec3::splat(-1.)).all());
+
+        for displacement_texture in [
+            ProceduralTexture {
+                texture_type: ProceduralTextureType::FBMNoise,
+                ..Default::default()
+            },
+            ProceduralTexture {
+                gain: 10.,
+                texture_type: ProceduralTextureType::Grade,
+                ..Default::default()
+            },
+            ProceduralTexture {
+                use_colour_ramp: true,
+                ..Default::default()
+            },
+        ] {
+            assert!(Primitive {
+                displacement_texture: displacement_texture.clone(),
+                ..sphere.clone()
+            }
+            .bounding_box()
+            .is_none());
+
+            // The texture is unused without an amplitude
+            assert!(Primitive {
+                displacement_texture,
+                displacement_amplitude: 0.,
+                ..sphere.clone()
+            }
+            .bounding_box()
+            .is_some());
+        }
+    }
+
+    #[test]
+    fn test_unbounded_shapes() {
+        for shape in [Shapes::Plane, Shapes::InfiniteCylinder, Shapes::Mandelbulb] {
+            let primitive = Primitive {
+                shape,
+                ..Default::default()
+            };
+            assert!(primitive.bounding_box().is_none(), "{:?} is bounded", shape);
         }
     }
 }
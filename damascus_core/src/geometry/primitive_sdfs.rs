@@ -0,0 +1,724 @@
+// Copyright (c) 2024, Owen Bulka
+// All rights reserved.
+// This source code is licensed under the BSD-style license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! CPU ports of the signed distance functions in `primitive_sdfs.wgsl`,
+//! for work that has to happen before anything reaches the GPU, such as
+//! scattering primitives over a surface. Only the shapes that have a
+//! port are evaluated by `Primitive::signed_distance`.
+
+use glam::{Mat3, Vec2, Vec2Swizzles, Vec3, Vec3Swizzles, Vec4, Vec4Swizzles};
+
+/// The signed length of a vector from a point to the nearest surface.
+fn sdf_length_vec2(vector: Vec2) -> f32 {
+    vector.max(Vec2::ZERO).length() + vector.max_element().min(0.)
+}
+
+/// The signed length of a vector from a point to the nearest surface.
+fn sdf_length_vec3(vector: Vec3) -> f32 {
+    vector.max(Vec3::ZERO).length() + vector.max_element().min(0.)
+}
+
+/// Cylindrical coordinates symmetric about the y-axis, ignoring the angle.
+fn cartesian_to_cylindrical(position: Vec3) -> Vec2 {
+    Vec2::new(position.xz().length(), position.y)
+}
+
+pub fn distance_to_sphere(position: Vec3, radius: f32) -> f32 {
+    position.length() - radius
+}
+
+pub fn distance_to_ellipsoid(position: Vec3, radii: Vec3) -> f32 {
+    let transformed_position: Vec3 = position / radii;
+    let scaled_length: f32 = transformed_position.length();
+    scaled_length * (scaled_length - 1.) / (transformed_position / radii).length()
+}
+
+pub fn distance_to_hollow_sphere(
+    position: Vec3,
+    radius: f32,
+    cut_height: f32,
+    thickness: f32,
+) -> f32 {
+    let half_thickness: f32 = thickness / 2.;
+    let cylindrical_position: Vec2 = cartesian_to_cylindrical(position);
+    let cut_radius: f32 = (radius * radius - cut_height * cut_height).sqrt();
+
+    if cut_height * cylindrical_position.x < cut_radius * cylindrical_position.y {
+        (cylindrical_position - Vec2::new(cut_radius, cut_height)).length() - half_thickness
+    } else {
+        (cylindrical_position.length() - radius).abs() - half_thickness
+    }
+}
+
+pub fn distance_to_rectangular_prism(position: Vec3, width: f32, height: f32, depth: f32) -> f32 {
+    sdf_length_vec3(position.abs() - Vec3::new(width, height, depth) / 2.)
+}
+
+pub fn distance_to_rectangular_prism_frame(
+    position: Vec3,
+    width: f32,
+    height: f32,
+    depth: f32,
+    thickness: f32,
+) -> f32 {
+    let prism_to_position: Vec3 = position.abs() - Vec3::new(width, height, depth) / 2.;
+    let inner_reflected: Vec3 = (prism_to_position + thickness).abs() - thickness;
+
+    sdf_length_vec3(Vec3::new(
+        prism_to_position.x,
+        inner_reflected.y,
+        inner_reflected.z,
+    ))
+    .min(sdf_length_vec3(Vec3::new(
+        inner_reflected.x,
+        prism_to_position.y,
+        inner_reflected.z,
+    )))
+    .min(sdf_length_vec3(Vec3::new(
+        inner_reflected.x,
+        inner_reflected.y,
+        prism_to_position.z,
+    )))
+}
+
+pub fn distance_to_rhombus(
+    position: Vec3,
+    width: f32,
+    height: f32,
+    depth: f32,
+    corner_radius: f32,
+) -> f32 {
+    let abs_position: Vec3 = position.abs();
+    let half_width_height = Vec2::new(width, height) / 2.;
+
+    let s: Vec2 = half_width_height * (half_width_height - 2. * abs_position.xy());
+    let f: f32 = ((s.x - s.y) / half_width_height.length_squared()).clamp(-1., 1.);
+
+    let inside: f32 = (abs_position.xy().dot(half_width_height.yx())
+        - half_width_height.x * half_width_height.y)
+        .signum();
+
+    sdf_length_vec2(Vec2::new(
+        inside * (abs_position.xy() - 0.5 * half_width_height * Vec2::new(1. - f, 1. + f)).length()
+            - corner_radius,
+        abs_position.z - depth / 2.,
+    ))
+}
+
+pub fn distance_to_triangular_prism(position: Vec3, base: f32, depth: f32) -> f32 {
+    (position.z.abs() - depth)
+        .max((position.x.abs() * 0.8660254 + position.y * 0.5).max(-position.y) - 0.28867513 * base)
+}
+
+pub fn distance_to_cylinder(position: Vec3, radius: f32, height: f32) -> f32 {
+    sdf_length_vec2(cartesian_to_cylindrical(position).abs() - Vec2::new(radius, height / 2.))
+}
+
+pub fn distance_to_infinite_cylinder(position: Vec3, radius: f32) -> f32 {
+    position.xz().length() - radius
+}
+
+pub fn distance_to_plane(position: Vec3, normal: Vec3) -> f32 {
+    position.dot(normal)
+}
+
+pub fn distance_to_capsule(
+    position: Vec3,
+    radius: f32,
+    negative_height: f32,
+    positive_height: f32,
+) -> f32 {
+    Vec3::new(
+        position.x,
+        position.y - position.y.clamp(-negative_height, positive_height),
+        position.z,
+    )
+    .length()
+        - radius
+}
+
+pub fn distance_to_torus(position: Vec3, ring_radius: f32, tube_radius: f32) -> f32 {
+    Vec2::new(position.xy().length() - ring_radius, position.z).length() - tube_radius
+}
+
+pub fn distance_to_capped_torus(
+    position: Vec3,
+    ring_radius: f32,
+    tube_radius: f32,
+    cap_angle: f32,
+) -> f32 {
+    let cap_direction = Vec2::new(cap_angle.sin(), cap_angle.cos());
+    let abs_x_position = Vec3::new(position.x.abs(), position.y, position.z);
+
+    let cap_factor: f32 = if cap_direction.y * abs_x_position.x > cap_direction.x * abs_x_position.y
+    {
+        abs_x_position.xy().dot(cap_direction)
+    } else {
+        abs_x_position.xy().length()
+    };
+
+    (abs_x_position.length_squared() + ring_radius * ring_radius - 2. * ring_radius * cap_factor)
+        .sqrt()
+        - tube_radius
+}
+
+pub fn distance_to_link(position: Vec3, ring_radius: f32, tube_radius: f32, height: f32) -> f32 {
+    let height_difference: f32 = position.y.abs() - height / 2.;
+    let distance_in_xy_plane: f32 =
+        Vec2::new(position.x, height_difference.max(0.)).length() - ring_radius;
+    Vec2::new(distance_in_xy_plane, position.z).length() - tube_radius
+}
+
+pub fn distance_to_hexagonal_prism(position: Vec3, height: f32, depth: f32) -> f32 {
+    let cos_sin_tan = Vec3::new(-0.8660254, 0.5, 0.57735027);
+    let half_height: f32 = height / 2.;
+
+    let mut abs_position: Vec3 = position.abs();
+    let fold: Vec2 = 2. * cos_sin_tan.xy() * -cos_sin_tan.xy().dot(abs_position.xy()).min(0.);
+    abs_position += Vec3::from((fold, 0.));
+
+    sdf_length_vec2(Vec2::new(
+        (abs_position.y - half_height).signum()
+            * (abs_position.xy()
+                - Vec2::new(
+                    abs_position
+                        .x
+                        .clamp(-cos_sin_tan.z * half_height, cos_sin_tan.z * half_height),
+                    half_height,
+                ))
+            .length(),
+        abs_position.z - depth / 2.,
+    ))
+}
+
+pub fn distance_to_octahedron(position: Vec3, radial_extent: f32) -> f32 {
+    let abs_position: Vec3 = position.abs();
+    let position_sum_to_extent: f32 = abs_position.element_sum() - radial_extent;
+
+    let three_position: Vec3 = 3. * abs_position;
+    let change_of_axes: Vec3 = if three_position.x < position_sum_to_extent {
+        abs_position
+    } else if three_position.y < position_sum_to_extent {
+        abs_position.yzx()
+    } else if three_position.z < position_sum_to_extent {
+        abs_position.zxy()
+    } else {
+        return position_sum_to_extent * 0.57735027;
+    };
+
+    let surface: f32 =
+        (0.5 * (change_of_axes.z - change_of_axes.y + radial_extent)).clamp(0., radial_extent);
+
+    Vec3::new(
+        change_of_axes.x,
+        change_of_axes.y - radial_extent + surface,
+        change_of_axes.z - surface,
+    )
+    .length()
+}
+
+pub fn distance_to_mandelbulb(
+    position: Vec3,
+    power: f32,
+    iterations: u32,
+    max_square_radius: f32,
+) -> f32 {
+    let mut current_position: Vec3 = position;
+    let mut radius_squared: f32 = current_position.length_squared();
+
+    let mut dradius: f32 = 1.;
+    for _ in 0..iterations.max(1) {
+        dradius = power * radius_squared.powf((power - 1.) / 2.) * dradius + 1.;
+
+        let current_radius: f32 = current_position.length();
+        let theta: f32 = power * (current_position.z / current_radius).acos();
+        let phi: f32 = power * current_position.y.atan2(current_position.x);
+
+        current_position = position
+            + current_radius.powf(power)
+                * Vec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                );
+
+        radius_squared = current_position.length_squared();
+        if radius_squared > max_square_radius {
+            break;
+        }
+    }
+
+    0.25 * radius_squared.ln() * radius_squared.sqrt() / dradius
+}
+
+pub fn distance_to_mandelbox(
+    position: Vec3,
+    scale: f32,
+    iterations: i32,
+    min_square_radius: f32,
+    folding_limit: f32,
+) -> f32 {
+    let scale_vector = Vec4::new(scale, scale, scale, scale.abs()) / min_square_radius;
+    let initial_position = Vec4::from((position, 1.));
+    let mut current_position: Vec4 = initial_position;
+
+    for _ in 0..iterations {
+        let folded_position: Vec3 = current_position
+            .xyz()
+            .clamp(Vec3::splat(-folding_limit), Vec3::splat(folding_limit))
+            * 2.
+            - current_position.xyz();
+
+        let radius_squared: f32 = folded_position.length_squared();
+        current_position = Vec4::from((folded_position, current_position.w))
+            * (min_square_radius / radius_squared)
+                .max(min_square_radius)
+                .clamp(0., 1.);
+
+        current_position = scale_vector * current_position + initial_position;
+    }
+
+    (current_position.xyz() - (scale - 1.).abs()).length() / current_position.w
+        - scale.abs().powf((1 - iterations) as f32)
+}
+
+pub fn distance_to_menger_sponge(position: Vec3, radial_extent: f32, iterations: u32) -> f32 {
+    let current_position: Vec3 = position / radial_extent;
+    let mut distance: f32 = distance_to_rectangular_prism(current_position, 2., 2., 2.);
+
+    let mut scale: f32 = 1.;
+    for _ in 0..iterations {
+        let scaled_position: Vec3 = current_position * scale;
+        let repeated_position: Vec3 = scaled_position - 2. * (scaled_position / 2.).floor() - 1.;
+        scale *= 3.;
+
+        let cross_position: Vec3 = (1. - 3. * repeated_position.abs()).abs();
+        let cross_distance: f32 = (cross_position
+            .x
+            .max(cross_position.y)
+            .min(cross_position.y.max(cross_position.z))
+            .min(cross_position.z.max(cross_position.x))
+            - 1.)
+            / scale;
+        distance = distance.max(cross_distance);
+    }
+
+    distance * radial_extent
+}
+
+pub fn distance_to_sierpinski_octahedron(
+    position: Vec3,
+    radial_extent: f32,
+    iterations: u32,
+) -> f32 {
+    let mut current_position: Vec3 = position / radial_extent;
+
+    let mut scale: f32 = 1.;
+    for _ in 0..iterations {
+        if current_position.x + current_position.y < 0. {
+            current_position =
+                Vec3::new(-current_position.y, -current_position.x, current_position.z);
+        }
+        if current_position.x + current_position.z < 0. {
+            current_position =
+                Vec3::new(-current_position.z, current_position.y, -current_position.x);
+        }
+        if current_position.x - current_position.y < 0. {
+            current_position = current_position.yxz();
+        }
+        if current_position.x - current_position.z < 0. {
+            current_position = current_position.zyx();
+        }
+        current_position = 2. * current_position - Vec3::X;
+        scale *= 2.;
+    }
+
+    distance_to_octahedron(current_position, 1.) * radial_extent / scale
+}
+
+pub fn distance_to_sierpinski_tetrahedron(
+    position: Vec3,
+    radial_extent: f32,
+    iterations: u32,
+) -> f32 {
+    let mut current_position: Vec3 = position / radial_extent;
+
+    let mut scale: f32 = 1.;
+    for _ in 0..iterations {
+        if current_position.x + current_position.y < 0. {
+            current_position =
+                Vec3::new(-current_position.y, -current_position.x, current_position.z);
+        }
+        if current_position.x + current_position.z < 0. {
+            current_position =
+                Vec3::new(-current_position.z, current_position.y, -current_position.x);
+        }
+        if current_position.y + current_position.z < 0. {
+            current_position =
+                Vec3::new(current_position.x, -current_position.z, -current_position.y);
+        }
+        current_position = 2. * current_position - 1.;
+        scale *= 2.;
+    }
+
+    let tetrahedron_distance: f32 =
+        ((-current_position.x - current_position.y - current_position.z)
+            .max(current_position.x + current_position.y - current_position.z)
+            .max(
+                (-current_position.x + current_position.y + current_position.z)
+                    .max(current_position.x - current_position.y + current_position.z),
+            )
+            - 1.)
+            * 0.57735027;
+
+    tetrahedron_distance * radial_extent / scale
+}
+
+pub fn distance_to_kaleidoscopic_ifs(
+    position: Vec3,
+    radial_extent: f32,
+    scale: f32,
+    iterations: u32,
+    fold_planes: Mat3,
+    rotation: Mat3,
+    offset: Vec3,
+) -> f32 {
+    let mut current_position: Vec3 = position / radial_extent;
+
+    let mut total_scale: f32 = 1.;
+    for _ in 0..iterations {
+        for plane in 0..3 {
+            let fold_plane: Vec3 = fold_planes.col(plane);
+            current_position -= 2. * current_position.dot(fold_plane).min(0.) * fold_plane;
+        }
+        current_position = rotation * current_position;
+        current_position = scale * current_position - offset * (scale - 1.);
+        total_scale *= scale.abs();
+    }
+
+    distance_to_rectangular_prism(current_position, 2., 2., 2.) * radial_extent / total_scale
+}
+
+fn square_quaternion(quaternion: Vec4) -> Vec4 {
+    Vec4::from((
+        quaternion.x * quaternion.x - quaternion.yzw().length_squared(),
+        2. * quaternion.x * quaternion.yzw(),
+    ))
+}
+
+pub fn distance_to_quaternion_julia(
+    position: Vec3,
+    julia_constant: Vec4,
+    iterations: u32,
+    escape_radius: f32,
+) -> f32 {
+    let mut current_position = Vec4::from((position, 0.));
+    let mut square_radius: f32 = current_position.length_squared();
+    let square_escape_radius: f32 = escape_radius * escape_radius;
+    let mut square_derivative: f32 = 1.;
+
+    for _ in 0..iterations {
+        square_derivative *= 4. * square_radius;
+        current_position = square_quaternion(current_position) + julia_constant;
+        square_radius = current_position.length_squared();
+
+        if square_radius > square_escape_radius {
+            break;
+        }
+    }
+
+    0.25 * (square_radius / square_derivative).sqrt() * square_radius.ln()
+}
+
+fn box_fold(position: Vec3, folding_limit: Vec3) -> Vec3 {
+    position.clamp(-folding_limit, folding_limit) * 2. - position
+}
+
+fn sphere_fold(position: Vec4, radius_squared: f32, min_square_radius: f32) -> Vec4 {
+    position
+        * (min_square_radius / radius_squared)
+            .max(min_square_radius)
+            .clamp(0., 1.)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn distance_to_hybrid_fractal(
+    position: Vec3,
+    scale: f32,
+    iterations: u32,
+    min_square_radius: f32,
+    folding_limit: f32,
+    power: f32,
+    max_square_radius: f32,
+    box_folds_per_cycle: u32,
+    bulb_iterations_per_cycle: u32,
+) -> f32 {
+    let scale_vector = Vec4::new(scale, scale, scale, scale.abs()) / min_square_radius;
+    let cycle_length: u32 = (box_folds_per_cycle + bulb_iterations_per_cycle).max(1);
+
+    // The derivative is tracked in the w component
+    let mut current_position = Vec4::from((position, 1.));
+    let mut square_radius: f32 = position.length_squared();
+
+    for iteration in 0..iterations {
+        if iteration % cycle_length < box_folds_per_cycle {
+            let folded_position: Vec3 =
+                box_fold(current_position.xyz(), Vec3::splat(folding_limit));
+            current_position = sphere_fold(
+                Vec4::from((folded_position, current_position.w)),
+                folded_position.length_squared(),
+                min_square_radius,
+            );
+            current_position = scale_vector * current_position + Vec4::from((position, 1.));
+            square_radius = current_position.xyz().length_squared();
+        } else {
+            let current_radius: f32 = square_radius.sqrt();
+            let derivative: f32 = power * current_radius.powf(power - 1.) * current_position.w + 1.;
+
+            let theta: f32 = power * (current_position.z / current_radius).acos();
+            let phi: f32 = power * current_position.y.atan2(current_position.x);
+            current_position = Vec4::from((
+                position
+                    + current_radius.powf(power)
+                        * Vec3::new(
+                            theta.sin() * phi.cos(),
+                            theta.sin() * phi.sin(),
+                            theta.cos(),
+                        ),
+                derivative,
+            ));
+            square_radius = current_position.xyz().length_squared();
+
+            if square_radius > max_square_radius {
+                break;
+            }
+        }
+    }
+
+    let derivative: f32 = current_position.w.abs();
+    if bulb_iterations_per_cycle == 0 || square_radius < 1. {
+        return square_radius.sqrt() / derivative;
+    }
+    0.25 * square_radius.ln() * square_radius.sqrt() / derivative
+}
+
+/// The conservative distance to the sheet of a triply periodic minimal
+/// surface, from the value of its implicit function.
+fn triply_periodic_sheet_distance(
+    implicit_value: f32,
+    frequency: f32,
+    gradient_bound: f32,
+    thickness: f32,
+) -> f32 {
+    implicit_value.abs() / (frequency * gradient_bound) - thickness / 2.
+}
+
+pub fn distance_to_gyroid(position: Vec3, period: f32, thickness: f32) -> f32 {
+    let frequency: f32 = std::f32::consts::TAU / period;
+    let scaled_position: Vec3 = frequency * position;
+    let sine = Vec3::new(
+        scaled_position.x.sin(),
+        scaled_position.y.sin(),
+        scaled_position.z.sin(),
+    );
+    let cosine = Vec3::new(
+        scaled_position.y.cos(),
+        scaled_position.z.cos(),
+        scaled_position.x.cos(),
+    );
+    triply_periodic_sheet_distance(sine.dot(cosine), frequency, 1.7320508, thickness)
+}
+
+pub fn distance_to_schwarz_p(position: Vec3, period: f32, thickness: f32) -> f32 {
+    let frequency: f32 = std::f32::consts::TAU / period;
+    let scaled_position: Vec3 = frequency * position;
+    triply_periodic_sheet_distance(
+        scaled_position.x.cos() + scaled_position.y.cos() + scaled_position.z.cos(),
+        frequency,
+        1.7320508,
+        thickness,
+    )
+}
+
+pub fn distance_to_schwarz_d(position: Vec3, period: f32, thickness: f32) -> f32 {
+    let frequency: f32 = std::f32::consts::TAU / period;
+    let scaled_position: Vec3 = frequency * position;
+    let (sine_x, cosine_x) = scaled_position.x.sin_cos();
+    let (sine_y, cosine_y) = scaled_position.y.sin_cos();
+    let (sine_z, cosine_z) = scaled_position.z.sin_cos();
+    triply_periodic_sheet_distance(
+        sine_x * sine_y * sine_z
+            + sine_x * cosine_y * cosine_z
+            + cosine_x * sine_y * cosine_z
+            + cosine_x * cosine_y * sine_z,
+        frequency,
+        1.7320508,
+        thickness,
+    )
+}
+
+pub fn distance_to_neovius(position: Vec3, period: f32, thickness: f32) -> f32 {
+    let frequency: f32 = std::f32::consts::TAU / period;
+    let scaled_position: Vec3 = frequency * position;
+    let cosine = Vec3::new(
+        scaled_position.x.cos(),
+        scaled_position.y.cos(),
+        scaled_position.z.cos(),
+    );
+    triply_periodic_sheet_distance(
+        3. * cosine.element_sum() + 4. * cosine.x * cosine.y * cosine.z,
+        frequency,
+        7.,
+        thickness,
+    )
+}
+
+pub fn distance_to_superellipsoid(
+    position: Vec3,
+    radii: Vec3,
+    east_west_exponent: f32,
+    north_south_exponent: f32,
+) -> f32 {
+    let east_west: f32 = east_west_exponent.max(0.01);
+    let north_south: f32 = north_south_exponent.max(0.01);
+
+    let scaled_position: Vec3 = (position / radii).abs().max(Vec3::splat(1e-6));
+
+    let horizontal = Vec2::new(
+        scaled_position.x.powf(2. / east_west),
+        scaled_position.z.powf(2. / east_west),
+    );
+    let horizontal_sum: f32 = horizontal.x + horizontal.y;
+    let inside_outside: f32 =
+        horizontal_sum.powf(east_west / north_south) + scaled_position.y.powf(2. / north_south);
+    let radial_value: f32 = inside_outside.powf(north_south / 2.);
+
+    let common_factor: f32 = inside_outside.powf(north_south / 2. - 1.);
+    let horizontal_factor: f32 = common_factor * horizontal_sum.powf(east_west / north_south - 1.);
+    let gradient = Vec3::new(
+        horizontal_factor * scaled_position.x.powf(2. / east_west - 1.) / radii.x,
+        common_factor * scaled_position.y.powf(2. / north_south - 1.) / radii.y,
+        horizontal_factor * scaled_position.z.powf(2. / east_west - 1.) / radii.z,
+    );
+
+    (radial_value - 1.) / gradient.length().max(1e-6)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-6;
+
+    #[test]
+    fn test_distance_to_sphere() {
+        assert!((distance_to_sphere(Vec3::ZERO, 0.5) + 0.5).abs() < EPSILON);
+        assert!(distance_to_sphere(Vec3::new(0., 0.5, 0.), 0.5).abs() < EPSILON);
+        assert!((distance_to_sphere(Vec3::new(3., 4., 0.), 2.) - 3.).abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_distance_to_rectangular_prism() {
+        // Inside, the distance is to the nearest face
+        assert!((distance_to_rectangular_prism(Vec3::ZERO, 2., 4., 6.) + 1.).abs() < EPSILON);
+        // Outside a face
+        assert!(
+            (distance_to_rectangular_prism(Vec3::new(0., 3., 0.), 2., 4., 6.) - 1.).abs() < EPSILON
+        );
+        // Outside a corner, the distance is to the corner
+        assert!(
+            (distance_to_rectangular_prism(Vec3::splat(2.), 2., 2., 2.) - 3_f32.sqrt()).abs()
+                < EPSILON
+        );
+    }
+
+    #[test]
+    fn test_distance_to_cylinder() {
+        assert!((distance_to_cylinder(Vec3::new(2., 0., 0.), 1., 2.) - 1.).abs() < EPSILON);
+        assert!((distance_to_cylinder(Vec3::new(0., 3., 0.), 1., 2.) - 2.).abs() < EPSILON);
+        assert!((distance_to_cylinder(Vec3::ZERO, 1., 4.) + 1.).abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_distance_to_torus() {
+        // The torus lies in the xy-plane
+        assert!(distance_to_torus(Vec3::new(1.25, 0., 0.), 1., 0.25).abs() < EPSILON);
+        assert!((distance_to_torus(Vec3::ZERO, 1., 0.25) - 0.75).abs() < EPSILON);
+        assert!((distance_to_torus(Vec3::new(0., 1., 0.5), 1., 0.25) - 0.25).abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_distance_to_sierpinski_fractals() {
+        // The vertices of the first shape are kept by every iteration
+        for iterations in 0..4 {
+            assert!(
+                distance_to_sierpinski_octahedron(Vec3::new(0., 2., 0.), 2., iterations).abs()
+                    < 1e-5
+            );
+            assert!(
+                distance_to_sierpinski_tetrahedron(Vec3::splat(2.), 2., iterations).abs() < 1e-5
+            );
+        }
+        assert!(distance_to_sierpinski_octahedron(Vec3::new(0., 3., 0.), 2., 3) > 0.);
+        assert!(distance_to_sierpinski_tetrahedron(Vec3::splat(-3.), 2., 3) > 0.);
+    }
+
+    #[test]
+    fn test_distance_to_kaleidoscopic_ifs() {
+        // Without iterations it is a cube
+        let distance: f32 = distance_to_kaleidoscopic_ifs(
+            Vec3::new(3., 0., 0.),
+            2.,
+            2.,
+            0,
+            Mat3::IDENTITY,
+            Mat3::IDENTITY,
+            Vec3::ONE,
+        );
+        assert!((distance - 1.).abs() < EPSILON);
+
+        // Folding across the planes through the origin makes it symmetric
+        let distance_to_fractal = |position: Vec3| {
+            distance_to_kaleidoscopic_ifs(
+                position,
+                1.,
+                2.,
+                4,
+                Mat3::IDENTITY,
+                Mat3::IDENTITY,
+                Vec3::ONE,
+            )
+        };
+        let position = Vec3::new(0.3, 0.7, 1.1);
+        assert!((distance_to_fractal(position) - distance_to_fractal(-position)).abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_distance_to_quaternion_julia() {
+        // With no constant the julia set is the unit ball
+        let far: f32 = distance_to_quaternion_julia(Vec3::new(2., 0., 0.), Vec4::ZERO, 16, 4.);
+        let near: f32 = distance_to_quaternion_julia(Vec3::new(0., 1.1, 0.), Vec4::ZERO, 16, 4.);
+        assert!(near > 0.);
+        assert!(far > near);
+    }
+
+    #[test]
+    fn test_distance_to_hybrid_fractal() {
+        // Without box folds it is a mandelbulb
+        let position = Vec3::new(1.5, 0.25, -0.5);
+        assert!(
+            (distance_to_hybrid_fractal(position, 2., 8, 0.25, 1., 8., 4., 0, 1)
+                - distance_to_mandelbulb(position, 8., 8, 4.))
+            .abs()
+                < EPSILON
+        );
+    }
+
+    #[test]
+    fn test_distance_to_plane() {
+        assert!((distance_to_plane(Vec3::new(1., 2., 3.), Vec3::Y) - 2.).abs() < EPSILON);
+        assert!((distance_to_plane(Vec3::new(1., -2., 3.), Vec3::Y) + 2.).abs() < EPSILON);
+    }
+}
@@ -0,0 +1,255 @@
+// Copyright (c) 2024, Owen Bulka
+// All rights reserved.
+// This source code is licensed under the BSD-style license found in the
+// LICENSE file in the root directory of this source tree.
+
+use glam::{EulerRot, Mat4, Quat, Vec2, Vec3};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use super::primitive::Primitive;
+
+/// The number of candidate points tried for each instance before
+/// giving up on finding one that is far enough from the others.
+const ATTEMPTS_PER_INSTANCE: u32 = 30;
+
+/// The maximum number of steps taken to project a candidate point onto
+/// the surface of the target.
+const MAX_PROJECTION_STEPS: u32 = 64;
+
+#[derive(Debug, Copy, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Scatter {
+    pub count: u32,
+    pub seed: u32,
+    pub minimum_spacing: f32,
+    pub search_radius: f32,
+    pub align_to_normal: bool,
+    pub scale_range: Vec2,
+    pub rotation_range: Vec3,
+}
+
+impl Default for Scatter {
+    fn default() -> Self {
+        Self {
+            count: 16,
+            seed: 0,
+            minimum_spacing: 0.1,
+            search_radius: 2.,
+            align_to_normal: true,
+            scale_range: Vec2::ONE,
+            rotation_range: Vec3::ZERO,
+        }
+    }
+}
+
+impl Scatter {
+    /// Find points on the surface of the target, at least the minimum
+    /// spacing apart (Poisson disk sampling by dart throwing), along with
+    /// the surface normals at those points. Candidates are drawn from
+    /// the cube of half width `search_radius` centred on the target, and
+    /// projected onto its zero level set with Newton steps.
+    pub fn surface_points(&self, target: &Primitive) -> Option<Vec<(Vec3, Vec3)>> {
+        let mut rng = StdRng::seed_from_u64(self.seed as u64);
+        let centre: Vec3 = target.world_matrix.w_axis.truncate();
+        let tolerance: f32 = 1e-4 * self.search_radius.max(1.);
+
+        let mut points: Vec<(Vec3, Vec3)> = vec![];
+        for _ in 0..self.count * ATTEMPTS_PER_INSTANCE {
+            if points.len() >= self.count as usize {
+                break;
+            }
+
+            let mut candidate: Vec3 = centre
+                + self.search_radius
+                    * Vec3::new(
+                        rng.random_range(-1.0..=1.),
+                        rng.random_range(-1.0..=1.),
+                        rng.random_range(-1.0..=1.),
+                    );
+            let mut on_surface = false;
+            for _ in 0..MAX_PROJECTION_STEPS {
+                let distance: f32 = target.signed_distance(candidate)?;
+                if distance.abs() < tolerance {
+                    on_surface = true;
+                    break;
+                }
+                candidate -= distance * target.normal(candidate, tolerance)?;
+            }
+
+            if !on_surface
+                || (candidate - centre).abs().max_element() > self.search_radius
+                || points.iter().any(|(point, _)| {
+                    point.distance_squared(candidate) < self.minimum_spacing.powi(2)
+                })
+            {
+                continue;
+            }
+            points.push((candidate, target.normal(candidate, tolerance)?));
+        }
+
+        Some(points)
+    }
+
+    /// Place a copy of the instanced primitives, and their descendants,
+    /// at each point scattered over the surface of the target. Each copy
+    /// is randomly scaled and rotated within the ranges, with the rotation
+    /// in degrees, and optionally has its y-axis aligned to the normal.
+    /// Returns `None` if the target's shape cannot be evaluated on the
+    /// CPU.
+    pub fn scatter(&self, target: &Primitive, instances: &[Primitive]) -> Option<Vec<Primitive>> {
+        let mut rng = StdRng::seed_from_u64(self.seed as u64 ^ 0x5ca7_7e12);
+        let minimum_scale: f32 = self.scale_range.min_element();
+        let maximum_scale: f32 = self.scale_range.max_element();
+
+        let mut scattered: Vec<Primitive> = vec![];
+        for (point, normal) in self.surface_points(target)? {
+            let alignment: Quat = if self.align_to_normal {
+                Quat::from_rotation_arc(Vec3::Y, normal)
+            } else {
+                Quat::IDENTITY
+            };
+            let angles: Vec3 = self.rotation_range * std::f32::consts::PI / 180.
+                * Vec3::new(
+                    rng.random_range(-1.0..=1.),
+                    rng.random_range(-1.0..=1.),
+                    rng.random_range(-1.0..=1.),
+                );
+            let scale: f32 = minimum_scale + (maximum_scale - minimum_scale) * rng.random::<f32>();
+
+            let instance_matrix = Mat4::from_scale_rotation_translation(
+                Vec3::splat(scale),
+                alignment * Quat::from_euler(EulerRot::XYZ, angles.x, angles.y, angles.z),
+                point,
+            );
+            for instance in instances {
+                let mut instance = instance.clone();
+                instance.world_matrix = instance_matrix * instance.world_matrix;
+                scattered.push(instance);
+            }
+        }
+
+        Some(scattered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::primitive::Shapes;
+
+    fn sphere(radius: f32, centre: Vec3) -> Primitive {
+        Primitive {
+            shape: Shapes::Sphere,
+            world_matrix: Mat4::from_translation(centre),
+            dimensional_data: radius * glam::Vec4::X,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_surface_points_lie_on_surface() {
+        let centre = Vec3::new(1., -2., 0.5);
+        let target = sphere(0.75, centre);
+        let scatter = Scatter {
+            count: 32,
+            minimum_spacing: 0.05,
+            ..Default::default()
+        };
+
+        let points = scatter.surface_points(&target).unwrap();
+        assert!(!points.is_empty());
+        for (point, normal) in points.iter() {
+            assert!(((*point - centre).length() - 0.75).abs() < 1e-3);
+            assert!(normal.abs_diff_eq((*point - centre).normalize(), 1e-2));
+        }
+    }
+
+    #[test]
+    fn test_surface_points_are_spaced() {
+        let scatter = Scatter {
+            count: 64,
+            minimum_spacing: 0.2,
+            ..Default::default()
+        };
+        let points = scatter.surface_points(&sphere(1., Vec3::ZERO)).unwrap();
+        for (index, (point, _)) in points.iter().enumerate() {
+            for (other, _) in points.iter().skip(index + 1) {
+                assert!(point.distance(*other) >= 0.2);
+            }
+        }
+    }
+
+    #[test]
+    fn test_zero_count() {
+        let scatter = Scatter {
+            count: 0,
+            ..Default::default()
+        };
+        let target = sphere(1., Vec3::ZERO);
+        assert!(scatter.surface_points(&target).unwrap().is_empty());
+        assert!(scatter
+            .scatter(&target, &[Primitive::default()])
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_seed_is_deterministic() {
+        let target = sphere(1., Vec3::ZERO);
+        let instances = [Primitive::default()];
+        let scatter = Scatter {
+            seed: 7,
+            scale_range: glam::Vec2::new(0.5, 2.),
+            rotation_range: Vec3::splat(180.),
+            ..Default::default()
+        };
+
+        let first = scatter.scatter(&target, &instances).unwrap();
+        let second = scatter.scatter(&target, &instances).unwrap();
+        assert_eq!(first.len(), scatter.count as usize);
+        assert_eq!(
+            first
+                .iter()
+                .map(|primitive| primitive.world_matrix)
+                .collect::<Vec<Mat4>>(),
+            second
+                .iter()
+                .map(|primitive| primitive.world_matrix)
+                .collect::<Vec<Mat4>>(),
+        );
+
+        let reseeded = Scatter { seed: 8, ..scatter }
+            .scatter(&target, &instances)
+            .unwrap();
+        assert_ne!(first[0].world_matrix, reseeded[0].world_matrix);
+    }
+
+    #[test]
+    fn test_scatter_over_fractal() {
+        let target = Primitive {
+            shape: Shapes::SierpinskiTetrahedron,
+            dimensional_data: glam::Vec4::new(1., 3., 0., 0.),
+            ..Default::default()
+        };
+        let scatter = Scatter {
+            minimum_spacing: 0.05,
+            search_radius: 1.,
+            ..Default::default()
+        };
+
+        let points = scatter.surface_points(&target).unwrap();
+        assert!(!points.is_empty());
+        for (point, _) in points.iter() {
+            assert!(target.signed_distance(*point).unwrap().abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_target_without_cpu_distance() {
+        let target = Primitive {
+            shape: Shapes::DeathStar,
+            ..Default::default()
+        };
+        assert!(Scatter::default().surface_points(&target).is_none());
+    }
+}
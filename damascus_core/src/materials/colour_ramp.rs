@@ -0,0 +1,319 @@
+// Copyright (c) 2024, Owen Bulka
+// All rights reserved.
+// This source code is licensed under the BSD-style license found in the
+// LICENSE file in the root directory of this source tree.
+
+use crevice::std430::AsStd430;
+use glam::{Mat3, Vec3};
+use strum::{Display, EnumIter, EnumString};
+
+use crate::DualDevice;
+
+// The published OKLab matrices, kept at full precision
+#[allow(clippy::excessive_precision)]
+const LINEAR_SRGB_TO_LMS: Mat3 = Mat3::from_cols_array(&[
+    0.4122214708,
+    0.2119034982,
+    0.0883024619,
+    0.5363325363,
+    0.6806995451,
+    0.2817188376,
+    0.0514459929,
+    0.1073969566,
+    0.6299787005,
+]);
+#[allow(clippy::excessive_precision)]
+const LMS_TO_OKLAB: Mat3 = Mat3::from_cols_array(&[
+    0.2104542553,
+    1.9779984951,
+    0.0259040371,
+    0.7936177850,
+    -2.4285922050,
+    0.7827717662,
+    -0.0040720468,
+    0.4505937099,
+    -0.8086757660,
+]);
+#[allow(clippy::excessive_precision)]
+const OKLAB_TO_LMS: Mat3 = Mat3::from_cols_array(&[
+    1.,
+    1.,
+    1.,
+    0.3963377774,
+    -0.1055613458,
+    -0.0894841775,
+    0.2158037573,
+    -0.0638541728,
+    -1.2914855480,
+]);
+#[allow(clippy::excessive_precision)]
+const LMS_TO_LINEAR_SRGB: Mat3 = Mat3::from_cols_array(&[
+    4.0767416621,
+    -1.2684380046,
+    -0.0041960863,
+    -3.3077115913,
+    2.6097574011,
+    -0.7034186147,
+    0.2309699292,
+    -0.3413193965,
+    1.7076147010,
+]);
+
+fn linear_srgb_to_oklab(colour: Vec3) -> Vec3 {
+    let lms: Vec3 = LINEAR_SRGB_TO_LMS * colour;
+    LMS_TO_OKLAB * Vec3::new(lms.x.cbrt(), lms.y.cbrt(), lms.z.cbrt())
+}
+
+fn oklab_to_linear_srgb(colour: Vec3) -> Vec3 {
+    let lms: Vec3 = OKLAB_TO_LMS * colour;
+    LMS_TO_LINEAR_SRGB * (lms * lms * lms)
+}
+
+#[derive(
+    Debug,
+    Default,
+    Display,
+    Copy,
+    Clone,
+    EnumIter,
+    EnumString,
+    PartialEq,
+    PartialOrd,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum RampInterpolation {
+    Constant,
+    #[default]
+    Linear,
+    Smoothstep,
+    OKLab,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, AsStd430)]
+pub struct GPUColourStop {
+    colour: Vec3,
+    position: f32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ColourStop {
+    pub position: f32,
+    pub colour: Vec3,
+}
+
+impl Default for ColourStop {
+    fn default() -> Self {
+        Self {
+            position: 0.,
+            colour: Vec3::ZERO,
+        }
+    }
+}
+
+impl ColourStop {
+    pub fn new(position: f32, colour: Vec3) -> Self {
+        Self { position, colour }
+    }
+}
+
+impl DualDevice<GPUColourStop, Std430GPUColourStop> for ColourStop {
+    fn to_gpu(&self) -> GPUColourStop {
+        GPUColourStop {
+            colour: self.colour,
+            position: self.position,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ColourRamp {
+    pub stops: Vec<ColourStop>,
+    pub interpolation: RampInterpolation,
+}
+
+impl Default for ColourRamp {
+    fn default() -> Self {
+        Self {
+            stops: vec![
+                ColourStop::new(0., Vec3::ZERO),
+                ColourStop::new(1., Vec3::ONE),
+            ],
+            interpolation: RampInterpolation::Linear,
+        }
+    }
+}
+
+impl ColourRamp {
+    /// The stops ordered by their position along the ramp.
+    pub fn sorted_stops(&self) -> Vec<ColourStop> {
+        let mut stops: Vec<ColourStop> = self.stops.clone();
+        stops.sort_by(|stop, other| stop.position.total_cmp(&other.position));
+        stops
+    }
+
+    /// Get the colour of the ramp at a position. Positions outside the
+    /// stops take the colour of the nearest stop. This mirrors
+    /// `sample_colour_ramp` in the shaders, so that the gradient the UI
+    /// previews, and the colour of the stops it inserts, match what is
+    /// rendered.
+    pub fn colour_at(&self, position: f32) -> Vec3 {
+        let stops: Vec<ColourStop> = self.sorted_stops();
+        if stops.is_empty() {
+            return Vec3::splat(position);
+        }
+        if position <= stops[0].position {
+            return stops[0].colour;
+        }
+
+        for (lower, upper) in stops.iter().zip(stops.iter().skip(1)) {
+            if position >= upper.position {
+                continue;
+            }
+            let weight: f32 =
+                (position - lower.position) / (upper.position - lower.position).max(1e-6);
+            return match self.interpolation {
+                RampInterpolation::Constant => lower.colour,
+                RampInterpolation::Linear => lower.colour.lerp(upper.colour, weight),
+                RampInterpolation::Smoothstep => lower
+                    .colour
+                    .lerp(upper.colour, weight * weight * (3. - 2. * weight)),
+                RampInterpolation::OKLab => oklab_to_linear_srgb(
+                    linear_srgb_to_oklab(lower.colour)
+                        .lerp(linear_srgb_to_oklab(upper.colour), weight),
+                ),
+            };
+        }
+
+        stops[stops.len() - 1].colour
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn black_to_white(interpolation: RampInterpolation) -> ColourRamp {
+        ColourRamp {
+            stops: vec![
+                ColourStop::new(0.2, Vec3::ZERO),
+                ColourStop::new(0.6, Vec3::ONE),
+            ],
+            interpolation,
+        }
+    }
+
+    #[test]
+    fn test_sorted_stops() {
+        let ramp = ColourRamp {
+            stops: vec![
+                ColourStop::new(1., Vec3::X),
+                ColourStop::new(0., Vec3::Y),
+                ColourStop::new(0.5, Vec3::Z),
+            ],
+            ..Default::default()
+        };
+        let positions: Vec<f32> = ramp
+            .sorted_stops()
+            .iter()
+            .map(|stop| stop.position)
+            .collect();
+        assert_eq!(positions, vec![0., 0.5, 1.]);
+
+        // Unsorted stops sample the same as sorted ones
+        assert_eq!(ramp.colour_at(0.25), Vec3::new(0., 0.5, 0.5));
+        assert_eq!(ramp.colour_at(0.75), Vec3::new(0.5, 0., 0.5));
+    }
+
+    #[test]
+    fn test_sampling_outside_stops() {
+        for interpolation in [
+            RampInterpolation::Constant,
+            RampInterpolation::Linear,
+            RampInterpolation::Smoothstep,
+            RampInterpolation::OKLab,
+        ] {
+            let ramp = black_to_white(interpolation);
+            assert_eq!(ramp.colour_at(-1.), Vec3::ZERO);
+            assert_eq!(ramp.colour_at(0.2), Vec3::ZERO);
+            assert_eq!(ramp.colour_at(0.6), Vec3::ONE);
+            assert_eq!(ramp.colour_at(2.), Vec3::ONE);
+        }
+    }
+
+    #[test]
+    fn test_coincident_stops() {
+        let ramp = ColourRamp {
+            stops: vec![
+                ColourStop::new(0., Vec3::ZERO),
+                ColourStop::new(0.5, Vec3::X),
+                ColourStop::new(0.5, Vec3::Y),
+                ColourStop::new(1., Vec3::ONE),
+            ],
+            ..Default::default()
+        };
+
+        // Stops at the same position make a hard edge, taking the colour
+        // of the last of them from its position onward
+        assert!(ramp.colour_at(0.4999).abs_diff_eq(Vec3::X, 1e-3));
+        assert_eq!(ramp.colour_at(0.5), Vec3::Y);
+        assert!(ramp.colour_at(0.5001).abs_diff_eq(Vec3::Y, 1e-3));
+        assert!(ramp.colour_at(0.75).is_finite());
+    }
+
+    #[test]
+    fn test_single_stop() {
+        let ramp = ColourRamp {
+            stops: vec![ColourStop::new(0.5, Vec3::X)],
+            ..Default::default()
+        };
+        for position in [-1., 0., 0.5, 1., 2.] {
+            assert_eq!(ramp.colour_at(position), Vec3::X);
+        }
+    }
+
+    #[test]
+    fn test_empty_ramp() {
+        let ramp = ColourRamp {
+            stops: vec![],
+            ..Default::default()
+        };
+        assert_eq!(ramp.colour_at(0.3), Vec3::splat(0.3));
+    }
+
+    #[test]
+    fn test_interpolation_midpoints() {
+        assert_eq!(
+            black_to_white(RampInterpolation::Constant).colour_at(0.4),
+            Vec3::ZERO
+        );
+        assert!(black_to_white(RampInterpolation::Linear)
+            .colour_at(0.4)
+            .abs_diff_eq(Vec3::splat(0.5), 1e-6));
+        assert!(black_to_white(RampInterpolation::Linear)
+            .colour_at(0.3)
+            .abs_diff_eq(Vec3::splat(0.25), 1e-6));
+
+        // Smoothstep matches linear at the midpoint, but eases in
+        assert!(black_to_white(RampInterpolation::Smoothstep)
+            .colour_at(0.4)
+            .abs_diff_eq(Vec3::splat(0.5), 1e-6));
+        assert!(black_to_white(RampInterpolation::Smoothstep)
+            .colour_at(0.3)
+            .abs_diff_eq(Vec3::splat(0.15625), 1e-6));
+
+        // Halfway in OKLab lightness is a darker grey in linear sRGB
+        assert!(black_to_white(RampInterpolation::OKLab)
+            .colour_at(0.4)
+            .abs_diff_eq(Vec3::splat(0.125), 1e-3));
+    }
+
+    #[test]
+    fn test_oklab_round_trip() {
+        let colour = Vec3::new(0.8, 0.3, 0.1);
+        assert!(oklab_to_linear_srgb(linear_srgb_to_oklab(colour)).abs_diff_eq(colour, 1e-4));
+    }
+}
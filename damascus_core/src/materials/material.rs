@@ -5,11 +5,31 @@
 
 use crevice::std430::AsStd430;
 use glam::Vec3;
+use strum::{Display, EnumIter, EnumString};
 
-use super::{GPUProceduralTexture, ProceduralTexture};
+use super::{GPUProceduralTexture, ProceduralTexture, ProceduralTextureType, TextureBuffers};
 
 use crate::DualDevice;
 
+#[derive(
+    Debug,
+    Default,
+    Display,
+    Copy,
+    Clone,
+    EnumIter,
+    EnumString,
+    PartialEq,
+    PartialOrd,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum TangentType {
+    #[default]
+    LocalAxis,
+    TextureGradient,
+}
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone, AsStd430)]
 pub struct GPUMaterial {
@@ -19,8 +39,16 @@ pub struct GPUMaterial {
     specular_probability_texture: GPUProceduralTexture,
     specular_roughness: f32,
     specular_roughness_texture: GPUProceduralTexture,
+    specular_anisotropy: f32,
+    specular_rotation: f32,
+    tangent_type: u32,
+    tangent_axis: Vec3,
+    tangent_texture: GPUProceduralTexture,
     specular_colour: Vec3,
     specular_colour_texture: GPUProceduralTexture,
+    film_thickness: f32,
+    film_thickness_texture: GPUProceduralTexture,
+    film_refractive_index: f32,
     transmissive_probability: f32,
     transmissive_probability_texture: GPUProceduralTexture,
     transmissive_roughness: f32,
@@ -31,11 +59,13 @@ pub struct GPUMaterial {
     emissive_colour_texture: GPUProceduralTexture,
     refractive_index: f32,
     refractive_index_texture: GPUProceduralTexture,
+    abbe_number: f32,
     scattering_colour: Vec3,
     scattering_colour_texture: GPUProceduralTexture,
+    density_texture: GPUProceduralTexture,
 }
 
-#[derive(Debug, Copy, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(default)]
 pub struct Material {
     pub diffuse_colour: Vec3,
@@ -44,8 +74,16 @@ pub struct Material {
     pub specular_probability_texture: ProceduralTexture,
     pub specular_roughness: f32,
     pub specular_roughness_texture: ProceduralTexture,
+    pub specular_anisotropy: f32,
+    pub specular_rotation: f32,
+    pub tangent_type: TangentType,
+    pub tangent_axis: Vec3,
+    pub tangent_texture: ProceduralTexture,
     pub specular_colour: Vec3,
     pub specular_colour_texture: ProceduralTexture,
+    pub film_thickness: f32,
+    pub film_thickness_texture: ProceduralTexture,
+    pub film_refractive_index: f32,
     pub transmissive_probability: f32,
     pub transmissive_probability_texture: ProceduralTexture,
     pub transmissive_roughness: f32,
@@ -58,9 +96,12 @@ pub struct Material {
     pub emissive_colour_texture: ProceduralTexture,
     pub refractive_index: f32,
     pub refractive_index_texture: ProceduralTexture,
+    pub abbe_number: f32,
     pub scattering_coefficient: f32,
     pub scattering_colour: Vec3,
     pub scattering_colour_texture: ProceduralTexture,
+    pub density: f32,
+    pub density_texture: ProceduralTexture,
 }
 
 impl Default for Material {
@@ -72,8 +113,16 @@ impl Default for Material {
             specular_probability_texture: ProceduralTexture::default(),
             specular_roughness: 0.,
             specular_roughness_texture: ProceduralTexture::default(),
+            specular_anisotropy: 0.,
+            specular_rotation: 0.,
+            tangent_type: TangentType::LocalAxis,
+            tangent_axis: Vec3::X,
+            tangent_texture: ProceduralTexture::default(),
             specular_colour: Vec3::ONE,
             specular_colour_texture: ProceduralTexture::default(),
+            film_thickness: 0.,
+            film_thickness_texture: ProceduralTexture::default(),
+            film_refractive_index: 1.33,
             transmissive_probability: 0.,
             transmissive_probability_texture: ProceduralTexture::default(),
             transmissive_roughness: 0.,
@@ -86,9 +135,12 @@ impl Default for Material {
             emissive_colour_texture: ProceduralTexture::default(),
             refractive_index: 1.3,
             refractive_index_texture: ProceduralTexture::default(),
+            abbe_number: 0.,
             scattering_coefficient: 0.,
             scattering_colour: Vec3::ONE,
             scattering_colour_texture: ProceduralTexture::default(),
+            density: 1.,
+            density_texture: ProceduralTexture::default(),
         }
     }
 }
@@ -101,34 +153,109 @@ impl Material {
     pub fn is_emissive(&self) -> bool {
         self.scaled_emissive_colour().length_squared() > 0.
     }
-}
 
-impl DualDevice<GPUMaterial, Std430GPUMaterial> for Material {
-    fn to_gpu(&self) -> GPUMaterial {
+    /// Whether the medium inside the material varies in density, and
+    /// must be sampled by tracking rather than analytically.
+    pub fn is_heterogeneous_medium(&self) -> bool {
+        self.density_texture.texture_type > ProceduralTextureType::None
+            && self.density > 0.
+            && (self.extinction_coefficient > 0. || self.scattering_coefficient > 0.)
+    }
+
+    /// Whether any of the procedural textures projects an image along
+    /// the surface normal.
+    pub fn uses_surface_normal(&self) -> bool {
+        [
+            &self.diffuse_colour_texture,
+            &self.specular_probability_texture,
+            &self.specular_roughness_texture,
+            &self.tangent_texture,
+            &self.specular_colour_texture,
+            &self.film_thickness_texture,
+            &self.transmissive_probability_texture,
+            &self.transmissive_roughness_texture,
+            &self.transmissive_colour_texture,
+            &self.emissive_colour_texture,
+            &self.refractive_index_texture,
+            &self.scattering_colour_texture,
+            &self.density_texture,
+        ]
+        .into_iter()
+        .any(ProceduralTexture::uses_surface_normal)
+    }
+
+    /// Convert to the GPU representation, appending the data that the
+    /// procedural textures refer to by index to the texture buffers.
+    pub fn to_gpu_with_texture_buffers(&self, texture_buffers: &mut TextureBuffers) -> GPUMaterial {
         GPUMaterial {
             diffuse_colour: self.diffuse_colour,
-            diffuse_colour_texture: self.diffuse_colour_texture.to_gpu(),
+            diffuse_colour_texture: self
+                .diffuse_colour_texture
+                .to_gpu_with_texture_buffers(texture_buffers),
             specular_probability: self.specular_probability,
-            specular_probability_texture: self.specular_probability_texture.to_gpu(),
+            specular_probability_texture: self
+                .specular_probability_texture
+                .to_gpu_with_texture_buffers(texture_buffers),
             specular_roughness: self.specular_roughness,
-            specular_roughness_texture: self.specular_roughness_texture.to_gpu(),
+            specular_roughness_texture: self
+                .specular_roughness_texture
+                .to_gpu_with_texture_buffers(texture_buffers),
+            specular_anisotropy: self.specular_anisotropy.clamp(0., 1.),
+            specular_rotation: self.specular_rotation * std::f32::consts::PI / 180.,
+            tangent_type: self.tangent_type as u32,
+            tangent_axis: self.tangent_axis.normalize_or(Vec3::X),
+            tangent_texture: self
+                .tangent_texture
+                .to_gpu_with_texture_buffers(texture_buffers),
             specular_colour: self.specular_colour,
-            specular_colour_texture: self.specular_colour_texture.to_gpu(),
+            specular_colour_texture: self
+                .specular_colour_texture
+                .to_gpu_with_texture_buffers(texture_buffers),
+            film_thickness: self.film_thickness.max(0.),
+            film_thickness_texture: self
+                .film_thickness_texture
+                .to_gpu_with_texture_buffers(texture_buffers),
+            film_refractive_index: self.film_refractive_index,
             transmissive_probability: self
                 .transmissive_probability
                 .min(1. - self.specular_probability),
-            transmissive_probability_texture: self.transmissive_probability_texture.to_gpu(),
+            transmissive_probability_texture: self
+                .transmissive_probability_texture
+                .to_gpu_with_texture_buffers(texture_buffers),
             transmissive_roughness: self.transmissive_roughness,
-            transmissive_roughness_texture: self.transmissive_roughness_texture.to_gpu(),
+            transmissive_roughness_texture: self
+                .transmissive_roughness_texture
+                .to_gpu_with_texture_buffers(texture_buffers),
             extinction_colour: (1. - self.transmissive_colour.clamp(Vec3::ZERO, Vec3::ONE))
-                * self.extinction_coefficient,
-            extinction_colour_texture: self.transmissive_colour_texture.to_gpu(),
+                * self.extinction_coefficient
+                * self.density.max(0.),
+            extinction_colour_texture: self
+                .transmissive_colour_texture
+                .to_gpu_with_texture_buffers(texture_buffers),
             emissive_colour: self.scaled_emissive_colour(),
-            emissive_colour_texture: self.emissive_colour_texture.to_gpu(),
+            emissive_colour_texture: self
+                .emissive_colour_texture
+                .to_gpu_with_texture_buffers(texture_buffers),
             refractive_index: self.refractive_index,
-            refractive_index_texture: self.refractive_index_texture.to_gpu(),
-            scattering_colour: self.scattering_colour * self.scattering_coefficient,
-            scattering_colour_texture: self.scattering_colour_texture.to_gpu(),
+            refractive_index_texture: self
+                .refractive_index_texture
+                .to_gpu_with_texture_buffers(texture_buffers),
+            abbe_number: self.abbe_number.max(0.),
+            scattering_colour: self.scattering_colour
+                * self.scattering_coefficient
+                * self.density.max(0.),
+            scattering_colour_texture: self
+                .scattering_colour_texture
+                .to_gpu_with_texture_buffers(texture_buffers),
+            density_texture: self
+                .density_texture
+                .to_gpu_with_texture_buffers(texture_buffers),
         }
     }
 }
+
+impl DualDevice<GPUMaterial, Std430GPUMaterial> for Material {
+    fn to_gpu(&self) -> GPUMaterial {
+        self.to_gpu_with_texture_buffers(&mut TextureBuffers::default())
+    }
+}
@@ -3,8 +3,16 @@
 // This source code is licensed under the BSD-style license found in the
 // LICENSE file in the root directory of this source tree.
 
+mod colour_ramp;
 mod material;
 mod procedural_texture;
 
-pub use material::{GPUMaterial, Material, Std430GPUMaterial};
-pub use procedural_texture::{GPUProceduralTexture, ProceduralTexture, ProceduralTextureType};
+pub use colour_ramp::{
+    ColourRamp, ColourStop, GPUColourStop, RampInterpolation, Std430GPUColourStop,
+};
+pub use material::{GPUMaterial, Material, Std430GPUMaterial, TangentType};
+pub use procedural_texture::{
+    CoordinateSpace, DistanceMetric, GPUProceduralTexture, ProceduralTexture,
+    ProceduralTextureType, Std430GPUProceduralTexture, TextureBlendMode, TextureBuffers,
+    TextureProjection, VoronoiFeature, MAX_TEXTURE_STACK_DEPTH,
+};
@@ -7,8 +7,13 @@ use crevice::std430::AsStd430;
 use glam::{EulerRot, Mat3, Vec3, Vec4};
 use strum::{Display, EnumIter, EnumString};
 
+use super::{ColourRamp, Std430GPUColourStop};
 use crate::DualDevice;
 
+/// The most results of a texture stack that the shaders can hold at
+/// once while evaluating it.
+pub const MAX_TEXTURE_STACK_DEPTH: usize = 8;
+
 #[derive(
     Debug,
     Default,
@@ -29,7 +34,116 @@ pub enum ProceduralTextureType {
     Checkerboard,
     FBMNoise,
     TurbulenceNoise,
-    // VoronoiNoise,
+    Image,
+    VoronoiNoise,
+    ImageGrid,
+}
+
+#[derive(
+    Debug,
+    Default,
+    Display,
+    Copy,
+    Clone,
+    EnumIter,
+    EnumString,
+    PartialEq,
+    PartialOrd,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum VoronoiFeature {
+    #[default]
+    F1,
+    F2,
+    F2MinusF1,
+    CellID,
+}
+
+#[derive(
+    Debug,
+    Default,
+    Display,
+    Copy,
+    Clone,
+    EnumIter,
+    EnumString,
+    PartialEq,
+    PartialOrd,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum DistanceMetric {
+    #[default]
+    Euclidean,
+    Manhattan,
+    Chebyshev,
+}
+
+#[derive(
+    Debug,
+    Default,
+    Display,
+    Copy,
+    Clone,
+    EnumIter,
+    EnumString,
+    PartialEq,
+    PartialOrd,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum TextureProjection {
+    #[default]
+    Triplanar,
+    Planar,
+    Spherical,
+    Cylindrical,
+}
+
+#[derive(
+    Debug,
+    Default,
+    Display,
+    Copy,
+    Clone,
+    EnumIter,
+    EnumString,
+    PartialEq,
+    PartialOrd,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum CoordinateSpace {
+    #[default]
+    Object,
+    World,
+    Camera,
+    Spherical,
+}
+
+#[derive(
+    Debug,
+    Default,
+    Display,
+    Copy,
+    Clone,
+    EnumIter,
+    EnumString,
+    PartialEq,
+    PartialOrd,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum TextureBlendMode {
+    #[default]
+    Multiply,
+    Add,
+    Screen,
+    Overlay,
+    Minimum,
+    Maximum,
+    Mix,
 }
 
 #[repr(C)]
@@ -50,10 +164,54 @@ pub struct GPUProceduralTexture {
     low_frequency_translation: Vec4,
     high_frequency_translation: Vec4,
     hue_rotation: Mat3,
+    image_index: u32,
+    grid_slices: u32,
+    projection: u32,
+    coordinate_space: u32,
+    blend_sharpness: f32,
+    voronoi_feature: u32,
+    distance_metric: u32,
+    jitter: f32,
+    warp_strength: f32,
+    warp_octaves: u32,
+    blend_mode: u32,
+    ramp_interpolation: u32,
+    ramp_start: u32,
+    ramp_size: u32,
+    stack_start: u32,
+    stack_size: u32,
     flags: u32,
 }
 
-#[derive(Debug, Copy, Clone, serde::Serialize, serde::Deserialize)]
+/// The data that procedural textures refer to by index, which varies
+/// too much in size to store in the textures themselves. Textures whose
+/// data does not fit in the buffers are uploaded without it.
+#[derive(Debug, Clone)]
+pub struct TextureBuffers {
+    pub texture_stack: Vec<Std430GPUProceduralTexture>,
+    pub colour_ramp_stops: Vec<Std430GPUColourStop>,
+    max_texture_stack_size: usize,
+    max_colour_ramp_stops: usize,
+}
+
+impl Default for TextureBuffers {
+    fn default() -> Self {
+        Self::new(usize::MAX, usize::MAX)
+    }
+}
+
+impl TextureBuffers {
+    pub fn new(max_texture_stack_size: usize, max_colour_ramp_stops: usize) -> Self {
+        Self {
+            texture_stack: vec![],
+            colour_ramp_stops: vec![],
+            max_texture_stack_size,
+            max_colour_ramp_stops,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(default)]
 pub struct ProceduralTexture {
     pub texture_type: ProceduralTextureType,
@@ -73,6 +231,21 @@ pub struct ProceduralTexture {
     pub invert: bool,
     pub hue_rotation_angles: Vec3,
     pub use_trap_colour: bool,
+    pub image_index: u32,
+    pub grid_slices: u32,
+    pub projection: TextureProjection,
+    pub coordinate_space: CoordinateSpace,
+    pub blend_sharpness: f32,
+    pub voronoi_feature: VoronoiFeature,
+    pub distance_metric: DistanceMetric,
+    pub jitter: f32,
+    pub warp_strength: f32,
+    pub warp_octaves: u32,
+    pub input: Option<Box<ProceduralTexture>>,
+    pub mask: Option<Box<ProceduralTexture>>,
+    pub blend_mode: TextureBlendMode,
+    pub use_colour_ramp: bool,
+    pub colour_ramp: ColourRamp,
 }
 
 impl Default for ProceduralTexture {
@@ -95,11 +268,152 @@ impl Default for ProceduralTexture {
             invert: false,
             hue_rotation_angles: Vec3::ZERO,
             use_trap_colour: false,
+            image_index: 0,
+            grid_slices: 1,
+            projection: TextureProjection::Triplanar,
+            coordinate_space: CoordinateSpace::Object,
+            blend_sharpness: 4.,
+            voronoi_feature: VoronoiFeature::F1,
+            distance_metric: DistanceMetric::Euclidean,
+            jitter: 1.,
+            warp_strength: 0.,
+            warp_octaves: 4,
+            input: None,
+            mask: None,
+            blend_mode: TextureBlendMode::Multiply,
+            use_colour_ramp: false,
+            colour_ramp: ColourRamp::default(),
         }
     }
 }
 
-impl ProceduralTexture {}
+impl ProceduralTexture {
+    /// Whether the lookup position of the texture is offset by noise.
+    pub fn is_domain_warped(&self) -> bool {
+        self.warp_strength > 0.
+            && self.warp_octaves > 0
+            && self.texture_type > ProceduralTextureType::Grade
+    }
+
+    /// A texture that has to be composited with the textures it takes
+    /// as inputs, or remapped, rather than simply multiplying the
+    /// colour it is applied to.
+    pub fn is_composite(&self) -> bool {
+        self.input.is_some()
+            || self.mask.is_some()
+            || self.blend_mode != TextureBlendMode::Multiply
+            || self.use_colour_ramp
+    }
+
+    /// Whether the texture, or any texture it is composed from, projects
+    /// an image along the surface normal, which must then be estimated.
+    pub fn uses_surface_normal(&self) -> bool {
+        std::iter::once(self)
+            .chain(self.texture_stack())
+            .any(|texture| {
+                texture.texture_type == ProceduralTextureType::Image
+                    && texture.projection == TextureProjection::Triplanar
+            })
+    }
+
+    /// The textures this texture is composed from, excluding itself, in
+    /// the order they must be evaluated. Each texture follows its input
+    /// and mask, so the stack can be evaluated without recursion.
+    pub fn texture_stack(&self) -> Vec<&ProceduralTexture> {
+        let mut texture_stack = vec![];
+        for texture in [&self.input, &self.mask].into_iter().flatten() {
+            texture_stack.extend(texture.texture_stack());
+            texture_stack.push(texture.as_ref());
+        }
+        texture_stack
+    }
+
+    /// The most results of the texture stack, including this texture's
+    /// own, that are held at once while it is evaluated. Each texture
+    /// holds the result of its input while its mask is evaluated.
+    pub fn stack_depth(&self) -> usize {
+        let input_depth: usize = self.input.as_ref().map_or(0, |input| input.stack_depth());
+        let mask_depth: usize = self
+            .mask
+            .as_ref()
+            .map_or(0, |mask| self.input.is_some() as usize + mask.stack_depth());
+        input_depth.max(mask_depth).max(1)
+    }
+
+    /// Convert to the GPU representation, appending the stops of the
+    /// colour ramp to the buffer of colour ramp stops. A ramp that does
+    /// not fit in the buffer is not used.
+    fn to_gpu_with_colour_ramp(
+        &self,
+        colour_ramp_stops: &mut Vec<Std430GPUColourStop>,
+        max_colour_ramp_stops: usize,
+    ) -> GPUProceduralTexture {
+        if self.use_colour_ramp
+            && colour_ramp_stops.len() + self.colour_ramp.stops.len() > max_colour_ramp_stops
+        {
+            return ProceduralTexture {
+                use_colour_ramp: false,
+                ..self.clone()
+            }
+            .to_gpu();
+        }
+
+        let mut gpu_texture: GPUProceduralTexture = self.to_gpu();
+        if self.use_colour_ramp {
+            gpu_texture.ramp_start = colour_ramp_stops.len() as u32;
+            colour_ramp_stops.extend(
+                self.colour_ramp
+                    .sorted_stops()
+                    .iter()
+                    .map(|stop| stop.as_std430()),
+            );
+            gpu_texture.ramp_size = colour_ramp_stops.len() as u32 - gpu_texture.ramp_start;
+        }
+        gpu_texture
+    }
+
+    /// Convert to the GPU representation, appending the textures this
+    /// texture is composed from, and the stops of their colour ramps,
+    /// to the buffers that will be uploaded alongside it. A texture that
+    /// is nested too deeply for the shaders to evaluate, or whose stack
+    /// does not fit in the buffer, is uploaded without its input and
+    /// mask, rather than being composited incorrectly.
+    pub fn to_gpu_with_texture_buffers(
+        &self,
+        texture_buffers: &mut TextureBuffers,
+    ) -> GPUProceduralTexture {
+        let texture_stack: Vec<&ProceduralTexture> = self.texture_stack();
+        if !texture_stack.is_empty()
+            && (self.stack_depth() > MAX_TEXTURE_STACK_DEPTH
+                || texture_buffers.texture_stack.len() + texture_stack.len()
+                    > texture_buffers.max_texture_stack_size)
+        {
+            return ProceduralTexture {
+                input: None,
+                mask: None,
+                ..self.clone()
+            }
+            .to_gpu_with_texture_buffers(texture_buffers);
+        }
+
+        let stack_start = texture_buffers.texture_stack.len() as u32;
+        for texture in texture_stack {
+            let gpu_texture: GPUProceduralTexture = texture.to_gpu_with_colour_ramp(
+                &mut texture_buffers.colour_ramp_stops,
+                texture_buffers.max_colour_ramp_stops,
+            );
+            texture_buffers.texture_stack.push(gpu_texture.as_std430());
+        }
+
+        let mut gpu_texture: GPUProceduralTexture = self.to_gpu_with_colour_ramp(
+            &mut texture_buffers.colour_ramp_stops,
+            texture_buffers.max_colour_ramp_stops,
+        );
+        gpu_texture.stack_start = stack_start;
+        gpu_texture.stack_size = texture_buffers.texture_stack.len() as u32 - stack_start;
+        gpu_texture
+    }
+}
 
 impl DualDevice<GPUProceduralTexture, Std430GPUProceduralTexture> for ProceduralTexture {
     fn to_gpu(&self) -> GPUProceduralTexture {
@@ -125,7 +439,252 @@ impl DualDevice<GPUProceduralTexture, Std430GPUProceduralTexture> for Procedural
                 radian_hue_rotation.y,
                 radian_hue_rotation.z,
             ),
-            flags: self.invert as u32 | (self.use_trap_colour as u32) << 1,
+            image_index: self.image_index,
+            grid_slices: self.grid_slices.max(1),
+            projection: self.projection as u32,
+            coordinate_space: self.coordinate_space as u32,
+            blend_sharpness: self.blend_sharpness.max(1.),
+            voronoi_feature: self.voronoi_feature as u32,
+            distance_metric: self.distance_metric as u32,
+            jitter: self.jitter.clamp(0., 1.),
+            warp_strength: self.warp_strength.max(0.),
+            warp_octaves: self.warp_octaves,
+            blend_mode: self.blend_mode as u32,
+            ramp_interpolation: self.colour_ramp.interpolation as u32,
+            ramp_start: 0,
+            ramp_size: 0,
+            stack_start: 0,
+            stack_size: 0,
+            flags: self.invert as u32
+                | (self.use_trap_colour as u32) << 1
+                | (self.input.is_some() as u32) << 2
+                | (self.mask.is_some() as u32) << 3
+                | (self.use_colour_ramp as u32) << 4,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texture_of_type(texture_type: ProceduralTextureType) -> ProceduralTexture {
+        ProceduralTexture {
+            texture_type,
+            ..Default::default()
+        }
+    }
+
+    /// A grade of fbm noise, which is itself a grade of a checkerboard,
+    /// masked by voronoi noise.
+    fn composite_texture() -> ProceduralTexture {
+        ProceduralTexture {
+            input: Some(Box::new(ProceduralTexture {
+                input: Some(Box::new(texture_of_type(
+                    ProceduralTextureType::Checkerboard,
+                ))),
+                ..texture_of_type(ProceduralTextureType::FBMNoise)
+            })),
+            mask: Some(Box::new(texture_of_type(
+                ProceduralTextureType::VoronoiNoise,
+            ))),
+            ..texture_of_type(ProceduralTextureType::Grade)
+        }
+    }
+
+    #[test]
+    fn test_is_composite() {
+        assert!(!ProceduralTexture::default().is_composite());
+        assert!(composite_texture().is_composite());
+        assert!(ProceduralTexture {
+            blend_mode: TextureBlendMode::Add,
+            ..Default::default()
+        }
+        .is_composite());
+        assert!(ProceduralTexture {
+            use_colour_ramp: true,
+            ..Default::default()
+        }
+        .is_composite());
+    }
+
+    #[test]
+    fn test_uses_surface_normal() {
+        let image = ProceduralTexture {
+            texture_type: ProceduralTextureType::Image,
+            ..Default::default()
+        };
+        assert!(image.uses_surface_normal());
+        assert!(!ProceduralTexture {
+            projection: TextureProjection::Planar,
+            ..image.clone()
+        }
+        .uses_surface_normal());
+        assert!(!texture_of_type(ProceduralTextureType::Checkerboard).uses_surface_normal());
+
+        // An image anywhere in the stack needs the normal
+        assert!(ProceduralTexture {
+            mask: Some(Box::new(image)),
+            ..texture_of_type(ProceduralTextureType::Checkerboard)
+        }
+        .uses_surface_normal());
+    }
+
+    #[test]
+    fn test_is_domain_warped() {
+        let texture = ProceduralTexture {
+            warp_strength: 0.5,
+            ..texture_of_type(ProceduralTextureType::FBMNoise)
+        };
+        assert!(texture.is_domain_warped());
+
+        assert!(!ProceduralTexture {
+            warp_strength: 0.,
+            ..texture.clone()
+        }
+        .is_domain_warped());
+        assert!(!ProceduralTexture {
+            warp_octaves: 0,
+            ..texture.clone()
+        }
+        .is_domain_warped());
+
+        // Grading does not look up a position, so it cannot be warped
+        assert!(!ProceduralTexture {
+            texture_type: ProceduralTextureType::Grade,
+            ..texture.clone()
+        }
+        .is_domain_warped());
+        assert!(!ProceduralTexture {
+            texture_type: ProceduralTextureType::None,
+            ..texture
+        }
+        .is_domain_warped());
+    }
+
+    #[test]
+    fn test_texture_stack() {
+        assert!(ProceduralTexture::default().texture_stack().is_empty());
+
+        // Each texture follows the textures it is composed from
+        let texture_types: Vec<ProceduralTextureType> = composite_texture()
+            .texture_stack()
+            .iter()
+            .map(|texture| texture.texture_type)
+            .collect();
+        assert_eq!(
+            texture_types,
+            vec![
+                ProceduralTextureType::Checkerboard,
+                ProceduralTextureType::FBMNoise,
+                ProceduralTextureType::VoronoiNoise,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_gpu_with_texture_buffers() {
+        let mut texture_buffers = TextureBuffers::default();
+        let simple_texture: GPUProceduralTexture =
+            ProceduralTexture::default().to_gpu_with_texture_buffers(&mut texture_buffers);
+        assert_eq!(simple_texture.stack_size, 0);
+        assert_eq!(simple_texture.ramp_size, 0);
+        assert!(texture_buffers.texture_stack.is_empty());
+        assert!(texture_buffers.colour_ramp_stops.is_empty());
+
+        let mut texture = composite_texture();
+        texture.use_colour_ramp = true;
+        let num_stops: u32 = texture.colour_ramp.stops.len() as u32;
+        texture.mask.as_mut().unwrap().use_colour_ramp = true;
+
+        let first_texture: GPUProceduralTexture =
+            texture.to_gpu_with_texture_buffers(&mut texture_buffers);
+        assert_eq!(first_texture.stack_start, 0);
+        assert_eq!(first_texture.stack_size, 3);
+        assert_eq!(first_texture.flags, 0b11100);
+        // The stops of the mask are appended before those of the texture
+        assert_eq!(first_texture.ramp_start, num_stops);
+        assert_eq!(first_texture.ramp_size, num_stops);
+
+        // The textures of later materials are appended after the first
+        let second_texture: GPUProceduralTexture =
+            texture.to_gpu_with_texture_buffers(&mut texture_buffers);
+        assert_eq!(second_texture.stack_start, 3);
+        assert_eq!(second_texture.stack_size, 3);
+        assert_eq!(second_texture.ramp_start, 3 * num_stops);
+        assert_eq!(texture_buffers.texture_stack.len(), 6);
+        assert_eq!(
+            texture_buffers.colour_ramp_stops.len() as u32,
+            4 * num_stops
+        );
+    }
+
+    /// A chain of textures, each of which masks the one before it.
+    fn nested_texture(depth: usize) -> ProceduralTexture {
+        let mut texture = texture_of_type(ProceduralTextureType::Checkerboard);
+        for _ in 1..depth {
+            texture = ProceduralTexture {
+                input: Some(Box::new(texture_of_type(ProceduralTextureType::FBMNoise))),
+                mask: Some(Box::new(texture)),
+                ..texture_of_type(ProceduralTextureType::Grade)
+            };
         }
+        texture
+    }
+
+    #[test]
+    fn test_stack_depth() {
+        assert_eq!(ProceduralTexture::default().stack_depth(), 1);
+        // The result of the input is held while the mask is evaluated
+        assert_eq!(composite_texture().stack_depth(), 2);
+        for depth in 1..=MAX_TEXTURE_STACK_DEPTH + 1 {
+            assert_eq!(nested_texture(depth).stack_depth(), depth);
+        }
+    }
+
+    #[test]
+    fn test_to_gpu_with_too_deep_texture_stack() {
+        let mut texture_buffers = TextureBuffers::default();
+        let deepest_texture: GPUProceduralTexture = nested_texture(MAX_TEXTURE_STACK_DEPTH)
+            .to_gpu_with_texture_buffers(&mut texture_buffers);
+        assert_eq!(
+            deepest_texture.stack_size as usize,
+            2 * (MAX_TEXTURE_STACK_DEPTH - 1)
+        );
+
+        // Textures that are too deep are uploaded without their inputs
+        let mut texture_buffers = TextureBuffers::default();
+        let too_deep_texture: GPUProceduralTexture = nested_texture(MAX_TEXTURE_STACK_DEPTH + 1)
+            .to_gpu_with_texture_buffers(&mut texture_buffers);
+        assert_eq!(too_deep_texture.stack_size, 0);
+        assert_eq!(too_deep_texture.flags, 0);
+        assert_eq!(
+            too_deep_texture.texture_type,
+            ProceduralTextureType::Grade as u32
+        );
+        assert!(texture_buffers.texture_stack.is_empty());
+    }
+
+    #[test]
+    fn test_to_gpu_with_full_texture_buffers() {
+        let mut texture = composite_texture();
+        texture.use_colour_ramp = true;
+        let num_stops: usize = texture.colour_ramp.stops.len();
+
+        let mut texture_buffers = TextureBuffers::new(4, num_stops);
+        let first_texture: GPUProceduralTexture =
+            texture.to_gpu_with_texture_buffers(&mut texture_buffers);
+        assert_eq!(first_texture.stack_size, 3);
+        assert_eq!(first_texture.ramp_size as usize, num_stops);
+
+        // Neither the stack nor the ramp of the second texture fit, so
+        // it cannot refer to data beyond the end of the buffers
+        let second_texture: GPUProceduralTexture =
+            texture.to_gpu_with_texture_buffers(&mut texture_buffers);
+        assert_eq!(second_texture.stack_size, 0);
+        assert_eq!(second_texture.ramp_size, 0);
+        assert_eq!(second_texture.flags, 0);
+        assert_eq!(texture_buffers.texture_stack.len(), 3);
+        assert_eq!(texture_buffers.colour_ramp_stops.len(), num_stops);
     }
 }
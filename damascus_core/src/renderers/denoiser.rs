@@ -0,0 +1,139 @@
+// Copyright (c) 2024, Owen Bulka
+// All rights reserved.
+// This source code is licensed under the BSD-style license found in the
+// LICENSE file in the root directory of this source tree.
+
+use crevice::std430::AsStd430;
+
+/// The most iterations the filter can run for. The distance between
+/// the taps of the filter doubles each iteration, so this reaches 128
+/// pixels.
+pub const MAX_DENOISER_ITERATIONS: u32 = 8;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, AsStd430)]
+pub struct GPUDenoiser {
+    step_width: u32,
+    flags: u32,
+    colour_phi: f32,
+    normal_phi: f32,
+    depth_phi: f32,
+    albedo_phi: f32,
+}
+
+/// An edge-avoiding à-trous wavelet filter which denoises the
+/// progressive render, guided by the normal, depth, and albedo of the
+/// first surface hit in each pixel.
+#[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Denoiser {
+    pub enabled: bool,
+    pub split_view: bool,
+    pub iterations: u32,
+    pub colour_phi: f32,
+    pub normal_phi: f32,
+    pub depth_phi: f32,
+    pub albedo_phi: f32,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            split_view: false,
+            iterations: 5,
+            colour_phi: 1.,
+            normal_phi: 128.,
+            depth_phi: 0.05,
+            albedo_phi: 0.01,
+        }
+    }
+}
+
+impl Denoiser {
+    /// The number of iterations to run, which is at least one.
+    pub fn num_iterations(&self) -> u32 {
+        self.iterations.clamp(1, MAX_DENOISER_ITERATIONS)
+    }
+
+    /// The parameters of one iteration of the filter. As the taps get
+    /// further apart the colour is trusted less, since the noise has
+    /// already been smoothed out by the earlier iterations.
+    pub fn iteration_parameters(&self, iteration: u32) -> Std430GPUDenoiser {
+        self.gpu_iteration(iteration).as_std430()
+    }
+
+    fn gpu_iteration(&self, iteration: u32) -> GPUDenoiser {
+        GPUDenoiser {
+            step_width: 1 << iteration,
+            flags: (iteration == 0) as u32 | ((iteration + 1 == self.num_iterations()) as u32) << 1,
+            colour_phi: self.colour_phi * 0.5_f32.powi(iteration as i32),
+            normal_phi: self.normal_phi,
+            depth_phi: self.depth_phi,
+            albedo_phi: self.albedo_phi,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_num_iterations() {
+        let mut denoiser = Denoiser::default();
+        assert_eq!(denoiser.num_iterations(), 5);
+
+        denoiser.iterations = 0;
+        assert_eq!(denoiser.num_iterations(), 1);
+
+        denoiser.iterations = MAX_DENOISER_ITERATIONS + 4;
+        assert_eq!(denoiser.num_iterations(), MAX_DENOISER_ITERATIONS);
+    }
+
+    #[test]
+    fn test_iteration_parameters() {
+        let denoiser = Denoiser {
+            iterations: 3,
+            colour_phi: 2.,
+            ..Default::default()
+        };
+
+        let flags: Vec<u32> = (0..3)
+            .map(|iteration| denoiser.gpu_iteration(iteration).flags)
+            .collect();
+        assert_eq!(flags, vec![1, 0, 2]);
+
+        for iteration in 0..3 {
+            let parameters: GPUDenoiser = denoiser.gpu_iteration(iteration);
+            assert_eq!(parameters.step_width, 1 << iteration);
+            assert_eq!(parameters.colour_phi, 2. / (1 << iteration) as f32);
+            assert_eq!(parameters.normal_phi, denoiser.normal_phi);
+            assert_eq!(parameters.depth_phi, denoiser.depth_phi);
+            assert_eq!(parameters.albedo_phi, denoiser.albedo_phi);
+        }
+    }
+
+    #[test]
+    fn test_iteration_parameters_single_iteration() {
+        // A single iteration is both the first and the last
+        let denoiser = Denoiser {
+            iterations: 1,
+            ..Default::default()
+        };
+        assert_eq!(denoiser.gpu_iteration(0).flags, 3);
+
+        // The last iteration follows the clamped number of iterations
+        let denoiser = Denoiser {
+            iterations: MAX_DENOISER_ITERATIONS + 1,
+            ..Default::default()
+        };
+        assert_eq!(denoiser.gpu_iteration(MAX_DENOISER_ITERATIONS - 1).flags, 2);
+        assert_eq!(
+            denoiser
+                .gpu_iteration(MAX_DENOISER_ITERATIONS - 1)
+                .step_width,
+            128
+        );
+    }
+}
@@ -6,7 +6,9 @@
 use crevice::std430::AsStd430;
 
 pub mod compositor;
+pub mod denoiser;
 pub mod ray_marcher;
+pub mod texture_baker;
 
 use super::DualDevice;
 
@@ -11,7 +11,7 @@ use strum::{Display, EnumIter, EnumString};
 
 use super::Renderer;
 
-use crate::{scene::Scene, DualDevice};
+use crate::{scene::Scene, shaders::WORKGROUP_SIZE, DualDevice};
 
 #[derive(
     Debug,
@@ -35,6 +35,7 @@ pub enum AOVs {
     Depth,
     Cryptomatte,
     Stats,
+    SampleCount,
 }
 
 #[repr(C)]
@@ -43,6 +44,7 @@ pub struct GPURayMarcherRenderState {
     paths_rendered_per_pixel: f32,
     resolution: Vec2,
     flags: u32,
+    samples_per_frame: u32,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -54,6 +56,8 @@ pub struct RayMarcherRenderState {
     pub paths_rendered_per_pixel: u32,
     pub resolution: UVec2,
     pub paused: bool,
+    pub compute_backend: bool,
+    pub samples_per_frame: u32,
 }
 
 impl Default for RayMarcherRenderState {
@@ -65,6 +69,8 @@ impl Default for RayMarcherRenderState {
             paths_rendered_per_pixel: 0,
             resolution: UVec2::ZERO,
             paused: true,
+            compute_backend: false,
+            samples_per_frame: 1,
         }
     }
 }
@@ -78,7 +84,8 @@ impl DualDevice<GPURayMarcherRenderState, Std430GPURayMarcherRenderState>
         GPURayMarcherRenderState {
             paths_rendered_per_pixel: self.paths_rendered_per_pixel as f32,
             resolution: self.resolution.as_vec2(),
-            flags: self.paused as u32,
+            flags: self.paused as u32 | (self.compute_backend as u32) << 1,
+            samples_per_frame: self.samples_per_frame.max(1),
         }
     }
 }
@@ -98,6 +105,8 @@ pub struct GPURayMarcher {
     light_sampling_bias: f32,
     output_aov: u32,
     flags: u32,
+    noise_threshold: f32,
+    max_samples: u32,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -117,6 +126,9 @@ pub struct RayMarcher {
     pub sample_atmosphere: bool,
     pub light_sampling_bias: f32,
     pub secondary_sampling: bool,
+    pub spectral_rendering: bool,
+    pub noise_threshold: f32,
+    pub max_samples: u32,
     pub output_aov: AOVs,
 }
 
@@ -137,6 +149,9 @@ impl Default for RayMarcher {
             sample_atmosphere: false,
             light_sampling_bias: 0.,
             secondary_sampling: false,
+            spectral_rendering: false,
+            noise_threshold: 0.,
+            max_samples: 0,
             output_aov: AOVs::default(),
         }
     }
@@ -159,6 +174,15 @@ impl RayMarcher {
         self.sample_atmosphere = default_ray_marcher.sample_atmosphere;
         self.light_sampling_bias = default_ray_marcher.light_sampling_bias;
         self.secondary_sampling = default_ray_marcher.secondary_sampling;
+        self.spectral_rendering = default_ray_marcher.spectral_rendering;
+        self.noise_threshold = default_ray_marcher.noise_threshold;
+        self.max_samples = default_ray_marcher.max_samples;
+    }
+
+    /// Whether pixels stop sampling once they have converged, or reached
+    /// the maximum samples, sharing their samples with the other pixels.
+    pub fn adaptive_sampling(&self) -> bool {
+        self.noise_threshold > 0. || self.max_samples > 0
     }
 }
 
@@ -178,9 +202,136 @@ impl DualDevice<GPURayMarcher, Std430GPURayMarcher> for RayMarcher {
             output_aov: self.output_aov as u32,
             flags: self.dynamic_level_of_detail as u32
                 | (self.sample_atmosphere as u32) << 1
-                | (self.secondary_sampling as u32) << 2,
+                | (self.secondary_sampling as u32) << 2
+                | (self.spectral_rendering as u32) << 3,
+            noise_threshold: self.noise_threshold.max(0.),
+            max_samples: self.max_samples,
         }
     }
 }
 
 impl Renderer<GPURayMarcher, Std430GPURayMarcher> for RayMarcher {}
+
+/// The counters at the start of the ray marcher's adaptive sampling
+/// buffer. The unconverged pixels of each tile of the viewport are
+/// counted into them before every frame, and they are read back to find
+/// when the render has converged.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct AdaptiveSamplingCounters {
+    pub tiles: u32,
+    pub total_weight: u32,
+    pub unconverged_pixels: u32,
+}
+
+impl AdaptiveSamplingCounters {
+    /// The size of the counters in the buffer, which are padded so that
+    /// the weights of the tiles after them are aligned.
+    pub const SIZE: u64 = 4 * size_of::<u32>() as u64;
+
+    /// The size of the weight and number of unconverged pixels of a tile.
+    pub const TILE_SIZE: u64 = 2 * size_of::<u32>() as u64;
+
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let [tiles, total_weight, unconverged_pixels, _padding] =
+            bytemuck::pod_read_unaligned::<[u32; 4]>(&bytes[..Self::SIZE as usize]);
+        Self {
+            tiles,
+            total_weight,
+            unconverged_pixels,
+        }
+    }
+
+    /// Whether every pixel had converged before the frame the counters
+    /// were read back from. This is unknown if no tiles were counted.
+    pub fn converged(&self) -> Option<bool> {
+        (self.tiles > 0).then_some(self.unconverged_pixels == 0)
+    }
+
+    /// The number of tiles across and down a viewport.
+    pub fn num_tiles(resolution: UVec2) -> UVec2 {
+        (resolution + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE
+    }
+
+    /// The size of the adaptive sampling buffer for a viewport of up to
+    /// the given resolution.
+    pub fn buffer_size(max_resolution: UVec2) -> u64 {
+        let num_tiles: UVec2 = Self::num_tiles(max_resolution);
+        Self::SIZE + Self::TILE_SIZE * num_tiles.x as u64 * num_tiles.y as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adaptive_sampling() {
+        let mut ray_marcher = RayMarcher::default();
+        assert!(!ray_marcher.adaptive_sampling());
+
+        ray_marcher.max_samples = 64;
+        assert!(ray_marcher.adaptive_sampling());
+
+        ray_marcher.max_samples = 0;
+        ray_marcher.noise_threshold = 0.01;
+        assert!(ray_marcher.adaptive_sampling());
+    }
+
+    #[test]
+    fn test_adaptive_sampling_buffer_size() {
+        // The tiles are aligned to their own size after the counters
+        assert_eq!(
+            AdaptiveSamplingCounters::SIZE % AdaptiveSamplingCounters::TILE_SIZE,
+            0
+        );
+
+        assert_eq!(
+            AdaptiveSamplingCounters::num_tiles(UVec2::new(8, 8)),
+            UVec2::ONE
+        );
+        assert_eq!(
+            AdaptiveSamplingCounters::num_tiles(UVec2::new(9, 17)),
+            UVec2::new(2, 3)
+        );
+        assert_eq!(
+            AdaptiveSamplingCounters::num_tiles(UVec2::ZERO),
+            UVec2::ZERO
+        );
+
+        assert_eq!(
+            AdaptiveSamplingCounters::buffer_size(UVec2::new(8, 8)),
+            AdaptiveSamplingCounters::SIZE + AdaptiveSamplingCounters::TILE_SIZE
+        );
+        assert_eq!(
+            AdaptiveSamplingCounters::buffer_size(UVec2::new(1920, 1080)),
+            AdaptiveSamplingCounters::SIZE + AdaptiveSamplingCounters::TILE_SIZE * 240 * 135
+        );
+    }
+
+    #[test]
+    fn test_adaptive_sampling_counters() {
+        let bytes: Vec<u8> = [3_u32, 40, 7, u32::MAX, 1, 2]
+            .iter()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect();
+        let counters = AdaptiveSamplingCounters::from_bytes(&bytes);
+        assert_eq!(
+            counters,
+            AdaptiveSamplingCounters {
+                tiles: 3,
+                total_weight: 40,
+                unconverged_pixels: 7,
+            }
+        );
+        assert_eq!(counters.converged(), Some(false));
+
+        let converged = AdaptiveSamplingCounters {
+            tiles: 3,
+            ..Default::default()
+        };
+        assert_eq!(converged.converged(), Some(true));
+
+        // Counters that were never written say nothing about the render
+        assert_eq!(AdaptiveSamplingCounters::default().converged(), None);
+    }
+}
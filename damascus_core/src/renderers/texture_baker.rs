@@ -0,0 +1,145 @@
+// Copyright (c) 2024, Owen Bulka
+// All rights reserved.
+// This source code is licensed under the BSD-style license found in the
+// LICENSE file in the root directory of this source tree.
+
+use crevice::std430::AsStd430;
+use glam::UVec2;
+use strum::{Display, EnumIter, EnumString};
+
+use super::Renderer;
+
+use crate::{
+    geometry::primitive::Primitive, materials::ProceduralTexture, scene::Scene, DualDevice,
+};
+
+#[derive(
+    Debug,
+    Default,
+    Display,
+    Copy,
+    Clone,
+    EnumIter,
+    EnumString,
+    PartialEq,
+    PartialOrd,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum BakeProjection {
+    #[default]
+    Planar,
+    LatLong,
+    Triplanar,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, AsStd430)]
+pub struct GPUTextureBaker {
+    resolution: UVec2,
+    projection: u32,
+    size: f32,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct TextureBaker {
+    pub texture: ProceduralTexture,
+    pub projection: BakeProjection,
+    pub primitive: Primitive,
+    pub resolution: UVec2,
+    pub size: f32,
+}
+
+impl Default for TextureBaker {
+    fn default() -> Self {
+        Self {
+            texture: ProceduralTexture::default(),
+            projection: BakeProjection::Planar,
+            primitive: Primitive::default(),
+            resolution: UVec2::splat(1024),
+            size: 1.,
+        }
+    }
+}
+
+impl TextureBaker {
+    /// The scene that is uploaded to evaluate the texture in. The texture
+    /// is the diffuse texture of the atmosphere, as it is when previewing
+    /// procedural textures, and the primitive is the only primitive.
+    pub fn scene(&self) -> Scene {
+        let mut scene = Scene::default();
+        scene.atmosphere.diffuse_colour_texture = self.texture.clone();
+        if self.projection == BakeProjection::Triplanar {
+            scene.primitives = vec![self.primitive.clone()];
+        }
+        scene
+    }
+
+    /// The resolution of the baked image, which is at least one pixel.
+    pub fn image_resolution(&self) -> UVec2 {
+        self.resolution.max(UVec2::ONE)
+    }
+
+    /// The number of pixels in the baked image.
+    pub fn num_pixels(&self) -> usize {
+        let resolution: UVec2 = self.image_resolution();
+        resolution.x as usize * resolution.y as usize
+    }
+}
+
+impl Renderer<GPUTextureBaker, Std430GPUTextureBaker> for TextureBaker {}
+
+impl DualDevice<GPUTextureBaker, Std430GPUTextureBaker> for TextureBaker {
+    fn to_gpu(&self) -> GPUTextureBaker {
+        GPUTextureBaker {
+            resolution: self.image_resolution(),
+            projection: self.projection as u32,
+            size: self.size.max(1e-6),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::materials::ProceduralTextureType;
+
+    #[test]
+    fn test_image_resolution() {
+        let mut texture_baker = TextureBaker {
+            resolution: UVec2::new(64, 32),
+            ..Default::default()
+        };
+        assert_eq!(texture_baker.image_resolution(), UVec2::new(64, 32));
+        assert_eq!(texture_baker.num_pixels(), 2048);
+
+        // An empty image still bakes a single pixel
+        texture_baker.resolution = UVec2::new(0, 32);
+        assert_eq!(texture_baker.image_resolution(), UVec2::new(1, 32));
+        assert_eq!(texture_baker.num_pixels(), 32);
+    }
+
+    #[test]
+    fn test_scene() {
+        let mut texture_baker = TextureBaker {
+            texture: ProceduralTexture {
+                texture_type: ProceduralTextureType::Checkerboard,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let scene: Scene = texture_baker.scene();
+        assert_eq!(
+            scene.atmosphere.diffuse_colour_texture.texture_type,
+            ProceduralTextureType::Checkerboard
+        );
+        assert!(scene.primitives.is_empty());
+
+        // Only the triplanar projection needs the primitive's surface
+        texture_baker.projection = BakeProjection::Triplanar;
+        assert_eq!(texture_baker.scene().primitives.len(), 1);
+    }
+}
@@ -4,23 +4,55 @@
 // LICENSE file in the root directory of this source tree.
 
 use crevice::std430::AsStd430;
+use glam::Vec3;
+use strum::{Display, EnumIter, EnumString};
 
 use super::{
     geometry::{
+        bvh::{build_bvh, BoundingBox, Std430GPUBVHNode},
         camera::Camera,
         primitive::{Primitive, Std430GPUPrimitive},
     },
     lights::{Light, Std430GPULight},
-    materials::{Material, Std430GPUMaterial},
+    materials::{
+        Material, Std430GPUColourStop, Std430GPUMaterial, Std430GPUProceduralTexture,
+        TextureBuffers,
+    },
+    textures::Texture,
 };
 use crate::DualDevice;
 
+#[derive(
+    Debug,
+    Default,
+    Display,
+    Copy,
+    Clone,
+    EnumIter,
+    EnumString,
+    PartialEq,
+    PartialOrd,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum AtmosphereDensity {
+    #[default]
+    Constant,
+    HeightFog,
+    LayeredSky,
+}
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone, AsStd430)]
 pub struct GPUSceneParameters {
     num_primitives: u32,
     num_lights: u32,
     num_non_physical_lights: u32,
+    atmosphere_density: u32,
+    fog_base_height: f32,
+    fog_falloff: f32,
+    aerosol_density: f32,
+    aerosol_falloff: f32,
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -30,6 +62,12 @@ pub struct Scene {
     pub primitives: Vec<Primitive>,
     pub lights: Vec<Light>,
     pub atmosphere: Material,
+    pub atmosphere_density: AtmosphereDensity,
+    pub fog_base_height: f32,
+    pub fog_falloff: f32,
+    pub aerosol_density: f32,
+    pub aerosol_falloff: f32,
+    pub textures: Vec<Texture>,
 }
 
 impl Default for Scene {
@@ -41,6 +79,12 @@ impl Default for Scene {
             primitives: vec![],
             lights: vec![],
             atmosphere: atmosphere,
+            atmosphere_density: AtmosphereDensity::Constant,
+            fog_base_height: 0.,
+            fog_falloff: 1.,
+            aerosol_density: 0.,
+            aerosol_falloff: 5.,
+            textures: vec![],
         }
     }
 }
@@ -50,10 +94,24 @@ impl Scene {
         max_buffer_size / size_of::<Std430GPUPrimitive>()
     }
 
+    /// A hierarchy over `max_primitives` leaves has fewer than twice as
+    /// many nodes.
+    pub fn max_bvh_nodes(max_primitives: usize) -> usize {
+        2 * max_primitives
+    }
+
     pub fn max_lights_in_buffer(max_buffer_size: usize) -> usize {
         max_buffer_size / size_of::<Std430GPULight>()
     }
 
+    pub fn max_texture_stack_size_in_buffer(max_buffer_size: usize) -> usize {
+        max_buffer_size / size_of::<Std430GPUProceduralTexture>()
+    }
+
+    pub fn max_colour_ramp_stops_in_buffer(max_buffer_size: usize) -> usize {
+        max_buffer_size / size_of::<Std430GPUColourStop>()
+    }
+
     fn num_primitives(&self, max_primitives: usize) -> u32 {
         max_primitives.min(self.primitives.len()) as u32
     }
@@ -81,22 +139,91 @@ impl Scene {
             num_primitives: self.num_primitives(max_primitives),
             num_lights: self.num_lights(max_primitives + max_lights),
             num_non_physical_lights: self.num_non_physical_lights(max_lights),
+            atmosphere_density: self.atmosphere_density as u32,
+            fog_base_height: self.fog_base_height,
+            fog_falloff: self.fog_falloff.max(0.),
+            aerosol_density: self.aerosol_density.max(0.),
+            aerosol_falloff: self.aerosol_falloff.max(0.),
         }
     }
 
-    pub fn create_gpu_primitives(&self, max_primitives: usize) -> Vec<Std430GPUPrimitive> {
+    pub fn create_gpu_primitives(
+        &self,
+        max_primitives: usize,
+        texture_buffers: &mut TextureBuffers,
+    ) -> Vec<Std430GPUPrimitive> {
         self.primitives
             .iter()
             .take(max_primitives)
             .enumerate()
             .map(|(index, primitive)| {
-                let mut gpu_primitive = primitive.to_gpu();
+                let mut gpu_primitive = primitive.to_gpu_with_texture_buffers(texture_buffers);
                 gpu_primitive.id = (index + 1) as u32;
                 gpu_primitive.as_std430()
             })
             .collect::<Vec<Std430GPUPrimitive>>()
     }
 
+    /// The bounds of a primitive and all of its descendants, as they
+    /// are evaluated together when children interact. A family whose
+    /// root is a bounding volume cannot extend beyond the root, and the
+    /// blending of the others can extend the surface beyond the union
+    /// of its members by up to the blend strength and width.
+    fn family_bounding_box(&self, index: usize, max_primitives: usize) -> Option<BoundingBox> {
+        let primitive: &Primitive = &self.primitives[index];
+        let bounding_box: BoundingBox = primitive.bounding_box()?;
+        if primitive.bounding_volume || primitive.num_descendants == 0 {
+            return Some(bounding_box);
+        }
+
+        let mut family_bounding_box: BoundingBox = bounding_box;
+        let last_descendant: usize = max_primitives
+            .min(self.primitives.len())
+            .min(index + primitive.num_descendants as usize + 1);
+        let mut child_index: usize = index + 1;
+        while child_index < last_descendant {
+            family_bounding_box =
+                family_bounding_box.union(&self.family_bounding_box(child_index, max_primitives)?);
+            child_index += self.primitives[child_index].num_descendants as usize + 1;
+        }
+
+        Some(family_bounding_box.expand(Vec3::splat(
+            primitive.blend_strength.max(0.) + primitive.blend_width.max(0.),
+        )))
+    }
+
+    /// Build the bounding volume hierarchy that the shaders traverse to
+    /// find the primitives near a position. When children interact the
+    /// leaves are whole families, since their descendants are evaluated
+    /// along with them, otherwise they are individual primitives.
+    pub fn create_gpu_bvh_nodes(
+        &self,
+        max_primitives: usize,
+        child_interactions: bool,
+    ) -> Vec<Std430GPUBVHNode> {
+        let num_primitives = self.num_primitives(max_primitives) as usize;
+        let mut leaves: Vec<(u32, Option<BoundingBox>)> = vec![];
+        let mut index: usize = 0;
+        while index < num_primitives {
+            let primitive: &Primitive = &self.primitives[index];
+            if child_interactions {
+                leaves.push((
+                    index as u32,
+                    self.family_bounding_box(index, max_primitives),
+                ));
+                index += primitive.num_descendants as usize + 1;
+            } else {
+                leaves.push((index as u32, primitive.bounding_box()));
+                index += 1;
+            }
+        }
+
+        build_bvh(leaves)
+            .iter()
+            .map(|node| node.as_std430())
+            .collect::<Vec<Std430GPUBVHNode>>()
+    }
+
     pub fn create_gpu_lights(&self, max_lights: usize) -> Vec<Std430GPULight> {
         self.lights
             .iter()
@@ -129,8 +256,15 @@ impl Scene {
         self.to_gpu(max_primitives, max_lights).as_std430()
     }
 
-    pub fn atmosphere(&self) -> Std430GPUMaterial {
-        self.atmosphere.as_std430()
+    pub fn atmosphere(&self, texture_buffers: &mut TextureBuffers) -> Std430GPUMaterial {
+        self.atmosphere
+            .to_gpu_with_texture_buffers(texture_buffers)
+            .as_std430()
+    }
+
+    /// Whether the density of the atmosphere varies with altitude.
+    pub fn has_height_varying_atmosphere(&self) -> bool {
+        self.atmosphere_density > AtmosphereDensity::Constant
     }
 
     pub fn clear_primitives(&mut self) {
@@ -140,4 +274,109 @@ impl Scene {
     pub fn clear_lights(&mut self) {
         self.lights.clear();
     }
+
+    pub fn clear_textures(&mut self) {
+        self.textures.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Mat4;
+
+    use super::*;
+    use crate::geometry::primitive::Shapes;
+
+    fn sphere_at(translation: Vec3, num_descendants: u32) -> Primitive {
+        Primitive {
+            world_matrix: Mat4::from_translation(translation),
+            num_descendants,
+            ..Default::default()
+        }
+    }
+
+    fn assert_contains(outer: &BoundingBox, inner: &BoundingBox) {
+        assert!(
+            outer.minimum.cmple(inner.minimum).all() && outer.maximum.cmpge(inner.maximum).all(),
+            "{:?} does not contain {:?}",
+            outer,
+            inner,
+        );
+    }
+
+    #[test]
+    fn test_family_bounding_box() {
+        let mut scene = Scene {
+            primitives: vec![
+                sphere_at(Vec3::ZERO, 2),
+                sphere_at(Vec3::new(3., 0., 0.), 1),
+                sphere_at(Vec3::new(3., 0., -4.), 0),
+                sphere_at(Vec3::new(-10., 0., 0.), 0),
+            ],
+            ..Default::default()
+        };
+        scene.primitives[0].blend_strength = 0.25;
+        scene.primitives[0].blend_width = 0.5;
+
+        let family_bounding_box: BoundingBox = scene.family_bounding_box(0, 4).unwrap();
+        for primitive in &scene.primitives[..3] {
+            assert_contains(
+                &family_bounding_box,
+                &primitive.bounding_box().unwrap().expand(Vec3::splat(0.75)),
+            );
+        }
+        // The primitive after the family is not part of it
+        assert!(family_bounding_box.minimum.x > -9.);
+
+        // Descendants beyond the maximum number of primitives are ignored
+        let truncated_bounding_box: BoundingBox = scene.family_bounding_box(0, 2).unwrap();
+        assert!(truncated_bounding_box.minimum.z > -3.);
+
+        // The family of a bounding volume cannot extend beyond it
+        scene.primitives[0].bounding_volume = true;
+        assert_eq!(
+            scene.family_bounding_box(0, 4).unwrap(),
+            scene.primitives[0].bounding_box().unwrap(),
+        );
+
+        // An unbounded descendant makes the whole family unbounded
+        scene.primitives[0].bounding_volume = false;
+        scene.primitives[2].shape = Shapes::Plane;
+        assert!(scene.family_bounding_box(0, 4).is_none());
+        assert!(scene.family_bounding_box(3, 4).is_some());
+    }
+
+    #[test]
+    fn test_create_gpu_bvh_nodes() {
+        let scene = Scene {
+            primitives: vec![
+                sphere_at(Vec3::ZERO, 1),
+                sphere_at(Vec3::X, 0),
+                sphere_at(Vec3::new(-10., 0., 0.), 0),
+            ],
+            ..Default::default()
+        };
+
+        // A binary tree over the families, or the individual primitives
+        assert_eq!(scene.create_gpu_bvh_nodes(3, true).len(), 3);
+        assert_eq!(scene.create_gpu_bvh_nodes(3, false).len(), 5);
+        assert_eq!(scene.create_gpu_bvh_nodes(2, false).len(), 3);
+        assert!(Scene::default().create_gpu_bvh_nodes(3, true).is_empty());
+        for max_primitives in 1..=3 {
+            assert!(
+                scene.create_gpu_bvh_nodes(max_primitives, false).len()
+                    <= Scene::max_bvh_nodes(max_primitives)
+            );
+        }
+    }
+
+    #[test]
+    fn test_has_height_varying_atmosphere() {
+        let mut scene = Scene::default();
+        assert!(!scene.has_height_varying_atmosphere());
+        scene.atmosphere_density = AtmosphereDensity::HeightFog;
+        assert!(scene.has_height_varying_atmosphere());
+        scene.atmosphere_density = AtmosphereDensity::LayeredSky;
+        assert!(scene.has_height_varying_atmosphere());
+    }
 }
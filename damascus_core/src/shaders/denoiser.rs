@@ -0,0 +1,17 @@
+// Copyright (c) 2024, Owen Bulka
+// All rights reserved.
+// This source code is licensed under the BSD-style license found in the
+// LICENSE file in the root directory of this source tree.
+
+use std::collections::HashSet;
+
+use super::{process_shader_source, ray_marcher::RayMarcherPreprocessorDirectives};
+
+pub fn denoiser_shader(
+    preprocessor_directives: &HashSet<RayMarcherPreprocessorDirectives>,
+) -> String {
+    process_shader_source(
+        include_str!("./wgsl/renderers/denoiser/denoiser.wgsl"),
+        preprocessor_directives,
+    )
+}
@@ -11,7 +11,13 @@ use strum::{EnumCount, EnumString, IntoEnumIterator};
 use super::{renderers::Renderer, Settings};
 
 pub mod compositor;
+pub mod denoiser;
 pub mod ray_marcher;
+pub mod texture_baker;
+
+/// The width and height of the workgroups of the compute shaders, which
+/// must match the `@workgroup_size` of their entry points.
+pub const WORKGROUP_SIZE: u32 = 8;
 
 #[derive(Debug, EnumString)]
 pub enum Includes {
@@ -22,6 +28,7 @@ pub enum Includes {
     Lights,
     Math,
     Normals,
+    ParticipatingMedia,
     Primitive,
     PrimitiveModifiers,
     PrimitiveSDFs,
@@ -30,6 +37,7 @@ pub enum Includes {
     Ray,
     RayMarcherRenderParameters,
     SceneSDFs,
+    Spectral,
     Texture,
     VertexShader,
 }
@@ -46,6 +54,9 @@ impl Includes {
             Self::Material => include_str!("./wgsl/materials/material.wgsl"),
             Self::Math => include_str!("./wgsl/utils/math.wgsl"),
             Self::Normals => include_str!("./wgsl/geometry/normals.wgsl"),
+            Self::ParticipatingMedia => {
+                include_str!("./wgsl/materials/participating_media.wgsl")
+            }
             Self::Primitive => include_str!("./wgsl/geometry/primitive.wgsl"),
             Self::PrimitiveModifiers => include_str!("./wgsl/geometry/modifiers.wgsl"),
             Self::PrimitiveSDFs => include_str!("./wgsl/geometry/primitive_sdfs.wgsl"),
@@ -56,6 +67,7 @@ impl Includes {
                 include_str!("./wgsl/renderers/ray_marcher/ray_marcher_render_parameters.wgsl")
             }
             Self::SceneSDFs => include_str!("./wgsl/geometry/scene_sdfs.wgsl"),
+            Self::Spectral => include_str!("./wgsl/utils/spectral.wgsl"),
             Self::Texture => include_str!("./wgsl/textures/texture.wgsl"),
             Self::VertexShader => include_str!("./wgsl/renderers/vertex_shader.wgsl"),
         }
@@ -695,4 +707,92 @@ mod tests {
 
         assert_eq!(result_string, expected_result_string);
     }
+
+    #[test]
+    fn test_anisotropy_directive_requires_specular() {
+        let mut material = crate::materials::Material {
+            specular_anisotropy: 0.5,
+            transmissive_probability: 1.,
+            ..Default::default()
+        };
+        assert!(!directives_for_material(&material)
+            .contains(&RayMarcherPreprocessorDirectives::EnableAnisotropicSpecular));
+
+        material.specular_probability = 0.5;
+        assert!(directives_for_material(&material)
+            .contains(&RayMarcherPreprocessorDirectives::EnableAnisotropicSpecular));
+    }
+
+    #[test]
+    fn test_workgroup_size_matches_shaders() {
+        let workgroup_size = format!("@workgroup_size({0}, {0}, 1)", WORKGROUP_SIZE);
+        for source in [
+            texture_baker::texture_baker_shader(&HashSet::new()),
+            denoiser::denoiser_shader(&HashSet::new()),
+        ] {
+            assert!(source.contains("@workgroup_size"));
+            for line in source
+                .lines()
+                .filter(|line| line.contains("@workgroup_size"))
+            {
+                assert!(line.contains(&workgroup_size));
+            }
+        }
+
+        // The ray marcher's workgroups are its adaptive sampling tiles
+        assert!(ray_march_shader(&HashSet::new())
+            .contains(&format!("const TILE_SIZE: u32 = {}u;", WORKGROUP_SIZE)));
+    }
+
+    /// The body of a function in a shader, up to its closing brace.
+    fn function_body<'a>(source: &'a str, name: &str) -> &'a str {
+        let start: usize = source.find(&format!("fn {}(", name)).unwrap();
+        let body: &str = &source[start..];
+        let mut depth: usize = 0;
+        for (index, character) in body.char_indices() {
+            match character {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return &body[..=index];
+                    }
+                }
+                _ => {}
+            }
+        }
+        panic!("{} has no closing brace", name);
+    }
+
+    #[test]
+    fn test_atmosphere_attenuation_matches_homogeneous() {
+        let source: String = ray_march_shader(&HashSet::from([
+            RayMarcherPreprocessorDirectives::EnableHeterogeneousMedia,
+        ]));
+
+        // A height varying atmosphere with a flat density profile must
+        // attenuate light exactly as much as a constant one, so both
+        // only attenuate by scattering under the same condition
+        let homogeneous_attenuation: &str = function_body(&source, "homogeneous_attenuation");
+        assert!(homogeneous_attenuation.contains("out_scattering_attenuates()"));
+        assert!(!homogeneous_attenuation.contains("equiangular_samples"));
+
+        let atmosphere_optical_depth: &str = function_body(&source, "atmosphere_optical_depth");
+        assert!(atmosphere_optical_depth.contains("homogeneous_attenuation("));
+        assert!(!atmosphere_optical_depth.contains("scattering_colour"));
+        assert!(!atmosphere_optical_depth.contains("equiangular_samples"));
+        let aerosols: usize = atmosphere_optical_depth.find("aerosol_density").unwrap();
+        assert!(atmosphere_optical_depth[..aerosols].contains("out_scattering_attenuates()"));
+    }
+
+    #[test]
+    fn test_max_texture_stack_depth_matches_shaders() {
+        assert!(ray_march_shader(&HashSet::from([
+            RayMarcherPreprocessorDirectives::EnableTextureComposition,
+        ]))
+        .contains(&format!(
+            "const MAX_TEXTURE_STACK_DEPTH: u32 = {}u;",
+            crate::materials::MAX_TEXTURE_STACK_DEPTH
+        )));
+    }
 }
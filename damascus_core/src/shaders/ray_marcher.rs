@@ -12,14 +12,17 @@ use super::{process_shader_source, CompilerSettings, PreprocessorDirectives};
 use crate::{
     geometry::{
         primitive::{Primitive, Shapes},
-        BlendType, Repetition,
+        BlendKernel, BlendType, Repetition,
     },
     lights::{Light, Lights},
-    materials::{Material, ProceduralTexture, ProceduralTextureType},
+    materials::{Material, ProceduralTexture, ProceduralTextureType, TangentType},
     renderers::ray_marcher::{AOVs, GPURayMarcher, RayMarcher, Std430GPURayMarcher},
+    scene::Scene,
     Settings,
 };
 
+// The texture baker and denoiser are compiled from the same shader
+// sources as the ray marcher, so they share these directives
 #[derive(
     Debug,
     Clone,
@@ -38,6 +41,7 @@ pub enum RayMarcherPreprocessorDirectives {
     EnableSpecularProbabilityTexture,
     EnableSpecularRoughnessTexture,
     EnableSpecularColourTexture,
+    EnableFilmThicknessTexture,
     EnableTransmissiveProbabilityTexture,
     EnableTransmissiveRoughnessTexture,
     EnableEmissiveColourTexture,
@@ -47,6 +51,12 @@ pub enum RayMarcherPreprocessorDirectives {
     EnableGrade,
     EnableCheckerboard,
     EnableNoise,
+    EnableVoronoiNoise,
+    EnableOctaveNoise,
+    EnableImageTexture,
+    EnableTextureComposition,
+    EnableColourRamp,
+    EnableDomainWarping,
     EnableCappedCone,
     EnableCappedTorus,
     EnableCapsule,
@@ -55,34 +65,64 @@ pub enum RayMarcherPreprocessorDirectives {
     EnableCylinder,
     EnableDeathStar,
     EnableEllipsoid,
+    EnableGyroid,
     EnableHexagonalPrism,
     EnableHollowSphere,
+    EnableHybridFractal,
     EnableInfiniteCone,
     EnableInfiniteCylinder,
+    EnableKaleidoscopicIFS,
     EnableLink,
     EnableMandelbox,
     EnableMandelbulb,
+    EnableMengerSponge,
+    EnableNeovius,
     EnableOctahedron,
     EnablePlane,
+    EnableQuaternionJulia,
     EnableRectangularPrism,
     EnableRectangularPrismFrame,
     EnableRhombus,
     EnableRoundedCone,
+    EnableSchwarzD,
+    EnableSchwarzP,
+    EnableSierpinskiOctahedron,
+    EnableSierpinskiTetrahedron,
     EnableSolidAngle,
+    EnableSuperellipsoid,
     EnableTorus,
     EnableTriangularPrism,
     EnableChildInteractions,
     EnablePrimitiveBlendSubtraction,
     EnablePrimitiveBlendIntersection,
+    EnablePrimitiveBlendExclusiveOr,
+    EnablePrimitiveBlendGroove,
+    EnablePrimitiveBlendTongue,
+    EnablePrimitiveBlendEngrave,
+    EnableExponentialBlendKernel,
+    EnableCircularBlendKernel,
+    EnableChamferBlendKernel,
+    EnableStairsBlendKernel,
+    EnableColumnsBlendKernel,
     EnableInfiniteRepetition,
     EnableFiniteRepetition,
+    EnablePolarRepetition,
+    EnableCellVariation,
     EnableElongation,
     EnableMirroring,
     EnableHollowing,
+    EnableTwist,
+    EnableBend,
+    EnableTaper,
+    EnableDisplacement,
     EnableSpecularMaterials,
     EnableTransmissiveMaterials,
+    EnableAnisotropicSpecular,
+    EnableThinFilmInterference,
+    EnableHeterogeneousMedia,
     EnablePhysicalLights,
     EnableAOVs,
+    EnableSpectralRendering,
     EnableDirectionalLights,
     EnablePointLights,
     EnableAmbientOcclusion,
@@ -103,6 +143,7 @@ pub fn ray_march_shader(
 pub fn all_directives_for_ray_marcher() -> HashSet<RayMarcherPreprocessorDirectives> {
     HashSet::<RayMarcherPreprocessorDirectives>::from([
         RayMarcherPreprocessorDirectives::EnableAOVs,
+        RayMarcherPreprocessorDirectives::EnableSpectralRendering,
     ])
 }
 
@@ -113,6 +154,7 @@ pub fn all_directives_for_material() -> HashSet<RayMarcherPreprocessorDirectives
         RayMarcherPreprocessorDirectives::EnableSpecularProbabilityTexture,
         RayMarcherPreprocessorDirectives::EnableSpecularRoughnessTexture,
         RayMarcherPreprocessorDirectives::EnableSpecularColourTexture,
+        RayMarcherPreprocessorDirectives::EnableFilmThicknessTexture,
         RayMarcherPreprocessorDirectives::EnableTransmissiveProbabilityTexture,
         RayMarcherPreprocessorDirectives::EnableTransmissiveRoughnessTexture,
         RayMarcherPreprocessorDirectives::EnableEmissiveColourTexture,
@@ -122,8 +164,17 @@ pub fn all_directives_for_material() -> HashSet<RayMarcherPreprocessorDirectives
         RayMarcherPreprocessorDirectives::EnableGrade,
         RayMarcherPreprocessorDirectives::EnableCheckerboard,
         RayMarcherPreprocessorDirectives::EnableNoise,
+        RayMarcherPreprocessorDirectives::EnableVoronoiNoise,
+        RayMarcherPreprocessorDirectives::EnableOctaveNoise,
+        RayMarcherPreprocessorDirectives::EnableImageTexture,
+        RayMarcherPreprocessorDirectives::EnableTextureComposition,
+        RayMarcherPreprocessorDirectives::EnableColourRamp,
+        RayMarcherPreprocessorDirectives::EnableDomainWarping,
         RayMarcherPreprocessorDirectives::EnableSpecularMaterials,
         RayMarcherPreprocessorDirectives::EnableTransmissiveMaterials,
+        RayMarcherPreprocessorDirectives::EnableAnisotropicSpecular,
+        RayMarcherPreprocessorDirectives::EnableThinFilmInterference,
+        RayMarcherPreprocessorDirectives::EnableHeterogeneousMedia,
     ])
 }
 
@@ -137,30 +188,56 @@ pub fn all_directives_for_primitive() -> HashSet<RayMarcherPreprocessorDirective
         RayMarcherPreprocessorDirectives::EnableCylinder,
         RayMarcherPreprocessorDirectives::EnableDeathStar,
         RayMarcherPreprocessorDirectives::EnableEllipsoid,
+        RayMarcherPreprocessorDirectives::EnableGyroid,
         RayMarcherPreprocessorDirectives::EnableHexagonalPrism,
         RayMarcherPreprocessorDirectives::EnableHollowSphere,
+        RayMarcherPreprocessorDirectives::EnableHybridFractal,
         RayMarcherPreprocessorDirectives::EnableInfiniteCone,
         RayMarcherPreprocessorDirectives::EnableInfiniteCylinder,
+        RayMarcherPreprocessorDirectives::EnableKaleidoscopicIFS,
         RayMarcherPreprocessorDirectives::EnableLink,
         RayMarcherPreprocessorDirectives::EnableMandelbox,
         RayMarcherPreprocessorDirectives::EnableMandelbulb,
+        RayMarcherPreprocessorDirectives::EnableMengerSponge,
+        RayMarcherPreprocessorDirectives::EnableNeovius,
         RayMarcherPreprocessorDirectives::EnableOctahedron,
         RayMarcherPreprocessorDirectives::EnablePlane,
+        RayMarcherPreprocessorDirectives::EnableQuaternionJulia,
         RayMarcherPreprocessorDirectives::EnableRectangularPrism,
         RayMarcherPreprocessorDirectives::EnableRectangularPrismFrame,
         RayMarcherPreprocessorDirectives::EnableRhombus,
         RayMarcherPreprocessorDirectives::EnableRoundedCone,
+        RayMarcherPreprocessorDirectives::EnableSchwarzD,
+        RayMarcherPreprocessorDirectives::EnableSchwarzP,
+        RayMarcherPreprocessorDirectives::EnableSierpinskiOctahedron,
+        RayMarcherPreprocessorDirectives::EnableSierpinskiTetrahedron,
         RayMarcherPreprocessorDirectives::EnableSolidAngle,
+        RayMarcherPreprocessorDirectives::EnableSuperellipsoid,
         RayMarcherPreprocessorDirectives::EnableTorus,
         RayMarcherPreprocessorDirectives::EnableTriangularPrism,
         RayMarcherPreprocessorDirectives::EnableChildInteractions,
         RayMarcherPreprocessorDirectives::EnablePrimitiveBlendSubtraction,
         RayMarcherPreprocessorDirectives::EnablePrimitiveBlendIntersection,
+        RayMarcherPreprocessorDirectives::EnablePrimitiveBlendExclusiveOr,
+        RayMarcherPreprocessorDirectives::EnablePrimitiveBlendGroove,
+        RayMarcherPreprocessorDirectives::EnablePrimitiveBlendTongue,
+        RayMarcherPreprocessorDirectives::EnablePrimitiveBlendEngrave,
+        RayMarcherPreprocessorDirectives::EnableExponentialBlendKernel,
+        RayMarcherPreprocessorDirectives::EnableCircularBlendKernel,
+        RayMarcherPreprocessorDirectives::EnableChamferBlendKernel,
+        RayMarcherPreprocessorDirectives::EnableStairsBlendKernel,
+        RayMarcherPreprocessorDirectives::EnableColumnsBlendKernel,
         RayMarcherPreprocessorDirectives::EnableInfiniteRepetition,
         RayMarcherPreprocessorDirectives::EnableFiniteRepetition,
+        RayMarcherPreprocessorDirectives::EnablePolarRepetition,
+        RayMarcherPreprocessorDirectives::EnableCellVariation,
         RayMarcherPreprocessorDirectives::EnableElongation,
         RayMarcherPreprocessorDirectives::EnableMirroring,
         RayMarcherPreprocessorDirectives::EnableHollowing,
+        RayMarcherPreprocessorDirectives::EnableTwist,
+        RayMarcherPreprocessorDirectives::EnableBend,
+        RayMarcherPreprocessorDirectives::EnableTaper,
+        RayMarcherPreprocessorDirectives::EnableDisplacement,
         RayMarcherPreprocessorDirectives::EnablePhysicalLights,
     ])
 }
@@ -183,6 +260,10 @@ pub fn directives_for_ray_marcher(
         preprocessor_directives.insert(RayMarcherPreprocessorDirectives::EnableAOVs);
     }
 
+    if ray_marcher.spectral_rendering {
+        preprocessor_directives.insert(RayMarcherPreprocessorDirectives::EnableSpectralRendering);
+    }
+
     preprocessor_directives
 }
 
@@ -205,6 +286,46 @@ pub fn directives_for_primitive(
                 preprocessor_directives
                     .insert(RayMarcherPreprocessorDirectives::EnablePrimitiveBlendIntersection);
             }
+            BlendType::ExclusiveOr => {
+                preprocessor_directives
+                    .insert(RayMarcherPreprocessorDirectives::EnablePrimitiveBlendExclusiveOr);
+            }
+            BlendType::Groove => {
+                preprocessor_directives
+                    .insert(RayMarcherPreprocessorDirectives::EnablePrimitiveBlendGroove);
+            }
+            BlendType::Tongue => {
+                preprocessor_directives
+                    .insert(RayMarcherPreprocessorDirectives::EnablePrimitiveBlendTongue);
+            }
+            BlendType::Engrave => {
+                preprocessor_directives
+                    .insert(RayMarcherPreprocessorDirectives::EnablePrimitiveBlendEngrave);
+            }
+            _ => {}
+        }
+
+        match primitive.blend_kernel {
+            BlendKernel::Exponential => {
+                preprocessor_directives
+                    .insert(RayMarcherPreprocessorDirectives::EnableExponentialBlendKernel);
+            }
+            BlendKernel::Circular => {
+                preprocessor_directives
+                    .insert(RayMarcherPreprocessorDirectives::EnableCircularBlendKernel);
+            }
+            BlendKernel::Chamfer => {
+                preprocessor_directives
+                    .insert(RayMarcherPreprocessorDirectives::EnableChamferBlendKernel);
+            }
+            BlendKernel::Stairs => {
+                preprocessor_directives
+                    .insert(RayMarcherPreprocessorDirectives::EnableStairsBlendKernel);
+            }
+            BlendKernel::Columns => {
+                preprocessor_directives
+                    .insert(RayMarcherPreprocessorDirectives::EnableColumnsBlendKernel);
+            }
             _ => {}
         }
     }
@@ -218,9 +339,16 @@ pub fn directives_for_primitive(
             preprocessor_directives
                 .insert(RayMarcherPreprocessorDirectives::EnableInfiniteRepetition);
         }
+        Repetition::Polar => {
+            preprocessor_directives.insert(RayMarcherPreprocessorDirectives::EnablePolarRepetition);
+        }
         _ => {}
     }
 
+    if primitive.cell_variation && primitive.repetition != Repetition::None {
+        preprocessor_directives.insert(RayMarcherPreprocessorDirectives::EnableCellVariation);
+    }
+
     if primitive.elongate {
         preprocessor_directives.insert(RayMarcherPreprocessorDirectives::EnableElongation);
     }
@@ -233,6 +361,25 @@ pub fn directives_for_primitive(
         preprocessor_directives.insert(RayMarcherPreprocessorDirectives::EnableHollowing);
     }
 
+    if primitive.twist {
+        preprocessor_directives.insert(RayMarcherPreprocessorDirectives::EnableTwist);
+    }
+
+    if primitive.bend {
+        preprocessor_directives.insert(RayMarcherPreprocessorDirectives::EnableBend);
+    }
+
+    if primitive.taper {
+        preprocessor_directives.insert(RayMarcherPreprocessorDirectives::EnableTaper);
+    }
+
+    if primitive.displacement_amplitude != 0. {
+        preprocessor_directives.insert(RayMarcherPreprocessorDirectives::EnableDisplacement);
+        preprocessor_directives.extend(directives_for_procedural_texture(
+            &primitive.displacement_texture,
+        ));
+    }
+
     if primitive.shape == Shapes::Sphere {
         return preprocessor_directives;
     }
@@ -260,6 +407,33 @@ pub fn directives_for_procedural_texture(
         || procedural_texture.texture_type == ProceduralTextureType::TurbulenceNoise
     {
         preprocessor_directives.insert(RayMarcherPreprocessorDirectives::EnableNoise);
+        preprocessor_directives.insert(RayMarcherPreprocessorDirectives::EnableOctaveNoise);
+    } else if procedural_texture.texture_type == ProceduralTextureType::VoronoiNoise {
+        preprocessor_directives.insert(RayMarcherPreprocessorDirectives::EnableVoronoiNoise);
+        preprocessor_directives.insert(RayMarcherPreprocessorDirectives::EnableOctaveNoise);
+    } else if procedural_texture.texture_type == ProceduralTextureType::Image
+        || procedural_texture.texture_type == ProceduralTextureType::ImageGrid
+    {
+        preprocessor_directives.insert(RayMarcherPreprocessorDirectives::EnableImageTexture);
+    }
+
+    if procedural_texture.use_colour_ramp {
+        preprocessor_directives.insert(RayMarcherPreprocessorDirectives::EnableColourRamp);
+    }
+
+    if procedural_texture.is_domain_warped() {
+        preprocessor_directives.insert(RayMarcherPreprocessorDirectives::EnableNoise);
+        preprocessor_directives.insert(RayMarcherPreprocessorDirectives::EnableDomainWarping);
+    }
+
+    if procedural_texture.is_composite() {
+        preprocessor_directives.insert(RayMarcherPreprocessorDirectives::EnableTextureComposition);
+        for texture in [&procedural_texture.input, &procedural_texture.mask]
+            .into_iter()
+            .flatten()
+        {
+            preprocessor_directives.extend(directives_for_procedural_texture(texture));
+        }
     }
 
     preprocessor_directives
@@ -296,6 +470,13 @@ pub fn directives_for_material(material: &Material) -> HashSet<RayMarcherPreproc
             &material.specular_colour_texture,
         ));
     }
+    if material.film_thickness_texture.texture_type > ProceduralTextureType::None {
+        preprocessor_directives
+            .insert(RayMarcherPreprocessorDirectives::EnableFilmThicknessTexture);
+        preprocessor_directives.extend(directives_for_procedural_texture(
+            &material.film_thickness_texture,
+        ));
+    }
     if material.transmissive_probability_texture.texture_type > ProceduralTextureType::None {
         preprocessor_directives
             .insert(RayMarcherPreprocessorDirectives::EnableTransmissiveProbabilityTexture);
@@ -338,6 +519,11 @@ pub fn directives_for_material(material: &Material) -> HashSet<RayMarcherPreproc
             &material.scattering_colour_texture,
         ));
     }
+    if material.is_heterogeneous_medium() {
+        preprocessor_directives.insert(RayMarcherPreprocessorDirectives::EnableHeterogeneousMedia);
+        preprocessor_directives
+            .extend(directives_for_procedural_texture(&material.density_texture));
+    }
 
     if material.transmissive_probability > 0. {
         preprocessor_directives.insert(RayMarcherPreprocessorDirectives::EnableSpecularMaterials);
@@ -347,6 +533,22 @@ pub fn directives_for_material(material: &Material) -> HashSet<RayMarcherPreproc
         preprocessor_directives.insert(RayMarcherPreprocessorDirectives::EnableSpecularMaterials);
     }
 
+    // Only the specular reflection lobe is anisotropic
+    if material.specular_probability > 0. && material.specular_anisotropy > 0. {
+        preprocessor_directives.insert(RayMarcherPreprocessorDirectives::EnableAnisotropicSpecular);
+        if material.tangent_type == TangentType::TextureGradient {
+            preprocessor_directives
+                .extend(directives_for_procedural_texture(&material.tangent_texture));
+        }
+    }
+
+    if (material.specular_probability > 0. || material.transmissive_probability > 0.)
+        && material.film_thickness > 0.
+    {
+        preprocessor_directives
+            .insert(RayMarcherPreprocessorDirectives::EnableThinFilmInterference);
+    }
+
     if material.diffuse_colour_texture.use_trap_colour
         || material.specular_colour_texture.use_trap_colour
         || material.emissive_colour_texture.use_trap_colour
@@ -361,6 +563,16 @@ pub fn directives_for_material(material: &Material) -> HashSet<RayMarcherPreproc
     preprocessor_directives
 }
 
+pub fn directives_for_atmosphere(scene: &Scene) -> HashSet<RayMarcherPreprocessorDirectives> {
+    let mut preprocessor_directives = directives_for_material(&scene.atmosphere);
+
+    if scene.has_height_varying_atmosphere() {
+        preprocessor_directives.insert(RayMarcherPreprocessorDirectives::EnableHeterogeneousMedia);
+    }
+
+    preprocessor_directives
+}
+
 pub fn directives_for_light(light: &Light) -> HashSet<RayMarcherPreprocessorDirectives> {
     let mut preprocessor_directives = HashSet::<RayMarcherPreprocessorDirectives>::new();
 
@@ -434,7 +646,7 @@ impl
         if !self.enable_dynamic_recompilation_for_materials {
             preprocessor_directives.extend(all_directives_for_material());
         } else {
-            preprocessor_directives.extend(directives_for_material(&renderer.scene.atmosphere));
+            preprocessor_directives.extend(directives_for_atmosphere(&renderer.scene));
         }
 
         if !self.enable_dynamic_recompilation_for_lights {
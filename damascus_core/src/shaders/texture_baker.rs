@@ -0,0 +1,37 @@
+// Copyright (c) 2024, Owen Bulka
+// All rights reserved.
+// This source code is licensed under the BSD-style license found in the
+// LICENSE file in the root directory of this source tree.
+
+use std::collections::HashSet;
+
+use super::{
+    process_shader_source,
+    ray_marcher::{
+        directives_for_material, directives_for_primitive, RayMarcherPreprocessorDirectives,
+    },
+};
+
+use crate::renderers::texture_baker::{BakeProjection, TextureBaker};
+
+pub fn texture_baker_shader(
+    preprocessor_directives: &HashSet<RayMarcherPreprocessorDirectives>,
+) -> String {
+    process_shader_source(
+        include_str!("./wgsl/renderers/texture_baker/texture_baker.wgsl"),
+        preprocessor_directives,
+    )
+}
+
+pub fn directives_for_texture_baker(
+    texture_baker: &TextureBaker,
+) -> HashSet<RayMarcherPreprocessorDirectives> {
+    let scene = texture_baker.scene();
+    let mut preprocessor_directives = directives_for_material(&scene.atmosphere);
+
+    if texture_baker.projection == BakeProjection::Triplanar {
+        preprocessor_directives.extend(directives_for_primitive(&texture_baker.primitive));
+    }
+
+    preprocessor_directives
+}
@@ -93,6 +93,7 @@ fn create_render_camera_ray(seed: vec2f, uv_coordinate: vec2f) -> Ray {
             ),
             vec3(0.),
             vec3(1.),
+            0.,
         );
     }
     var ray = Ray(
@@ -106,6 +107,7 @@ fn create_render_camera_ray(seed: vec2f, uv_coordinate: vec2f) -> Ray {
         ).xyz),
         vec3(0.),
         vec3(1.),
+        0.,
     );
 
     if (!bool(_render_camera.flags & ENABLE_DEPTH_OF_FIELD)) {
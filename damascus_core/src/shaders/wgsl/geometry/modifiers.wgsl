@@ -11,10 +11,39 @@ const MIRROR_X: u32 = 8u;
 const MIRROR_Y: u32 = 16u;
 const MIRROR_Z: u32 = 32u;
 const HOLLOW: u32 = 64u;
-const SUBTRACTION: u32 = 128u;
-const INTERSECTION: u32 = 256u;
-const BLEND_TYPE_MASK: u32 = 384u;
+const POLAR_REPETITION: u32 = 128u;
+const POLAR_MIRROR: u32 = 256u;
 const BOUNDING_VOLUME: u32 = 512u;
+const TWIST: u32 = 1024u;
+const BEND: u32 = 2048u;
+const TAPER: u32 = 4096u;
+const CELL_VARIATION: u32 = 8192u;
+// The material, or displacement, textures project images along the
+// surface normal, which has to be estimated
+const MATERIAL_TEXTURE_NORMAL: u32 = 16384u;
+const DISPLACEMENT_TEXTURE_NORMAL: u32 = 32768u;
+
+// Blend types
+const UNION: u32 = 0u;
+const SUBTRACTION: u32 = 1u;
+const INTERSECTION: u32 = 2u;
+const EXCLUSIVE_OR: u32 = 3u;
+const GROOVE: u32 = 4u;
+const TONGUE: u32 = 5u;
+const ENGRAVE: u32 = 6u;
+
+// Blend kernels
+const POLYNOMIAL_BLEND_KERNEL: u32 = 0u;
+const EXPONENTIAL_BLEND_KERNEL: u32 = 1u;
+const CIRCULAR_BLEND_KERNEL: u32 = 2u;
+const CHAMFER_BLEND_KERNEL: u32 = 3u;
+const STAIRS_BLEND_KERNEL: u32 = 4u;
+const COLUMNS_BLEND_KERNEL: u32 = 5u;
+
+// Axes
+const X_AXIS: u32 = 0u;
+const Y_AXIS: u32 = 1u;
+const Z_AXIS: u32 = 2u;
 
 
 /**
@@ -105,6 +134,449 @@ fn mirrored_infinite_repetition(
 #endif
 
 
+#ifdef EnablePolarRepetition
+/**
+ * Repeat an object in segments around an axis. The object is offset
+ * from the axis along the next axis, cyclically (x to y, y to z, z to
+ * x), by the polar radius.
+ *
+ * @arg position: The position of the ray.
+ * @arg primitive: The primitive to repeat.
+ *
+ * @returns: The modified ray position that results in repetion.
+ */
+fn polar_repetition(
+    position: vec3f,
+    primitive: ptr<function, Primitive>,
+) -> vec3f {
+    var axis_position: vec3f = to_axis_space(position, (*primitive).polar_axis);
+    var segment_angle: f32 = TWO_PI / (*primitive).polar_segments;
+
+    var angle: f32 = atan2(axis_position.y, axis_position.x) + 0.5 * segment_angle;
+    var segment: f32 = floor(angle / segment_angle);
+    angle = modulo_f32(angle, segment_angle) - 0.5 * segment_angle;
+
+    var repeated_position: vec2f = length(axis_position.xy) * vec2(cos(angle), sin(angle));
+    repeated_position.x -= (*primitive).polar_radius;
+
+    // Mirror every other segment so that neighbours meet symmetrically
+    repeated_position.y = select(
+        repeated_position.y,
+        -repeated_position.y,
+        bool((*primitive).modifiers & POLAR_MIRROR) && (i32(segment) & 1) == 1,
+    );
+
+    return from_axis_space(
+        vec3(repeated_position, axis_position.z),
+        (*primitive).polar_axis,
+    );
+}
+#endif
+
+
+#ifdef EnableCellVariation
+/**
+ * Get the index of the repetition cell that a position falls in.
+ *
+ * @arg position: The position of the ray, before repetition.
+ * @arg primitive: The repeated primitive.
+ *
+ * @returns: The index of the cell. Polar repetition only uses x.
+ */
+fn repetition_cell(position: vec3f, primitive: ptr<function, Primitive>) -> vec3f {
+    if bool((*primitive).modifiers & POLAR_REPETITION) {
+        var axis_position: vec3f = to_axis_space(position, (*primitive).polar_axis);
+        var segment_angle: f32 = TWO_PI / (*primitive).polar_segments;
+        var segment: f32 = floor(
+            (atan2(axis_position.y, axis_position.x) + 0.5 * segment_angle) / segment_angle
+        );
+        return vec3(modulo_f32(segment, (*primitive).polar_segments), 0., 0.);
+    }
+
+    var cell: vec3f = round(position / (*primitive).spacing);
+    return select(
+        cell,
+        clamp(cell, -(*primitive).negative_repetitions, (*primitive).positive_repetitions),
+        bool((*primitive).modifiers & FINITE_REPETITION),
+    );
+}
+
+
+/**
+ * Get a conservative distance for a hidden cell, which is the distance
+ * to the cell's boundary plus a margin that will always exceed the ray
+ * marcher's hit tolerance, so that the ray passes into the next cell.
+ *
+ * @arg position: The position of the ray, before repetition.
+ * @arg cell: The index of the cell the position is in.
+ * @arg primitive: The repeated primitive.
+ *
+ * @returns: The distance to step through the hidden cell.
+ */
+fn hidden_cell_distance(
+    position: vec3f,
+    cell: vec3f,
+    primitive: ptr<function, Primitive>,
+) -> f32 {
+    var distance_to_boundary: f32;
+    if bool((*primitive).modifiers & POLAR_REPETITION) {
+        var axis_position: vec3f = to_axis_space(position, (*primitive).polar_axis);
+        var half_segment_angle: f32 = PI / (*primitive).polar_segments;
+        var angle_to_boundary: f32 = half_segment_angle - abs(
+            modulo_f32(
+                atan2(axis_position.y, axis_position.x) + half_segment_angle,
+                2. * half_segment_angle,
+            ) - half_segment_angle
+        );
+        var radius: f32 = length(axis_position.xy);
+        distance_to_boundary = select(
+            radius * sin(angle_to_boundary),
+            radius,
+            angle_to_boundary > 0.5 * PI,
+        );
+        distance_to_boundary = select(
+            distance_to_boundary,
+            _render_parameters.max_distance,
+            (*primitive).polar_segments < 2.,
+        );
+    } else {
+        var cell_position: vec3f = position - (*primitive).spacing * cell;
+        var distance_to_lower: vec3f = cell_position + 0.5 * (*primitive).spacing;
+        var distance_to_upper: vec3f = 0.5 * (*primitive).spacing - cell_position;
+
+        // The outermost cells of finite repetition extend to infinity
+        var finite: bool = bool((*primitive).modifiers & FINITE_REPETITION);
+        distance_to_lower = select(
+            distance_to_lower,
+            vec3(_render_parameters.max_distance),
+            vec3(finite) & (cell <= -(*primitive).negative_repetitions),
+        );
+        distance_to_upper = select(
+            distance_to_upper,
+            vec3(_render_parameters.max_distance),
+            vec3(finite) & (cell >= (*primitive).positive_repetitions),
+        );
+        distance_to_boundary = min_component_vec3f(min(distance_to_lower, distance_to_upper));
+    }
+
+    return (
+        max(distance_to_boundary, 0.)
+        + _render_parameters.hit_tolerance * (2. + _render_parameters.max_distance)
+    );
+}
+
+
+/**
+ * Hash the index of a repetition cell into an ID.
+ *
+ * @arg cell: The index of the cell.
+ *
+ * @returns: The ID of the cell, which is zero for the origin cell.
+ */
+fn repetition_cell_id(cell: vec3f) -> u32 {
+    var index = vec3u(vec3i(cell));
+    return (index.x * 73856093u) ^ (index.y * 19349663u) ^ (index.z * 83492791u);
+}
+
+
+/**
+ * Get a random value on the interval [0, 1] that is constant within a
+ * repetition cell.
+ *
+ * @arg cell_id: The ID of the cell.
+ * @arg seed: The random seed.
+ *
+ * @returns: A random value on the interval [0, 1].
+ */
+fn cell_random_f32(cell_id: u32, seed: f32) -> f32 {
+    return vec3f_to_random_f32(
+        vec3(f32(cell_id >> 16u), f32(cell_id & 0xffffu), seed),
+    );
+}
+
+
+/**
+ * Get a random vector with components on the interval [-1, 1] that is
+ * constant within a repetition cell.
+ *
+ * @arg cell_id: The ID of the cell.
+ * @arg seed: The random seed.
+ *
+ * @returns: A random vector with components on the interval [-1, 1].
+ */
+fn cell_random_signed_vec3f(cell_id: u32, seed: f32) -> vec3f {
+    return 2. * vec3(
+        cell_random_f32(cell_id, seed),
+        cell_random_f32(cell_id, seed + 0.03),
+        cell_random_f32(cell_id, seed + 0.06),
+    ) - 1.;
+}
+
+
+/**
+ * Randomly scale, rotate, and jitter the copy of a primitive in each
+ * repetition cell, and hide cells at random. The ID of the cell is
+ * stored on the primitive so that its material and AOVs can use it.
+ * The fractional offsets added to the seeds keep each attribute
+ * independent when they share a seed.
+ *
+ * @arg position: The position of the ray, before repetition.
+ * @arg repeated_position: The position of the ray, after repetition.
+ * @arg primitive: The repeated primitive.
+ * @arg lipschitz_bound: Will be multiplied by the Lipschitz bound of
+ *     the scaling, which the distance must be divided by.
+ *
+ * @returns: The position within the varied cell.
+ */
+fn vary_cell(
+    position: vec3f,
+    repeated_position: vec3f,
+    primitive: ptr<function, Primitive>,
+    lipschitz_bound: ptr<function, f32>,
+) -> vec3f {
+    var cell: vec3f = repetition_cell(position, primitive);
+    var cell_id: u32 = repetition_cell_id(cell);
+    (*primitive).cell_id = cell_id;
+
+    var visible: bool = (
+        cell_random_f32(cell_id, (*primitive).cell_material_seeds.z + 0.6)
+        < (*primitive).cell_visibility
+    );
+    (*primitive).cell_clearance = select(
+        hidden_cell_distance(position, cell, primitive),
+        0.,
+        visible,
+    );
+
+    var varied_position: vec3f = repeated_position - (*primitive).cell_jitter * (
+        cell_random_signed_vec3f(cell_id, (*primitive).cell_transform_seeds.z + 0.3)
+    );
+
+    // The transpose of a rotation is its inverse
+    varied_position = transpose(
+        euler_rotation_matrix(
+            (*primitive).cell_rotation_variation * cell_random_signed_vec3f(
+                cell_id,
+                (*primitive).cell_transform_seeds.y + 0.2,
+            ),
+        )
+    ) * varied_position;
+
+    var scale: f32 = 1. + (*primitive).cell_scale_variation * (
+        2. * cell_random_f32(cell_id, (*primitive).cell_transform_seeds.x + 0.1) - 1.
+    );
+    *lipschitz_bound /= scale;
+
+    return varied_position / scale;
+}
+
+
+/**
+ * Rotate the hue of a colour around the achromatic axis.
+ *
+ * @arg colour: The colour to rotate.
+ * @arg angle: The angle, in radians, to rotate the hue by.
+ *
+ * @returns: The colour with its hue rotated.
+ */
+fn rotate_hue(colour: vec3f, angle: f32) -> vec3f {
+    var axis = vec3(0.5773502691896258);
+    var cosine: f32 = cos(angle);
+    return max(
+        colour * cosine
+        + cross(axis, colour) * sin(angle)
+        + axis * dot(axis, colour) * (1. - cosine),
+        vec3(0.),
+    );
+}
+
+
+/**
+ * Randomly vary the hue and value of the diffuse colour of a primitive
+ * in the repetition cell it was last evaluated in.
+ *
+ * @arg primitive: The repeated primitive.
+ */
+fn vary_cell_material(primitive: ptr<function, Primitive>) {
+    if !bool((*primitive).modifiers & CELL_VARIATION) {
+        return;
+    }
+    var hue_angle: f32 = PI * (*primitive).cell_hue_variation * (
+        2. * cell_random_f32((*primitive).cell_id, (*primitive).cell_material_seeds.x + 0.4) - 1.
+    );
+    var value_scale: f32 = 1. + (*primitive).cell_value_variation * (
+        2. * cell_random_f32((*primitive).cell_id, (*primitive).cell_material_seeds.y + 0.5) - 1.
+    );
+    (*primitive).material.diffuse_colour = value_scale * rotate_hue(
+        (*primitive).material.diffuse_colour,
+        hue_angle,
+    );
+}
+#endif
+
+
+/**
+ * Cyclically permute the components of a position so that the chosen
+ * axis becomes the z-axis.
+ *
+ * @arg position: The position to permute.
+ * @arg axis: The axis that will become the z-axis.
+ *
+ * @returns: The permuted position.
+ */
+fn to_axis_space(position: vec3f, axis: u32) -> vec3f {
+    switch axis {
+        case X_AXIS {
+            return position.yzx;
+        }
+        case Y_AXIS {
+            return position.zxy;
+        }
+        default {
+            return position;
+        }
+    }
+}
+
+
+/**
+ * Undo the permutation performed by `to_axis_space`.
+ *
+ * @arg position: The permuted position.
+ * @arg axis: The axis that was made the z-axis.
+ *
+ * @returns: The position in its original space.
+ */
+fn from_axis_space(position: vec3f, axis: u32) -> vec3f {
+    switch axis {
+        case X_AXIS {
+            return position.zxy;
+        }
+        case Y_AXIS {
+            return position.yzx;
+        }
+        default {
+            return position;
+        }
+    }
+}
+
+
+#ifdef EnableTwist
+/**
+ * Twist the space around an axis, by an angle proportional to the
+ * distance along the axis.
+ *
+ * @arg position: The position to twist.
+ * @arg primitive: The primitive which determines the twist.
+ * @arg lipschitz_bound: Will be multiplied by the Lipschitz bound of
+ *     the twist at this position.
+ *
+ * @returns: The twisted position.
+ */
+fn twist_position(
+    position: vec3f,
+    primitive: ptr<function, Primitive>,
+    lipschitz_bound: ptr<function, f32>,
+) -> vec3f {
+    var axis_position: vec3f = to_axis_space(position, (*primitive).twist_axis);
+    var angle: f32 = (*primitive).twist_rate * axis_position.z;
+    var cosine: f32 = cos(angle);
+    var sine: f32 = sin(angle);
+
+    // Points further from the axis are sheared further by the twist
+    *lipschitz_bound *= sqrt(
+        1. + pow((*primitive).twist_rate * length(axis_position.xy), 2.)
+    );
+
+    return from_axis_space(
+        vec3(
+            cosine * axis_position.x + sine * axis_position.y,
+            cosine * axis_position.y - sine * axis_position.x,
+            axis_position.z,
+        ),
+        (*primitive).twist_axis,
+    );
+}
+#endif
+
+
+#ifdef EnableBend
+/**
+ * Bend the space around an axis, so that the next axis, cyclically,
+ * follows an arc with the bend radius.
+ *
+ * @arg position: The position to bend.
+ * @arg primitive: The primitive which determines the bend.
+ * @arg lipschitz_bound: Will be multiplied by the Lipschitz bound of
+ *     the bend at this position.
+ *
+ * @returns: The bent position.
+ */
+fn bend_position(
+    position: vec3f,
+    primitive: ptr<function, Primitive>,
+    lipschitz_bound: ptr<function, f32>,
+) -> vec3f {
+    var axis_position: vec3f = to_axis_space(position, (*primitive).bend_axis);
+    var radius: f32 = max(abs((*primitive).bend_radius), 1e-3);
+    var bend_sign: f32 = select(1., -1., (*primitive).bend_radius < 0.);
+
+    var to_centre = vec2(axis_position.x, radius - bend_sign * axis_position.y);
+    var distance_to_centre: f32 = max(length(to_centre), 1e-6);
+
+    // Space inside the arc is compressed, and must be stretched back
+    *lipschitz_bound *= max(1., radius / distance_to_centre);
+
+    return from_axis_space(
+        vec3(
+            radius * atan2(to_centre.x, to_centre.y),
+            bend_sign * (radius - distance_to_centre),
+            axis_position.z,
+        ),
+        (*primitive).bend_axis,
+    );
+}
+#endif
+
+
+#ifdef EnableTaper
+/**
+ * Taper the space along an axis, scaling the perpendicular axes by an
+ * amount proportional to the distance along the axis.
+ *
+ * @arg position: The position to taper.
+ * @arg primitive: The primitive which determines the taper.
+ * @arg lipschitz_bound: Will be multiplied by the Lipschitz bound of
+ *     the taper at this position.
+ *
+ * @returns: The tapered position.
+ */
+fn taper_position(
+    position: vec3f,
+    primitive: ptr<function, Primitive>,
+    lipschitz_bound: ptr<function, f32>,
+) -> vec3f {
+    var axis_position: vec3f = to_axis_space(position, (*primitive).taper_axis);
+    var scale: f32 = max(1. + (*primitive).taper_rate * axis_position.z, 0.01);
+    var inverse_scale: f32 = 1. / scale;
+
+    *lipschitz_bound *= sqrt(
+        max(1., inverse_scale * inverse_scale)
+        + pow(
+            (*primitive).taper_rate * length(axis_position.xy) * inverse_scale * inverse_scale,
+            2.,
+        )
+    );
+
+    return from_axis_space(
+        vec3(axis_position.xy * inverse_scale, axis_position.z),
+        (*primitive).taper_axis,
+    );
+}
+#endif
+
+
 /**
  * Modify the distance a ray has travelled, resulting in various
  * effects.
@@ -116,14 +588,23 @@ fn mirrored_infinite_repetition(
  */
 fn modify_distance(distance: f32, primitive: ptr<function, Primitive>) -> f32 {
 #ifdef EnableHollowing
-    return select(
+    var modified_distance: f32 = select(
         distance,
         abs(distance) - (*primitive).wall_thickness,
         bool((*primitive).modifiers & HOLLOW),
     ) - (*primitive).edge_radius;
 #else
-    return distance - (*primitive).edge_radius;
+    var modified_distance: f32 = distance - (*primitive).edge_radius;
+#endif
+#ifdef EnableCellVariation
+    // Step over the repetition cells that have been hidden
+    modified_distance = select(
+        modified_distance,
+        (*primitive).cell_clearance,
+        (*primitive).cell_clearance > 0.,
+    );
 #endif
+    return modified_distance;
 }
 
 
@@ -147,16 +628,19 @@ fn rotate_translate_position(
 
 
 /**
- * Mirror/elongate/repeate primitive at a position.
+ * Mirror/elongate/repeate/deform primitive at a position.
  *
  * @arg position: The location the ray originates from.
  * @arg primitive: The primitive which determines the transformation.
+ * @arg lipschitz_bound: Will be multiplied by the Lipschitz bound of
+ *     the deformations, which the distance must be divided by.
  *
  * @returns: The transformed ray origin.
  */
 fn transform_position(
     position: vec3f,
     primitive: ptr<function, Primitive>,
+    lipschitz_bound: ptr<function, f32>,
 ) -> vec3f {
     // Perform finite or infinite repetition if enabled
 #ifdef EnableFiniteRepetition
@@ -191,6 +675,25 @@ fn transform_position(
     );
 #endif
 
+#ifdef EnablePolarRepetition
+    // Perform polar repetition if enabled
+    if bool((*primitive).modifiers & POLAR_REPETITION) {
+        transformed_position = polar_repetition(transformed_position, primitive);
+    }
+#endif
+
+#ifdef EnableCellVariation
+    // Randomly vary each repetition cell if enabled
+    if bool((*primitive).modifiers & CELL_VARIATION) {
+        transformed_position = vary_cell(
+            position,
+            transformed_position,
+            primitive,
+            lipschitz_bound,
+        );
+    }
+#endif
+
 #ifdef EnableElongation
     // Perform elongation if enabled
     transformed_position -= select(
@@ -206,7 +709,7 @@ fn transform_position(
 
 #ifdef EnableMirroring
     // Perform mirroring if enabled
-    return select(
+    transformed_position = select(
         transformed_position,
         abs(transformed_position),
         vec3<bool>(
@@ -215,10 +718,177 @@ fn transform_position(
             bool((*primitive).modifiers & MIRROR_Z),
         ),
     );
-#else
+#endif
+
+#ifdef EnableBend
+    // Perform bending if enabled
+    if bool((*primitive).modifiers & BEND) {
+        transformed_position = bend_position(
+            transformed_position,
+            primitive,
+            lipschitz_bound,
+        );
+    }
+#endif
+
+#ifdef EnableTwist
+    // Perform twisting if enabled
+    if bool((*primitive).modifiers & TWIST) {
+        transformed_position = twist_position(
+            transformed_position,
+            primitive,
+            lipschitz_bound,
+        );
+    }
+#endif
+
+#ifdef EnableTaper
+    // Perform tapering if enabled
+    if bool((*primitive).modifiers & TAPER) {
+        transformed_position = taper_position(
+            transformed_position,
+            primitive,
+            lipschitz_bound,
+        );
+    }
+#endif
+
     return transformed_position;
+}
+
+
+/**
+ * Get the coordinates to evaluate a primitive's textures at.
+ *
+ * @arg position: The position in the primitive's local space.
+ * @arg normal: The surface normal in the primitive's local space.
+ * @arg primitive: The primitive being textured.
+ *
+ * @returns: The texture coordinates in local and world space.
+ */
+fn primitive_texture_coordinates(
+    position: vec3f,
+    normal: vec3f,
+    primitive: ptr<function, Primitive>,
+) -> TextureCoordinates {
+    var seed_offset: f32 = length((*primitive).dimensional_data);
+#ifdef EnableCellVariation
+    // Give each repetition cell its own procedural pattern
+    seed_offset += f32((*primitive).cell_id & 0xffffu);
+#endif
+    // The inverse rotation is orthonormal so its transpose takes us
+    // from local space back to world space
+    var local_to_world: mat3x3f = transpose((*primitive).transform.inverse_rotation);
+    return TextureCoordinates(
+        vec4(position, seed_offset),
+        normal,
+        vec4(local_to_world * position + (*primitive).transform.translation, seed_offset),
+        local_to_world * normal,
+    );
+}
+
+
+#ifdef EnableImageTexture
+/**
+ * Estimate the normal of a primitive before it is textured, so that
+ * images can be projected onto it.
+ *
+ * @arg position: The position in the primitive's local space.
+ * @arg primitive: The primitive to get the normal of.
+ *
+ * @returns: The normalized surface normal in local space.
+ */
+fn estimate_texture_normal(
+    position: vec3f,
+    primitive: ptr<function, Primitive>,
+) -> vec3f {
+    // Use a copy so that the trap colours of fractals are not applied
+    var untextured_primitive: Primitive = *primitive;
+    // The gradient is normalized, so the deformations' bound is not needed
+    var lipschitz_bound: f32 = 1.;
+    var normal_offset = vec2(0.5773, -0.5773) * _render_parameters.hit_tolerance;
+    var gradient: vec3f = (
+        normal_offset.xyy * distance_to_transformed_primitive(
+            untextured_primitive.transform.inverse_scale_shear * transform_position(
+                position + normal_offset.xyy,
+                &untextured_primitive,
+                &lipschitz_bound,
+            ),
+            &untextured_primitive,
+        )
+        + normal_offset.yyx * distance_to_transformed_primitive(
+            untextured_primitive.transform.inverse_scale_shear * transform_position(
+                position + normal_offset.yyx,
+                &untextured_primitive,
+                &lipschitz_bound,
+            ),
+            &untextured_primitive,
+        )
+        + normal_offset.yxy * distance_to_transformed_primitive(
+            untextured_primitive.transform.inverse_scale_shear * transform_position(
+                position + normal_offset.yxy,
+                &untextured_primitive,
+                &lipschitz_bound,
+            ),
+            &untextured_primitive,
+        )
+        + normal_offset.xxx * distance_to_transformed_primitive(
+            untextured_primitive.transform.inverse_scale_shear * transform_position(
+                position + normal_offset.xxx,
+                &untextured_primitive,
+                &lipschitz_bound,
+            ),
+            &untextured_primitive,
+        )
+    );
+    return select(vec3(0., 1., 0.), normalize(gradient), dot(gradient, gradient) > 0.);
+}
+#endif
+
+
+#ifdef EnableDisplacement
+/**
+ * Displace the surface of a primitive along its normal by a procedural
+ * texture. The displaced field is no longer a true distance, so it is
+ * divided by the Lipschitz bound to keep the ray from overstepping.
+ *
+ * @arg distance: The distance to the undisplaced primitive.
+ * @arg position: The position in the primitive's local space.
+ * @arg primitive: The primitive to displace.
+ *
+ * @returns: The conservative distance to the displaced primitive.
+ */
+fn displace_distance(
+    distance: f32,
+    position: vec3f,
+    primitive: ptr<function, Primitive>,
+) -> f32 {
+    if (*primitive).displacement_amplitude == 0. {
+        return distance;
+    }
+    var texture_normal = vec3f();
+#ifdef EnableImageTexture
+    if bool((*primitive).modifiers & DISPLACEMENT_TEXTURE_NORMAL) {
+        texture_normal = estimate_texture_normal(position, primitive);
+    }
 #endif
+    var texture_coordinates: TextureCoordinates = primitive_texture_coordinates(
+        position,
+        texture_normal,
+        primitive,
+    );
+    var displacement: f32 = (*primitive).displacement_amplitude * (
+        procedurally_texture_f32(
+            texture_coordinates,
+            1.,
+            (*primitive).displacement_texture,
+        )
+        + (*primitive).displacement_offset
+    );
+
+    return (distance - displacement) / (*primitive).lipschitz_bound;
 }
+#endif
 
 
 /**
@@ -231,62 +901,78 @@ fn texture_primitive(
     position: vec3f,
     primitive: ptr<function, Primitive>,
 ) {
-    var procedural_texture_seed = vec4(
+    var texture_normal = vec3f();
+#ifdef EnableImageTexture
+    // Estimating the normal evaluates the primitive four more times, so
+    // it is only done for the images that are projected along it
+    if bool((*primitive).modifiers & MATERIAL_TEXTURE_NORMAL) {
+        texture_normal = estimate_texture_normal(position, primitive);
+    }
+#endif
+    var texture_coordinates: TextureCoordinates = primitive_texture_coordinates(
         position,
-        length((*primitive).dimensional_data),
+        texture_normal,
+        primitive,
     );
 #ifdef EnableDiffuseColourTexture
     (*primitive).material.diffuse_colour = procedurally_texture_vec3f(
-        procedural_texture_seed,
+        texture_coordinates,
         (*primitive).material.diffuse_colour,
         (*primitive).material.diffuse_colour_texture,
     );
 #endif
 #ifdef EnableSpecularProbabilityTexture
     (*primitive).material.specular_probability = procedurally_texture_f32(
-        procedural_texture_seed,
+        texture_coordinates,
         (*primitive).material.specular_probability,
         (*primitive).material.specular_probability_texture,
     );
 #endif
 #ifdef EnableSpecularRoughnessTexture
     (*primitive).material.specular_roughness = procedurally_texture_f32(
-        procedural_texture_seed,
+        texture_coordinates,
         (*primitive).material.specular_roughness,
         (*primitive).material.specular_roughness_texture,
     );
 #endif
 #ifdef EnableSpecularColourTexture
     (*primitive).material.specular_colour = procedurally_texture_vec3f(
-        procedural_texture_seed,
+        texture_coordinates,
         (*primitive).material.specular_colour,
         (*primitive).material.specular_colour_texture,
     );
 #endif
+#ifdef EnableFilmThicknessTexture
+    (*primitive).material.film_thickness = procedurally_texture_f32(
+        texture_coordinates,
+        (*primitive).material.film_thickness,
+        (*primitive).material.film_thickness_texture,
+    );
+#endif
 #ifdef EnableTransmissiveProbabilityTexture
     (*primitive).material.transmissive_probability = procedurally_texture_f32(
-        procedural_texture_seed,
+        texture_coordinates,
         (*primitive).material.transmissive_probability,
         (*primitive).material.transmissive_probability_texture,
     );
 #endif
 #ifdef EnableTransmissiveRoughnessTexture
     (*primitive).material.transmissive_roughness = procedurally_texture_f32(
-        procedural_texture_seed,
+        texture_coordinates,
         (*primitive).material.transmissive_roughness,
         (*primitive).material.transmissive_roughness_texture,
     );
 #endif
 #ifdef EnableEmissiveColourTexture
     (*primitive).material.emissive_colour = procedurally_texture_vec3f(
-        procedural_texture_seed,
+        texture_coordinates,
         (*primitive).material.emissive_colour,
         (*primitive).material.emissive_colour_texture,
     );
 #endif
 #ifdef EnableRefractiveIndexTexture
     (*primitive).material.refractive_index = procedurally_texture_f32(
-        procedural_texture_seed,
+        texture_coordinates,
         (*primitive).material.refractive_index,
         (*primitive).material.refractive_index_texture,
     );
@@ -407,6 +1093,15 @@ fn distance_to_transformed_primitive(
             );
         }
 #endif
+#ifdef EnableGyroid
+        case GYROID {
+            distance = distance_to_gyroid(
+                position,
+                (*primitive).dimensional_data.x,
+                (*primitive).dimensional_data.y,
+            );
+        }
+#endif
 #ifdef EnableHexagonalPrism
         case HEXAGONAL_PRISM {
             distance = distance_to_hexagonal_prism(
@@ -426,6 +1121,24 @@ fn distance_to_transformed_primitive(
             );
         }
 #endif
+#ifdef EnableHybridFractal
+        case HYBRID_FRACTAL {
+            var trap_colour = vec3(1.);
+            distance = distance_to_hybrid_fractal(
+                position,
+                (*primitive).dimensional_data.x,
+                u32((*primitive).dimensional_data.y),
+                (*primitive).dimensional_data.z,
+                (*primitive).dimensional_data.w,
+                (*primitive).extended_dimensional_data.x,
+                (*primitive).extended_dimensional_data.y,
+                u32((*primitive).extended_dimensional_data.z),
+                u32((*primitive).extended_dimensional_data.w),
+                &trap_colour,
+            );
+            apply_trap_colour(trap_colour, primitive);
+        }
+#endif
 #ifdef EnableInfiniteCone
         case INFINITE_CONE {
             distance = distance_to_infinite_cone(
@@ -442,6 +1155,22 @@ fn distance_to_transformed_primitive(
             );
         }
 #endif
+#ifdef EnableKaleidoscopicIFS
+        case KALEIDOSCOPIC_IFS {
+            var trap_colour = vec3(1.);
+            distance = distance_to_kaleidoscopic_ifs(
+                position,
+                (*primitive).dimensional_data.x,
+                (*primitive).dimensional_data.z,
+                u32((*primitive).dimensional_data.y),
+                (*primitive).fold_planes,
+                (*primitive).fractal_rotation,
+                (*primitive).fractal_offset,
+                &trap_colour,
+            );
+            apply_trap_colour(trap_colour, primitive);
+        }
+#endif
 #ifdef EnableLink
         case LINK {
             distance = distance_to_link(
@@ -479,6 +1208,27 @@ fn distance_to_transformed_primitive(
             apply_trap_colour(trap_colour, primitive);
         }
 #endif
+#ifdef EnableMengerSponge
+        case MENGER_SPONGE {
+            var trap_colour = vec3(1.);
+            distance = distance_to_menger_sponge(
+                position,
+                (*primitive).dimensional_data.x,
+                u32((*primitive).dimensional_data.y),
+                &trap_colour,
+            );
+            apply_trap_colour(trap_colour, primitive);
+        }
+#endif
+#ifdef EnableNeovius
+        case NEOVIUS {
+            distance = distance_to_neovius(
+                position,
+                (*primitive).dimensional_data.x,
+                (*primitive).dimensional_data.y,
+            );
+        }
+#endif
 #ifdef EnableOctahedron
         case OCTAHEDRON {
             distance = distance_to_octahedron(
@@ -495,6 +1245,19 @@ fn distance_to_transformed_primitive(
             );
         }
 #endif
+#ifdef EnableQuaternionJulia
+        case QUATERNION_JULIA {
+            var trap_colour = vec3(1.);
+            distance = distance_to_quaternion_julia(
+                position,
+                (*primitive).dimensional_data,
+                u32((*primitive).extended_dimensional_data.x),
+                (*primitive).extended_dimensional_data.y,
+                &trap_colour,
+            );
+            apply_trap_colour(trap_colour, primitive);
+        }
+#endif
 #ifdef EnableRectangularPrism
         case RECTANGULAR_PRISM {
             distance = distance_to_rectangular_prism(
@@ -537,6 +1300,48 @@ fn distance_to_transformed_primitive(
             );
         }
 #endif
+#ifdef EnableSchwarzD
+        case SCHWARZ_D {
+            distance = distance_to_schwarz_d(
+                position,
+                (*primitive).dimensional_data.x,
+                (*primitive).dimensional_data.y,
+            );
+        }
+#endif
+#ifdef EnableSchwarzP
+        case SCHWARZ_P {
+            distance = distance_to_schwarz_p(
+                position,
+                (*primitive).dimensional_data.x,
+                (*primitive).dimensional_data.y,
+            );
+        }
+#endif
+#ifdef EnableSierpinskiOctahedron
+        case SIERPINSKI_OCTAHEDRON {
+            var trap_colour = vec3(1.);
+            distance = distance_to_sierpinski_octahedron(
+                position,
+                (*primitive).dimensional_data.x,
+                u32((*primitive).dimensional_data.y),
+                &trap_colour,
+            );
+            apply_trap_colour(trap_colour, primitive);
+        }
+#endif
+#ifdef EnableSierpinskiTetrahedron
+        case SIERPINSKI_TETRAHEDRON {
+            var trap_colour = vec3(1.);
+            distance = distance_to_sierpinski_tetrahedron(
+                position,
+                (*primitive).dimensional_data.x,
+                u32((*primitive).dimensional_data.y),
+                &trap_colour,
+            );
+            apply_trap_colour(trap_colour, primitive);
+        }
+#endif
 #ifdef EnableSolidAngle
         case SOLID_ANGLE {
             distance = distance_to_solid_angle(
@@ -552,6 +1357,16 @@ fn distance_to_transformed_primitive(
                 (*primitive).dimensional_data.x,
             );
         }
+#ifdef EnableSuperellipsoid
+        case SUPERELLIPSOID {
+            distance = distance_to_superellipsoid(
+                position,
+                (*primitive).dimensional_data.xyz,
+                (*primitive).extended_dimensional_data.x,
+                (*primitive).extended_dimensional_data.y,
+            );
+        }
+#endif
 #ifdef EnableTorus
         case TORUS {
             distance = distance_to_torus(
@@ -577,7 +1392,40 @@ fn distance_to_transformed_primitive(
 
 
 /**
- * Compute the min distance from a point to a geometric object.
+ * Compute the min distance from a point, that has already been moved
+ * into the space of a primitive, to the primitive. Both the marched and
+ * the textured distances go through this, so that the surface that rays
+ * hit is the surface that is textured.
+ *
+ * @arg local_position: The point in the primitive's local space.
+ * @arg transformed_position: The point after the primitive's transform.
+ * @arg lipschitz_bound: The Lipschitz bound of the transform.
+ * @arg primitive: The primitive to get the distance to.
+ *
+ * @returns: The minimum distance from the point to the shape.
+ */
+fn distance_to_local_primitive(
+    local_position: vec3f,
+    transformed_position: vec3f,
+    lipschitz_bound: f32,
+    primitive: ptr<function, Primitive>,
+) -> f32 {
+    var distance: f32 = distance_to_transformed_primitive(
+        transformed_position,
+        primitive,
+    ) * (*primitive).transform.minimum_scale / lipschitz_bound;
+
+#ifdef EnableDisplacement
+    distance = displace_distance(distance, local_position, primitive);
+#endif
+
+    return modify_distance(distance, primitive);
+}
+
+
+/**
+ * Compute the min distance from a point to a geometric object, and
+ * apply the primitive's procedural textures to its material.
  *
  * @arg position: The point to get the distance to, from the primitive.
  * @arg primitive: The primitive to get the distance to.
@@ -588,19 +1436,24 @@ fn distance_to_textured_primitive(
     position: vec3f,
     primitive: ptr<function, Primitive>,
 ) -> f32 {
-    var transformed_position: vec3f = rotate_translate_position(position, primitive);
-    texture_primitive(transformed_position, primitive);
-    transformed_position = transform_position(
-        transformed_position,
-        primitive,
-    ) / (*primitive).transform.uniform_scale;
+    var local_position: vec3f = rotate_translate_position(position, primitive);
+    var lipschitz_bound: f32 = 1.;
+    var transformed_position: vec3f = (
+        (*primitive).transform.inverse_scale_shear
+        * transform_position(local_position, primitive, &lipschitz_bound)
+    );
+    // Texture after transforming so that the repetition cell is known
+    texture_primitive(local_position, primitive);
+#ifdef EnableCellVariation
+    vary_cell_material(primitive);
+#endif
 
-    var distance: f32 = distance_to_transformed_primitive(
+    return distance_to_local_primitive(
+        local_position,
         transformed_position,
+        lipschitz_bound,
         primitive,
     );
-
-    return modify_distance(distance * (*primitive).transform.uniform_scale, primitive);
 }
 
 
@@ -616,20 +1469,19 @@ fn distance_to_primitive(
     position: vec3f,
     primitive: ptr<function, Primitive>,
 ) -> f32 {
-    var transformed_position: vec3f = transform_position(
-        rotate_translate_position(
-            position,
-            primitive,
-        ),
-        primitive,
-    ) / (*primitive).transform.uniform_scale;
+    var local_position: vec3f = rotate_translate_position(position, primitive);
+    var lipschitz_bound: f32 = 1.;
+    var transformed_position: vec3f = (
+        (*primitive).transform.inverse_scale_shear
+        * transform_position(local_position, primitive, &lipschitz_bound)
+    );
 
-    var distance: f32 = distance_to_transformed_primitive(
+    return distance_to_local_primitive(
+        local_position,
         transformed_position,
+        lipschitz_bound,
         primitive,
     );
-
-    return modify_distance(distance * (*primitive).transform.uniform_scale, primitive);
 }
 
 
@@ -653,11 +1505,31 @@ fn mix_primitives(
         (*primitive_1).material.specular_roughness,
         smoothing,
     );
+    (*primitive_0).material.specular_anisotropy = mix(
+        (*primitive_0).material.specular_anisotropy,
+        (*primitive_1).material.specular_anisotropy,
+        smoothing,
+    );
+    (*primitive_0).material.specular_rotation = mix(
+        (*primitive_0).material.specular_rotation,
+        (*primitive_1).material.specular_rotation,
+        smoothing,
+    );
     (*primitive_0).material.specular_colour = mix(
         (*primitive_0).material.specular_colour,
         (*primitive_1).material.specular_colour,
         smoothing,
     );
+    (*primitive_0).material.film_thickness = mix(
+        (*primitive_0).material.film_thickness,
+        (*primitive_1).material.film_thickness,
+        smoothing,
+    );
+    (*primitive_0).material.film_refractive_index = mix(
+        (*primitive_0).material.film_refractive_index,
+        (*primitive_1).material.film_refractive_index,
+        smoothing,
+    );
     (*primitive_0).material.transmissive_probability = mix(
         (*primitive_0).material.transmissive_probability,
         (*primitive_1).material.transmissive_probability,
@@ -683,6 +1555,11 @@ fn mix_primitives(
         (*primitive_1).material.refractive_index,
         smoothing,
     );
+    (*primitive_0).material.abbe_number = mix(
+        (*primitive_0).material.abbe_number,
+        (*primitive_1).material.abbe_number,
+        smoothing,
+    );
     (*primitive_0).material.scattering_colour = mix(
         (*primitive_0).material.scattering_colour,
         (*primitive_1).material.scattering_colour,
@@ -695,6 +1572,12 @@ fn mix_primitives(
         smoothing > 0.5,
     );
     (*primitive_1).id = (*primitive_0).id;
+    (*primitive_0).cell_id = select(
+        (*primitive_0).cell_id,
+        (*primitive_1).cell_id,
+        smoothing > 0.5,
+    );
+    (*primitive_1).cell_id = (*primitive_0).cell_id;
 }
 
 
@@ -718,11 +1601,41 @@ fn select_primitive(
         (*primitive_1).material.specular_roughness,
         choice,
     );
+    (*primitive_0).material.specular_anisotropy = select(
+        (*primitive_0).material.specular_anisotropy,
+        (*primitive_1).material.specular_anisotropy,
+        choice,
+    );
+    (*primitive_0).material.specular_rotation = select(
+        (*primitive_0).material.specular_rotation,
+        (*primitive_1).material.specular_rotation,
+        choice,
+    );
+    (*primitive_0).material.tangent_type = select(
+        (*primitive_0).material.tangent_type,
+        (*primitive_1).material.tangent_type,
+        choice,
+    );
+    (*primitive_0).material.tangent_axis = select(
+        (*primitive_0).material.tangent_axis,
+        (*primitive_1).material.tangent_axis,
+        choice,
+    );
     (*primitive_0).material.specular_colour = select(
         (*primitive_0).material.specular_colour,
         (*primitive_1).material.specular_colour,
         choice,
     );
+    (*primitive_0).material.film_thickness = select(
+        (*primitive_0).material.film_thickness,
+        (*primitive_1).material.film_thickness,
+        choice,
+    );
+    (*primitive_0).material.film_refractive_index = select(
+        (*primitive_0).material.film_refractive_index,
+        (*primitive_1).material.film_refractive_index,
+        choice,
+    );
     (*primitive_0).material.transmissive_probability = select(
         (*primitive_0).material.transmissive_probability,
         (*primitive_1).material.transmissive_probability,
@@ -748,6 +1661,11 @@ fn select_primitive(
         (*primitive_1).material.refractive_index,
         choice,
     );
+    (*primitive_0).material.abbe_number = select(
+        (*primitive_0).material.abbe_number,
+        (*primitive_1).material.abbe_number,
+        choice,
+    );
     (*primitive_0).material.scattering_colour = select(
         (*primitive_0).material.scattering_colour,
         (*primitive_1).material.scattering_colour,
@@ -760,15 +1678,224 @@ fn select_primitive(
         choice,
     );
     (*primitive_1).id = (*primitive_0).id;
+    (*primitive_0).cell_id = select(
+        (*primitive_0).cell_id,
+        (*primitive_1).cell_id,
+        choice,
+    );
+    (*primitive_1).cell_id = (*primitive_0).cell_id;
+}
+
+#ifdef EnableExponentialBlendKernel
+/**
+ * Exponential smooth minimum of two distances.
+ *
+ * @arg distance_0: The first distance.
+ * @arg distance_1: The second distance.
+ * @arg strength: The size of the blended region.
+ *
+ * @returns: The blended distance.
+ */
+fn exponential_smooth_union(distance_0: f32, distance_1: f32, strength: f32) -> f32 {
+    // Offset by the minimum to avoid overflowing the exponentials
+    var minimum: f32 = min(distance_0, distance_1);
+    return minimum - strength * log2(
+        exp2((minimum - distance_0) / strength)
+        + exp2((minimum - distance_1) / strength)
+    );
+}
+#endif
+
+
+#ifdef EnableCircularBlendKernel
+/**
+ * Union of two distances with a circular fillet.
+ *
+ * @arg distance_0: The first distance.
+ * @arg distance_1: The second distance.
+ * @arg strength: The radius of the fillet.
+ *
+ * @returns: The blended distance.
+ */
+fn circular_smooth_union(distance_0: f32, distance_1: f32, strength: f32) -> f32 {
+    var inside_fillet: vec2f = max(
+        vec2(strength - distance_0, strength - distance_1),
+        vec2(0.),
+    );
+    return max(strength, min(distance_0, distance_1)) - length(inside_fillet);
+}
+#endif
+
+
+#ifdef EnableChamferBlendKernel
+/**
+ * Union of two distances with a 45 degree chamfer.
+ *
+ * @arg distance_0: The first distance.
+ * @arg distance_1: The second distance.
+ * @arg strength: The size of the chamfer.
+ *
+ * @returns: The blended distance.
+ */
+fn chamfer_union(distance_0: f32, distance_1: f32, strength: f32) -> f32 {
+    return min(
+        min(distance_0, distance_1),
+        (distance_0 - strength + distance_1) * 0.70710678,
+    );
+}
+#endif
+
+
+#ifdef EnableStairsBlendKernel
+/**
+ * Union of two distances with a staircase where they meet.
+ *
+ * @arg distance_0: The first distance.
+ * @arg distance_1: The second distance.
+ * @arg strength: The size of the staircase.
+ * @arg steps: The number of steps in the staircase.
+ *
+ * @returns: The blended distance.
+ */
+fn stairs_union(distance_0: f32, distance_1: f32, strength: f32, steps: f32) -> f32 {
+    var step_size: f32 = strength / steps;
+    var offset_distance: f32 = distance_1 - strength;
+    return min(
+        min(distance_0, distance_1),
+        0.5 * (
+            offset_distance
+            + distance_0
+            + abs(
+                modulo_f32(offset_distance - distance_0 + step_size, 2. * step_size)
+                - step_size
+            )
+        ),
+    );
+}
+#endif
+
+
+#ifdef EnableColumnsBlendKernel
+/**
+ * Union of two distances with a row of columns where they meet.
+ *
+ * @arg distance_0: The first distance.
+ * @arg distance_1: The second distance.
+ * @arg strength: The size of the region containing the columns.
+ * @arg columns: The number of columns.
+ *
+ * @returns: The blended distance.
+ */
+fn columns_union(distance_0: f32, distance_1: f32, strength: f32, columns: f32) -> f32 {
+    if distance_0 >= strength || distance_1 >= strength {
+        return min(distance_0, distance_1);
+    }
+    var column_radius: f32 = strength * 1.41421356 / (2. * (columns - 1.) + 1.41421356);
+
+    // Rotate by 45 degrees so the columns lie along the diagonal
+    var position = vec2(distance_0, distance_1);
+    position = (position + vec2(position.y, -position.x)) * 0.70710678;
+    position.x += 1.41421356 * (column_radius - 0.5 * strength);
+    if modulo_f32(columns, 2.) == 1. {
+        position.y += column_radius;
+    }
+    position.y = modulo_f32(position.y + column_radius, 2. * column_radius) - column_radius;
+
+    return min(
+        min(length(position) - column_radius, position.x),
+        min(distance_0, distance_1),
+    );
+}
+#endif
+
+
+/**
+ * Combine two distances with the union blend kernel of a primitive.
+ *
+ * @arg distance_0: The first distance.
+ * @arg distance_1: The second distance.
+ * @arg primitive: The primitive whose blend kernel to use.
+ *
+ * @returns: The blended distance.
+ */
+fn smooth_union(
+    distance_0: f32,
+    distance_1: f32,
+    primitive: ptr<function, Primitive>,
+) -> f32 {
+    switch (*primitive).blend_kernel {
+#ifdef EnableExponentialBlendKernel
+        case EXPONENTIAL_BLEND_KERNEL {
+            return exponential_smooth_union(
+                distance_0,
+                distance_1,
+                (*primitive).blend_strength,
+            );
+        }
+#endif
+#ifdef EnableCircularBlendKernel
+        case CIRCULAR_BLEND_KERNEL {
+            return circular_smooth_union(
+                distance_0,
+                distance_1,
+                (*primitive).blend_strength,
+            );
+        }
+#endif
+#ifdef EnableChamferBlendKernel
+        case CHAMFER_BLEND_KERNEL {
+            return chamfer_union(
+                distance_0,
+                distance_1,
+                (*primitive).blend_strength,
+            );
+        }
+#endif
+#ifdef EnableStairsBlendKernel
+        case STAIRS_BLEND_KERNEL {
+            return stairs_union(
+                distance_0,
+                distance_1,
+                (*primitive).blend_strength,
+                (*primitive).blend_steps,
+            );
+        }
+#endif
+#ifdef EnableColumnsBlendKernel
+        case COLUMNS_BLEND_KERNEL {
+            return columns_union(
+                distance_0,
+                distance_1,
+                (*primitive).blend_strength,
+                (*primitive).blend_steps,
+            );
+        }
+#endif
+        default {
+            // Polynomial
+            var smoothing: f32 = saturate_f32(
+                0.5
+                + 0.5
+                * (distance_1 - distance_0)
+                / (*primitive).blend_strength
+            );
+            return mix(
+                distance_1,
+                distance_0,
+                smoothing,
+            ) - (*primitive).blend_strength * smoothing * (1. - smoothing);
+        }
+    }
 }
 
+
 fn blend_primitives(
     distance_to_parent: f32,
     distance_to_child: f32,
     parent: ptr<function, Primitive>,
     child: ptr<function, Primitive>,
 ) -> f32 {
-    switch (*parent).modifiers & BLEND_TYPE_MASK {
+    switch (*parent).blend_type {
 #ifdef EnablePrimitiveBlendSubtraction
         case SUBTRACTION {
             var smoothing: f32 = saturate_f32(
@@ -778,11 +1905,6 @@ fn blend_primitives(
                 / (*parent).blend_strength
             );
             mix_primitives(parent, child, smoothing);
-            return mix(
-                distance_to_parent,
-                -distance_to_child,
-                smoothing,
-            ) + (*parent).blend_strength * smoothing * (1. - smoothing);
         }
 #endif
 #ifdef EnablePrimitiveBlendIntersection
@@ -794,15 +1916,9 @@ fn blend_primitives(
                 / (*parent).blend_strength
             );
             mix_primitives(child, parent, smoothing);
-            return mix(
-                distance_to_child,
-                distance_to_parent,
-                smoothing,
-            ) + (*parent).blend_strength * smoothing * (1. - smoothing);
         }
 #endif
-        default {
-            // Union
+        case UNION {
             var smoothing: f32 = saturate_f32(
                 0.5
                 + 0.5
@@ -810,13 +1926,19 @@ fn blend_primitives(
                 / (*parent).blend_strength
             );
             mix_primitives(child, parent, smoothing);
-            return mix(
-                distance_to_child,
-                distance_to_parent,
-                smoothing,
-            ) - (*parent).blend_strength * smoothing * (1. - smoothing);
+        }
+        default {
+            // The remaining operations are not smooth, so use the
+            // material of whichever surface is nearest
+            select_primitive(
+                child,
+                parent,
+                abs(distance_to_parent) < abs(distance_to_child),
+            );
         }
     }
+
+    return blend_distances(distance_to_parent, distance_to_child, parent);
 }
 
 
@@ -825,50 +1947,65 @@ fn blend_distances(
     distance_to_child: f32,
     parent: ptr<function, Primitive>,
 ) -> f32 {
-    switch (*parent).modifiers & BLEND_TYPE_MASK {
+    switch (*parent).blend_type {
 #ifdef EnablePrimitiveBlendSubtraction
         case SUBTRACTION {
-            var smoothing: f32 = saturate_f32(
-                0.5
-                - 0.5
-                * (distance_to_parent + distance_to_child)
-                / (*parent).blend_strength
-            );
-            return mix(
-                distance_to_parent,
-                -distance_to_child,
-                smoothing,
-            ) + (*parent).blend_strength * smoothing * (1. - smoothing);
+            return -smooth_union(-distance_to_parent, distance_to_child, parent);
         }
 #endif
 #ifdef EnablePrimitiveBlendIntersection
         case INTERSECTION {
-            var smoothing: f32 = saturate_f32(
-                0.5
-                - 0.5
-                * (distance_to_child - distance_to_parent)
-                / (*parent).blend_strength
+            return -smooth_union(-distance_to_parent, -distance_to_child, parent);
+        }
+#endif
+#ifdef EnablePrimitiveBlendExclusiveOr
+        case EXCLUSIVE_OR {
+            return max(
+                min(distance_to_parent, distance_to_child),
+                -max(distance_to_parent, distance_to_child),
             );
-            return mix(
-                distance_to_child,
+        }
+#endif
+#ifdef EnablePrimitiveBlendGroove
+        case GROOVE {
+            // Cut a groove, the shape of the child, into the parent
+            return max(
                 distance_to_parent,
-                smoothing,
-            ) + (*parent).blend_strength * smoothing * (1. - smoothing);
+                min(
+                    distance_to_parent + (*parent).blend_strength,
+                    (*parent).blend_width - abs(distance_to_child),
+                ),
+            );
         }
 #endif
-        default {
-            // Union
-            var smoothing: f32 = saturate_f32(
-                0.5
-                + 0.5
-                * (distance_to_child - distance_to_parent)
-                / (*parent).blend_strength
+#ifdef EnablePrimitiveBlendTongue
+        case TONGUE {
+            // Raise a tongue, the shape of the child, from the parent
+            return min(
+                distance_to_parent,
+                max(
+                    distance_to_parent - (*parent).blend_strength,
+                    abs(distance_to_child) - (*parent).blend_width,
+                ),
             );
-            return mix(
-                distance_to_child,
+        }
+#endif
+#ifdef EnablePrimitiveBlendEngrave
+        case ENGRAVE {
+            // Engrave the outline of the child into the parent
+            return max(
                 distance_to_parent,
-                smoothing,
-            ) - (*parent).blend_strength * smoothing * (1. - smoothing);
+                (
+                    distance_to_parent
+                    + (*parent).blend_strength
+                    - abs(distance_to_child)
+                ) * 0.70710678,
+            );
+        }
+#endif
+        default {
+            // Union
+            return smooth_union(distance_to_parent, distance_to_child, parent);
         }
     }
 }
@@ -7,12 +7,14 @@
 struct Transform {
     translation: vec3f,
     inverse_rotation: mat3x3f,
-    uniform_scale: f32,
+    inverse_scale_shear: mat3x3f,
+    minimum_scale: f32,
 }
 
 
 struct Primitive {
     id: u32,
+    cell_id: u32,
     shape: u32,
     transform: Transform,
     material: Material,
@@ -20,12 +22,42 @@ struct Primitive {
     negative_repetitions: vec3f,
     positive_repetitions: vec3f,
     spacing: vec3f,
+    polar_segments: f32,
+    polar_axis: u32,
+    polar_radius: f32,
+    cell_scale_variation: f32,
+    cell_rotation_variation: vec3f,
+    cell_jitter: vec3f,
+    cell_hue_variation: f32,
+    cell_value_variation: f32,
+    cell_visibility: f32,
+    cell_transform_seeds: vec3f,
+    cell_material_seeds: vec3f,
+    cell_clearance: f32,
+    blend_type: u32,
+    blend_kernel: u32,
     blend_strength: f32,
+    blend_steps: f32,
+    blend_width: f32,
     wall_thickness: f32,
     edge_radius: f32,
     elongation: vec3f,
+    twist_axis: u32,
+    twist_rate: f32,
+    bend_axis: u32,
+    bend_radius: f32,
+    taper_axis: u32,
+    taper_rate: f32,
     num_descendants: u32,
     dimensional_data: vec4f,
+    extended_dimensional_data: vec4f,
+    fold_planes: mat3x3f,
+    fractal_rotation: mat3x3f,
+    fractal_offset: vec3f,
+    displacement_texture: ProceduralTexture,
+    displacement_amplitude: f32,
+    displacement_offset: f32,
+    lipschitz_bound: f32,
 }
 
 
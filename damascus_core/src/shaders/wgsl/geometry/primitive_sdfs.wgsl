@@ -25,23 +25,34 @@ const CUT_SPHERE: u32 = 4u;
 const CYLINDER: u32 = 5u;
 const DEATH_STAR: u32 = 6u;
 const ELLIPSOID: u32 = 7u;
-const HEXAGONAL_PRISM: u32 = 8u;
-const HOLLOW_SPHERE: u32 = 9u;
-const INFINITE_CONE: u32 = 10u;
-const INFINITE_CYLINDER: u32 = 11u;
-const LINK: u32 = 12u;
-const MANDELBOX: u32 = 13u;
-const MANDELBULB: u32 = 14u;
-const OCTAHEDRON: u32 = 15u;
-const PLANE: u32 = 16u;
-const RECTANGULAR_PRISM: u32 = 17u;
-const RECTANGULAR_PRISM_FRAME: u32 = 18u;
-const RHOMBUS: u32 = 19u;
-const ROUNDED_CONE: u32 = 20u;
-const SOLID_ANGLE: u32 = 21u;
-const SPHERE: u32 = 22u;
-const TORUS: u32 = 23u;
-const TRIANGULAR_PRISM: u32 = 24u;
+const GYROID: u32 = 8u;
+const HEXAGONAL_PRISM: u32 = 9u;
+const HOLLOW_SPHERE: u32 = 10u;
+const HYBRID_FRACTAL: u32 = 11u;
+const INFINITE_CONE: u32 = 12u;
+const INFINITE_CYLINDER: u32 = 13u;
+const KALEIDOSCOPIC_IFS: u32 = 14u;
+const LINK: u32 = 15u;
+const MANDELBOX: u32 = 16u;
+const MANDELBULB: u32 = 17u;
+const MENGER_SPONGE: u32 = 18u;
+const NEOVIUS: u32 = 19u;
+const OCTAHEDRON: u32 = 20u;
+const PLANE: u32 = 21u;
+const QUATERNION_JULIA: u32 = 22u;
+const RECTANGULAR_PRISM: u32 = 23u;
+const RECTANGULAR_PRISM_FRAME: u32 = 24u;
+const RHOMBUS: u32 = 25u;
+const ROUNDED_CONE: u32 = 26u;
+const SCHWARZ_D: u32 = 27u;
+const SCHWARZ_P: u32 = 28u;
+const SIERPINSKI_OCTAHEDRON: u32 = 29u;
+const SIERPINSKI_TETRAHEDRON: u32 = 30u;
+const SOLID_ANGLE: u32 = 31u;
+const SPHERE: u32 = 32u;
+const SUPERELLIPSOID: u32 = 33u;
+const TORUS: u32 = 34u;
+const TRIANGULAR_PRISM: u32 = 35u;
 
 
 /**
@@ -939,3 +950,542 @@ fn distance_to_mandelbox(
         - pow(abs(scale), f32(1 - iterations))
     );
 }
+
+
+/**
+ * Compute the min distance from a point to a menger sponge.
+ *
+ * @arg position: The point to get the distance to, from the object.
+ * @arg radial_extent: Half the width of the cube the sponge is carved from.
+ * @arg iterations: The number of iterations to compute, the higher this
+ *     is the slower it will be to compute, but the deeper the fractal
+ *     will have detail.
+ * @arg trap_colour: Will be set to the orbit trap colour of the fractal.
+ *
+ * @returns: The minimum distance from the point to the shape.
+ */
+fn distance_to_menger_sponge(
+    position: vec3f,
+    radial_extent: f32,
+    iterations: u32,
+    trap_colour: ptr<function, vec3f>,
+) -> f32 {
+    var current_position: vec3f = position / radial_extent;
+    var distance: f32 = distance_to_rectangular_prism(current_position, 2., 2., 2.);
+    *trap_colour = abs(current_position);
+
+    var scale: f32 = 1.;
+    for (var iteration = 0u; iteration < iterations; iteration++) {
+        // Floored modulo so that the cross is repeated in every octant
+        var scaled_position: vec3f = current_position * scale;
+        var repeated_position: vec3f = (
+            scaled_position - 2. * floor(scaled_position / 2.) - 1.
+        );
+        scale *= 3.;
+
+        var cross_position: vec3f = abs(1. - 3. * abs(repeated_position));
+        var cross_distance: f32 = (
+            min(
+                max(cross_position.x, cross_position.y),
+                min(
+                    max(cross_position.y, cross_position.z),
+                    max(cross_position.z, cross_position.x),
+                ),
+            ) - 1.
+        ) / scale;
+        distance = max(distance, cross_distance);
+
+        *trap_colour = min(*trap_colour, abs(repeated_position));
+    }
+
+    *trap_colour = saturate_vec3f(*trap_colour);
+
+    return distance * radial_extent;
+}
+
+
+/**
+ * Compute the min distance from a point to a sierpinski octahedron.
+ *
+ * @arg position: The point to get the distance to, from the object.
+ * @arg radial_extent: The maximum distance along the x, y, and z axes.
+ *     ie. The vertices are at +/-radial_extent on the x, y, and z axes.
+ * @arg iterations: The number of iterations to compute, the higher this
+ *     is the slower it will be to compute, but the deeper the fractal
+ *     will have detail.
+ * @arg trap_colour: Will be set to the orbit trap colour of the fractal.
+ *
+ * @returns: The minimum distance from the point to the shape.
+ */
+fn distance_to_sierpinski_octahedron(
+    position: vec3f,
+    radial_extent: f32,
+    iterations: u32,
+    trap_colour: ptr<function, vec3f>,
+) -> f32 {
+    var current_position: vec3f = position / radial_extent;
+    *trap_colour = abs(current_position);
+
+    var scale: f32 = 1.;
+    for (var iteration = 0u; iteration < iterations; iteration++) {
+        if current_position.x + current_position.y < 0. {
+            current_position = vec3(-current_position.yx, current_position.z);
+        }
+        if current_position.x + current_position.z < 0. {
+            current_position = vec3(-current_position.zx, current_position.y).xzy;
+        }
+        if current_position.x - current_position.y < 0. {
+            current_position = current_position.yxz;
+        }
+        if current_position.x - current_position.z < 0. {
+            current_position = current_position.zyx;
+        }
+        current_position = 2. * current_position - vec3(1., 0., 0.);
+        scale *= 2.;
+
+        *trap_colour = min(*trap_colour, abs(current_position));
+    }
+
+    *trap_colour = saturate_vec3f(*trap_colour);
+
+    return distance_to_octahedron(current_position, 1.) * radial_extent / scale;
+}
+
+
+/**
+ * Compute the min distance from a point to a sierpinski tetrahedron.
+ *
+ * @arg position: The point to get the distance to, from the object.
+ * @arg radial_extent: The distance from the center to each vertex along
+ *     each axis. ie. The vertices are at (1, 1, 1) * radial_extent,
+ *     (-1, -1, 1) * radial_extent, and so on.
+ * @arg iterations: The number of iterations to compute, the higher this
+ *     is the slower it will be to compute, but the deeper the fractal
+ *     will have detail.
+ * @arg trap_colour: Will be set to the orbit trap colour of the fractal.
+ *
+ * @returns: The minimum distance from the point to the shape.
+ */
+fn distance_to_sierpinski_tetrahedron(
+    position: vec3f,
+    radial_extent: f32,
+    iterations: u32,
+    trap_colour: ptr<function, vec3f>,
+) -> f32 {
+    var current_position: vec3f = position / radial_extent;
+    *trap_colour = abs(current_position);
+
+    var scale: f32 = 1.;
+    for (var iteration = 0u; iteration < iterations; iteration++) {
+        if current_position.x + current_position.y < 0. {
+            current_position = vec3(-current_position.yx, current_position.z);
+        }
+        if current_position.x + current_position.z < 0. {
+            current_position = vec3(-current_position.zx, current_position.y).xzy;
+        }
+        if current_position.y + current_position.z < 0. {
+            current_position = vec3(current_position.x, -current_position.zy);
+        }
+        current_position = 2. * current_position - 1.;
+        scale *= 2.;
+
+        *trap_colour = min(*trap_colour, abs(current_position));
+    }
+
+    *trap_colour = saturate_vec3f(*trap_colour);
+
+    var tetrahedron_distance: f32 = (
+        max(
+            max(
+                -current_position.x - current_position.y - current_position.z,
+                current_position.x + current_position.y - current_position.z,
+            ),
+            max(
+                -current_position.x + current_position.y + current_position.z,
+                current_position.x - current_position.y + current_position.z,
+            ),
+        ) - 1.
+    ) * 0.57735027;
+
+    return tetrahedron_distance * radial_extent / scale;
+}
+
+
+/**
+ * Compute the min distance from a point to a kaleidoscopic iterated
+ * function system fractal.
+ *
+ * Each iteration reflects the position across each of the fold planes,
+ * rotates it, then scales it about the offset.
+ *
+ * @arg position: The point to get the distance to, from the object.
+ * @arg radial_extent: Half the width of the cube that is placed at each
+ *     point of the fractal.
+ * @arg scale: The amount to scale the position after each fold.
+ * @arg iterations: The number of iterations to compute, the higher this
+ *     is the slower it will be to compute, but the deeper the fractal
+ *     will have detail.
+ * @arg fold_planes: The normals of the planes, through the origin, to
+ *     fold across, one per column. Zero columns are skipped.
+ * @arg rotation: The rotation to apply after folding on each iteration.
+ * @arg offset: The fixed point of the scaling.
+ * @arg trap_colour: Will be set to the orbit trap colour of the fractal.
+ *
+ * @returns: The minimum distance from the point to the shape.
+ */
+fn distance_to_kaleidoscopic_ifs(
+    position: vec3f,
+    radial_extent: f32,
+    scale: f32,
+    iterations: u32,
+    fold_planes: mat3x3f,
+    rotation: mat3x3f,
+    offset: vec3f,
+    trap_colour: ptr<function, vec3f>,
+) -> f32 {
+    var current_position: vec3f = position / radial_extent;
+    *trap_colour = abs(current_position);
+
+    var total_scale: f32 = 1.;
+    for (var iteration = 0u; iteration < iterations; iteration++) {
+        for (var plane = 0; plane < 3; plane++) {
+            current_position -= (
+                2.
+                * min(0., dot(current_position, fold_planes[plane]))
+                * fold_planes[plane]
+            );
+        }
+        current_position = rotation * current_position;
+        current_position = scale * current_position - offset * (scale - 1.);
+        total_scale *= abs(scale);
+
+        *trap_colour = min(*trap_colour, abs(current_position));
+    }
+
+    *trap_colour = saturate_vec3f(*trap_colour);
+
+    return (
+        distance_to_rectangular_prism(current_position, 2., 2., 2.)
+        * radial_extent
+        / total_scale
+    );
+}
+
+
+/**
+ * Square a quaternion stored as (real, i, j, k).
+ *
+ * @arg quaternion: The quaternion to square.
+ *
+ * @returns: The squared quaternion.
+ */
+fn square_quaternion(quaternion: vec4f) -> vec4f {
+    return vec4(
+        quaternion.x * quaternion.x - dot2_vec3f(quaternion.yzw),
+        2. * quaternion.x * quaternion.yzw,
+    );
+}
+
+
+/**
+ * Compute the min distance from a point to a quaternion julia set,
+ * using the analytic distance estimator.
+ *
+ * @arg position: The point to get the distance to, from the object.
+ * @arg julia_constant: The quaternion constant, (real, i, j, k), that
+ *     is added on each iteration and determines the shape of the set.
+ * @arg iterations: The number of iterations to compute, the higher this
+ *     is the slower it will be to compute, but the deeper the fractal
+ *     will have detail.
+ * @arg escape_radius: Stop iterating once the position is further than
+ *     this from the origin.
+ * @arg trap_colour: Will be set to the orbit trap colour of the fractal.
+ *
+ * @returns: The minimum distance from the point to the shape.
+ */
+fn distance_to_quaternion_julia(
+    position: vec3f,
+    julia_constant: vec4f,
+    iterations: u32,
+    escape_radius: f32,
+    trap_colour: ptr<function, vec3f>,
+) -> f32 {
+    var current_position = vec4(position, 0.);
+    var square_radius: f32 = dot(current_position, current_position);
+    var square_escape_radius: f32 = escape_radius * escape_radius;
+    var square_derivative: f32 = 1.;
+    *trap_colour = abs(position);
+
+    for (var iteration = 0u; iteration < iterations; iteration++) {
+        square_derivative *= 4. * square_radius;
+        current_position = square_quaternion(current_position) + julia_constant;
+        square_radius = dot(current_position, current_position);
+
+        *trap_colour = min(*trap_colour, abs(current_position.xyz));
+
+        if square_radius > square_escape_radius {
+            break;
+        }
+    }
+
+    *trap_colour = saturate_vec3f(*trap_colour);
+
+    return 0.25 * sqrt(square_radius / square_derivative) * log(square_radius);
+}
+
+
+/**
+ * Compute the min distance from a point to a hybrid fractal that
+ * alternates between mandelbox and mandelbulb iterations.
+ *
+ * Each cycle of the sequence performs `box_folds_per_cycle` mandelbox
+ * iterations followed by `bulb_iterations_per_cycle` mandelbulb
+ * iterations, and the cycle repeats until `iterations` have been
+ * computed in total.
+ *
+ * @arg position: The point to get the distance to, from the object.
+ * @arg scale: The amount to scale the position between folds in the
+ *     mandelbox iterations.
+ * @arg iterations: The total number of iterations to compute.
+ * @arg min_square_radius: The minimum square radius to use when
+ *     spherically folding in the mandelbox iterations.
+ * @arg folding_limit: Clamp the position between +/- this value when
+ *     box folding in the mandelbox iterations.
+ * @arg power: The power of the mandelbulb iterations.
+ * @arg max_square_radius: When the square radius has reached this
+ *     length after a mandelbulb iteration, stop iterating.
+ * @arg box_folds_per_cycle: The number of mandelbox iterations at the
+ *     start of each cycle.
+ * @arg bulb_iterations_per_cycle: The number of mandelbulb iterations
+ *     at the end of each cycle.
+ * @arg trap_colour: Will be set to the orbit trap colour of the fractal.
+ *
+ * @returns: The minimum distance from the point to the shape.
+ */
+fn distance_to_hybrid_fractal(
+    position: vec3f,
+    scale: f32,
+    iterations: u32,
+    min_square_radius: f32,
+    folding_limit: f32,
+    power: f32,
+    max_square_radius: f32,
+    box_folds_per_cycle: u32,
+    bulb_iterations_per_cycle: u32,
+    trap_colour: ptr<function, vec3f>,
+) -> f32 {
+    var scale_vector = vec4(scale, scale, scale, abs(scale)) / min_square_radius;
+    var folding_limit_vec3f = vec3(folding_limit);
+    var cycle_length: u32 = max(box_folds_per_cycle + bulb_iterations_per_cycle, 1u);
+
+    // The derivative is tracked in the w component
+    var current_position = vec4(position, 1.);
+    var square_radius: f32 = dot2_vec3f(position);
+    *trap_colour = abs(position);
+
+    for (var iteration = 0u; iteration < iterations; iteration++) {
+        if iteration % cycle_length < box_folds_per_cycle {
+            var folded_position = box_fold(current_position.xyz, folding_limit_vec3f);
+            current_position = sphere_fold(
+                vec4(folded_position, current_position.w),
+                dot2_vec3f(folded_position),
+                min_square_radius,
+            );
+            current_position = scale_vector * current_position + vec4(position, 1.);
+            square_radius = dot2_vec3f(current_position.xyz);
+        } else {
+            var current_radius: f32 = sqrt(square_radius);
+            current_position.w = (
+                power * pow(current_radius, power - 1.) * current_position.w + 1.
+            );
+
+            var theta: f32 = power * acos(current_position.z / current_radius);
+            var phi: f32 = power * atan2(current_position.y, current_position.x);
+            current_position = vec4(
+                position + pow(current_radius, power) * vec3(
+                    sin(theta) * cos(phi),
+                    sin(theta) * sin(phi),
+                    cos(theta),
+                ),
+                current_position.w,
+            );
+            square_radius = dot2_vec3f(current_position.xyz);
+
+            if square_radius > max_square_radius {
+                *trap_colour = min(*trap_colour, abs(current_position.xyz));
+                break;
+            }
+        }
+
+        *trap_colour = min(*trap_colour, abs(current_position.xyz));
+    }
+
+    *trap_colour = saturate_vec3f(*trap_colour);
+
+    var derivative: f32 = abs(current_position.w);
+    if bulb_iterations_per_cycle == 0u || square_radius < 1. {
+        return sqrt(square_radius) / derivative;
+    }
+    return 0.25 * log(square_radius) * sqrt(square_radius) / derivative;
+}
+
+
+/**
+ * Convert the value of a triply periodic implicit function into the
+ * distance to a sheet of constant thickness around its zero set.
+ *
+ * @arg implicit_value: The value of the implicit function.
+ * @arg frequency: The angular frequency the function was evaluated at.
+ * @arg gradient_bound: The maximum length of the gradient of the
+ *     implicit function at unit frequency.
+ * @arg thickness: The thickness of the sheet.
+ *
+ * @returns: The conservative distance to the sheet.
+ */
+fn triply_periodic_sheet_distance(
+    implicit_value: f32,
+    frequency: f32,
+    gradient_bound: f32,
+    thickness: f32,
+) -> f32 {
+    return abs(implicit_value) / (frequency * gradient_bound) - thickness / 2.;
+}
+
+
+/**
+ * Compute the min distance from a point to a gyroid.
+ *
+ * @arg position: The point to get the distance to, from the object.
+ * @arg period: The distance over which the surface repeats.
+ * @arg thickness: The thickness of the sheet.
+ *
+ * @returns: The minimum distance from the point to the shape.
+ */
+fn distance_to_gyroid(position: vec3f, period: f32, thickness: f32) -> f32 {
+    var frequency: f32 = TWO_PI / period;
+    var scaled_position: vec3f = frequency * position;
+    return triply_periodic_sheet_distance(
+        dot(sin(scaled_position), cos(scaled_position.yzx)),
+        frequency,
+        1.7320508,
+        thickness,
+    );
+}
+
+
+/**
+ * Compute the min distance from a point to a Schwarz P surface.
+ *
+ * @arg position: The point to get the distance to, from the object.
+ * @arg period: The distance over which the surface repeats.
+ * @arg thickness: The thickness of the sheet.
+ *
+ * @returns: The minimum distance from the point to the shape.
+ */
+fn distance_to_schwarz_p(position: vec3f, period: f32, thickness: f32) -> f32 {
+    var frequency: f32 = TWO_PI / period;
+    return triply_periodic_sheet_distance(
+        element_sum_vec3f(cos(frequency * position)),
+        frequency,
+        1.7320508,
+        thickness,
+    );
+}
+
+
+/**
+ * Compute the min distance from a point to a Schwarz D surface.
+ *
+ * @arg position: The point to get the distance to, from the object.
+ * @arg period: The distance over which the surface repeats.
+ * @arg thickness: The thickness of the sheet.
+ *
+ * @returns: The minimum distance from the point to the shape.
+ */
+fn distance_to_schwarz_d(position: vec3f, period: f32, thickness: f32) -> f32 {
+    var frequency: f32 = TWO_PI / period;
+    var sine: vec3f = sin(frequency * position);
+    var cosine: vec3f = cos(frequency * position);
+    return triply_periodic_sheet_distance(
+        sine.x * sine.y * sine.z
+        + sine.x * cosine.y * cosine.z
+        + cosine.x * sine.y * cosine.z
+        + cosine.x * cosine.y * sine.z,
+        frequency,
+        1.7320508,
+        thickness,
+    );
+}
+
+
+/**
+ * Compute the min distance from a point to a Neovius surface.
+ *
+ * @arg position: The point to get the distance to, from the object.
+ * @arg period: The distance over which the surface repeats.
+ * @arg thickness: The thickness of the sheet.
+ *
+ * @returns: The minimum distance from the point to the shape.
+ */
+fn distance_to_neovius(position: vec3f, period: f32, thickness: f32) -> f32 {
+    var frequency: f32 = TWO_PI / period;
+    var cosine: vec3f = cos(frequency * position);
+    return triply_periodic_sheet_distance(
+        3. * element_sum_vec3f(cosine) + 4. * cosine.x * cosine.y * cosine.z,
+        frequency,
+        7.,
+        thickness,
+    );
+}
+
+
+/**
+ * Compute the min distance from a point to a superellipsoid.
+ *
+ * The distance is the inside-outside function divided by the length
+ * of its gradient, so that the pinched shapes produced by large
+ * exponents do not cause the ray to overstep the surface.
+ *
+ * @arg position: The point to get the distance to, from the object.
+ * @arg radii: The radii along the x, y, and z axes.
+ * @arg east_west_exponent: The squareness of the cross sections in the
+ *     xz-plane. 1 is round, less than 1 is boxy, and greater than 1
+ *     is pinched.
+ * @arg north_south_exponent: The squareness of the cross sections
+ *     along the y-axis. 1 is round, less than 1 is boxy, and greater
+ *     than 1 is pinched.
+ *
+ * @returns: The minimum distance from the point to the shape.
+ */
+fn distance_to_superellipsoid(
+    position: vec3f,
+    radii: vec3f,
+    east_west_exponent: f32,
+    north_south_exponent: f32,
+) -> f32 {
+    var east_west: f32 = max(east_west_exponent, 0.01);
+    var north_south: f32 = max(north_south_exponent, 0.01);
+
+    // Avoid raising zero to a negative power in the gradient
+    var scaled_position: vec3f = max(abs(position / radii), vec3(1e-6));
+
+    var horizontal: vec2f = pow(scaled_position.xz, vec2(2. / east_west));
+    var horizontal_sum: f32 = horizontal.x + horizontal.y;
+    var inside_outside: f32 = (
+        pow(horizontal_sum, east_west / north_south)
+        + pow(scaled_position.y, 2. / north_south)
+    );
+    var radial_value: f32 = pow(inside_outside, north_south / 2.);
+
+    var common_factor: f32 = pow(inside_outside, north_south / 2. - 1.);
+    var horizontal_factor: f32 = (
+        common_factor * pow(horizontal_sum, east_west / north_south - 1.)
+    );
+    var gradient = vec3(
+        horizontal_factor * pow(scaled_position.x, 2. / east_west - 1.) / radii.x,
+        common_factor * pow(scaled_position.y, 2. / north_south - 1.) / radii.y,
+        horizontal_factor * pow(scaled_position.z, 2. / east_west - 1.) / radii.z,
+    );
+
+    return (radial_value - 1.) / max(length(gradient), 1e-6);
+}
@@ -9,4 +9,5 @@ struct Ray {
     direction: vec3f,
     colour: vec3f,
     throughput: vec3f,
+    wavelength: f32,
 }
@@ -4,6 +4,79 @@
 // LICENSE file in the root directory of this source tree.
 
 
+struct BVHNode {
+    minimum: vec3f,
+    // The node to continue from once this one, and its subtree, are
+    // done with, or zero if there is nothing left to evaluate
+    escape_index: u32,
+    maximum: vec3f,
+    // The index + 1 of the primitive at a leaf, or zero for an
+    // interior node
+    primitive_id: u32,
+}
+
+
+@group(STORAGE_BIND_GROUP) @binding(6)
+var<storage, read> _bvh_nodes: array<BVHNode>;
+
+
+/**
+ * Get the distance from a position to an axis aligned box, which is
+ * zero inside it.
+ *
+ * @arg position: The position to get the distance from.
+ * @arg minimum: The minimum corner of the box.
+ * @arg maximum: The maximum corner of the box.
+ *
+ * @returns: The distance to the box.
+ */
+fn distance_to_bounding_box(position: vec3f, minimum: vec3f, maximum: vec3f) -> f32 {
+    return length(max(max(minimum - position, position - maximum), vec3(0.)));
+}
+
+
+/**
+ * Walk the bounding volume hierarchy to the next leaf whose bounds are
+ * closer than the nearest distance found so far.
+ *
+ * @arg position: The position to find the primitives near.
+ * @arg distance_to_scene: The nearest distance found so far.
+ * @arg node_index: The node to continue the walk from, which is left
+ *     at the node to continue from next, or zero once the walk is done.
+ *
+ * @returns: The index + 1 of the primitive at the leaf, or zero if
+ *     there are no more leaves near enough.
+ */
+fn next_bvh_leaf(
+    position: vec3f,
+    distance_to_scene: f32,
+    node_index: ptr<function, u32>,
+) -> u32 {
+    loop {
+        var node: BVHNode = _bvh_nodes[*node_index];
+        if distance_to_bounding_box(
+            position,
+            node.minimum,
+            node.maximum,
+        ) >= abs(distance_to_scene) {
+            // Nothing within this node can be closer, so skip it
+            *node_index = node.escape_index;
+        } else if node.primitive_id == 0u {
+            // Descend into the first child of the interior node
+            *node_index += 1u;
+        } else {
+            *node_index = node.escape_index;
+            return node.primitive_id;
+        }
+
+        if *node_index == 0u {
+            break;
+        }
+    }
+    return 0u;
+}
+
+
 fn find_nearest_descendant(
     position: vec3f,
     hit_tolerance: f32,
@@ -79,6 +152,7 @@ fn find_nearest_descendant(
             // Get the next parent and apply the current blended material
             *family = _primitives.primitives[current_parent_index];
             (*family).id = child.id;
+            (*family).cell_id = child.cell_id;
             (*family).material = child.material;
 
             // Update the child index to point to the first child of the
@@ -165,40 +239,42 @@ fn find_nearest_primitive(
 ) {
     var distance_to_scene: f32 = _render_parameters.max_distance;
     var primitive: Primitive;
-    var primitives_processed = 0u;
     var hit_tolerance: f32 = _render_parameters.hit_tolerance + pixel_footprint;
-    while primitives_processed < _scene_parameters.num_primitives {
-        primitive = _primitives.primitives[primitives_processed];
-        var num_descendants: u32 = primitive.num_descendants;
-
-        var signed_distance_field: f32 = find_nearest_descendant(
-            position,
-            hit_tolerance,
-            primitives_processed,
-            &primitive,
-        );
 
-        var primitive_is_new_closest: bool = (
-            abs(signed_distance_field) < abs(distance_to_scene)
-        );
-        distance_to_scene = select(
-            distance_to_scene,
-            signed_distance_field,
-            primitive_is_new_closest,
-        );
-        select_primitive(
-            closest_primitive,
-            &primitive,
-            primitive_is_new_closest,
-        );
+    // Walk the bounding volume hierarchy, only evaluating the primitives
+    // whose bounds are closer than the nearest distance found so far
+    var node_index = 0u;
+    var traversing: bool = _scene_parameters.num_primitives > 0u;
+    while traversing {
+        var primitive_id: u32 = next_bvh_leaf(position, distance_to_scene, &node_index);
+        if primitive_id > 0u {
+            var primitive_index: u32 = primitive_id - 1u;
+            primitive = _primitives.primitives[primitive_index];
+
+            // The descendants are only processed if the primitive is
+            // the root of a family which interacts
+            var signed_distance_field: f32 = find_nearest_descendant(
+                position,
+                hit_tolerance,
+                primitive_index,
+                &primitive,
+            );
 
-#ifdef EnableChildInteractions
-        // Skip all descendants, they were processed in the
-        // `distance_to_descendants` function
-        primitives_processed += num_descendants + 1u;
-#else
-        primitives_processed++;
-#endif
+            var primitive_is_new_closest: bool = (
+                abs(signed_distance_field) < abs(distance_to_scene)
+            );
+            distance_to_scene = select(
+                distance_to_scene,
+                signed_distance_field,
+                primitive_is_new_closest,
+            );
+            select_primitive(
+                closest_primitive,
+                &primitive,
+                primitive_is_new_closest,
+            );
+        }
+        traversing = node_index > 0u;
     }
     // Ensure the number of descendants is that of the closest primitive
     var unmodified_closest_primitive: Primitive = (
@@ -360,35 +436,34 @@ fn signed_distance_to_scene(
 ) -> f32 {
     var distance_to_scene: f32 = _render_parameters.max_distance;
     var primitive: Primitive;
-    var primitives_processed = 0u;
     var hit_tolerance: f32 = _render_parameters.hit_tolerance + pixel_footprint;
-    while primitives_processed < _scene_parameters.num_primitives {
-        primitive = _primitives.primitives[primitives_processed];
-        var num_descendants: u32 = primitive.num_descendants;
 
-        var signed_distance_field: f32 = distance_to_descendants(
-            position,
-            hit_tolerance,
-            primitives_processed,
-            &primitive,
-        );
-
-        var primitive_is_new_closest: bool = (
-            abs(signed_distance_field) < abs(distance_to_scene)
-        );
-        distance_to_scene = select(
-            distance_to_scene,
-            signed_distance_field,
-            primitive_is_new_closest,
-        );
+    // Walk the bounding volume hierarchy, only evaluating the primitives
+    // whose bounds are closer than the nearest distance found so far
+    var node_index = 0u;
+    var traversing: bool = _scene_parameters.num_primitives > 0u;
+    while traversing {
+        var primitive_id: u32 = next_bvh_leaf(position, distance_to_scene, &node_index);
+        if primitive_id > 0u {
+            var primitive_index: u32 = primitive_id - 1u;
+            primitive = _primitives.primitives[primitive_index];
+
+            // The descendants are only processed if the primitive is
+            // the root of a family which interacts
+            var signed_distance_field: f32 = distance_to_descendants(
+                position,
+                hit_tolerance,
+                primitive_index,
+                &primitive,
+            );
 
-#ifdef EnableChildInteractions
-        // Skip all descendants, they were processed in the
-        // `distance_to_descendants` function
-        primitives_processed += num_descendants + 1u;
-#else
-        primitives_processed++;
-#endif
+            distance_to_scene = select(
+                distance_to_scene,
+                signed_distance_field,
+                abs(signed_distance_field) < abs(distance_to_scene),
+            );
+        }
+        traversing = node_index > 0u;
     }
 
     return distance_to_scene;
@@ -317,7 +317,16 @@ fn sample_non_physical_light(
             }
 #endif
 
-            return light.colour * light.intensity * shadow_intensity_at_position;
+            return (
+                light.colour
+                * light.intensity
+                * shadow_intensity_at_position
+                * atmosphere_shadow_transmittance(
+                    surface_position,
+                    light_direction,
+                    _render_parameters.max_distance,
+                )
+            );
         }
 #endif
 #ifdef EnablePointLights
@@ -351,6 +360,11 @@ fn sample_non_physical_light(
                 light.colour
                 * light_intensity(&light, distance_to_light)
                 * shadow_intensity_at_position
+                * atmosphere_shadow_transmittance(
+                    surface_position,
+                    light_direction,
+                    distance_to_light,
+                )
             );
         }
 #endif
@@ -383,7 +397,7 @@ fn sample_physical_light(
     var emissive_primitive: Primitive = _primitives.primitives[_emissive_indices[light_index]];
     var light_position: vec3f = emissive_primitive.transform.translation;
     var radius: f32 = length(
-        emissive_primitive.transform.uniform_scale * emissive_primitive.dimensional_data,
+        emissive_primitive.transform.minimum_scale * emissive_primitive.dimensional_data,
     );
 
     var light_normal: vec3f = cosine_direction_in_hemisphere(
@@ -391,7 +405,7 @@ fn sample_physical_light(
         normalize(surface_position - light_position),
     );
     var light_direction: vec3f = light_position - surface_position + light_normal * length(
-        emissive_primitive.transform.uniform_scale * emissive_primitive.dimensional_data,
+        emissive_primitive.transform.minimum_scale * emissive_primitive.dimensional_data,
     );
     var distance_to_light: f32 = length(light_direction);
     light_direction = normalize(mix(
@@ -431,10 +445,14 @@ fn sample_physical_light(
                 pixel_footprint,
             );
             var radius: f32 = length(
-                nearest_primitive.transform.uniform_scale * nearest_primitive.dimensional_data,
+                nearest_primitive.transform.minimum_scale * nearest_primitive.dimensional_data,
             );
             *light_sampling_pdf /= 2.0f * PI * radius * radius;
-            return nearest_primitive.material.emissive_colour;
+            return nearest_primitive.material.emissive_colour * atmosphere_shadow_transmittance(
+                surface_position,
+                light_direction,
+                distance_travelled,
+            );
         }
 
         position += light_direction * step_distance;
@@ -500,6 +518,11 @@ fn light_sampling(
         );
     }
 #endif
+#ifdef EnableSpectralRendering
+    if spectral_rendering_enabled() {
+        light_colour = rgb_to_spectral_vec3f(light_colour, (*ray).wavelength);
+    }
+#endif
 
     return multiple_importance_sample(
         light_colour,
@@ -7,6 +7,12 @@
 // Increasing OR decreasing this number seems to negatively affect performance
 const NESTED_DIELECTRIC_DEPTH: u32 = 7u;
 
+// Tangent types
+const LOCAL_AXIS_TANGENT: u32 = 0u;
+const TEXTURE_GRADIENT_TANGENT: u32 = 1u;
+
+const TANGENT_GRADIENT_EPSILON: f32 = 0.001;
+
 
 struct Material {
     diffuse_colour: vec3f,
@@ -15,8 +21,16 @@ struct Material {
     specular_probability_texture: ProceduralTexture,
     specular_roughness: f32,
     specular_roughness_texture: ProceduralTexture,
+    specular_anisotropy: f32,
+    specular_rotation: f32,
+    tangent_type: u32,
+    tangent_axis: vec3f,
+    tangent_texture: ProceduralTexture,
     specular_colour: vec3f,
     specular_colour_texture: ProceduralTexture,
+    film_thickness: f32,
+    film_thickness_texture: ProceduralTexture,
+    film_refractive_index: f32,
     transmissive_probability: f32,
     transmissive_probability_texture: ProceduralTexture,
     transmissive_roughness: f32,
@@ -27,8 +41,10 @@ struct Material {
     emissive_colour_texture: ProceduralTexture,
     refractive_index: f32,
     refractive_index_texture: ProceduralTexture,
+    abbe_number: f32,
     scattering_colour: vec3f,
     scattering_colour_texture: ProceduralTexture,
+    density_texture: ProceduralTexture,
 }
 
 
@@ -150,6 +166,424 @@ fn schlick_reflection_coefficient(
 }
 
 
+#ifdef EnableThinFilmInterference
+/**
+ * Evaluate the spectral sensitivity of the eye, integrated against
+ * the interference pattern of a thin film, as an RGB colour. Uses the
+ * gaussian fit of the CIE colour matching functions from
+ * Belcour & Barla 2017.
+ *
+ * @arg optical_path_difference: The optical path difference, in
+ *     nanometres, between the light reflected off of the top and bottom
+ *     of the film.
+ * @arg phase_shift: The phase shift on reflection.
+ *
+ * @returns: The sensitivity in linear sRGB.
+ */
+fn thin_film_sensitivity(optical_path_difference: f32, phase_shift: vec3f) -> vec3f {
+    var phase: f32 = TWO_PI * optical_path_difference * 1e-9;
+    var phase_squared: f32 = phase * phase;
+
+    var amplitude = vec3(5.4856e-13, 4.4201e-13, 5.2481e-13);
+    var centre = vec3(1.6810e+06, 1.7953e+06, 2.2084e+06);
+    var variance = vec3(4.3278e+09, 9.3046e+09, 6.6121e+09);
+
+    var xyz: vec3f = (
+        amplitude
+        * sqrt(TWO_PI * variance)
+        * cos(centre * phase + phase_shift)
+        * exp(-phase_squared * variance)
+    );
+    xyz.x += (
+        9.7470e-14
+        * sqrt(TWO_PI * 4.5282e+09)
+        * cos(2.2399e+06 * phase + phase_shift.x)
+        * exp(-4.5282e+09 * phase_squared)
+    );
+
+    return XYZ_TO_LINEAR_SRGB * (xyz / 1.0685e-7);
+}
+
+
+/**
+ * Compute the reflection coefficient of a surface coated in a thin
+ * film, using the analytic RGB approximation from Belcour & Barla 2017.
+ *
+ * @arg incident_ray_direction: The incident direction.
+ * @arg surface_normal_direction: The normal to the surface.
+ * @arg incident_refractive_index: The refractive index the incident ray
+ *     is travelling through.
+ * @arg film_refractive_index: The refractive index of the film.
+ * @arg refracted_refractive_index: The refractive index beneath the
+ *     film.
+ * @arg film_thickness: The thickness of the film in nanometres.
+ *
+ * @returns: The reflection coefficient of each colour channel.
+ */
+fn thin_film_reflection_coefficient(
+    incident_ray_direction: vec3f,
+    surface_normal_direction: vec3f,
+    incident_refractive_index: f32,
+    film_refractive_index: f32,
+    refracted_refractive_index: f32,
+    film_thickness: f32,
+) -> vec3f {
+    var cos_incident: f32 = -dot(surface_normal_direction, incident_ray_direction);
+    var film_refractive_ratio: f32 = incident_refractive_index / film_refractive_index;
+    var sin_film_squared: f32 = film_refractive_ratio * film_refractive_ratio * (
+        1. - cos_incident * cos_incident
+    );
+    if sin_film_squared >= 1. {
+        // Total internal reflection at the top of the film
+        return vec3(1.);
+    }
+    var cos_film: f32 = sqrt(1. - sin_film_squared);
+
+    // Reflection at the top of the film
+    var top_reflectance: f32 = schlick_reflection_coefficient(
+        incident_ray_direction,
+        surface_normal_direction,
+        incident_refractive_index,
+        film_refractive_index,
+    );
+    var top_transmittance: f32 = 1. - top_reflectance;
+
+    // Reflection at the bottom of the film
+    var parallel_coefficient: f32 = (
+        (film_refractive_index - refracted_refractive_index)
+        / (film_refractive_index + refracted_refractive_index)
+    );
+    parallel_coefficient *= parallel_coefficient;
+    var cos_x: f32 = cos_film;
+    var bottom_reflectance: f32 = 1.;
+    var base_refractive_ratio: f32 = film_refractive_index / refracted_refractive_index;
+    var sin_transmitted_squared: f32 = base_refractive_ratio * base_refractive_ratio * (
+        1. - cos_film * cos_film
+    );
+    if sin_transmitted_squared < 1. {
+        if film_refractive_index > refracted_refractive_index {
+            cos_x = sqrt(1. - sin_transmitted_squared);
+        }
+        var one_minus_cos_x: f32 = 1. - cos_x;
+        var one_minus_cos_x_squared: f32 = one_minus_cos_x * one_minus_cos_x;
+        bottom_reflectance = (
+            parallel_coefficient
+            + (1. - parallel_coefficient)
+            * one_minus_cos_x_squared
+            * one_minus_cos_x_squared
+            * one_minus_cos_x
+        );
+    }
+
+    // Phase shifts from reflecting off of optically denser media
+    var top_phase_shift: f32 = select(
+        PI,
+        0.,
+        film_refractive_index < incident_refractive_index,
+    );
+    var bottom_phase_shift: f32 = select(
+        0.,
+        PI,
+        refracted_refractive_index < film_refractive_index,
+    );
+    var phase_shift = vec3(top_phase_shift + bottom_phase_shift);
+
+    var optical_path_difference: f32 = 2. * film_refractive_index * film_thickness * cos_film;
+
+    // Sum the first few terms of the series of internal reflections
+    var both_reflectance: f32 = clamp(top_reflectance * bottom_reflectance, 1e-5, 0.9999);
+    var both_amplitude: f32 = sqrt(both_reflectance);
+    var transmitted_reflectance: f32 = (
+        top_transmittance * top_transmittance * bottom_reflectance / (1. - both_reflectance)
+    );
+
+    var reflectance = vec3(top_reflectance + transmitted_reflectance);
+    var coefficient: f32 = transmitted_reflectance - top_transmittance;
+    for (var order: u32 = 1u; order <= 2u; order++) {
+        coefficient *= both_amplitude;
+        reflectance += coefficient * 2. * thin_film_sensitivity(
+            f32(order) * optical_path_difference,
+            f32(order) * phase_shift,
+        );
+    }
+
+    return saturate_vec3f(reflectance);
+}
+#endif
+
+
+#ifdef EnableAnisotropicSpecular
+/**
+ * Compute the tangent to the surface that anisotropic reflections will
+ * be stretched along. SDFs have no UV coordinates so the tangent is
+ * either an axis in the primitive's local space, or the gradient of a
+ * procedural texture, projected onto the surface and rotated about the
+ * normal by the specular rotation.
+ *
+ * @arg position: The position on the surface.
+ * @arg surface_normal: The normal to the surface at the position.
+ * @arg primitive: The primitive whose surface we are on.
+ *
+ * @returns: The unit tangent to the surface.
+ */
+fn anisotropic_surface_tangent(
+    position: vec3f,
+    surface_normal: vec3f,
+    primitive: ptr<function, Primitive>,
+) -> vec3f {
+    var local_tangent: vec3f = (*primitive).material.tangent_axis;
+
+    if (*primitive).material.tangent_type == TEXTURE_GRADIENT_TANGENT {
+        var local_position: vec3f = rotate_translate_position(position, primitive);
+        var local_normal: vec3f = (*primitive).transform.inverse_rotation * surface_normal;
+        var epsilon = vec2(TANGENT_GRADIENT_EPSILON, 0.);
+        local_tangent = vec3(
+            procedurally_texture_f32(
+                primitive_texture_coordinates(
+                    local_position + epsilon.xyy,
+                    local_normal,
+                    primitive,
+                ),
+                1.,
+                (*primitive).material.tangent_texture,
+            ) - procedurally_texture_f32(
+                primitive_texture_coordinates(
+                    local_position - epsilon.xyy,
+                    local_normal,
+                    primitive,
+                ),
+                1.,
+                (*primitive).material.tangent_texture,
+            ),
+            procedurally_texture_f32(
+                primitive_texture_coordinates(
+                    local_position + epsilon.yxy,
+                    local_normal,
+                    primitive,
+                ),
+                1.,
+                (*primitive).material.tangent_texture,
+            ) - procedurally_texture_f32(
+                primitive_texture_coordinates(
+                    local_position - epsilon.yxy,
+                    local_normal,
+                    primitive,
+                ),
+                1.,
+                (*primitive).material.tangent_texture,
+            ),
+            procedurally_texture_f32(
+                primitive_texture_coordinates(
+                    local_position + epsilon.yyx,
+                    local_normal,
+                    primitive,
+                ),
+                1.,
+                (*primitive).material.tangent_texture,
+            ) - procedurally_texture_f32(
+                primitive_texture_coordinates(
+                    local_position - epsilon.yyx,
+                    local_normal,
+                    primitive,
+                ),
+                1.,
+                (*primitive).material.tangent_texture,
+            ),
+        );
+    }
+
+    // The inverse rotation is orthonormal so its transpose takes us
+    // from local space back to world space
+    var tangent: vec3f = transpose((*primitive).transform.inverse_rotation) * local_tangent;
+    tangent -= dot(tangent, surface_normal) * surface_normal;
+
+    if dot(tangent, tangent) < 1e-12 {
+        // The tangent is parallel to the normal, or the texture is flat
+        tangent = cross(
+            select(vec3(1., 0., 0.), vec3(0., 1., 0.), abs(surface_normal.x) > 0.5),
+            surface_normal,
+        );
+    }
+    tangent = normalize(tangent);
+
+    var rotation: f32 = (*primitive).material.specular_rotation;
+    return cos(rotation) * tangent + sin(rotation) * cross(surface_normal, tangent);
+}
+
+
+/**
+ * Get the GGX roughness along the tangent and bitangent.
+ *
+ * @arg roughness: The squared specular roughness of the material.
+ * @arg anisotropy: The specular anisotropy of the material.
+ *
+ * @returns: The roughness along the tangent and bitangent.
+ */
+fn anisotropic_roughness(roughness: f32, anisotropy: f32) -> vec2f {
+    var aspect: f32 = sqrt(1. - 0.9 * anisotropy);
+    return max(vec2(roughness / aspect, roughness * aspect), vec2(1e-4));
+}
+
+
+/**
+ * The anisotropic GGX normal distribution function.
+ *
+ * @arg microfacet_normal: The microfacet normal in tangent space.
+ * @arg roughness: The roughness along the tangent and bitangent.
+ *
+ * @returns: The density of microfacets with the given normal.
+ */
+fn ggx_distribution(microfacet_normal: vec3f, roughness: vec2f) -> f32 {
+    var stretched_normal = vec3(microfacet_normal.xy / roughness, microfacet_normal.z);
+    var squared_length: f32 = dot(stretched_normal, stretched_normal);
+    return 1. / (PI * roughness.x * roughness.y * squared_length * squared_length);
+}
+
+
+/**
+ * The Smith lambda function for the anisotropic GGX distribution.
+ *
+ * @arg direction: The direction in tangent space.
+ * @arg roughness: The roughness along the tangent and bitangent.
+ *
+ * @returns: The lambda value used to compute the masking/shadowing.
+ */
+fn ggx_lambda(direction: vec3f, roughness: vec2f) -> f32 {
+    var stretched_xy: vec2f = direction.xy * roughness;
+    return 0.5 * (
+        sqrt(1. + dot(stretched_xy, stretched_xy) / max(direction.z * direction.z, 1e-8))
+        - 1.
+    );
+}
+
+
+/**
+ * Sample a microfacet normal from the distribution of normals visible
+ * from the view direction (Heitz 2018).
+ *
+ * @arg seed: The seed to use in randomization.
+ * @arg view_direction: The direction to the viewer in tangent space.
+ * @arg roughness: The roughness along the tangent and bitangent.
+ *
+ * @returns: The microfacet normal in tangent space.
+ */
+fn sample_ggx_visible_normal(
+    seed: vec2f,
+    view_direction: vec3f,
+    roughness: vec2f,
+) -> vec3f {
+    var uniform_random_numbers: vec2f = random_vec2f(seed);
+
+    // Stretch the view direction into the hemisphere configuration
+    var stretched_view: vec3f = normalize(vec3(
+        roughness * view_direction.xy,
+        view_direction.z,
+    ));
+
+    var squared_length: f32 = dot(stretched_view.xy, stretched_view.xy);
+    var basis_0: vec3f = select(
+        vec3(1., 0., 0.),
+        vec3(-stretched_view.y, stretched_view.x, 0.) * inverseSqrt(squared_length),
+        squared_length > 0.,
+    );
+    var basis_1: vec3f = cross(stretched_view, basis_0);
+
+    var radius: f32 = sqrt(uniform_random_numbers.x);
+    var angle: f32 = TWO_PI * uniform_random_numbers.y;
+    var point_0: f32 = radius * cos(angle);
+    var point_1: f32 = radius * sin(angle);
+    var blend: f32 = 0.5 * (1. + stretched_view.z);
+    point_1 = (1. - blend) * sqrt(1. - point_0 * point_0) + blend * point_1;
+
+    var hemisphere_normal: vec3f = (
+        point_0 * basis_0
+        + point_1 * basis_1
+        + sqrt(max(0., 1. - point_0 * point_0 - point_1 * point_1)) * stretched_view
+    );
+
+    // Unstretch back to the ellipsoid configuration
+    return normalize(vec3(
+        roughness * hemisphere_normal.xy,
+        max(0., hemisphere_normal.z),
+    ));
+}
+
+
+/**
+ * Sample an anisotropic GGX specular reflection.
+ *
+ * @arg seed: The seed to use in randomization.
+ * @arg surface_normal: The normal to the surface.
+ * @arg offset: The amount to offset the ray in order to escape the
+ *     surface.
+ * @arg specular_probability: The probability of a specular bounce.
+ * @arg primitive: The primitive whose surface we are reflecting off.
+ * @arg ray: The ray which has hit the surface.
+ * @arg material_brdf: The BRDF of the surface, weighted by the cosine
+ *     of the reflected direction.
+ *
+ * @returns: The material PDF.
+ */
+fn sample_anisotropic_specular(
+    seed: vec3f,
+    surface_normal: vec3f,
+    offset: f32,
+    specular_probability: f32,
+    primitive: ptr<function, Primitive>,
+    ray: ptr<function, Ray>,
+    material_brdf: ptr<function, vec3f>,
+) -> f32 {
+    var tangent: vec3f = anisotropic_surface_tangent(
+        (*ray).origin,
+        surface_normal,
+        primitive,
+    );
+    var bitangent: vec3f = cross(surface_normal, tangent);
+    var roughness: vec2f = anisotropic_roughness(
+        (*primitive).material.specular_roughness,
+        (*primitive).material.specular_anisotropy,
+    );
+
+    var view_direction: vec3f = -(*ray).direction;
+    var local_view_direction = vec3(
+        dot(view_direction, tangent),
+        dot(view_direction, bitangent),
+        max(dot(view_direction, surface_normal), 1e-4),
+    );
+
+    var microfacet_normal: vec3f = sample_ggx_visible_normal(
+        seed.yz,
+        local_view_direction,
+        roughness,
+    );
+    var local_reflected_direction: vec3f = reflect(-local_view_direction, microfacet_normal);
+
+    (*ray).direction = normalize(
+        local_reflected_direction.x * tangent
+        + local_reflected_direction.y * bitangent
+        + local_reflected_direction.z * surface_normal
+    );
+
+    // Offset the point so that it doesn't get trapped on the surface.
+    (*ray).origin += offset * surface_normal;
+
+    var distribution: f32 = ggx_distribution(microfacet_normal, roughness);
+    var view_lambda: f32 = ggx_lambda(local_view_direction, roughness);
+    var reflected_lambda: f32 = ggx_lambda(local_reflected_direction, roughness);
+    var masking_shadowing: f32 = select(
+        0.,
+        1. / (1. + view_lambda + reflected_lambda),
+        local_reflected_direction.z > 0.,
+    );
+
+    var normalisation: f32 = distribution / (4. * local_view_direction.z);
+    *material_brdf = (*primitive).material.specular_colour * normalisation * masking_shadowing;
+
+    return specular_probability * normalisation / (1. + view_lambda);
+}
+#endif
+
+
 /**
  * Perform material sampling.
  *
@@ -205,6 +639,31 @@ fn sample_material(
         refracted_dielectric.refractive_index,
     );
 
+#ifdef EnableThinFilmInterference
+    // Replace the fresnel term with that of the thin film, the hue
+    // of which we apply to the reflected and transmitted light
+    var specular_tint = vec3(1.);
+    var transmissive_tint = vec3(1.);
+    if (*primitive).material.film_thickness > 0. {
+        var film_reflectivity: vec3f = thin_film_reflection_coefficient(
+            (*ray).direction,
+            surface_normal,
+            incident_dielectric.refractive_index,
+            (*primitive).material.film_refractive_index,
+            refracted_dielectric.refractive_index,
+            (*primitive).material.film_thickness,
+        );
+#ifdef EnableSpectralRendering
+        if spectral_rendering_enabled() {
+            film_reflectivity = rgb_to_spectral_vec3f(film_reflectivity, (*ray).wavelength);
+        }
+#endif
+        reflectivity = element_sum_vec3f(film_reflectivity) / 3.;
+        specular_tint = film_reflectivity / max(reflectivity, 1e-6);
+        transmissive_tint = (1. - film_reflectivity) / max(1. - reflectivity, 1e-6);
+    }
+#endif
+
     // Adjust probabilities according to fresnel
     specular_probability = select(
         specular_probability,
@@ -261,6 +720,9 @@ fn sample_material(
             (*ray).origin += offset * ((*ray).direction - surface_normal);
 
             *material_brdf = vec3(1.);
+#ifdef EnableThinFilmInterference
+            *material_brdf *= transmissive_tint;
+#endif
             *light_sampling_pdf = 0.;
 
             if is_exiting {
@@ -286,6 +748,24 @@ fn sample_material(
         || (specular_probability > 0. && rng <= specular_probability + transmissive_probability)
     ) {
         // Specular bounce
+#ifdef EnableAnisotropicSpecular
+        if (*primitive).material.specular_anisotropy > 0. {
+            *light_sampling_pdf = 0.;
+            var anisotropic_pdf: f32 = sample_anisotropic_specular(
+                seed,
+                surface_normal,
+                offset,
+                specular_probability,
+                primitive,
+                ray,
+                material_brdf,
+            );
+#ifdef EnableThinFilmInterference
+            *material_brdf *= specular_tint;
+#endif
+            return anisotropic_pdf;
+        }
+#endif
         var ideal_specular_direction: vec3f = reflect(
             (*ray).direction,
             surface_normal,
@@ -301,6 +781,9 @@ fn sample_material(
         (*ray).origin += offset * surface_normal;
 
         *material_brdf = (*primitive).material.specular_colour;
+#ifdef EnableThinFilmInterference
+        *material_brdf *= specular_tint;
+#endif
         *light_sampling_pdf = 0.;
 
         return (
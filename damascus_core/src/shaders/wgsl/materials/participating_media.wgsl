@@ -0,0 +1,684 @@
+// Copyright (c) 2024, Owen Bulka
+// All rights reserved.
+// This source code is licensed under the BSD-style license found in the
+// LICENSE file in the root directory of this source tree.
+
+
+// The phase function of a medium which scatters light equally in all
+// directions, 1 / (4 pi)
+const ISOTROPIC_PHASE_FUNCTION: f32 = 0.07957747154594767;
+
+// The maximum number of tentative collisions to track through a medium,
+// beyond which the remainder of the segment is treated as empty
+const MAX_TRACKING_STEPS: u32 = 256u;
+
+// The seed offset used for textures on the atmosphere, which matches
+// the one used when previewing textures
+const ATMOSPHERE_SEED_OFFSET: f32 = 8.27447;
+
+// AtmosphereDensity
+const CONSTANT_DENSITY: u32 = 0u;
+const HEIGHT_FOG_DENSITY: u32 = 1u;
+const LAYERED_SKY_DENSITY: u32 = 2u;
+
+
+/**
+ * The rates at which a medium absorbs and scatters light at a point.
+ */
+struct MediumCoefficients {
+    absorption: vec3f,
+    scattering: vec3f,
+}
+
+
+#ifdef EnableHeterogeneousMedia
+/**
+ * Get the texture which varies the density of a medium.
+ *
+ * @arg dielectric: The medium.
+ *
+ * @returns: The density texture of the medium's material.
+ */
+fn medium_density_texture(dielectric: Dielectric) -> ProceduralTexture {
+    if dielectric.id == 0u {
+        return _atmosphere.density_texture;
+    }
+    return _primitives.primitives[dielectric.id - 1u].material.density_texture;
+}
+#endif
+
+
+/**
+ * Check if the density of a medium varies with altitude, which is only
+ * the case for the atmosphere.
+ *
+ * @arg dielectric: The medium.
+ *
+ * @returns: True if the medium is an atmosphere that varies in height.
+ */
+fn is_height_varying_medium(dielectric: Dielectric) -> bool {
+#ifdef EnableHeterogeneousMedia
+    return dielectric.id == 0u && _scene_parameters.atmosphere_density != CONSTANT_DENSITY;
+#else
+    return false;
+#endif
+}
+
+
+/**
+ * Check if a medium absorbs or scatters any light.
+ *
+ * @arg dielectric: The medium.
+ *
+ * @returns: True if the medium interacts with light.
+ */
+fn is_participating_medium(dielectric: Dielectric) -> bool {
+    return (
+        element_sum_vec3f(dielectric.extinction_colour + dielectric.scattering_colour) > 0.
+        || (
+            is_height_varying_medium(dielectric)
+            && _scene_parameters.atmosphere_density == LAYERED_SKY_DENSITY
+            && _scene_parameters.aerosol_density > 0.
+        )
+    );
+}
+
+
+/**
+ * Check if the density of a medium varies throughout it, in which case
+ * it must be sampled by tracking rather than analytically.
+ *
+ * @arg dielectric: The medium.
+ *
+ * @returns: True if the medium is heterogeneous.
+ */
+fn is_heterogeneous_medium(dielectric: Dielectric) -> bool {
+#ifdef EnableHeterogeneousMedia
+    return (
+        (
+            medium_density_texture(dielectric).texture_type != NONE
+            || is_height_varying_medium(dielectric)
+        )
+        && is_participating_medium(dielectric)
+    );
+#else
+    return false;
+#endif
+}
+
+
+/**
+ * Get the density of a medium at a position. The density textures are
+ * clamped to the unit interval so that the extinction of the medium
+ * bounds the extinction at every point within it.
+ *
+ * @arg position: The position in world space.
+ * @arg direction: The direction the ray is travelling, used as the
+ *     normal when projecting the texture.
+ * @arg dielectric: The medium.
+ *
+ * @returns: The density, relative to the density of the material.
+ */
+fn medium_density(position: vec3f, direction: vec3f, dielectric: Dielectric) -> f32 {
+#ifdef EnableHeterogeneousMedia
+    if dielectric.id == 0u {
+        var seed = vec4(position, ATMOSPHERE_SEED_OFFSET);
+        return saturate_f32(procedurally_texture_f32(
+            TextureCoordinates(seed, direction, seed, direction),
+            1.,
+            _atmosphere.density_texture,
+        ));
+    }
+
+    var primitive: Primitive = _primitives.primitives[dielectric.id - 1u];
+    return saturate_f32(procedurally_texture_f32(
+        primitive_texture_coordinates(
+            rotate_translate_position(position, &primitive),
+            primitive.transform.inverse_rotation * direction,
+            &primitive,
+        ),
+        1.,
+        primitive.material.density_texture,
+    ));
+#else
+    return 1.;
+#endif
+}
+
+
+/**
+ * Get the relative density of an exponential layer of the atmosphere,
+ * which is constant below the base height.
+ *
+ * @arg height: The height above the base height.
+ * @arg falloff: The rate at which the density decreases with height.
+ *
+ * @returns: The relative density.
+ */
+fn exponential_layer_density(height: f32, falloff: f32) -> f32 {
+    return exp(-falloff * positive_part_f32(height));
+}
+
+
+/**
+ * Integrate the relative density of an exponential layer of the
+ * atmosphere along a ray.
+ *
+ * @arg start_height: The height of the start of the ray above the base
+ *     height.
+ * @arg direction_y: The vertical component of the ray direction.
+ * @arg distance: The distance along the ray to integrate over.
+ * @arg falloff: The rate at which the density decreases with height.
+ *
+ * @returns: The integrated density.
+ */
+fn exponential_layer_optical_depth(
+    start_height: f32,
+    direction_y: f32,
+    distance: f32,
+    falloff: f32,
+) -> f32 {
+    var vertical_rate: f32 = falloff * abs(direction_y);
+    if vertical_rate < 1e-6 {
+        return distance * exponential_layer_density(
+            start_height + 0.5 * distance * direction_y,
+            falloff,
+        );
+    }
+
+    var end_height: f32 = start_height + distance * direction_y;
+    var lower_height: f32 = min(start_height, end_height);
+    var upper_height: f32 = max(start_height, end_height);
+    if upper_height <= 0. {
+        return distance;
+    }
+
+    // The part of the ray below the base height has a constant density
+    var distance_below_base: f32 = distance * negative_part_f32(lower_height) / (
+        upper_height - lower_height
+    );
+    return distance_below_base + (
+        exp(-falloff * positive_part_f32(lower_height)) - exp(-falloff * upper_height)
+    ) / vertical_rate;
+}
+
+
+/**
+ * Get the absorption and scattering of the atmosphere at a height.
+ *
+ * @arg position: The position in world space.
+ * @arg dielectric: The atmosphere.
+ *
+ * @returns: The coefficients of the atmosphere.
+ */
+fn atmosphere_coefficients(position: vec3f, dielectric: Dielectric) -> MediumCoefficients {
+    var height: f32 = position.y - _scene_parameters.fog_base_height;
+    var density: f32 = exponential_layer_density(height, _scene_parameters.fog_falloff);
+    var coefficients = MediumCoefficients(
+        density * dielectric.extinction_colour,
+        density * dielectric.scattering_colour,
+    );
+    if _scene_parameters.atmosphere_density == LAYERED_SKY_DENSITY {
+        // Aerosols scatter all wavelengths equally
+        coefficients.scattering += _scene_parameters.aerosol_density * exponential_layer_density(
+            height,
+            _scene_parameters.aerosol_falloff,
+        );
+    }
+    return coefficients;
+}
+
+
+/**
+ * Get the absorption and scattering of a medium at a position.
+ *
+ * @arg position: The position in world space.
+ * @arg direction: The direction the ray is travelling.
+ * @arg dielectric: The medium.
+ *
+ * @returns: The coefficients of the medium.
+ */
+fn medium_coefficients(
+    position: vec3f,
+    direction: vec3f,
+    dielectric: Dielectric,
+) -> MediumCoefficients {
+    var coefficients = MediumCoefficients(
+        dielectric.extinction_colour,
+        dielectric.scattering_colour,
+    );
+    if is_height_varying_medium(dielectric) {
+        coefficients = atmosphere_coefficients(position, dielectric);
+    }
+
+    var density: f32 = medium_density(position, direction, dielectric);
+    coefficients.absorption *= density;
+    coefficients.scattering *= density;
+    return coefficients;
+}
+
+
+/**
+ * Get an upper bound of the extinction of a medium along a ray.
+ *
+ * @arg distance: The distance along the ray.
+ * @arg ray: The ray travelling through the medium.
+ * @arg dielectric: The medium.
+ *
+ * @returns: The majorant of the extinction.
+ */
+fn medium_majorant(distance: f32, ray: ptr<function, Ray>, dielectric: Dielectric) -> f32 {
+    if is_height_varying_medium(dielectric) {
+        // The atmosphere is densest at the lowest point of the ray
+        var lowest_position: vec3f = select(
+            (*ray).origin,
+            (*ray).origin + distance * (*ray).direction,
+            (*ray).direction.y < 0.,
+        );
+        var coefficients: MediumCoefficients = atmosphere_coefficients(
+            lowest_position,
+            dielectric,
+        );
+        return max_component_vec3f(coefficients.absorption + coefficients.scattering);
+    }
+    return max_component_vec3f(dielectric.extinction_colour + dielectric.scattering_colour);
+}
+
+
+/**
+ * Get the optical depth of a height varying atmosphere along a ray.
+ *
+ * @arg origin: The origin of the ray.
+ * @arg direction: The direction of the ray.
+ * @arg distance: The distance along the ray.
+ * @arg dielectric: The atmosphere.
+ *
+ * @returns: The optical depth of each channel.
+ */
+fn atmosphere_optical_depth(
+    origin: vec3f,
+    direction: vec3f,
+    distance: f32,
+    dielectric: Dielectric,
+) -> vec3f {
+    var start_height: f32 = origin.y - _scene_parameters.fog_base_height;
+    var optical_depth: vec3f = homogeneous_attenuation(
+        dielectric,
+    ) * exponential_layer_optical_depth(
+        start_height,
+        direction.y,
+        distance,
+        _scene_parameters.fog_falloff,
+    );
+    // Aerosols only scatter, so they are subject to the same condition
+    // as the scattering of the rest of the atmosphere
+    if (
+        _scene_parameters.atmosphere_density == LAYERED_SKY_DENSITY
+        && out_scattering_attenuates()
+    ) {
+        optical_depth += _scene_parameters.aerosol_density * exponential_layer_optical_depth(
+            start_height,
+            direction.y,
+            distance,
+            _scene_parameters.aerosol_falloff,
+        );
+    }
+    return optical_depth;
+}
+
+
+/**
+ * Get the transmittance of the atmosphere along a shadow ray. This is
+ * only computed when it can be done analytically, otherwise shadow
+ * rays are not attenuated.
+ *
+ * @arg origin: The origin of the shadow ray.
+ * @arg direction: The direction to the light.
+ * @arg distance: The distance to the light.
+ *
+ * @returns: The transmittance of each channel.
+ */
+fn atmosphere_shadow_transmittance(origin: vec3f, direction: vec3f, distance: f32) -> vec3f {
+#ifdef EnableHeterogeneousMedia
+    var atmosphere: Dielectric = dielectric_from_atmosphere();
+    if is_height_varying_medium(atmosphere) && _atmosphere.density_texture.texture_type == NONE {
+        return exp(-atmosphere_optical_depth(origin, direction, distance, atmosphere));
+    }
+#endif
+    return vec3(1.);
+}
+
+
+/**
+ * Whether light scattered out of a ray attenuates it, which is only the
+ * case when the light scattered back into it is being sampled.
+ *
+ * @returns: Whether the scattering of a medium attenuates light.
+ */
+fn out_scattering_attenuates() -> bool {
+    return _render_parameters.equiangular_samples > 0u;
+}
+
+
+/**
+ * Get the rate at which a homogeneous medium attenuates light.
+ *
+ * @arg dielectric: The medium.
+ *
+ * @returns: The attenuation coefficient of each channel.
+ */
+fn homogeneous_attenuation(dielectric: Dielectric) -> vec3f {
+    return dielectric.extinction_colour + select(
+        vec3f(),
+        dielectric.scattering_colour,
+        out_scattering_attenuates(),
+    );
+}
+
+
+#ifdef EnableHeterogeneousMedia
+/**
+ * Estimate the transmittance along a ray through a heterogeneous medium
+ * using ratio tracking.
+ *
+ * @arg seed: The seed to use in randomization.
+ * @arg distance: The distance along the ray to estimate the
+ *     transmittance over.
+ * @arg ray: The ray travelling through the medium.
+ * @arg dielectric: The medium.
+ *
+ * @returns: The transmittance of each channel.
+ */
+fn ratio_tracking_transmittance(
+    seed: vec3f,
+    distance: f32,
+    ray: ptr<function, Ray>,
+    dielectric: Dielectric,
+) -> vec3f {
+    var majorant: f32 = medium_majorant(distance, ray, dielectric);
+    var transmittance = vec3(1.);
+    if majorant <= 0. {
+        return transmittance;
+    }
+
+    var tracking_seed: vec3f = seed;
+    var distance_travelled: f32 = 0.;
+    for (var step: u32 = 0u; step < MAX_TRACKING_STEPS; step++) {
+        tracking_seed = random_vec3f(tracking_seed.yzx + f32(step));
+        distance_travelled -= log(1. - tracking_seed.x) / majorant;
+        if distance_travelled >= distance {
+            break;
+        }
+
+        var coefficients: MediumCoefficients = medium_coefficients(
+            (*ray).origin + distance_travelled * (*ray).direction,
+            (*ray).direction,
+            dielectric,
+        );
+        transmittance *= 1. - (coefficients.absorption + coefficients.scattering) / majorant;
+        if max_component_vec3f(transmittance) <= 0. {
+            break;
+        }
+    }
+
+    return transmittance;
+}
+
+
+/**
+ * Track a ray through a heterogeneous medium until it collides with a
+ * particle or reaches the end of the segment. The type of each
+ * collision is chosen in proportion to the throughput it would carry,
+ * which keeps the estimate unbiased in media whose extinction differs
+ * between channels. If the ray scatters, it leaves in an isotropically
+ * sampled direction from the point of the collision.
+ *
+ * @arg seed: The seed to use in randomization.
+ * @arg distance: The length of the segment through the medium.
+ * @arg ray: The ray travelling through the medium.
+ * @arg dielectric: The medium.
+ *
+ * @returns: True if the ray collided with the medium.
+ */
+fn delta_tracking(
+    seed: vec3f,
+    distance: f32,
+    ray: ptr<function, Ray>,
+    dielectric: Dielectric,
+) -> bool {
+    var majorant: f32 = medium_majorant(distance, ray, dielectric);
+    if majorant <= 0. {
+        return false;
+    }
+
+    var tracking_seed: vec3f = seed;
+    var distance_travelled: f32 = 0.;
+    for (var step: u32 = 0u; step < MAX_TRACKING_STEPS; step++) {
+        tracking_seed = random_vec3f(tracking_seed.yzx + f32(step));
+        distance_travelled -= log(1. - tracking_seed.x) / majorant;
+        if distance_travelled >= distance {
+            return false;
+        }
+
+        var position: vec3f = (*ray).origin + distance_travelled * (*ray).direction;
+        var coefficients: MediumCoefficients = medium_coefficients(
+            position,
+            (*ray).direction,
+            dielectric,
+        );
+
+        var absorption: vec3f = coefficients.absorption;
+        var scattering: vec3f = coefficients.scattering;
+        var null_collision: vec3f = majorant - absorption - scattering;
+
+        var absorption_probability: f32 = element_sum_vec3f(absorption * (*ray).throughput);
+        var scattering_probability: f32 = element_sum_vec3f(scattering * (*ray).throughput);
+        var null_probability: f32 = element_sum_vec3f(null_collision * (*ray).throughput);
+        var total_probability: f32 = (
+            absorption_probability
+            + scattering_probability
+            + null_probability
+        );
+        if total_probability <= 0. {
+            return false;
+        }
+
+        var rng: f32 = tracking_seed.y * total_probability;
+        if rng < absorption_probability {
+            // The medium does not emit, so absorption ends the path
+            (*ray).origin = position;
+            (*ray).throughput = vec3f();
+            return true;
+        }
+        if rng < absorption_probability + scattering_probability {
+            (*ray).origin = position;
+            (*ray).direction = uniform_direction_on_sphere(tracking_seed.zx);
+            (*ray).throughput *= (
+                scattering * total_probability / (majorant * scattering_probability)
+            );
+            return true;
+        }
+
+        (*ray).throughput *= null_collision * total_probability / (majorant * null_probability);
+    }
+
+    return false;
+}
+#endif
+
+
+/**
+ * Get the transmittance along a ray through a medium.
+ *
+ * @arg seed: The seed to use in randomization.
+ * @arg distance: The distance along the ray to get the transmittance
+ *     over.
+ * @arg ray: The ray travelling through the medium.
+ * @arg dielectric: The medium.
+ *
+ * @returns: The transmittance of each channel.
+ */
+fn medium_transmittance(
+    seed: vec3f,
+    distance: f32,
+    ray: ptr<function, Ray>,
+    dielectric: Dielectric,
+) -> vec3f {
+#ifdef EnableHeterogeneousMedia
+    if is_height_varying_medium(dielectric) && _atmosphere.density_texture.texture_type == NONE {
+        return exp(-atmosphere_optical_depth(
+            (*ray).origin,
+            (*ray).direction,
+            distance,
+            dielectric,
+        ));
+    }
+    if is_heterogeneous_medium(dielectric) {
+        return ratio_tracking_transmittance(seed, distance, ray, dielectric);
+    }
+#endif
+    return exp(-homogeneous_attenuation(dielectric) * distance);
+}
+
+
+/**
+ * Add the light from point lights which is scattered towards the
+ * camera by the medium along a ray, choosing the points to scatter
+ * from with equi-angular sampling.
+ *
+ * @arg seed: The seed to use in randomization.
+ * @arg distance: The length of the segment through the medium.
+ * @arg ray: The ray travelling through the medium.
+ * @arg dielectric: The medium.
+ */
+fn sample_equiangular(
+    seed: vec3f,
+    distance: f32,
+    ray: ptr<function, Ray>,
+    dielectric: Dielectric,
+) {
+    var num_lights: u32 = _scene_parameters.num_non_physical_lights;
+    if (
+        _render_parameters.equiangular_samples == 0u
+        || num_lights == 0u
+        || !is_participating_medium(dielectric)
+    ) {
+        return;
+    }
+
+    var in_scattered_light = vec3f();
+    for (
+        var sample_index: u32 = 0u;
+        sample_index < _render_parameters.equiangular_samples;
+        sample_index++
+    ) {
+        var sample_seed: vec3f = random_vec3f(seed.zxy + f32(sample_index));
+        var light: Light = _lights.lights[
+            min(u32(sample_seed.x * f32(num_lights)), num_lights - 1u)
+        ];
+        if light.light_type != POINT {
+            continue;
+        }
+
+        var sample_distance: f32;
+        var sample_pdf: f32 = sample_equiangular_pdf(
+            sample_seed.y,
+            distance,
+            light.dimensional_data,
+            ray,
+            &sample_distance,
+        );
+        if sample_pdf <= 0. || sample_distance < 0. || sample_distance > distance {
+            continue;
+        }
+
+        var sample_position: vec3f = (*ray).origin + sample_distance * (*ray).direction;
+        var light_direction: vec3f = light.dimensional_data - sample_position;
+        var distance_to_light: f32 = length(light_direction);
+        light_direction /= distance_to_light;
+
+        var shadow_intensity: f32 = sample_shadow(
+            sample_position,
+            light_direction,
+            distance_to_light,
+        );
+        if shadow_intensity <= 0. {
+            continue;
+        }
+
+        var light_colour: vec3f = light.colour * light_intensity(&light, distance_to_light);
+#ifdef EnableSpectralRendering
+        if spectral_rendering_enabled() {
+            light_colour = rgb_to_spectral_vec3f(light_colour, (*ray).wavelength);
+        }
+#endif
+
+        in_scattered_light += (
+            medium_transmittance(sample_seed.yzx, sample_distance, ray, dielectric)
+            * medium_coefficients(sample_position, (*ray).direction, dielectric).scattering
+            * ISOTROPIC_PHASE_FUNCTION
+            * shadow_intensity
+            * atmosphere_shadow_transmittance(sample_position, light_direction, distance_to_light)
+            * light_colour
+            / sample_pdf
+        );
+    }
+
+    (*ray).colour += (
+        (*ray).throughput
+        * in_scattered_light
+        * f32(num_lights)
+        / f32(_render_parameters.equiangular_samples)
+    );
+}
+
+
+/**
+ * Account for the medium the ray travelled through since its last
+ * bounce. The light scattered into the ray from point lights is
+ * added, and the throughput is attenuated by the medium. In a
+ * heterogeneous medium the ray may instead collide with the medium
+ * before reaching the end of the segment, in which case its origin
+ * and direction are updated and the nested dielectrics are left
+ * unchanged, since the ray is still within the same medium.
+ *
+ * @arg seed: The seed to use in randomization.
+ * @arg distance_since_last_bounce: The length of the segment.
+ * @arg allow_scattering: Whether or not the ray can collide with the
+ *     medium, which it cannot if it will not be marched any further.
+ * @arg ray: The ray travelling through the medium.
+ * @arg nested_dielectrics: The dielectrics the ray is inside of.
+ *
+ * @returns: True if the ray collided with the medium.
+ */
+fn sample_participating_medium(
+    seed: vec3f,
+    distance_since_last_bounce: f32,
+    allow_scattering: bool,
+    ray: ptr<function, Ray>,
+    nested_dielectrics: ptr<function, NestedDielectrics>,
+) -> bool {
+    // Get the material properties of the dielectric the ray is currently in
+    var current_dielectric: Dielectric = peek_dielectric(nested_dielectrics);
+
+    sample_equiangular(seed, distance_since_last_bounce, ray, current_dielectric);
+
+#ifdef EnableHeterogeneousMedia
+    if allow_scattering && is_heterogeneous_medium(current_dielectric) {
+        return delta_tracking(
+            seed.yzx,
+            distance_since_last_bounce,
+            ray,
+            current_dielectric,
+        );
+    }
+#endif
+
+    (*ray).throughput *= medium_transmittance(
+        seed.yzx,
+        distance_since_last_bounce,
+        ray,
+        current_dielectric,
+    );
+    return false;
+}
@@ -9,9 +9,53 @@ const GRADE: u32 = 1u;
 const CHECKER_BOARD: u32 = 2u;
 const FBM_NOISE: u32 = 3u;
 const TURBULENCE_NOISE: u32 = 4u;
+const IMAGE: u32 = 5u;
+const VORONOI_NOISE: u32 = 6u;
+const IMAGE_GRID: u32 = 7u;
+
+const F1_FEATURE: u32 = 0u;
+const F2_FEATURE: u32 = 1u;
+const F2_MINUS_F1_FEATURE: u32 = 2u;
+const CELL_ID_FEATURE: u32 = 3u;
+
+const EUCLIDEAN_DISTANCE: u32 = 0u;
+const MANHATTAN_DISTANCE: u32 = 1u;
+const CHEBYSHEV_DISTANCE: u32 = 2u;
+
+const TRIPLANAR_PROJECTION: u32 = 0u;
+const PLANAR_PROJECTION: u32 = 1u;
+const SPHERICAL_PROJECTION: u32 = 2u;
+const CYLINDRICAL_PROJECTION: u32 = 3u;
+
+const OBJECT_SPACE: u32 = 0u;
+const WORLD_SPACE: u32 = 1u;
+const CAMERA_SPACE: u32 = 2u;
+const SPHERICAL_SPACE: u32 = 3u;
+
+const MULTIPLY_BLEND: u32 = 0u;
+const ADD_BLEND: u32 = 1u;
+const SCREEN_BLEND: u32 = 2u;
+const OVERLAY_BLEND: u32 = 3u;
+const MINIMUM_BLEND: u32 = 4u;
+const MAXIMUM_BLEND: u32 = 5u;
+const MIX_BLEND: u32 = 6u;
+
+const CONSTANT_INTERPOLATION: u32 = 0u;
+const LINEAR_INTERPOLATION: u32 = 1u;
+const SMOOTHSTEP_INTERPOLATION: u32 = 2u;
+const OKLAB_INTERPOLATION: u32 = 3u;
 
 const INVERT: u32 = 1u;
 const USE_TRAP_COLOUR: u32 = 2u;
+const HAS_INPUT_TEXTURE: u32 = 4u;
+const HAS_MASK_TEXTURE: u32 = 8u;
+const USE_COLOUR_RAMP: u32 = 16u;
+
+// The number of intermediate results a composite texture can hold at
+// once, which must match the CPU, as it flattens textures that need more
+const MAX_TEXTURE_STACK_DEPTH: u32 = 8u;
+
+const LUMINANCE_WEIGHTS: vec3f = vec3f(0.2126, 0.7152, 0.0722);
 
 const RGB_TO_YIQ: mat3x3f = mat3x3f(
     vec3f(0.299, 0.596, 0.211),
@@ -24,6 +68,27 @@ const YIQ_TO_RGB: mat3x3f = mat3x3f(
     vec3f(0.621, -0.647, 1.705),
 );
 
+const LINEAR_SRGB_TO_LMS: mat3x3f = mat3x3f(
+    vec3f(0.4122214708, 0.2119034982, 0.0883024619),
+    vec3f(0.5363325363, 0.6806995451, 0.2817188376),
+    vec3f(0.0514459929, 0.1073969566, 0.6299787005),
+);
+const LMS_TO_OKLAB: mat3x3f = mat3x3f(
+    vec3f(0.2104542553, 1.9779984951, 0.0259040371),
+    vec3f(0.7936177850, -2.4285922050, 0.7827717662),
+    vec3f(-0.0040720468, 0.4505937099, -0.8086757660),
+);
+const OKLAB_TO_LMS: mat3x3f = mat3x3f(
+    vec3f(1., 1., 1.),
+    vec3f(0.3963377774, -0.1055613458, -0.0894841775),
+    vec3f(0.2158037573, -0.0638541728, -1.2914855480),
+);
+const LMS_TO_LINEAR_SRGB: mat3x3f = mat3x3f(
+    vec3f(4.0767416621, -1.2684380046, -0.0041960863),
+    vec3f(-3.3077115913, 2.6097574011, -0.7034186147),
+    vec3f(0.2309699292, -0.3413193965, 1.7076147010),
+);
+
 
 #ifdef EnableNoise
 const G4: f32 = 0.138196601;
@@ -211,14 +276,116 @@ fn perlin_simplex_noise(seed: vec4f) -> f32 {
 
     return 27. * n;
 }
+#endif
 
 
+#ifdef EnableVoronoiNoise
+/**
+ * Get the pseudo-random position of the feature point in a cell.
+ *
+ * @arg cell: The integer coordinates of the cell.
+ * @arg offset: A value that decorrelates the noise of different seeds.
+ *
+ * @returns: A random point in the unit cube.
+ */
+fn voronoi_cell_hash(cell: vec3f, offset: f32) -> vec3f {
+    return vec3(
+        vec3f_to_random_f32(cell + offset),
+        vec3f_to_random_f32(cell.yzx + offset + 17.13),
+        vec3f_to_random_f32(cell.zxy + offset + 31.71),
+    );
+}
+
+
+/**
+ * Measure the distance to a feature point with the texture's metric.
+ *
+ * @arg offset: The offset from the position to the feature point.
+ * @arg distance_metric: The metric to measure the distance with.
+ *
+ * @returns: The distance to the feature point.
+ */
+fn voronoi_distance(offset: vec3f, distance_metric: u32) -> f32 {
+    switch distance_metric {
+        case MANHATTAN_DISTANCE {
+            return element_sum_vec3f(abs(offset));
+        }
+        case CHEBYSHEV_DISTANCE {
+            var absolute_offset: vec3f = abs(offset);
+            return max(absolute_offset.x, max(absolute_offset.y, absolute_offset.z));
+        }
+        case EUCLIDEAN_DISTANCE, default {
+            return length(offset);
+        }
+    }
+}
+
+
+/**
+ * Worley/Voronoi cellular noise. Each unit cell contains one feature
+ * point, randomly displaced from the cell centre by the jitter.
+ *
+ * @arg seed: The noise seed, the fourth component offsets the hash.
+ * @arg texture: The texture properties.
+ *
+ * @returns: The distance to the closest (F1) or second closest (F2)
+ *     feature point, their difference, or a random value per cell.
+ */
+fn voronoi_noise(seed: vec4f, texture: ProceduralTexture) -> f32 {
+    var cell: vec3f = floor(seed.xyz);
+    var cell_position: vec3f = seed.xyz - cell;
+
+    var closest_distance: f32 = 1e10;
+    var second_closest_distance: f32 = 1e10;
+    var closest_cell: vec3f = cell;
+
+    for (var x: i32 = -1; x <= 1; x++) {
+        for (var y: i32 = -1; y <= 1; y++) {
+            for (var z: i32 = -1; z <= 1; z++) {
+                var neighbour = vec3f(f32(x), f32(y), f32(z));
+                var feature_point: vec3f = neighbour + 0.5 + texture.jitter * (
+                    voronoi_cell_hash(cell + neighbour, seed.w) - 0.5
+                );
+                var distance: f32 = voronoi_distance(
+                    feature_point - cell_position,
+                    texture.distance_metric,
+                );
+                if distance < closest_distance {
+                    second_closest_distance = closest_distance;
+                    closest_distance = distance;
+                    closest_cell = cell + neighbour;
+                } else if distance < second_closest_distance {
+                    second_closest_distance = distance;
+                }
+            }
+        }
+    }
+
+    switch texture.voronoi_feature {
+        case F2_FEATURE {
+            return second_closest_distance;
+        }
+        case F2_MINUS_F1_FEATURE {
+            return second_closest_distance - closest_distance;
+        }
+        case CELL_ID_FEATURE {
+            return voronoi_cell_hash(closest_cell, seed.w).x;
+        }
+        case F1_FEATURE, default {
+            return closest_distance;
+        }
+    }
+}
+#endif
+
+
+#ifdef EnableOctaveNoise
 /**
  * Octave noise.
  *
  * @arg seed: The noise seed.
  * @arg texture: The texture properties.
- * @arg turbulence: Use the absolute value of the simplex noise if true.
+ * @arg turbulence: Use the absolute value of the noise if true.
  *
  * @returns: The noise value in the range [-1, 1].
  */
@@ -245,10 +412,21 @@ fn octave_noise(
             + (texture.low_frequency_translation * (1. - octave_fraction))
         );
 
-        var simplex_noise: f32 = amplitude * perlin_simplex_noise(
-            (seed / scale - translation) * frequency / texture.scale,
-        );
-        output += select(simplex_noise, abs(simplex_noise), turbulence);
+        var octave_seed: vec4f = (seed / scale - translation) * frequency / texture.scale;
+        var noise: f32 = 0.;
+        switch texture.texture_type {
+#ifdef EnableVoronoiNoise
+            case VORONOI_NOISE {
+                noise = amplitude * voronoi_noise(octave_seed, texture);
+            }
+#endif
+            default {
+#ifdef EnableNoise
+                noise = amplitude * perlin_simplex_noise(octave_seed);
+#endif
+            }
+        }
+        output += select(noise, abs(noise), turbulence);
 
         frequency *= texture.lacunarity;
         max_amplitude += amplitude;
@@ -276,10 +454,384 @@ struct ProceduralTexture {
     low_frequency_translation: vec4f,
     high_frequency_translation: vec4f,
     hue_rotation: mat3x3f,
+    image_index: u32,
+    grid_slices: u32,
+    projection: u32,
+    coordinate_space: u32,
+    blend_sharpness: f32,
+    voronoi_feature: u32,
+    distance_metric: u32,
+    jitter: f32,
+    warp_strength: f32,
+    warp_octaves: u32,
+    blend_mode: u32,
+    ramp_interpolation: u32,
+    ramp_start: u32,
+    ramp_size: u32,
+    stack_start: u32,
+    stack_size: u32,
     flags: u32,
 }
 
 
+/**
+ * The points at which a procedural texture can be evaluated. The seeds
+ * have the position in their first three components, and a value to
+ * decorrelate the noise of different primitives in the fourth.
+ */
+struct TextureCoordinates {
+    seed: vec4f,
+    normal: vec3f,
+    world_seed: vec4f,
+    world_normal: vec3f,
+}
+
+
+#ifdef EnableDomainWarping
+/**
+ * Fractal noise with a separate signed value for each axis, used to
+ * offset the position a texture is evaluated at.
+ *
+ * @arg seed: The noise seed.
+ * @arg octaves: The number of frequencies to superimpose.
+ *
+ * @returns: An offset with each component in the range [-1, 1].
+ */
+fn warp_noise(seed: vec4f, octaves: u32) -> vec3f {
+    var offset = vec3f();
+    var frequency: f32 = 1.;
+    var amplitude: f32 = 1.;
+    var max_amplitude: f32 = 0.;
+
+    for (var octave=0u; octave < octaves; octave++) {
+        var octave_seed: vec4f = seed * frequency;
+        // Offset the seed of each axis so they are uncorrelated
+        offset += amplitude * vec3(
+            perlin_simplex_noise(octave_seed),
+            perlin_simplex_noise(octave_seed + vec4(5.2, 1.3, 2.8, 0.)),
+            perlin_simplex_noise(octave_seed + vec4(1.7, 9.2, 4.6, 0.)),
+        );
+
+        frequency *= 2.;
+        max_amplitude += amplitude;
+        amplitude *= 0.5;
+    }
+
+    return offset / max_amplitude;
+}
+
+
+/**
+ * Offset a seed by noise, so that the texture evaluated at the new
+ * seed swirls and flows. The strength is relative to the scale of the
+ * texture so that the pattern keeps its shape when scaled.
+ *
+ * @arg seed: The seed to warp.
+ * @arg texture: The texture properties.
+ *
+ * @returns: The warped seed.
+ */
+fn warp_seed(seed: vec4f, texture: ProceduralTexture) -> vec4f {
+    if texture.warp_strength == 0. || texture.warp_octaves == 0u {
+        return seed;
+    }
+    return seed + vec4(
+        texture.warp_strength * texture.scale.xyz * warp_noise(
+            vec4(seed.xyz / texture.scale.xyz, seed.w),
+            texture.warp_octaves,
+        ),
+        0.,
+    );
+}
+#endif
+
+
+/**
+ * Get the coordinates to evaluate a texture at in its coordinate space,
+ * warping the seed if the texture is domain warped.
+ *
+ * @arg coordinates: The position and normal in object and world space.
+ * @arg texture: The texture properties.
+ *
+ * @returns: The coordinates with the seed and normal in the coordinate
+ *     space of the texture.
+ */
+fn texture_space_coordinates(
+    coordinates: TextureCoordinates,
+    texture: ProceduralTexture,
+) -> TextureCoordinates {
+    var texture_coordinates: TextureCoordinates = coordinates;
+    switch texture.coordinate_space {
+        case WORLD_SPACE {
+            texture_coordinates.seed = coordinates.world_seed;
+            texture_coordinates.normal = coordinates.world_normal;
+        }
+        case CAMERA_SPACE {
+            texture_coordinates.seed = vec4(
+                (_render_camera.inverse_world_matrix * vec4(coordinates.world_seed.xyz, 1.)).xyz,
+                coordinates.world_seed.w,
+            );
+            texture_coordinates.normal = (
+                _render_camera.inverse_world_matrix * vec4(coordinates.world_normal, 0.)
+            ).xyz;
+        }
+        case SPHERICAL_SPACE {
+            // The azimuth and polar angles normalized to [0, 1], and the radius
+            var radius: f32 = length(coordinates.seed.xyz);
+            var direction: vec3f = coordinates.seed.xyz / max(radius, 1e-6);
+            texture_coordinates.seed = vec4(
+                atan2(direction.x, direction.z) / TWO_PI + 0.5,
+                acos(clamp(direction.y, -1., 1.)) / PI,
+                radius,
+                coordinates.seed.w,
+            );
+        }
+        case OBJECT_SPACE, default {}
+    }
+
+#ifdef EnableDomainWarping
+    texture_coordinates.seed = warp_seed(texture_coordinates.seed, texture);
+#endif
+
+    return texture_coordinates;
+}
+
+
+#ifdef EnableImageTexture
+@group(TEXTURE_BIND_GROUP) @binding(0)
+var _image_textures: texture_2d_array<f32>;
+
+
+/**
+ * Bilinearly filter a layer of the image textures, repeating the image
+ * outside of the unit square.
+ *
+ * @arg uv: The texture coordinates, with the origin in the bottom left.
+ * @arg layer: The layer of the texture array to sample.
+ *
+ * @returns: The filtered colour.
+ */
+fn sample_image_layer(uv: vec2f, layer: u32) -> vec4f {
+    var dimensions = vec2i(textureDimensions(_image_textures));
+    var clamped_layer: u32 = min(layer, textureNumLayers(_image_textures) - 1u);
+
+    // Images are stored top to bottom
+    var texel_position: vec2f = vec2(fract(uv.x), 1. - fract(uv.y)) * vec2f(dimensions) - 0.5;
+    var texel_weights: vec2f = fract(texel_position);
+    var lower_texel = vec2i(floor(texel_position));
+    var upper_texel: vec2i = lower_texel + 1;
+    lower_texel = (lower_texel % dimensions + dimensions) % dimensions;
+    upper_texel = (upper_texel % dimensions + dimensions) % dimensions;
+
+    return mix(
+        mix(
+            textureLoad(_image_textures, lower_texel, clamped_layer, 0),
+            textureLoad(
+                _image_textures,
+                vec2(upper_texel.x, lower_texel.y),
+                clamped_layer,
+                0,
+            ),
+            texel_weights.x,
+        ),
+        mix(
+            textureLoad(
+                _image_textures,
+                vec2(lower_texel.x, upper_texel.y),
+                clamped_layer,
+                0,
+            ),
+            textureLoad(_image_textures, upper_texel, clamped_layer, 0),
+            texel_weights.x,
+        ),
+        texel_weights.y,
+    );
+}
+
+
+/**
+ * Project an image texture onto a surface. SDFs have no UV coordinates
+ * so the texture coordinates are derived from the position, and the
+ * normal when blending triplanar projections.
+ *
+ * @arg coordinates: The position and normal to project onto, in the
+ *     coordinate space of the texture.
+ * @arg texture: The texture to sample.
+ *
+ * @returns: The colour of the image at the position.
+ */
+fn sample_image_texture(
+    coordinates: TextureCoordinates,
+    texture: ProceduralTexture,
+) -> vec4f {
+    var position: vec3f = coordinates.seed.xyz;
+    var normal: vec3f = coordinates.normal;
+    var scale: vec3f = texture.scale.xyz;
+
+    switch texture.projection {
+        case PLANAR_PROJECTION {
+            return sample_image_layer(position.xy / scale.xy, texture.image_index);
+        }
+        case SPHERICAL_PROJECTION {
+            var direction: vec3f = normalize(position);
+            return sample_image_layer(
+                vec2(
+                    atan2(direction.x, direction.z) / TWO_PI + 0.5,
+                    1. - acos(clamp(direction.y, -1., 1.)) / PI,
+                ) / scale.xy,
+                texture.image_index,
+            );
+        }
+        case CYLINDRICAL_PROJECTION {
+            return sample_image_layer(
+                vec2(atan2(position.x, position.z) / TWO_PI + 0.5, position.y) / scale.xy,
+                texture.image_index,
+            );
+        }
+        case TRIPLANAR_PROJECTION, default {
+            var weights: vec3f = pow(abs(normal), vec3(texture.blend_sharpness));
+            weights /= max(element_sum_vec3f(weights), 1e-6);
+            var scaled_position: vec3f = position / scale;
+            return (
+                weights.x * sample_image_layer(scaled_position.zy, texture.image_index)
+                + weights.y * sample_image_layer(scaled_position.xz, texture.image_index)
+                + weights.z * sample_image_layer(scaled_position.xy, texture.image_index)
+            );
+        }
+    }
+}
+
+
+/**
+ * Sample a slice of a volumetric image, which is stored with its
+ * slices stacked vertically from top to bottom. Positions are kept
+ * half a texel inside the slice so that the filtering does not bleed
+ * into the neighbouring slices.
+ *
+ * @arg uv: The position within the slice, in the unit square.
+ * @arg slice: The index of the slice to sample.
+ * @arg texture: The texture the grid belongs to.
+ *
+ * @returns: The filtered colour.
+ */
+fn sample_image_grid_slice(uv: vec2f, slice: u32, texture: ProceduralTexture) -> vec4f {
+    var slices = f32(texture.grid_slices);
+    var half_texel: f32 = 0.5 * slices / f32(textureDimensions(_image_textures).y);
+    var slice_v: f32 = clamp(uv.y, half_texel, 1. - half_texel);
+    return sample_image_layer(
+        vec2(uv.x, 1. - (f32(slice) + 1. - slice_v) / slices),
+        texture.image_index,
+    );
+}
+
+
+/**
+ * Trilinearly filter a volumetric image. The grid spans the scale of
+ * the texture in each direction about the origin, and is empty
+ * outside of it.
+ *
+ * @arg coordinates: The position to sample, in the coordinate space
+ *     of the texture.
+ * @arg texture: The texture to sample.
+ *
+ * @returns: The colour of the grid at the position.
+ */
+fn sample_image_grid(coordinates: TextureCoordinates, texture: ProceduralTexture) -> vec4f {
+    var grid_position: vec3f = 0.5 * coordinates.seed.xyz / texture.scale.xyz + 0.5;
+    if any(grid_position < vec3f()) || any(grid_position > vec3(1.)) {
+        return vec4f();
+    }
+
+    var slice_position: f32 = max(grid_position.z * f32(texture.grid_slices) - 0.5, 0.);
+    var lower_slice: u32 = min(u32(slice_position), texture.grid_slices - 1u);
+    var upper_slice: u32 = min(lower_slice + 1u, texture.grid_slices - 1u);
+
+    return mix(
+        sample_image_grid_slice(grid_position.xy, lower_slice, texture),
+        sample_image_grid_slice(grid_position.xy, upper_slice, texture),
+        fract(slice_position),
+    );
+}
+#endif
+
+
+#ifdef EnableColourRamp
+struct ColourStop {
+    colour: vec3f,
+    position: f32,
+}
+
+
+@group(STORAGE_BIND_GROUP) @binding(5)
+var<storage, read> _colour_ramp_stops: array<ColourStop>;
+
+
+fn linear_srgb_to_oklab(colour: vec3f) -> vec3f {
+    var lms: vec3f = LINEAR_SRGB_TO_LMS * colour;
+    return LMS_TO_OKLAB * (sign(lms) * pow(abs(lms), vec3(1. / 3.)));
+}
+
+
+fn oklab_to_linear_srgb(colour: vec3f) -> vec3f {
+    var lms: vec3f = OKLAB_TO_LMS * colour;
+    return LMS_TO_LINEAR_SRGB * (lms * lms * lms);
+}
+
+
+/**
+ * Map a value through the colour ramp of a texture. The stops are
+ * sorted by position when they are uploaded.
+ *
+ * @arg value: The position along the ramp.
+ * @arg texture: The texture whose colour ramp to sample.
+ *
+ * @returns: The colour of the ramp at the position.
+ */
+fn sample_colour_ramp(value: f32, texture: ProceduralTexture) -> vec3f {
+    if texture.ramp_size == 0u {
+        return vec3(value);
+    }
+
+    var lower_stop: ColourStop = _colour_ramp_stops[texture.ramp_start];
+    if value <= lower_stop.position {
+        return lower_stop.colour;
+    }
+
+    var ramp_end: u32 = texture.ramp_start + texture.ramp_size;
+    for (var index: u32 = texture.ramp_start + 1u; index < ramp_end; index++) {
+        var upper_stop: ColourStop = _colour_ramp_stops[index];
+        if value < upper_stop.position {
+            var weight: f32 = (value - lower_stop.position) / max(
+                upper_stop.position - lower_stop.position,
+                1e-6,
+            );
+            switch texture.ramp_interpolation {
+                case CONSTANT_INTERPOLATION {
+                    return lower_stop.colour;
+                }
+                case SMOOTHSTEP_INTERPOLATION {
+                    return mix(lower_stop.colour, upper_stop.colour, smoothstep(0., 1., weight));
+                }
+                case OKLAB_INTERPOLATION {
+                    return oklab_to_linear_srgb(mix(
+                        linear_srgb_to_oklab(lower_stop.colour),
+                        linear_srgb_to_oklab(upper_stop.colour),
+                        weight,
+                    ));
+                }
+                case LINEAR_INTERPOLATION, default {
+                    return mix(lower_stop.colour, upper_stop.colour, weight);
+                }
+            }
+        }
+        lower_stop = upper_stop;
+    }
+
+    return lower_stop.colour;
+}
+#endif
+
+
 fn trap_texture(
     trap_colour: vec3f,
     current_colour: vec3f,
@@ -288,6 +840,14 @@ fn trap_texture(
     if !bool(texture.flags & USE_TRAP_COLOUR) {
         return current_colour;
     }
+#ifdef EnableColourRamp
+    if bool(texture.flags & USE_COLOUR_RAMP) {
+        return current_colour * sample_colour_ramp(
+            dot(trap_colour, LUMINANCE_WEIGHTS),
+            texture,
+        );
+    }
+#endif
     return abs(YIQ_TO_RGB * (
         texture.hue_rotation * (
             RGB_TO_YIQ * (trap_colour * current_colour)
@@ -353,11 +913,15 @@ fn grade_vec3(colour: vec3f, texture: ProceduralTexture) -> vec3f {
 }
 
 
-fn procedurally_texture_f32(
-    seed: vec4f,
+fn evaluate_texture_f32(
+    coordinates: TextureCoordinates,
     colour: f32,
     texture: ProceduralTexture,
 ) -> f32 {
+    var texture_coordinates: TextureCoordinates = texture_space_coordinates(
+        coordinates,
+        texture,
+    );
     switch texture.texture_type {
         case NONE, default {
             return colour;
@@ -369,15 +933,47 @@ fn procedurally_texture_f32(
 #endif
 #ifdef EnableCheckerboard
         case CHECKER_BOARD {
-            return colour * grade_f32(checkerboard(seed / texture.scale), texture);
+            return colour * grade_f32(
+                checkerboard(texture_coordinates.seed / texture.scale),
+                texture,
+            );
         }
 #endif
-// Simply having this case slows things down, so allow it to be compiled out
+// Simply having this case slows things down, so allow it to be compiled out.
+// Domain warping enables the noise without the octaves
 #ifdef EnableNoise
+#ifdef EnableOctaveNoise
         case FBM_NOISE, TURBULENCE_NOISE {
             // FBM Noise
             return colour * grade_f32(
-                octave_noise(seed, texture, texture.texture_type == TURBULENCE_NOISE),
+                octave_noise(
+                    texture_coordinates.seed,
+                    texture,
+                    texture.texture_type == TURBULENCE_NOISE,
+                ),
+                texture,
+            );
+        }
+#endif
+#endif
+#ifdef EnableImageTexture
+        case IMAGE {
+            return colour * grade_f32(
+                sample_image_texture(texture_coordinates, texture).r,
+                texture,
+            );
+        }
+        case IMAGE_GRID {
+            return colour * grade_f32(
+                sample_image_grid(texture_coordinates, texture).r,
+                texture,
+            );
+        }
+#endif
+#ifdef EnableVoronoiNoise
+        case VORONOI_NOISE {
+            return colour * grade_f32(
+                octave_noise(texture_coordinates.seed, texture, false),
                 texture,
             );
         }
@@ -386,11 +982,15 @@ fn procedurally_texture_f32(
 }
 
 
-fn procedurally_texture_vec3f(
-    seed: vec4f,
+fn evaluate_texture_vec3f(
+    coordinates: TextureCoordinates,
     colour: vec3f,
     texture: ProceduralTexture,
 ) -> vec3f {
+    var texture_coordinates: TextureCoordinates = texture_space_coordinates(
+        coordinates,
+        texture,
+    );
     switch texture.texture_type {
         case NONE, default {
             return colour;
@@ -402,14 +1002,46 @@ fn procedurally_texture_vec3f(
 #endif
 #ifdef EnableCheckerboard
         case CHECKER_BOARD {
-            return colour * vec3(grade_f32(checkerboard(seed / texture.scale), texture));
+            return colour * vec3(grade_f32(
+                checkerboard(texture_coordinates.seed / texture.scale),
+                texture,
+            ));
         }
 #endif
-// Simply having this case slows things down, so allow it to be compiled out
+// Simply having this case slows things down, so allow it to be compiled out.
+// Domain warping enables the noise without the octaves
 #ifdef EnableNoise
+#ifdef EnableOctaveNoise
         case FBM_NOISE, TURBULENCE_NOISE {
             return colour * vec3(grade_f32(
-                octave_noise(seed, texture, texture.texture_type == TURBULENCE_NOISE),
+                octave_noise(
+                    texture_coordinates.seed,
+                    texture,
+                    texture.texture_type == TURBULENCE_NOISE,
+                ),
+                texture,
+            ));
+        }
+#endif
+#endif
+#ifdef EnableImageTexture
+        case IMAGE {
+            return colour * grade_vec3(
+                sample_image_texture(texture_coordinates, texture).rgb,
+                texture,
+            );
+        }
+        case IMAGE_GRID {
+            return colour * grade_vec3(
+                sample_image_grid(texture_coordinates, texture).rgb,
+                texture,
+            );
+        }
+#endif
+#ifdef EnableVoronoiNoise
+        case VORONOI_NOISE {
+            return colour * vec3(grade_f32(
+                octave_noise(texture_coordinates.seed, texture, false),
                 texture,
             ));
         }
@@ -418,23 +1050,190 @@ fn procedurally_texture_vec3f(
 }
 
 
-fn sample_equiangular(
-    distance_since_last_bounce: f32,
-    ray: ptr<function, Ray>,
-    nested_dielectrics: ptr<function, NestedDielectrics>,
-) {
-    // Get the material properties of the dielectric the ray is currently in
-    var current_dielectric: Dielectric = peek_dielectric(nested_dielectrics);
+#ifdef EnableTextureComposition
+@group(STORAGE_BIND_GROUP) @binding(4)
+var<storage, read> _texture_stack: array<ProceduralTexture>;
+
+
+fn is_composite_texture(texture: ProceduralTexture) -> bool {
+    return (
+        texture.stack_size > 0u
+        || texture.blend_mode != MULTIPLY_BLEND
+        || bool(texture.flags & USE_COLOUR_RAMP)
+    );
+}
+
+
+/**
+ * Blend a layer over a base colour.
+ *
+ * @arg base: The colour of the input texture.
+ * @arg layer: The colour of the texture being blended.
+ * @arg blend_mode: The operation used to combine the two.
+ *
+ * @returns: The blended colour.
+ */
+fn blend_colours(base: vec3f, layer: vec3f, blend_mode: u32) -> vec3f {
+    switch blend_mode {
+        case ADD_BLEND {
+            return base + layer;
+        }
+        case SCREEN_BLEND {
+            return 1. - (1. - base) * (1. - layer);
+        }
+        case OVERLAY_BLEND {
+            return select(
+                1. - 2. * (1. - base) * (1. - layer),
+                2. * base * layer,
+                base < vec3(0.5),
+            );
+        }
+        case MINIMUM_BLEND {
+            return min(base, layer);
+        }
+        case MAXIMUM_BLEND {
+            return max(base, layer);
+        }
+        case MIX_BLEND {
+            return layer;
+        }
+        case MULTIPLY_BLEND, default {
+            return base * layer;
+        }
+    }
+}
+
 
-    // If equiangular sampling is disabled or the dielectric does not scatter
-    // light, compute the extinction and exit early
+/**
+ * Evaluate a texture and composite it with the result of its input.
+ *
+ * @arg coordinates: The position and normal to texture.
+ * @arg base: The result of the input texture, or the colour being
+ *     textured if there is no input.
+ * @arg mask: The result of the mask texture, which is used to mix
+ *     between the base and the blended colours.
+ * @arg texture: The texture to composite.
+ *
+ * @returns: The composited colour.
+ */
+fn composite_texture(
+    coordinates: TextureCoordinates,
+    base: vec3f,
+    mask: vec3f,
+    texture: ProceduralTexture,
+) -> vec3f {
+#ifdef EnableColourRamp
+    // The ramp maps the value of the texture itself, evaluated with a
+    // unit base, before it is blended. Textures that use the trap colour
+    // map the trap through the ramp instead
     if (
-        _render_parameters.equiangular_samples == 0u
-        || element_sum_vec3f(current_dielectric.scattering_colour) == 0.
+        bool(texture.flags & USE_COLOUR_RAMP)
+        && !bool(texture.flags & USE_TRAP_COLOUR)
     ) {
-        (*ray).throughput *= exp(
-            -current_dielectric.extinction_colour * distance_since_last_bounce,
+        var value: f32 = dot(
+            evaluate_texture_vec3f(coordinates, vec3(1.), texture),
+            LUMINANCE_WEIGHTS,
+        );
+        return mix(
+            base,
+            blend_colours(base, sample_colour_ramp(value, texture), texture.blend_mode),
+            mask,
+        );
+    }
+#endif
+
+    var blended_colour: vec3f;
+    if texture.blend_mode == MULTIPLY_BLEND {
+        // Multiplying keeps the behaviour of a texture without inputs,
+        // which allows grades to be applied to the input
+        blended_colour = evaluate_texture_vec3f(coordinates, base, texture);
+    } else {
+        blended_colour = blend_colours(
+            base,
+            evaluate_texture_vec3f(coordinates, vec3(1.), texture),
+            texture.blend_mode,
+        );
+    }
+
+    return mix(base, blended_colour, mask);
+}
+
+
+/**
+ * Evaluate the stack of textures that a texture is composed from,
+ * followed by the texture itself. Each texture in the stack consumes
+ * the results of its input and mask from the top of the stack, and
+ * pushes its own result.
+ *
+ * @arg coordinates: The position and normal to texture.
+ * @arg colour: The colour being textured.
+ * @arg texture: The final texture in the stack.
+ *
+ * @returns: The composited colour.
+ */
+fn composite_texture_stack(
+    coordinates: TextureCoordinates,
+    colour: vec3f,
+    texture: ProceduralTexture,
+) -> vec3f {
+    var results: array<vec3f, MAX_TEXTURE_STACK_DEPTH>;
+    var depth: u32 = 0u;
+    var stack_end: u32 = texture.stack_start + texture.stack_size;
+    for (var index: u32 = texture.stack_start; index <= stack_end; index++) {
+        var operation: ProceduralTexture = texture;
+        if index < stack_end {
+            operation = _texture_stack[index];
+        }
+
+        var mask = vec3(1.);
+        if bool(operation.flags & HAS_MASK_TEXTURE) && depth > 0u {
+            depth--;
+            mask = results[depth];
+        }
+        var base: vec3f = colour;
+        if bool(operation.flags & HAS_INPUT_TEXTURE) && depth > 0u {
+            depth--;
+            base = results[depth];
+        }
+
+        results[min(depth, MAX_TEXTURE_STACK_DEPTH - 1u)] = composite_texture(
+            coordinates,
+            base,
+            mask,
+            operation,
         );
-        return;
+        depth = min(depth + 1u, MAX_TEXTURE_STACK_DEPTH);
+    }
+
+    return results[depth - 1u];
+}
+#endif
+
+
+fn procedurally_texture_f32(
+    coordinates: TextureCoordinates,
+    colour: f32,
+    texture: ProceduralTexture,
+) -> f32 {
+#ifdef EnableTextureComposition
+    if is_composite_texture(texture) {
+        return composite_texture_stack(coordinates, vec3(colour), texture).x;
     }
+#endif
+    return evaluate_texture_f32(coordinates, colour, texture);
 }
+
+
+fn procedurally_texture_vec3f(
+    coordinates: TextureCoordinates,
+    colour: vec3f,
+    texture: ProceduralTexture,
+) -> vec3f {
+#ifdef EnableTextureComposition
+    if is_composite_texture(texture) {
+        return composite_texture_stack(coordinates, colour, texture);
+    }
+#endif
+    return evaluate_texture_vec3f(coordinates, colour, texture);
+}
+
@@ -0,0 +1,207 @@
+// Copyright (c) 2024, Owen Bulka
+// All rights reserved.
+// This source code is licensed under the BSD-style license found in the
+// LICENSE file in the root directory of this source tree.
+
+
+//
+// Denoising shader
+//
+// An edge-avoiding a-trous wavelet filter, which repeatedly blurs the
+// progressive render with a sparse 5x5 kernel whose taps are twice as
+// far apart each iteration. Taps are weighted down where the colour,
+// normal, depth, or albedo of the first hit differ from the centre's.
+// The ray marcher accumulates these guides alongside the render.
+//
+
+const UNIFORM_BIND_GROUP: u32 = 0u;
+const STORAGE_BIND_GROUP: u32 = 1u;
+const TEXTURE_BIND_GROUP: u32 = 2u;
+const DENOISER_BIND_GROUP: u32 = 3u;
+
+#include Math
+#include Texture
+#include RayMarcherRenderParameters
+#include VertexShader
+
+
+// Denoiser flags
+const FIRST_ITERATION: u32 = 1u;
+const LAST_ITERATION: u32 = 2u;
+
+// The albedo that the colour is divided by is clamped to this, so that
+// black surfaces do not blow up the illumination
+const MIN_ALBEDO: f32 = 0.01;
+
+// The sum of the weights of the taps is clamped to this, in case every
+// weight underflows
+const MIN_WEIGHT_SUM: f32 = 1e-6;
+
+const KERNEL_RADIUS: i32 = 2;
+var<private> kernel_weights: array<f32, 3> = array<f32, 3>(0.375, 0.25, 0.0625);
+
+
+struct Denoiser {
+    step_width: u32,
+    flags: u32,
+    colour_phi: f32,
+    normal_phi: f32,
+    depth_phi: f32,
+    albedo_phi: f32,
+}
+
+
+@group(DENOISER_BIND_GROUP) @binding(0)
+var<uniform> _denoiser: Denoiser;
+
+
+@group(DENOISER_BIND_GROUP) @binding(1)
+var _filter_input_texture: texture_2d<f32>;
+
+
+@group(DENOISER_BIND_GROUP) @binding(2)
+var _filter_output_texture: texture_storage_2d<rgba32float, write>;
+
+
+// The normal, and distance from the camera, of the first hit
+@group(DENOISER_BIND_GROUP) @binding(3)
+var _normal_depth_texture: texture_2d<f32>;
+
+
+// The textured diffuse colour of the first hit
+@group(DENOISER_BIND_GROUP) @binding(4)
+var _albedo_texture: texture_2d<f32>;
+
+
+// The result of the last iteration, which is only bound for display
+@group(DENOISER_BIND_GROUP) @binding(5)
+var _denoised_texture: texture_2d<f32>;
+
+
+/**
+ * Normalise the normal guide, which is the mean of the normals of the
+ * first hits of every sample, and so shorter than unit length where
+ * they disagree.
+ *
+ * @arg normal_depth: The normal, and depth, of the first hits.
+ *
+ * @returns: The unit normal, or zero if the normals cancel out.
+ */
+fn guide_normal(normal_depth: vec4f) -> vec3f {
+    var normal_length: f32 = length(normal_depth.xyz);
+    if normal_length < 1e-6 {
+        return vec3f();
+    }
+    return normal_depth.xyz / normal_length;
+}
+
+
+/**
+ * Compute the weight of a tap of the filter, from how similar its
+ * colour and guides are to those at the centre of the kernel.
+ *
+ * @arg colour_difference: The difference in colour.
+ * @arg centre_normal_depth: The normal, and depth, at the centre.
+ * @arg tap_normal_depth: The normal, and depth, at the tap.
+ * @arg albedo_difference: The difference in albedo.
+ *
+ * @returns: The edge stopping weight.
+ */
+fn edge_stopping_weight(
+    colour_difference: vec3f,
+    centre_normal_depth: vec4f,
+    tap_normal_depth: vec4f,
+    albedo_difference: vec3f,
+) -> f32 {
+    var colour_weight: f32 = exp(
+        -dot(colour_difference, colour_difference) / max(_denoiser.colour_phi, 1e-6)
+    );
+    var normal_weight: f32 = pow(
+        saturate_f32(dot(guide_normal(centre_normal_depth), guide_normal(tap_normal_depth))),
+        _denoiser.normal_phi,
+    );
+    var depth_weight: f32 = exp(
+        -abs(centre_normal_depth.w - tap_normal_depth.w)
+        / max(_denoiser.depth_phi * centre_normal_depth.w, 1e-6)
+    );
+    var albedo_weight: f32 = exp(
+        -dot(albedo_difference, albedo_difference) / max(_denoiser.albedo_phi, 1e-6)
+    );
+    return colour_weight * normal_weight * depth_weight * albedo_weight;
+}
+
+
+/**
+ * Load the colour of a pixel from the input of this iteration. The
+ * first iteration divides the progressive render by the albedo so that
+ * only the illumination is blurred, and not the surface detail.
+ *
+ * @arg texture_coordinates: The position of the pixel.
+ *
+ * @returns: The colour to filter.
+ */
+fn load_filter_input(texture_coordinates: vec2i) -> vec3f {
+    var colour: vec3f = textureLoad(_filter_input_texture, texture_coordinates, 0).rgb;
+    if bool(_denoiser.flags & FIRST_ITERATION) {
+        var albedo: vec3f = textureLoad(_albedo_texture, texture_coordinates, 0).rgb;
+        return colour / max(albedo, vec3(MIN_ALBEDO));
+    }
+    return colour;
+}
+
+
+@compute @workgroup_size(8, 8, 1)
+fn cs_filter(@builtin(global_invocation_id) id: vec3u) {
+    var resolution = vec2i(_render_state.resolution);
+    var centre = vec2i(id.xy);
+    if any(centre >= resolution) {
+        return;
+    }
+
+    var centre_colour: vec3f = load_filter_input(centre);
+    var centre_normal_depth: vec4f = textureLoad(_normal_depth_texture, centre, 0);
+    var centre_albedo: vec3f = textureLoad(_albedo_texture, centre, 0).rgb;
+
+    var colour_sum = vec3f();
+    var weight_sum: f32 = 0.;
+    for (var y: i32 = -KERNEL_RADIUS; y <= KERNEL_RADIUS; y++) {
+        for (var x: i32 = -KERNEL_RADIUS; x <= KERNEL_RADIUS; x++) {
+            var tap: vec2i = centre + vec2(x, y) * i32(_denoiser.step_width);
+            if any(tap < vec2i()) || any(tap >= resolution) {
+                continue;
+            }
+
+            var tap_colour: vec3f = load_filter_input(tap);
+            var weight: f32 = kernel_weights[abs(x)] * kernel_weights[abs(y)];
+            // The centre tap is identical to itself, so it is never
+            // stopped, even where its own normals disagree
+            if x != 0 || y != 0 {
+                weight *= edge_stopping_weight(
+                    tap_colour - centre_colour,
+                    centre_normal_depth,
+                    textureLoad(_normal_depth_texture, tap, 0),
+                    textureLoad(_albedo_texture, tap, 0).rgb - centre_albedo,
+                );
+            }
+            colour_sum += weight * tap_colour;
+            weight_sum += weight;
+        }
+    }
+
+    var colour: vec3f = colour_sum / max(weight_sum, MIN_WEIGHT_SUM);
+    if bool(_denoiser.flags & LAST_ITERATION) {
+        colour *= max(centre_albedo, vec3(MIN_ALBEDO));
+    }
+
+    textureStore(_filter_output_texture, centre, vec4(colour, 1.));
+}
+
+
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4f {
+    var current_pixel_indices: vec2f = uv_to_screen(
+        in.uv_coordinate.xy,
+        _render_state.resolution,
+    );
+    return textureLoad(_denoised_texture, vec2u(current_pixel_indices), 0);
+}
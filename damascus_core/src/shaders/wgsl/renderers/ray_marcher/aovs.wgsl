@@ -11,6 +11,7 @@ const SURFACE_NORMAL_AOV: u32 = 3u;
 const DEPTH_AOV: u32 = 4u;
 const CRYPTOMATTE_AOV: u32 = 5u;
 const STATS_AOV: u32 = 6u;
+const SAMPLE_COUNT_AOV: u32 = 7u;
 
 
 #ifdef EnableAOVs
@@ -20,6 +21,7 @@ fn early_exit_aovs(
     local_position: vec3f,
     surface_normal: vec3f,
     primitive_id: u32,
+    cell_id: u32,
     ray: ptr<function, Ray>,
 ) {
     switch aov_type {
@@ -37,8 +39,15 @@ fn early_exit_aovs(
             (*ray).colour = vec3(abs(world_to_camera_space(world_position).z));
         }
         case CRYPTOMATTE_AOV {
-            // Cryptomatte
-            (*ray).colour = random_vec3f(f32(primitive_id) * vec3(1., 2., 3.));
+            // Cryptomatte, with each repetition cell given its own colour
+            (*ray).colour = random_vec3f(
+                f32(primitive_id) * vec3(1., 2., 3.)
+                + 0.1 * vec3(
+                    f32(cell_id >> 16u),
+                    f32(cell_id & 0xffffu),
+                    f32((cell_id >> 8u) & 0xffffu),
+                ),
+            );
         }
         default {}
     }
@@ -67,6 +76,7 @@ fn final_aovs(
 
 
 fn ray_miss_aovs(
+    seed: vec3f,
     aov_type: u32,
     bounces: u32,
     iterations: u32,
@@ -78,22 +88,37 @@ fn ray_miss_aovs(
 
 #ifdef EnableAOVs
     switch aov_type {
-        case BEAUTY_AOV {
+        // The sample count is displayed in place of the beauty
+        case BEAUTY_AOV, SAMPLE_COUNT_AOV {
 #endif
-            sample_equiangular(
+            sample_participating_medium(
+                seed,
                 distance_travelled,
+                false,
                 ray,
                 nested_dielectrics,
             );
 #ifdef EnableEmissiveColourTexture
-            (*ray).colour += (*ray).throughput * procedurally_texture_vec3f(
-                vec4((*ray).direction, 8.27447),
+            var atmosphere_seed = vec4((*ray).direction, 8.27447);
+            var atmosphere_colour: vec3f = procedurally_texture_vec3f(
+                TextureCoordinates(
+                    atmosphere_seed,
+                    (*ray).direction,
+                    atmosphere_seed,
+                    (*ray).direction,
+                ),
                 _atmosphere.emissive_colour,
                 _atmosphere.emissive_colour_texture,
             );
 #else
-            (*ray).colour += (*ray).throughput * _atmosphere.emissive_colour;
+            var atmosphere_colour: vec3f = _atmosphere.emissive_colour;
+#endif
+#ifdef EnableSpectralRendering
+            if spectral_rendering_enabled() {
+                atmosphere_colour = rgb_to_spectral_vec3f(atmosphere_colour, (*ray).wavelength);
+            }
 #endif
+            (*ray).colour += (*ray).throughput * atmosphere_colour;
 #ifdef EnableAOVs
         }
         case WORLD_POSITION_AOV, LOCAL_POSITION_AOV {
@@ -10,12 +10,13 @@
 
 const UNIFORM_BIND_GROUP: u32 = 0u;
 const STORAGE_BIND_GROUP: u32 = 1u;
-// const TEXTURE_BIND_GROUP: u32 = 2u;
-const STORAGE_TEXTURE_BIND_GROUP: u32 = 2u;
+const TEXTURE_BIND_GROUP: u32 = 2u;
+const STORAGE_TEXTURE_BIND_GROUP: u32 = 3u;
 
 #include Ray
 #include Math
 #include Random
+#include Spectral
 #include PrimitiveSDFs
 #include Texture
 #include ProceduralTexture
@@ -26,6 +27,7 @@ const STORAGE_TEXTURE_BIND_GROUP: u32 = 2u;
 #include SceneSDFs
 #include Normals
 #include Lights
+#include ParticipatingMedia
 #include Camera
 #include AOVs
 #include VertexShader
@@ -60,13 +62,24 @@ fn material_interaction(
     primitive: ptr<function, Primitive>,
     nested_dielectrics: ptr<function, NestedDielectrics>,
 ) -> f32 {
-    (*ray).origin = intersection_position;
-
-    sample_equiangular(
+    if sample_participating_medium(
+        seed,
         distance_since_last_bounce,
+        true,
         ray,
         nested_dielectrics,
-    );
+    ) {
+        // The ray collided with the medium before reaching the surface
+        return ISOTROPIC_PHASE_FUNCTION;
+    }
+
+    (*ray).origin = intersection_position;
+
+#ifdef EnableSpectralRendering
+    if spectral_rendering_enabled() {
+        spectrally_sample_material((*ray).wavelength, primitive);
+    }
+#endif
 
     var material_brdf: vec3f;
     var light_sampling_material_pdf: f32;
@@ -112,12 +125,36 @@ fn material_interaction(
  *
  * @arg seed: The seed to use in randomization.
  * @arg ray: The ray to march.
+ * @arg normal_depth: Will be set to the normal, and distance from the
+ *     camera, of the first surface the path hits.
+ * @arg albedo: Will be set to the textured diffuse colour of the first
+ *     surface the path hits.
  *
  * @returns: The ray colour.
  */
-fn march_path(seed: vec3f, ray: ptr<function, Ray>) {
+fn march_path(
+    seed: vec3f,
+    ray: ptr<function, Ray>,
+    normal_depth: ptr<function, vec4f>,
+    albedo: ptr<function, vec4f>,
+) {
+    // Paths that hit nothing face back towards the camera from the
+    // maximum distance, and take the colour of the atmosphere
+    *normal_depth = vec4(-(*ray).direction, _render_parameters.max_distance);
+    *albedo = vec4(_atmosphere.diffuse_colour, 0.);
+
     var nested_dielectrics: NestedDielectrics;
-    push_dielectric(dielectric_from_atmosphere(), &nested_dielectrics);
+    var atmosphere: Dielectric = dielectric_from_atmosphere();
+#ifdef EnableSpectralRendering
+    if spectral_rendering_enabled() {
+        atmosphere = spectral_dielectric(
+            atmosphere,
+            _atmosphere.abbe_number,
+            (*ray).wavelength,
+        );
+    }
+#endif
+    push_dielectric(atmosphere, &nested_dielectrics);
 
 #ifdef EnableAOVs
     var exit_early_with_aov: bool = (
@@ -185,6 +222,11 @@ fn march_path(seed: vec3f, ray: ptr<function, Ray>) {
                 &nearest_primitive,
             );
 
+            if bounces == 1u {
+                *normal_depth = vec4(surface_normal, distance_travelled);
+                *albedo = vec4(nearest_primitive.material.diffuse_colour, 1.);
+            }
+
 #ifdef EnableAOVs
             // Early exit for the various AOVs that are not 'beauty'
             if exit_early_with_aov {
@@ -194,6 +236,7 @@ fn march_path(seed: vec3f, ray: ptr<function, Ray>) {
                     intersection_position, // TODO world to local
                     surface_normal,
                     nearest_primitive.id,
+                    nearest_primitive.cell_id,
                     ray,
                 );
                 return;
@@ -258,6 +301,7 @@ fn march_path(seed: vec3f, ray: ptr<function, Ray>) {
     );
 
     ray_miss_aovs(
+        path_seed,
         _render_parameters.output_aov,
         bounces,
         iterations,
@@ -272,6 +316,352 @@ fn march_path(seed: vec3f, ray: ptr<function, Ray>) {
 var _progressive_rendering_texture: texture_storage_2d<rgba32float, read_write>;
 
 
+// The number of paths traced through each pixel, and the running mean,
+// and sum of squared deviations from the mean, of their luminance
+@group(STORAGE_TEXTURE_BIND_GROUP) @binding(1)
+var _pixel_statistics_texture: texture_storage_2d<rgba32float, read_write>;
+
+
+// The guides of the denoiser, which are the mean normal, and distance
+// from the camera, and the mean albedo, of the first surface hit by
+// the paths traced through each pixel
+@group(STORAGE_TEXTURE_BIND_GROUP) @binding(2)
+var _normal_depth_texture: texture_storage_2d<rgba16float, read_write>;
+
+
+@group(STORAGE_TEXTURE_BIND_GROUP) @binding(3)
+var _albedo_texture: texture_storage_2d<rgba16float, read_write>;
+
+
+// The number of paths a pixel must have before its error estimate is
+// trusted enough for it to stop sampling
+const MIN_SAMPLES_TO_CONVERGE: f32 = 16.;
+
+// The most that the samples a pixel gets each frame can be multiplied
+// by, when the converged pixels give it theirs
+const MAX_SAMPLE_REDISTRIBUTION: u32 = 4u;
+
+// The most that the error of a pixel can count for, relative to the
+// noise threshold, when sharing out the samples of a frame, and the
+// fraction of the threshold the weights are rounded to. The weights of
+// every pixel of the largest viewport must fit in a u32
+const MAX_PIXEL_WEIGHT: f32 = 16.;
+const PIXEL_WEIGHT_PRECISION: f32 = 2.;
+
+// The width and height in pixels of a tile, which is the workgroup size
+const TILE_SIZE: u32 = 8u;
+
+
+// The tiles of the viewport are counted before each frame is rendered,
+// so that the samples of the frame can be shared out between them
+struct AdaptiveSampling {
+    // The number of tiles counted this frame
+    tiles: atomic<u32>,
+    // The sum of the weights of the unconverged pixels
+    total_weight: atomic<u32>,
+    unconverged_pixels: atomic<u32>,
+    _padding: u32,
+    // The weight, and number, of the unconverged pixels in each tile
+    tile_weights: array<vec2u>,
+}
+
+
+@group(STORAGE_BIND_GROUP) @binding(7)
+var<storage, read_write> _adaptive_sampling: AdaptiveSampling;
+
+
+/**
+ * Load the progressive render and statistics of a pixel, which are
+ * zero on the first frame of a render.
+ *
+ * @arg texture_coordinates: The position of the pixel in the textures.
+ * @arg pixel_colour: Will be set to the current state of the pixel.
+ * @arg pixel_statistics: Will be set to the statistics of the pixel.
+ */
+fn load_pixel(
+    texture_coordinates: vec2u,
+    pixel_colour: ptr<function, vec4f>,
+    pixel_statistics: ptr<function, vec4f>,
+) {
+    var first_frame: bool = _render_state.paths_rendered_per_pixel == 0.;
+    *pixel_colour = select(
+        textureLoad(_progressive_rendering_texture, texture_coordinates),
+        vec4f(),
+        first_frame,
+    );
+    *pixel_statistics = select(
+        textureLoad(_pixel_statistics_texture, texture_coordinates),
+        vec4f(),
+        first_frame,
+    );
+}
+
+
+/**
+ * Estimate the relative error of a pixel from the standard error of the
+ * mean luminance of its paths. Dark pixels are compared to a luminance
+ * of 0.01 so that they can still converge.
+ *
+ * @arg pixel_statistics: The statistics of the pixel.
+ *
+ * @returns: The estimated error.
+ */
+fn pixel_error(pixel_statistics: vec4f) -> f32 {
+    var samples: f32 = max(pixel_statistics.x, 1.);
+    var variance: f32 = pixel_statistics.z / max(samples - 1., 1.);
+    return sqrt(variance / samples) / max(pixel_statistics.y, 0.01);
+}
+
+
+/**
+ * Check if a pixel has reached the maximum number of samples, or its
+ * estimated error is below the noise threshold.
+ *
+ * @arg pixel_statistics: The statistics of the pixel.
+ *
+ * @returns: Whether the pixel should stop sampling.
+ */
+fn pixel_converged(pixel_statistics: vec4f) -> bool {
+    var samples: f32 = pixel_statistics.x;
+    return (
+        (
+            _render_parameters.max_samples > 0u
+            && samples >= f32(_render_parameters.max_samples)
+        )
+        || (
+            samples >= MIN_SAMPLES_TO_CONVERGE
+            && pixel_error(pixel_statistics) < _render_parameters.noise_threshold
+        )
+    );
+}
+
+
+/**
+ * Check if the pixels can stop sampling, or share out their samples.
+ *
+ * @returns: Whether there is a noise threshold or maximum samples.
+ */
+fn adaptive_sampling_enabled() -> bool {
+    return (
+        _render_parameters.noise_threshold > 0.
+        || _render_parameters.max_samples > 0u
+    );
+}
+
+
+/**
+ * Get the index of a tile of the viewport in the adaptive sampling
+ * buffer.
+ *
+ * @arg tile: The position of the tile in the viewport.
+ *
+ * @returns: The index of the tile.
+ */
+fn tile_index(tile: vec2u) -> u32 {
+    var tiles_per_row: u32 = (u32(_render_state.resolution.x) + TILE_SIZE - 1u) / TILE_SIZE;
+    return tile.y * tiles_per_row + tile.x;
+}
+
+
+/**
+ * Weigh how many samples an unconverged pixel needs by its estimated
+ * error relative to the noise threshold. Pixels whose error is not yet
+ * trusted, or that only stop at the maximum samples, weigh the least.
+ *
+ * @arg pixel_statistics: The statistics of the pixel.
+ *
+ * @returns: The weight of the pixel, in fractions of the threshold.
+ */
+fn pixel_weight(pixel_statistics: vec4f) -> u32 {
+    var relative_error: f32 = 1.;
+    if (
+        _render_parameters.noise_threshold > 0.
+        && pixel_statistics.x >= MIN_SAMPLES_TO_CONVERGE
+    ) {
+        relative_error = pixel_error(pixel_statistics) / _render_parameters.noise_threshold;
+    }
+    return u32(ceil(PIXEL_WEIGHT_PRECISION * clamp(relative_error, 1., MAX_PIXEL_WEIGHT)));
+}
+
+
+/**
+ * Get the number of paths to trace through an unconverged pixel this
+ * frame. The samples of the whole frame, including those of the
+ * converged pixels, are shared out between the tiles in proportion to
+ * the weights of their unconverged pixels, then evenly between the
+ * unconverged pixels of each tile.
+ *
+ * @arg texture_coordinates: The position of the pixel in the textures.
+ *
+ * @returns: The number of paths to trace.
+ */
+fn adaptive_samples(texture_coordinates: vec2u) -> u32 {
+    var samples_per_frame: u32 = _render_state.samples_per_frame;
+    if !adaptive_sampling_enabled() {
+        return samples_per_frame;
+    }
+
+    var tile_weight: vec2u = _adaptive_sampling.tile_weights[
+        tile_index(texture_coordinates / TILE_SIZE)
+    ];
+    var total_weight: u32 = atomicLoad(&_adaptive_sampling.total_weight);
+    if tile_weight.y == 0u || total_weight == 0u {
+        return samples_per_frame;
+    }
+
+    var resolution = vec2u(_render_state.resolution);
+    var samples: f32 = (
+        f32(samples_per_frame) * f32(resolution.x) * f32(resolution.y)
+        * f32(tile_weight.x) / f32(total_weight) / f32(tile_weight.y)
+    );
+    return clamp(u32(samples), 1u, samples_per_frame * MAX_SAMPLE_REDISTRIBUTION);
+}
+
+
+/**
+ * Get the colour to display for a pixel, which is the number of paths
+ * traced through it when outputting the sample count AOV. The count is
+ * shown as a fraction of the maximum samples, or of the number of paths
+ * per pixel if there is no maximum.
+ *
+ * @arg pixel_colour: The accumulated colour of the pixel.
+ * @arg pixel_statistics: The statistics of the pixel.
+ *
+ * @returns: The colour to display.
+ */
+fn displayed_colour(pixel_colour: vec4f, pixel_statistics: vec4f) -> vec4f {
+    if _render_parameters.output_aov == SAMPLE_COUNT_AOV {
+        var max_samples: f32 = select(
+            max(_render_state.paths_rendered_per_pixel, 1.),
+            f32(_render_parameters.max_samples),
+            _render_parameters.max_samples > 0u,
+        );
+        return vec4(vec3(pixel_statistics.x / max_samples), 1.);
+    }
+    return pixel_colour;
+}
+
+
+/**
+ * Trace paths through a pixel, and accumulate them into the progressive
+ * render, unless the pixel has converged.
+ *
+ * @arg current_pixel_indices: The position of the pixel in the
+ *     progressive rendering texture.
+ * @arg frag_coordinate: The position of the pixel on the screen.
+ * @arg samples: The number of paths to trace.
+ *
+ * @returns: The colour to display for the pixel.
+ */
+fn render_pixel(
+    current_pixel_indices: vec2f,
+    frag_coordinate: vec2f,
+    samples: u32,
+) -> vec4f {
+    var texture_coordinates = vec2u(current_pixel_indices);
+
+    // Load the current state of the progressive render
+    var pixel_colour: vec4f;
+    var pixel_statistics: vec4f;
+    load_pixel(texture_coordinates, &pixel_colour, &pixel_statistics);
+
+    // If the render is paused, or the pixel has converged, just return
+    // the current texture value
+    if bool(_render_state.flags & PAUSED) || pixel_converged(pixel_statistics) {
+        return displayed_colour(pixel_colour, pixel_statistics);
+    }
+
+    var normal_depth: vec4f = select(
+        textureLoad(_normal_depth_texture, texture_coordinates),
+        vec4f(),
+        pixel_statistics.x == 0.,
+    );
+    var albedo: vec4f = select(
+        textureLoad(_albedo_texture, texture_coordinates),
+        vec4f(),
+        pixel_statistics.x == 0.,
+    );
+
+    // Create a random seed which will be different for each pixel
+    var frag_coord_seed = vec3(vec2f_to_random_f32(frag_coordinate));
+
+    // Don't exceed the maximum samples
+    var samples_to_render: u32 = select(
+        samples,
+        min(samples, _render_parameters.max_samples - u32(pixel_statistics.x)),
+        _render_parameters.max_samples > 0u,
+    );
+    for (var sample_index: u32 = 0u; sample_index < samples_to_render; sample_index++) {
+        var paths_rendered_per_pixel: f32 = pixel_statistics.x;
+        var seed = vec3(2214.2410943055584, 5844.16158969744, 6821.991985188833)
+            * random_vec3f(
+                _render_parameters.seeds
+                + frag_coord_seed
+                + paths_rendered_per_pixel
+            ) + vec3(3553.392716193805, 7251.898513581492, 1848.9387464811002)
+            * vec2f_to_random_f32(current_pixel_indices);
+
+        // Get modified UV coordinates with a random offset from the original
+        // without straying outside the bounds of the current pixel. This
+        // provides antialiasing for free
+        var uv_coordinates: vec2f = pixels_to_uv(
+            // Add a random offset to the uv_coordinates for anti-aliasing 
+            current_pixel_indices + random_vec2f(seed.xy),
+            _render_state.resolution,
+        );
+
+        // Create and march a ray
+        var ray: Ray = create_render_camera_ray(seed.zx, uv_coordinates);
+#ifdef EnableSpectralRendering
+        if spectral_rendering_enabled() {
+            ray.wavelength = sample_wavelength(seed.yzx);
+        }
+#endif
+        var path_normal_depth: vec4f;
+        var path_albedo: vec4f;
+        march_path(seed, &ray, &path_normal_depth, &path_albedo);
+#ifdef EnableSpectralRendering
+        // Convert the radiance at the sampled wavelength back to RGB
+        if (
+            spectral_rendering_enabled()
+            && (
+                _render_parameters.output_aov == BEAUTY_AOV
+                || _render_parameters.output_aov == SAMPLE_COUNT_AOV
+            )
+        ) {
+            ray.colour = spectral_to_rgb(element_sum_vec3f(ray.colour) / 3., ray.wavelength);
+        }
+#endif
+
+        // Update the current value for our pixel so that the render
+        // can be done progressively
+        pixel_colour = (
+            paths_rendered_per_pixel * pixel_colour
+            + vec4(ray.colour, 1.)
+        ) / (paths_rendered_per_pixel + 1.);
+        normal_depth = (
+            paths_rendered_per_pixel * normal_depth + path_normal_depth
+        ) / (paths_rendered_per_pixel + 1.);
+        albedo = (paths_rendered_per_pixel * albedo + path_albedo) / (paths_rendered_per_pixel + 1.);
+
+        // Update the running statistics of the luminance with
+        // Welford's algorithm
+        var luminance: f32 = dot(ray.colour, LUMINANCE_WEIGHTS);
+        var deviation: f32 = luminance - pixel_statistics.y;
+        pixel_statistics.x += 1.;
+        pixel_statistics.y += deviation / pixel_statistics.x;
+        pixel_statistics.z += deviation * (luminance - pixel_statistics.y);
+    }
+
+    textureStore(_progressive_rendering_texture, texture_coordinates, pixel_colour);
+    textureStore(_pixel_statistics_texture, texture_coordinates, pixel_statistics);
+    textureStore(_normal_depth_texture, texture_coordinates, normal_depth);
+    textureStore(_albedo_texture, texture_coordinates, albedo);
+
+    return displayed_colour(pixel_colour, pixel_statistics);
+}
+
+
 @fragment
 fn fs_main(in: VertexOut) -> @location(0) vec4f {
     // Use the UV coordinates and resolution to get texture coordinates
@@ -279,51 +669,75 @@ fn fs_main(in: VertexOut) -> @location(0) vec4f {
         in.uv_coordinate.xy,
         _render_state.resolution,
     );
-    var texture_coordinates = vec2u(current_pixel_indices);
 
-    // Load the current state of the progressive render, unless this is
-    // the first path, in which case initialise as black
-    var pixel_colour: vec4f = select(
-        vec4f(),
-        textureLoad(_progressive_rendering_texture, texture_coordinates),
-        _render_state.paths_rendered_per_pixel > 0.,
+    // The compute shader has already rendered the pixel, so display it,
+    // unless the render has been reset while paused
+    if bool(_render_state.flags & COMPUTE_BACKEND) {
+        var texture_coordinates = vec2u(current_pixel_indices);
+        return select(
+            displayed_colour(
+                textureLoad(_progressive_rendering_texture, texture_coordinates),
+                textureLoad(_pixel_statistics_texture, texture_coordinates),
+            ),
+            vec4f(),
+            (
+                bool(_render_state.flags & PAUSED)
+                && _render_state.paths_rendered_per_pixel == 0.
+            ),
+        );
+    }
+
+    return render_pixel(
+        current_pixel_indices,
+        in.frag_coordinate.xy,
+        adaptive_samples(vec2u(current_pixel_indices)),
     );
+}
 
-    // If the render is paused just return the current texture value
-    if bool(_render_state.flags & PAUSED) {
-        return pixel_colour;
+
+// The sum of the weights, and the number, of the unconverged pixels in
+// the current tile. Workgroup memory starts at zero
+var<workgroup> _tile_weight: atomic<u32>;
+var<workgroup> _unconverged_pixels_in_tile: atomic<u32>;
+
+
+@compute @workgroup_size(TILE_SIZE, TILE_SIZE, 1)
+fn cs_count_tiles(
+    @builtin(global_invocation_id) id: vec3u,
+    @builtin(workgroup_id) tile: vec3u,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    if all(id.xy < vec2u(_render_state.resolution)) {
+        var pixel_colour: vec4f;
+        var pixel_statistics: vec4f;
+        load_pixel(id.xy, &pixel_colour, &pixel_statistics);
+        if !pixel_converged(pixel_statistics) {
+            atomicAdd(&_tile_weight, pixel_weight(pixel_statistics));
+            atomicAdd(&_unconverged_pixels_in_tile, 1u);
+        }
     }
+    workgroupBarrier();
 
-    // Create a random seed which will be different for each pixel
-    var frag_coord_seed = vec3(vec2f_to_random_f32(in.frag_coordinate.xy));
-    var seed = vec3(2214.2410943055584, 5844.16158969744, 6821.991985188833)
-        * random_vec3f(
-            _render_parameters.seeds
-            + frag_coord_seed
-            + _render_state.paths_rendered_per_pixel
-        ) + vec3(3553.392716193805, 7251.898513581492, 1848.9387464811002)
-        * vec2f_to_random_f32(current_pixel_indices);
-
-    // Get modified UV coordinates with a random offset from the original
-    // without straying outside the bounds of the current pixel. This
-    // provides antialiasing for free
-    var uv_coordinates: vec2f = pixels_to_uv(
-        // Add a random offset to the uv_coordinates for anti-aliasing 
-        current_pixel_indices + random_vec2f(seed.xy),
-        _render_state.resolution,
-    );
+    if local_index == 0u {
+        var tile_weight = vec2u(
+            atomicLoad(&_tile_weight),
+            atomicLoad(&_unconverged_pixels_in_tile),
+        );
+        _adaptive_sampling.tile_weights[tile_index(tile.xy)] = tile_weight;
+        atomicAdd(&_adaptive_sampling.tiles, 1u);
+        atomicAdd(&_adaptive_sampling.total_weight, tile_weight.x);
+        atomicAdd(&_adaptive_sampling.unconverged_pixels, tile_weight.y);
+    }
+}
 
-    // Create and march a ray
-    var ray: Ray = create_render_camera_ray(seed.zx, uv_coordinates);
-    march_path(seed, &ray);
 
-    // Read, update, and store the current value for our pixel
-    // so that the render can be done progressively
-    pixel_colour = (
-        _render_state.paths_rendered_per_pixel * pixel_colour
-        + vec4(ray.colour, 1.)
-    ) / (_render_state.paths_rendered_per_pixel + 1.);
-    textureStore(_progressive_rendering_texture, texture_coordinates, pixel_colour);
+@compute @workgroup_size(TILE_SIZE, TILE_SIZE, 1)
+fn cs_main(@builtin(global_invocation_id) id: vec3u) {
+    if any(id.xy >= vec2u(_render_state.resolution)) {
+        return;
+    }
 
-    return pixel_colour;
+    // Match the pixel centres the fragment shader is evaluated at
+    var current_pixel_indices: vec2f = vec2f(id.xy) + 0.5;
+    render_pixel(current_pixel_indices, current_pixel_indices, adaptive_samples(id.xy));
 }
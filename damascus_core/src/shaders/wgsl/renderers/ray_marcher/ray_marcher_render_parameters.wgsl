@@ -9,9 +9,11 @@
 const DYNAMIC_LEVEL_OF_DETAIL: u32 = 1u;
 const SAMPLE_ATMOSPHERE: u32 = 2u;
 const SECONDARY_SAMPLING: u32 = 4u;
+const SPECTRAL_RENDERING: u32 = 8u;
 
 // RenderState
 const PAUSED: u32 = 1u;
+const COMPUTE_BACKEND: u32 = 2u;
 
 
 struct RenderParameters {
@@ -27,6 +29,8 @@ struct RenderParameters {
     light_sampling_bias: f32,
     output_aov: u32,
     flags: u32,
+    noise_threshold: f32,
+    max_samples: u32,
 }
 
 
@@ -35,6 +39,11 @@ struct SceneParameters {
     // Number of emissive prims + num_non_physical_lights
     num_lights: u32,
     num_non_physical_lights: u32,
+    atmosphere_density: u32,
+    fog_base_height: f32,
+    fog_falloff: f32,
+    aerosol_density: f32,
+    aerosol_falloff: f32,
 }
 
 
@@ -42,6 +51,7 @@ struct RenderState {
     paths_rendered_per_pixel: f32,
     resolution: vec2f,
     flags: u32,
+    samples_per_frame: u32,
 }
 
 
@@ -0,0 +1,244 @@
+// Copyright (c) 2024, Owen Bulka
+// All rights reserved.
+// This source code is licensed under the BSD-style license found in the
+// LICENSE file in the root directory of this source tree.
+
+
+//
+// Texture Baking shader
+//
+
+const UNIFORM_BIND_GROUP: u32 = 0u;
+const STORAGE_BIND_GROUP: u32 = 1u;
+const TEXTURE_BIND_GROUP: u32 = 2u;
+const BAKE_BIND_GROUP: u32 = 3u;
+
+#include Ray
+#include Math
+#include Random
+#include Spectral
+#include PrimitiveSDFs
+#include Texture
+#include ProceduralTexture
+#include Material
+#include Primitive
+#include PrimitiveModifiers
+#include RayMarcherRenderParameters
+#include SceneSDFs
+#include Normals
+#include Lights
+#include ParticipatingMedia
+#include Camera
+
+
+// BakeProjection
+const PLANAR_BAKE: u32 = 0u;
+const LATLONG_BAKE: u32 = 1u;
+const TRIPLANAR_BAKE: u32 = 2u;
+
+// The seed offset used when the texture is not on a primitive, which
+// matches the one used when previewing textures on the atmosphere
+const BAKE_SEED_OFFSET: f32 = 8.27447;
+
+
+struct TextureBaker {
+    resolution: vec2u,
+    projection: u32,
+    size: f32,
+}
+
+
+@group(BAKE_BIND_GROUP) @binding(0)
+var<uniform> _texture_baker: TextureBaker;
+
+
+@group(BAKE_BIND_GROUP) @binding(1)
+var<storage, read_write> _baked_texture: array<vec4f>;
+
+
+/**
+ * Get the texture coordinates of a point on a plane.
+ *
+ * @arg uv_coordinates: The coordinates of the pixel in the image.
+ *
+ * @returns: The texture coordinates.
+ */
+fn planar_texture_coordinates(uv_coordinates: vec2f) -> TextureCoordinates {
+    var seed = vec4(
+        _texture_baker.size * vec2(2. * uv_coordinates.x - 1., 1. - 2. * uv_coordinates.y),
+        0.,
+        BAKE_SEED_OFFSET,
+    );
+    var normal = vec3(0., 0., 1.);
+    return TextureCoordinates(seed, normal, seed, normal);
+}
+
+
+/**
+ * Get the texture coordinates of a point on a sphere, where the image
+ * is an equirectangular projection of it.
+ *
+ * @arg uv_coordinates: The coordinates of the pixel in the image.
+ *
+ * @returns: The texture coordinates.
+ */
+fn latlong_texture_coordinates(uv_coordinates: vec2f) -> TextureCoordinates {
+    var azimuth: f32 = (uv_coordinates.x - 0.5) * TWO_PI;
+    var polar: f32 = uv_coordinates.y * PI;
+    var direction = vec3(
+        sin(polar) * sin(azimuth),
+        cos(polar),
+        sin(polar) * cos(azimuth),
+    );
+    var seed = vec4(direction * _texture_baker.size, BAKE_SEED_OFFSET);
+    return TextureCoordinates(seed, direction, seed, direction);
+}
+
+
+/**
+ * Compute the distance to the displaced primitive being baked, without
+ * applying its material textures.
+ *
+ * @arg position: The position in world space.
+ * @arg primitive: The primitive being baked.
+ *
+ * @returns: The distance to the primitive.
+ */
+fn distance_to_baked_primitive(
+    position: vec3f,
+    primitive: ptr<function, Primitive>,
+) -> f32 {
+    var local_position: vec3f = rotate_translate_position(position, primitive);
+    var lipschitz_bound: f32 = 1.;
+    var distance: f32 = distance_to_transformed_primitive(
+        (*primitive).transform.inverse_scale_shear * transform_position(
+            local_position,
+            primitive,
+            &lipschitz_bound,
+        ),
+        primitive,
+    ) * (*primitive).transform.minimum_scale / lipschitz_bound;
+
+#ifdef EnableDisplacement
+    distance = displace_distance(distance, local_position, primitive);
+#endif
+
+    return modify_distance(distance, primitive);
+}
+
+
+/**
+ * Get the normal of the primitive being baked.
+ *
+ * @arg position: The position on the surface in world space.
+ * @arg primitive: The primitive being baked.
+ *
+ * @returns: The normalized surface normal in world space.
+ */
+fn baked_primitive_normal(
+    position: vec3f,
+    primitive: ptr<function, Primitive>,
+) -> vec3f {
+    var normal_offset = vec2(0.5773, -0.5773) * _render_parameters.hit_tolerance;
+    return normalize(
+        normal_offset.xyy * distance_to_baked_primitive(position + normal_offset.xyy, primitive)
+        + normal_offset.yyx * distance_to_baked_primitive(position + normal_offset.yyx, primitive)
+        + normal_offset.yxy * distance_to_baked_primitive(position + normal_offset.yxy, primitive)
+        + normal_offset.xxx * distance_to_baked_primitive(position + normal_offset.xxx, primitive)
+    );
+}
+
+
+/**
+ * Get the colour of the texture on the surface of the primitive, where
+ * the image is an atlas of the six faces of a cube surrounding it. The
+ * top row holds the +X, +Y, and +Z faces and the bottom row holds the
+ * -X, -Y, and -Z faces. Each face is found by marching rays towards
+ * the primitive along the axis the face is normal to.
+ *
+ * @arg uv_coordinates: The coordinates of the pixel in the image.
+ *
+ * @returns: The colour, with an alpha of zero where nothing was hit.
+ */
+fn triplanar_texture_colour(uv_coordinates: vec2f) -> vec4f {
+    var face_coordinates = vec2(3., 2.) * uv_coordinates;
+    var face = vec2u(min(face_coordinates, vec2(2.999, 1.999)));
+    var face_uv: vec2f = 2. * fract(face_coordinates) - 1.;
+
+    var axis = vec3f();
+    axis[face.x] = select(-1., 1., face.y == 0u);
+    var tangent = vec3f();
+    tangent[(face.x + 1u) % 3u] = 1.;
+    var bitangent = vec3f();
+    bitangent[(face.x + 2u) % 3u] = 1.;
+
+    var primitive: Primitive = _primitives.primitives[0];
+    var position: vec3f = primitive.transform.translation + _texture_baker.size * (
+        axis + face_uv.x * tangent - face_uv.y * bitangent
+    );
+    var direction: vec3f = -axis;
+
+    var distance_travelled: f32 = 0.;
+    for (var step: u32 = 0u; step < _render_parameters.max_ray_steps; step++) {
+        var signed_distance: f32 = distance_to_baked_primitive(position, &primitive);
+        if signed_distance < _render_parameters.hit_tolerance {
+            var normal: vec3f = baked_primitive_normal(position, &primitive);
+            var colour: vec3f = procedurally_texture_vec3f(
+                primitive_texture_coordinates(
+                    rotate_translate_position(position, &primitive),
+                    primitive.transform.inverse_rotation * normal,
+                    &primitive,
+                ),
+                vec3(1.),
+                _atmosphere.diffuse_colour_texture,
+            );
+            return vec4(colour, 1.);
+        }
+        distance_travelled += signed_distance;
+        if distance_travelled > 2. * _texture_baker.size {
+            break;
+        }
+        position += signed_distance * direction;
+    }
+
+    return vec4f();
+}
+
+
+@compute @workgroup_size(8, 8, 1)
+fn cs_main(@builtin(global_invocation_id) id: vec3u) {
+    if any(id.xy >= _texture_baker.resolution) {
+        return;
+    }
+
+    var uv_coordinates: vec2f = (vec2f(id.xy) + 0.5) / vec2f(_texture_baker.resolution);
+
+    var colour: vec4f;
+    switch _texture_baker.projection {
+        case LATLONG_BAKE {
+            colour = vec4(
+                procedurally_texture_vec3f(
+                    latlong_texture_coordinates(uv_coordinates),
+                    vec3(1.),
+                    _atmosphere.diffuse_colour_texture,
+                ),
+                1.,
+            );
+        }
+        case TRIPLANAR_BAKE {
+            colour = triplanar_texture_colour(uv_coordinates);
+        }
+        default {
+            colour = vec4(
+                procedurally_texture_vec3f(
+                    planar_texture_coordinates(uv_coordinates),
+                    vec3(1.),
+                    _atmosphere.diffuse_colour_texture,
+                ),
+                1.,
+            );
+        }
+    }
+
+    _baked_texture[id.y * _texture_baker.resolution.x + id.x] = colour;
+}
@@ -7,6 +7,12 @@
 const PI: f32 = 3.141592653589793;
 const TWO_PI: f32 = 6.28318530718;
 
+const XYZ_TO_LINEAR_SRGB: mat3x3f = mat3x3f(
+    3.2404542, -0.9692660, 0.0556434,
+    -1.5371385, 1.8760108, -0.2040259,
+    -0.4985314, 0.0415560, 1.0572252,
+);
+
 
 // wish we could overload functions
 fn max_component_vec2f(vector_: vec2f) -> f32 {
@@ -19,6 +25,11 @@ fn max_component_vec3f(vector_: vec3f) -> f32 {
 }
 
 
+fn min_component_vec3f(vector_: vec3f) -> f32 {
+    return min(vector_.x, min(vector_.y, vector_.z));
+}
+
+
 /**
  * The positive part of the vector. Ie. any negative values will be 0.
  *
@@ -217,6 +228,47 @@ fn balance_heuristic(pdf_0: f32, pdf_1: f32) -> f32 {
 }
 
 
+/**
+ * Create a rotation matrix from Euler angles, rotating around the x,
+ * then y, then z axes.
+ *
+ * @arg angles: The angles, in radians, to rotate around each axis.
+ *
+ * @returns: The rotation matrix.
+ */
+fn euler_rotation_matrix(angles: vec3f) -> mat3x3f {
+    var cosines: vec3f = cos(angles);
+    var sines: vec3f = sin(angles);
+    return mat3x3f(
+        cosines.z, sines.z, 0.,
+        -sines.z, cosines.z, 0.,
+        0., 0., 1.,
+    ) * mat3x3f(
+        cosines.y, 0., -sines.y,
+        0., 1., 0.,
+        sines.y, 0., cosines.y,
+    ) * mat3x3f(
+        1., 0., 0.,
+        0., cosines.x, sines.x,
+        0., -sines.x, cosines.x,
+    );
+}
+
+
+/**
+ * Floored modulo, which unlike `%` is always the same sign as the
+ * divisor.
+ *
+ * @arg value: The dividend.
+ * @arg divisor: The divisor.
+ *
+ * @returns: The remainder.
+ */
+fn modulo_f32(value: f32, divisor: f32) -> f32 {
+    return value - divisor * floor(value / divisor);
+}
+
+
 fn power_of_u32(base: f32, exponent: u32) -> f32 {
     var base_: f32 = base;
     var exponent_: u32 = exponent;
@@ -110,6 +110,23 @@ fn cosine_direction_in_hemisphere(seed: vec2f, axis: vec3f) -> vec3f {
 }
 
 
+/**
+ * Create a random unit vector, uniformly distributed over the sphere.
+ *
+ * @arg seed: The random seed.
+ *
+ * @returns: A random unit vector.
+ */
+fn uniform_direction_on_sphere(seed: vec2f) -> vec3f {
+    var uniform_random_numbers: vec2f = random_vec2f(seed);
+    var z: f32 = 1. - 2. * uniform_random_numbers.x;
+    var r: f32 = sqrt(max(0., 1. - z * z));
+    var angle: f32 = TWO_PI * uniform_random_numbers.y;
+
+    return vec3(r * cos(angle), r * sin(angle), z);
+}
+
+
 /**
  * Create a random point that lies within the unit circle.
  *
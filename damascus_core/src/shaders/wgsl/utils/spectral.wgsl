@@ -0,0 +1,225 @@
+// Copyright (c) 2024, Owen Bulka
+// All rights reserved.
+// This source code is licensed under the BSD-style license found in the
+// LICENSE file in the root directory of this source tree.
+
+
+#ifdef EnableSpectralRendering
+// Range of wavelengths, in nanometres, that paths will be traced with
+const MIN_WAVELENGTH: f32 = 380.;
+const MAX_WAVELENGTH: f32 = 730.;
+
+// Fraunhofer lines, in micrometres, used to define the Abbe number
+const FRAUNHOFER_D_WAVELENGTH: f32 = 0.58756;
+const FRAUNHOFER_F_WAVELENGTH: f32 = 0.48613;
+const FRAUNHOFER_C_WAVELENGTH: f32 = 0.65627;
+
+// The reciprocal of the average of the colour matching functions in
+// linear sRGB over the range of wavelengths, so that a constant
+// spectrum integrates to white
+const SPECTRAL_WHITE_BALANCE: vec3f = vec3f(2.7266663, 3.4471288, 3.6058191);
+
+// The peaks and width of the basis functions used to upsample RGB
+const RGB_BASIS_WAVELENGTHS: vec3f = vec3f(615., 545., 455.);
+const RGB_BASIS_WIDTH: f32 = 28.;
+
+
+/**
+ * Check if spectral rendering is enabled.
+ *
+ * @returns: True if paths should be traced with a single wavelength.
+ */
+fn spectral_rendering_enabled() -> bool {
+    return bool(_render_parameters.flags & SPECTRAL_RENDERING);
+}
+
+
+/**
+ * Uniformly sample a wavelength in the visible spectrum.
+ *
+ * @arg seed: The seed to use in randomization.
+ *
+ * @returns: The wavelength in nanometres.
+ */
+fn sample_wavelength(seed: vec3f) -> f32 {
+    return mix(MIN_WAVELENGTH, MAX_WAVELENGTH, vec3f_to_random_f32(seed));
+}
+
+
+/**
+ * Upsample an RGB colour to a spectrum and evaluate it at a
+ * wavelength. The spectrum is a blend of three smooth basis functions
+ * which sum to one, so greys map to constant spectra.
+ *
+ * @arg colour: The RGB colour.
+ * @arg wavelength: The wavelength in nanometres.
+ *
+ * @returns: The value of the spectrum at the wavelength.
+ */
+fn rgb_to_spectral(colour: vec3f, wavelength: f32) -> f32 {
+    var distance_to_peaks: vec3f = (wavelength - RGB_BASIS_WAVELENGTHS) / RGB_BASIS_WIDTH;
+    var basis: vec3f = exp(-0.5 * distance_to_peaks * distance_to_peaks);
+    return dot(colour, basis) / max(element_sum_vec3f(basis), 1e-8);
+}
+
+
+/**
+ * Upsample an RGB colour to a spectrum, evaluate it at a wavelength
+ * and store the result in every channel, so that the rest of the
+ * renderer can remain unaware of the spectral mode.
+ *
+ * @arg colour: The RGB colour.
+ * @arg wavelength: The wavelength in nanometres.
+ *
+ * @returns: The value of the spectrum in each channel.
+ */
+fn rgb_to_spectral_vec3f(colour: vec3f, wavelength: f32) -> vec3f {
+    return vec3(rgb_to_spectral(colour, wavelength));
+}
+
+
+/**
+ * Compute the refractive index at a wavelength using Cauchy's
+ * equation, fit to the refractive index at the Fraunhofer d line
+ * and the Abbe number.
+ *
+ * @arg refractive_index: The refractive index at the d line.
+ * @arg abbe_number: The Abbe number, 0 for no dispersion.
+ * @arg wavelength: The wavelength in nanometres.
+ *
+ * @returns: The refractive index at the wavelength.
+ */
+fn cauchy_refractive_index(
+    refractive_index: f32,
+    abbe_number: f32,
+    wavelength: f32,
+) -> f32 {
+    if abbe_number <= 0. {
+        return refractive_index;
+    }
+    var wavelength_micrometres: f32 = wavelength * 0.001;
+    var coefficient: f32 = (refractive_index - 1.) / (abbe_number * (
+        1. / (FRAUNHOFER_F_WAVELENGTH * FRAUNHOFER_F_WAVELENGTH)
+        - 1. / (FRAUNHOFER_C_WAVELENGTH * FRAUNHOFER_C_WAVELENGTH)
+    ));
+    return refractive_index + coefficient * (
+        1. / (wavelength_micrometres * wavelength_micrometres)
+        - 1. / (FRAUNHOFER_D_WAVELENGTH * FRAUNHOFER_D_WAVELENGTH)
+    );
+}
+
+
+fn piecewise_gaussian(
+    wavelength: f32,
+    mean: f32,
+    lower_deviation: f32,
+    upper_deviation: f32,
+) -> f32 {
+    var distance: f32 = (wavelength - mean) / select(
+        upper_deviation,
+        lower_deviation,
+        wavelength < mean,
+    );
+    return exp(-0.5 * distance * distance);
+}
+
+
+/**
+ * The CIE 1931 colour matching functions, using the multi-lobe fit
+ * from Wyman, Sloan & Shirley 2013.
+ *
+ * @arg wavelength: The wavelength in nanometres.
+ *
+ * @returns: The XYZ tristimulus values of the wavelength.
+ */
+fn wavelength_to_xyz(wavelength: f32) -> vec3f {
+    return vec3(
+        1.056 * piecewise_gaussian(wavelength, 599.8, 37.9, 31.)
+        + 0.362 * piecewise_gaussian(wavelength, 442., 16., 26.7)
+        - 0.065 * piecewise_gaussian(wavelength, 501.1, 20.4, 26.2),
+        0.821 * piecewise_gaussian(wavelength, 568.8, 46.9, 40.5)
+        + 0.286 * piecewise_gaussian(wavelength, 530.9, 16.3, 31.1),
+        1.217 * piecewise_gaussian(wavelength, 437., 11.8, 36.)
+        + 0.681 * piecewise_gaussian(wavelength, 459., 26., 13.8),
+    );
+}
+
+
+/**
+ * Convert the radiance carried by a path at a single wavelength to
+ * linear sRGB, accounting for the uniform wavelength sampling.
+ *
+ * @arg radiance: The radiance at the wavelength.
+ * @arg wavelength: The wavelength in nanometres.
+ *
+ * @returns: The contribution of the path in linear sRGB.
+ */
+fn spectral_to_rgb(radiance: f32, wavelength: f32) -> vec3f {
+    return (
+        radiance
+        * SPECTRAL_WHITE_BALANCE
+        * (XYZ_TO_LINEAR_SRGB * wavelength_to_xyz(wavelength))
+    );
+}
+
+
+/**
+ * Evaluate the spectral properties of a dielectric at a wavelength.
+ *
+ * @arg dielectric: The dielectric.
+ * @arg abbe_number: The Abbe number of the dielectric.
+ * @arg wavelength: The wavelength in nanometres.
+ *
+ * @returns: The dielectric at the wavelength.
+ */
+fn spectral_dielectric(
+    dielectric: Dielectric,
+    abbe_number: f32,
+    wavelength: f32,
+) -> Dielectric {
+    return Dielectric(
+        dielectric.id,
+        cauchy_refractive_index(dielectric.refractive_index, abbe_number, wavelength),
+        rgb_to_spectral_vec3f(dielectric.extinction_colour, wavelength),
+        rgb_to_spectral_vec3f(dielectric.scattering_colour, wavelength),
+    );
+}
+
+
+/**
+ * Evaluate the material of a primitive at a wavelength.
+ *
+ * @arg wavelength: The wavelength in nanometres.
+ * @arg primitive: The primitive whose material will be modified.
+ */
+fn spectrally_sample_material(
+    wavelength: f32,
+    primitive: ptr<function, Primitive>,
+) {
+    (*primitive).material.diffuse_colour = rgb_to_spectral_vec3f(
+        (*primitive).material.diffuse_colour,
+        wavelength,
+    );
+    (*primitive).material.specular_colour = rgb_to_spectral_vec3f(
+        (*primitive).material.specular_colour,
+        wavelength,
+    );
+    (*primitive).material.extinction_colour = rgb_to_spectral_vec3f(
+        (*primitive).material.extinction_colour,
+        wavelength,
+    );
+    (*primitive).material.emissive_colour = rgb_to_spectral_vec3f(
+        (*primitive).material.emissive_colour,
+        wavelength,
+    );
+    (*primitive).material.scattering_colour = rgb_to_spectral_vec3f(
+        (*primitive).material.scattering_colour,
+        wavelength,
+    );
+    (*primitive).material.refractive_index = cauchy_refractive_index(
+        (*primitive).material.refractive_index,
+        (*primitive).material.abbe_number,
+        wavelength,
+    );
+}
+#endif
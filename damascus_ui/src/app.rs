@@ -6,13 +6,14 @@
 use std::collections::{HashMap, HashSet};
 use std::time::{Duration, SystemTime};
 
-use eframe::egui;
-use egui_node_graph::NodeResponse;
+use eframe::{egui, egui_wgpu};
+use egui_node_graph::{NodeId, NodeResponse};
 use serde_hashkey::{to_key_with_ordered_float, Key, OrderedFloatPolicy};
 
 use super::widgets::{
+    dialog,
     node_graph::{
-        evaluate_node,
+        evaluate_node, evaluate_texture_baker,
         node::{
             callbacks::NodeCallbacks,
             value_type::{Bool, NodeValueType, UIInput},
@@ -114,6 +115,42 @@ impl Damascus {
             egui::Color32::RED,
         );
     }
+
+    fn bake_procedural_texture(
+        &self,
+        ctx: &egui::Context,
+        render_state: &egui_wgpu::RenderState,
+        node_id: NodeId,
+    ) {
+        let modal = egui_modal::Modal::new(ctx, "dialog_modal");
+        let texture_baker =
+            match evaluate_texture_baker(&self.node_graph.editor_state().graph, node_id) {
+                Ok(texture_baker) => texture_baker,
+                Err(error) => {
+                    dialog::error(&modal, "Texture Evaluation Error", &error.to_string());
+                    return;
+                }
+            };
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("bake texture to file")
+            .add_filter("OpenEXR", &["exr"])
+            .add_filter("PNG", &["png"])
+            .save_file()
+        else {
+            return;
+        };
+        match self
+            .viewport
+            .bake_texture(render_state, &texture_baker, &path)
+        {
+            Ok(()) => dialog::success(
+                &modal,
+                "Success",
+                &format!("Texture baked to {:}", path.display()),
+            ),
+            Err(error) => dialog::error(&modal, "Texture Baking Error", &error.to_string()),
+        }
+    }
 }
 
 impl eframe::App for Damascus {
@@ -195,6 +232,9 @@ impl eframe::App for Damascus {
                             NodeGraphResponse::ReconstructRenderPipeline => {
                                 self.viewport.reconstruct_pipeline(render_state);
                             }
+                            NodeGraphResponse::BakeProceduralTexture(node_id) => {
+                                self.bake_procedural_texture(ctx, render_state, node_id);
+                            }
                         }
                     }
                     // NodeResponse::DisconnectEvent { output, input } => {
@@ -268,9 +308,8 @@ impl eframe::App for Damascus {
                             self.viewport.switch_to_ray_marcher_view(render_state);
 
                             match &mut self.viewport.view {
-                                Views::RayMarcher { view } => {
-                                    view.set_renderer_to_default_with_atmosphere(*value.value())
-                                }
+                                Views::RayMarcher { view } => view
+                                    .set_renderer_to_default_with_atmosphere(value.value().clone()),
                                 Views::Error { error } => Self::display_error(ctx, error),
                                 _ => {}
                             }
@@ -281,7 +320,7 @@ impl eframe::App for Damascus {
                             match &mut self.viewport.view {
                                 Views::RayMarcher { view } => view
                                     .set_renderer_to_default_with_procedural_texture(
-                                        *value.value(),
+                                        value.value().clone(),
                                     ),
                                 Views::Error { error } => Self::display_error(ctx, error),
                                 _ => {}
@@ -301,7 +340,11 @@ impl eframe::App for Damascus {
                             self.viewport.switch_to_ray_marcher_view(render_state);
 
                             match &mut self.viewport.view {
-                                Views::RayMarcher { view } => view.set_renderer(value),
+                                Views::RayMarcher { view } => {
+                                    view.set_renderer(value);
+                                    self.node_graph
+                                        .set_image_index_range(view.renderer.scene.textures.len());
+                                }
                                 Views::Error { error } => Self::display_error(ctx, error),
                                 _ => {}
                             }
@@ -311,7 +354,9 @@ impl eframe::App for Damascus {
 
                             match &mut self.viewport.view {
                                 Views::RayMarcher { view } => {
-                                    view.set_renderer_to_default_with_scene(value.value().clone())
+                                    view.set_renderer_to_default_with_scene(value.value().clone());
+                                    self.node_graph
+                                        .set_image_index_range(view.renderer.scene.textures.len());
                                 }
                                 Views::Error { error } => Self::display_error(ctx, error),
                                 _ => {}
@@ -322,7 +367,7 @@ impl eframe::App for Damascus {
 
                             match &mut self.viewport.view {
                                 Views::Compositor { view } => view.set_texture(
-                                    value.value().clone(),
+                                    value.value().last().cloned().unwrap_or_default(),
                                     render_state,
                                     &self.viewport.settings.compositor_view,
                                 ),
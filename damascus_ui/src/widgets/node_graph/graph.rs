@@ -9,12 +9,13 @@ use std::str::FromStr;
 
 use egui_node_graph;
 use glam::Vec4Swizzles;
+use serde_hashkey::{to_key_with_ordered_float, Key, OrderedFloatPolicy};
 use strum::IntoEnumIterator;
 
 use damascus_core::{
     geometry::{self, camera, primitive},
     lights, materials,
-    renderers::ray_marcher,
+    renderers::{ray_marcher, texture_baker},
     scene, textures,
 };
 
@@ -65,6 +66,40 @@ pub fn evaluate_node(
             evaluate_input(self.graph, self.node_id, name, self.outputs_cache)
         }
 
+        /// Evaluates a procedural texture node along with the settings used to
+        /// bake it to an image.
+        pub fn evaluate_texture_baker(
+            graph: &Graph,
+            node_id: egui_node_graph::NodeId,
+        ) -> anyhow::Result<texture_baker::TextureBaker> {
+            let mut outputs_cache = OutputsCache::new();
+            let texture =
+                evaluate_node(graph, node_id, &mut outputs_cache)?.try_to_procedural_texture()?;
+            let projection = evaluate_input(graph, node_id, "bake_projection", &mut outputs_cache)?
+                .try_to_enum::<texture_baker::BakeProjection>()?;
+            let primitives = evaluate_input(graph, node_id, "bake_primitive", &mut outputs_cache)?
+                .try_to_primitive()?;
+            let width =
+                evaluate_input(graph, node_id, "bake_width", &mut outputs_cache)?.try_to_uint()?;
+            let height =
+                evaluate_input(graph, node_id, "bake_height", &mut outputs_cache)?.try_to_uint()?;
+            let size =
+                evaluate_input(graph, node_id, "bake_size", &mut outputs_cache)?.try_to_float()?;
+
+            if projection == texture_baker::BakeProjection::Triplanar && primitives.is_empty() {
+                anyhow::bail!("A primitive must be connected to bake a triplanar texture");
+            }
+
+            Ok(texture_baker::TextureBaker {
+                texture: texture,
+                projection: projection,
+                // Only the topmost primitive is unwrapped
+                primitive: primitives.into_iter().next().unwrap_or_default(),
+                resolution: glam::UVec2::new(width, height),
+                size: size,
+            })
+        }
+
         fn populate_output(
             &mut self,
             name: &str,
@@ -94,6 +129,10 @@ pub fn evaluate_node(
             self.evaluate_input(name)?.try_to_bvec3()
         }
 
+        fn input_colour_ramp(&mut self, name: &str) -> anyhow::Result<materials::ColourRamp> {
+            self.evaluate_input(name)?.try_to_colour_ramp()
+        }
+
         fn input_combo_box<E: IntoEnumIterator + Display + FromStr>(
             &mut self,
             name: &str,
@@ -121,9 +160,9 @@ pub fn evaluate_node(
             self.evaluate_input(name)?.try_to_float()
         }
 
-        // fn input_vector2(&mut self, name: &str) -> anyhow::Result<glam::Vec2> {
-        //     self.evaluate_input(name)?.try_to_vec2()
-        // }
+        fn input_vector2(&mut self, name: &str) -> anyhow::Result<glam::Vec2> {
+            self.evaluate_input(name)?.try_to_vec2()
+        }
 
         fn input_vector3(&mut self, name: &str) -> anyhow::Result<glam::Vec3> {
             self.evaluate_input(name)?.try_to_vec3()
@@ -271,14 +310,14 @@ pub fn evaluate_node(
             )
         }
 
-        fn _input_texture(&mut self, name: &str) -> anyhow::Result<textures::Texture> {
+        fn input_texture(&mut self, name: &str) -> anyhow::Result<Vec<textures::Texture>> {
             self.evaluate_input(name)?.try_to_texture()
         }
 
         fn output_texture(
             &mut self,
             name: &str,
-            value: textures::Texture,
+            value: Vec<textures::Texture>,
         ) -> anyhow::Result<NodeValueType> {
             self.populate_output(
                 name,
@@ -296,6 +335,7 @@ pub fn evaluate_node(
             let input_axis = evaluator.input_matrix4("axis")?;
             let translate = evaluator.input_vector3("translate")?;
             let rotate = evaluator.input_vector3("rotate")? * std::f32::consts::PI / 180.0;
+            let scale = evaluator.input_vector3("scale")?;
             let uniform_scale = evaluator.input_float("uniform_scale")?;
 
             let quaternion =
@@ -305,7 +345,7 @@ pub fn evaluate_node(
                 "out",
                 input_axis
                     * glam::Mat4::from_scale_rotation_translation(
-                        glam::Vec3::splat(uniform_scale),
+                        uniform_scale * scale,
                         quaternion,
                         translate,
                     ),
@@ -383,9 +423,19 @@ pub fn evaluate_node(
             let specular_roughness = evaluator.input_float("specular_roughness")?;
             let specular_roughness_texture =
                 evaluator.input_procedural_texture("specular_roughness_texture")?;
+            let specular_anisotropy = evaluator.input_float("specular_anisotropy")?;
+            let specular_rotation = evaluator.input_float("specular_rotation")?;
+            let tangent_type =
+                evaluator.input_combo_box::<materials::TangentType>("tangent_type")?;
+            let tangent_axis = evaluator.input_vector3("tangent_axis")?;
+            let tangent_texture = evaluator.input_procedural_texture("tangent_texture")?;
             let specular_colour = evaluator.input_vector3("specular_colour")?;
             let specular_colour_texture =
                 evaluator.input_procedural_texture("specular_colour_texture")?;
+            let film_thickness = evaluator.input_float("film_thickness")?;
+            let film_thickness_texture =
+                evaluator.input_procedural_texture("film_thickness_texture")?;
+            let film_refractive_index = evaluator.input_float("film_refractive_index")?;
             let transmissive_probability = evaluator.input_float("transmissive_probability")?;
             let transmissive_probability_texture =
                 evaluator.input_procedural_texture("transmissive_probability_texture")?;
@@ -403,10 +453,13 @@ pub fn evaluate_node(
             let refractive_index = evaluator.input_float("refractive_index")?;
             let refractive_index_texture =
                 evaluator.input_procedural_texture("refractive_index_texture")?;
+            let abbe_number = evaluator.input_float("abbe_number")?;
             let scattering_coefficient = evaluator.input_float("scattering_coefficient")?;
             let scattering_colour = evaluator.input_vector3("scattering_colour")?;
             let scattering_colour_texture =
                 evaluator.input_procedural_texture("scattering_colour_texture")?;
+            let density = evaluator.input_float("density")?;
+            let density_texture = evaluator.input_procedural_texture("density_texture")?;
 
             evaluator.output_material(
                 "out",
@@ -417,8 +470,16 @@ pub fn evaluate_node(
                     specular_probability_texture: specular_probability_texture,
                     specular_roughness: specular_roughness * specular_roughness,
                     specular_roughness_texture: specular_roughness_texture,
+                    specular_anisotropy: specular_anisotropy,
+                    specular_rotation: specular_rotation,
+                    tangent_type: tangent_type,
+                    tangent_axis: tangent_axis,
+                    tangent_texture: tangent_texture,
                     specular_colour: specular_colour,
                     specular_colour_texture: specular_colour_texture,
+                    film_thickness: film_thickness,
+                    film_thickness_texture: film_thickness_texture,
+                    film_refractive_index: film_refractive_index,
                     transmissive_probability: transmissive_probability,
                     transmissive_probability_texture: transmissive_probability_texture,
                     transmissive_roughness: transmissive_roughness * transmissive_roughness,
@@ -431,9 +492,12 @@ pub fn evaluate_node(
                     emissive_colour_texture: emissive_colour_texture,
                     refractive_index: refractive_index,
                     refractive_index_texture: refractive_index_texture,
+                    abbe_number: abbe_number,
                     scattering_coefficient: scattering_coefficient,
                     scattering_colour: scattering_colour,
                     scattering_colour_texture: scattering_colour_texture,
+                    density: density,
+                    density_texture: density_texture,
                 },
             )
         }
@@ -489,6 +553,15 @@ pub fn evaluate_node(
                 primitive::Shapes::Ellipsoid => {
                     glam::Vec4::from((evaluator.input_vector3("radii")?, 0.))
                 }
+                primitive::Shapes::Gyroid
+                | primitive::Shapes::Neovius
+                | primitive::Shapes::SchwarzD
+                | primitive::Shapes::SchwarzP => glam::Vec4::new(
+                    evaluator.input_float("period")?,
+                    evaluator.input_float("thickness")?,
+                    0.,
+                    0.,
+                ),
                 primitive::Shapes::HexagonalPrism => glam::Vec4::new(
                     evaluator.input_float("height")?,
                     evaluator.input_float("depth")?,
@@ -501,12 +574,24 @@ pub fn evaluate_node(
                     evaluator.input_float("thickness")?,
                     0.,
                 ),
+                primitive::Shapes::HybridFractal => glam::Vec4::new(
+                    evaluator.input_float("scale")?,
+                    evaluator.input_uint("iterations")? as f32,
+                    evaluator.input_float("min_square_radius")?,
+                    evaluator.input_float("folding_limit")?,
+                ),
                 primitive::Shapes::InfiniteCone => {
                     glam::Vec4::new(evaluator.input_float("angle")?, 0., 0., 0.)
                 }
                 primitive::Shapes::InfiniteCylinder => {
                     glam::Vec4::new(evaluator.input_float("radius")?, 0., 0., 0.)
                 }
+                primitive::Shapes::KaleidoscopicIFS => glam::Vec4::new(
+                    evaluator.input_float("radial_extent")?,
+                    evaluator.input_uint("iterations")? as f32,
+                    evaluator.input_float("fractal_scale")?,
+                    0.,
+                ),
                 primitive::Shapes::Link => glam::Vec4::new(
                     evaluator.input_float("ring_radius")?,
                     evaluator.input_float("tube_radius")?,
@@ -525,12 +610,21 @@ pub fn evaluate_node(
                     evaluator.input_float("max_square_radius")?,
                     0.,
                 ),
+                primitive::Shapes::MengerSponge
+                | primitive::Shapes::SierpinskiOctahedron
+                | primitive::Shapes::SierpinskiTetrahedron => glam::Vec4::new(
+                    evaluator.input_float("radial_extent")?,
+                    evaluator.input_uint("iterations")? as f32,
+                    0.,
+                    0.,
+                ),
                 primitive::Shapes::Octahedron => {
                     glam::Vec4::new(evaluator.input_float("radial_extent")?, 0., 0., 0.)
                 }
                 primitive::Shapes::Plane => {
                     glam::Vec4::from((evaluator.input_vector3("normal")?, 0.))
                 }
+                primitive::Shapes::QuaternionJulia => evaluator.input_vector4("julia_constant")?,
                 primitive::Shapes::RectangularPrism => glam::Vec4::new(
                     evaluator.input_float("width")?,
                     evaluator.input_float("height")?,
@@ -558,6 +652,9 @@ pub fn evaluate_node(
                 primitive::Shapes::Sphere => {
                     glam::Vec4::new(evaluator.input_float("radius")?, 0., 0., 0.)
                 }
+                primitive::Shapes::Superellipsoid => {
+                    glam::Vec4::from((evaluator.input_vector3("radii")?, 0.))
+                }
                 primitive::Shapes::Torus => glam::Vec4::new(
                     evaluator.input_float("ring_radius")?,
                     evaluator.input_float("tube_radius")?,
@@ -571,19 +668,82 @@ pub fn evaluate_node(
                     0.,
                 ),
             };
+            let extended_dimensional_data = match shape {
+                primitive::Shapes::HybridFractal => glam::Vec4::new(
+                    evaluator.input_float("power")?,
+                    evaluator.input_float("max_square_radius")?,
+                    evaluator.input_uint("box_folds_per_cycle")? as f32,
+                    evaluator.input_uint("bulb_iterations_per_cycle")? as f32,
+                ),
+                primitive::Shapes::QuaternionJulia => glam::Vec4::new(
+                    evaluator.input_uint("iterations")? as f32,
+                    evaluator.input_float("escape_radius")?,
+                    0.,
+                    0.,
+                ),
+                primitive::Shapes::Superellipsoid => glam::Vec4::new(
+                    evaluator.input_float("east_west_exponent")?,
+                    evaluator.input_float("north_south_exponent")?,
+                    0.,
+                    0.,
+                ),
+                _ => glam::Vec4::ZERO,
+            };
+            let fold_planes = glam::Mat3::from_cols(
+                evaluator.input_vector3("first_fold_plane")?,
+                evaluator.input_vector3("second_fold_plane")?,
+                evaluator.input_vector3("third_fold_plane")?,
+            );
+            let fractal_rotation_angles = evaluator.input_vector3("fractal_rotation_angles")?;
+            let fractal_offset = evaluator.input_vector3("fractal_offset")?;
             let edge_radius = evaluator.input_float("edge_radius")?;
             let repetition = evaluator.input_combo_box::<geometry::Repetition>("repetition")?;
             let negative_repetitions = evaluator.input_uint_vector3("negative_repetitions")?;
             let positive_repetitions = evaluator.input_uint_vector3("positive_repetitions")?;
             let spacing = evaluator.input_vector3("spacing")?;
+            let polar_segments = evaluator.input_uint("polar_segments")?;
+            let polar_axis = evaluator.input_combo_box::<geometry::Axis>("polar_axis")?;
+            let polar_radius = evaluator.input_float("polar_radius")?;
+            let polar_mirror = evaluator.input_bool("polar_mirror")?;
+            let cell_variation = evaluator.input_bool("cell_variation")?;
+            let cell_scale_variation = evaluator.input_float("cell_scale_variation")?;
+            let cell_scale_seed = evaluator.input_uint("cell_scale_seed")?;
+            let cell_rotation_variation = evaluator.input_vector3("cell_rotation_variation")?;
+            let cell_rotation_seed = evaluator.input_uint("cell_rotation_seed")?;
+            let cell_jitter = evaluator.input_vector3("cell_jitter")?;
+            let cell_jitter_seed = evaluator.input_uint("cell_jitter_seed")?;
+            let cell_hue_variation = evaluator.input_float("cell_hue_variation")?;
+            let cell_hue_seed = evaluator.input_uint("cell_hue_seed")?;
+            let cell_value_variation = evaluator.input_float("cell_value_variation")?;
+            let cell_value_seed = evaluator.input_uint("cell_value_seed")?;
+            let cell_visibility = evaluator.input_float("cell_visibility")?;
+            let cell_visibility_seed = evaluator.input_uint("cell_visibility_seed")?;
             let bounding_volume = evaluator.input_bool("bounding_volume")?;
             let blend_type = evaluator.input_combo_box::<geometry::BlendType>("blend_type")?;
+            let blend_kernel =
+                evaluator.input_combo_box::<geometry::BlendKernel>("blend_kernel")?;
             let blend_strength = evaluator.input_float("blend_strength")?;
+            let blend_steps = evaluator.input_uint("blend_steps")?;
+            let blend_width = evaluator.input_float("blend_width")?;
             let mirror = evaluator.input_bool_vector3("mirror")?;
             let hollow = evaluator.input_bool("hollow")?;
             let wall_thickness = evaluator.input_float("wall_thickness")?;
             let elongate = evaluator.input_bool("elongate")?;
             let elongation = evaluator.input_vector3("elongation")?;
+            let twist = evaluator.input_bool("twist")?;
+            let twist_axis = evaluator.input_combo_box::<geometry::Axis>("twist_axis")?;
+            let twist_rate = evaluator.input_float("twist_rate")?;
+            let bend = evaluator.input_bool("bend")?;
+            let bend_axis = evaluator.input_combo_box::<geometry::Axis>("bend_axis")?;
+            let bend_radius = evaluator.input_float("bend_radius")?;
+            let taper = evaluator.input_bool("taper")?;
+            let taper_axis = evaluator.input_combo_box::<geometry::Axis>("taper_axis")?;
+            let taper_rate = evaluator.input_float("taper_rate")?;
+            let displacement_texture =
+                evaluator.input_procedural_texture("displacement_texture")?;
+            let displacement_amplitude = evaluator.input_float("displacement_amplitude")?;
+            let displacement_offset = evaluator.input_float("displacement_offset")?;
+            let lipschitz_bound = evaluator.input_float("lipschitz_bound")?;
             let world_matrix = evaluator.input_matrix4("world_matrix")?;
             for child in descendants.iter_mut() {
                 child.world_matrix = world_matrix * child.world_matrix;
@@ -599,15 +759,52 @@ pub fn evaluate_node(
                 mirror: mirror,
                 elongate: elongate,
                 elongation: elongation,
+                twist: twist,
+                twist_axis: twist_axis,
+                twist_rate: twist_rate,
+                bend: bend,
+                bend_axis: bend_axis,
+                bend_radius: bend_radius,
+                taper: taper,
+                taper_axis: taper_axis,
+                taper_rate: taper_rate,
                 repetition: repetition,
                 negative_repetitions: negative_repetitions,
                 positive_repetitions: positive_repetitions,
                 spacing: spacing,
+                polar_segments: polar_segments,
+                polar_axis: polar_axis,
+                polar_radius: polar_radius,
+                polar_mirror: polar_mirror,
+                cell_variation: cell_variation,
+                cell_scale_variation: cell_scale_variation,
+                cell_scale_seed: cell_scale_seed,
+                cell_rotation_variation: cell_rotation_variation,
+                cell_rotation_seed: cell_rotation_seed,
+                cell_jitter: cell_jitter,
+                cell_jitter_seed: cell_jitter_seed,
+                cell_hue_variation: cell_hue_variation,
+                cell_hue_seed: cell_hue_seed,
+                cell_value_variation: cell_value_variation,
+                cell_value_seed: cell_value_seed,
+                cell_visibility: cell_visibility,
+                cell_visibility_seed: cell_visibility_seed,
                 blend_type: blend_type,
+                blend_kernel: blend_kernel,
                 blend_strength: blend_strength,
+                blend_steps: blend_steps,
+                blend_width: blend_width,
                 bounding_volume: bounding_volume,
                 num_descendants: descendants.len() as u32,
                 dimensional_data: dimensional_data,
+                extended_dimensional_data: extended_dimensional_data,
+                fold_planes: fold_planes,
+                fractal_rotation_angles: fractal_rotation_angles,
+                fractal_offset: fractal_offset,
+                displacement_texture: displacement_texture,
+                displacement_amplitude: displacement_amplitude,
+                displacement_offset: displacement_offset,
+                lipschitz_bound: lipschitz_bound,
             };
 
             scene_primitives.push(primitive);
@@ -615,6 +812,8 @@ pub fn evaluate_node(
             evaluator.output_primitive("out", scene_primitives)
         }
         NodeTemplate::ProceduralTexture => {
+            let input = evaluator.input_procedural_texture("texture")?;
+            let mask = evaluator.input_procedural_texture("mask")?;
             let texture_type =
                 evaluator.input_combo_box::<materials::ProceduralTextureType>("texture_type")?;
             let scale = evaluator.input_vector4("scale")?;
@@ -634,29 +833,69 @@ pub fn evaluate_node(
             let invert = evaluator.input_bool("invert")?;
             let hue_rotation_angles = evaluator.input_vector3("hue_rotation_angles")?;
             let use_trap_colour = evaluator.input_bool("use_trap_colour")?;
+            let image_index = evaluator.input_uint("image_index")?;
+            let grid_slices = evaluator.input_uint("grid_slices")?;
+            let projection =
+                evaluator.input_combo_box::<materials::TextureProjection>("projection")?;
+            let coordinate_space =
+                evaluator.input_combo_box::<materials::CoordinateSpace>("coordinate_space")?;
+            let blend_sharpness = evaluator.input_float("blend_sharpness")?;
+            let voronoi_feature =
+                evaluator.input_combo_box::<materials::VoronoiFeature>("voronoi_feature")?;
+            let distance_metric =
+                evaluator.input_combo_box::<materials::DistanceMetric>("distance_metric")?;
+            let jitter = evaluator.input_float("jitter")?;
+            let warp_strength = evaluator.input_float("warp_strength")?;
+            let warp_octaves = evaluator.input_uint("warp_octaves")?;
+            let blend_mode =
+                evaluator.input_combo_box::<materials::TextureBlendMode>("blend_mode")?;
+            let use_colour_ramp = evaluator.input_bool("use_colour_ramp")?;
+            let colour_ramp = evaluator.input_colour_ramp("colour_ramp")?;
 
-            evaluator.output_procedural_texture(
-                "out",
-                materials::ProceduralTexture {
-                    texture_type: texture_type,
-                    scale: scale,
-                    black_point: black_point,
-                    white_point: white_point,
-                    lift: lift,
-                    gain: gain,
-                    octaves: octaves,
-                    lacunarity: lacunarity,
-                    amplitude_gain: amplitude_gain,
-                    gamma: gamma,
-                    low_frequency_scale: low_frequency_scale,
-                    high_frequency_scale: high_frequency_scale,
-                    low_frequency_translation: low_frequency_translation,
-                    high_frequency_translation: high_frequency_translation,
-                    invert: invert,
-                    hue_rotation_angles: hue_rotation_angles,
-                    use_trap_colour: use_trap_colour,
-                },
-            )
+            let texture = materials::ProceduralTexture {
+                texture_type: texture_type,
+                scale: scale,
+                black_point: black_point,
+                white_point: white_point,
+                lift: lift,
+                gain: gain,
+                octaves: octaves,
+                lacunarity: lacunarity,
+                amplitude_gain: amplitude_gain,
+                gamma: gamma,
+                low_frequency_scale: low_frequency_scale,
+                high_frequency_scale: high_frequency_scale,
+                low_frequency_translation: low_frequency_translation,
+                high_frequency_translation: high_frequency_translation,
+                invert: invert,
+                hue_rotation_angles: hue_rotation_angles,
+                use_trap_colour: use_trap_colour,
+                image_index: image_index,
+                grid_slices: grid_slices,
+                projection: projection,
+                coordinate_space: coordinate_space,
+                blend_sharpness: blend_sharpness,
+                voronoi_feature: voronoi_feature,
+                distance_metric: distance_metric,
+                jitter: jitter,
+                warp_strength: warp_strength,
+                warp_octaves: warp_octaves,
+                // Disconnected inputs have no texture type and are ignored
+                input: (input.texture_type > materials::ProceduralTextureType::None)
+                    .then(|| Box::new(input)),
+                mask: (mask.texture_type > materials::ProceduralTextureType::None)
+                    .then(|| Box::new(mask)),
+                blend_mode: blend_mode,
+                use_colour_ramp: use_colour_ramp,
+                colour_ramp: colour_ramp,
+            };
+            if texture.stack_depth() > materials::MAX_TEXTURE_STACK_DEPTH {
+                anyhow::bail!(
+                    "Procedural textures cannot be nested more than {} deep",
+                    materials::MAX_TEXTURE_STACK_DEPTH
+                );
+            }
+            evaluator.output_procedural_texture("out", texture)
         }
         NodeTemplate::RayMarcher => {
             let scene = evaluator.input_scene("scene")?;
@@ -673,6 +912,9 @@ pub fn evaluate_node(
             let sample_atmosphere = evaluator.input_bool("sample_atmosphere")?;
             let light_sampling_bias = evaluator.input_float("light_sampling_bias")?;
             let secondary_sampling = evaluator.input_bool("secondary_sampling")?;
+            let spectral_rendering = evaluator.input_bool("spectral_rendering")?;
+            let noise_threshold = evaluator.input_float("noise_threshold")?;
+            let max_samples = evaluator.input_uint("max_samples")?;
             let output_aov = evaluator.input_combo_box::<ray_marcher::AOVs>("output_aov")?;
 
             evaluator.output_ray_marcher(
@@ -692,15 +934,78 @@ pub fn evaluate_node(
                     sample_atmosphere: sample_atmosphere,
                     light_sampling_bias: light_sampling_bias,
                     secondary_sampling: secondary_sampling,
+                    spectral_rendering: spectral_rendering,
+                    noise_threshold: noise_threshold,
+                    max_samples: max_samples,
                     output_aov: output_aov,
                 },
             )
         }
+        NodeTemplate::Scatter => {
+            let mut scene_primitives = evaluator.input_primitive("siblings")?;
+            let target = evaluator.input_primitive("target")?;
+            let instances = evaluator.input_primitive("instances")?;
+            let count = evaluator.input_uint("count")?;
+            let seed = evaluator.input_uint("seed")?;
+            let minimum_spacing = evaluator.input_float("minimum_spacing")?;
+            let search_radius = evaluator.input_float("search_radius")?;
+            let align_to_normal = evaluator.input_bool("align_to_normal")?;
+            let scale_range = evaluator.input_vector2("scale_range")?;
+            let rotation_range = evaluator.input_vector3("rotation_range")?;
+
+            let scatter = geometry::scatter::Scatter {
+                count: count,
+                seed: seed,
+                minimum_spacing: minimum_spacing,
+                search_radius: search_radius,
+                align_to_normal: align_to_normal,
+                scale_range: scale_range,
+                rotation_range: rotation_range,
+            };
+
+            if let Some(target) = target.first() {
+                // The primitives are only scattered again when the inputs
+                // have changed since they were last scattered
+                let scatter_cache = &evaluator.graph[evaluator.node_id].user_data.scatter_cache;
+                let inputs_hash: Option<Key<OrderedFloatPolicy>> =
+                    to_key_with_ordered_float(&(&scatter, target, &instances)).ok();
+                let cached: Option<Vec<primitive::Primitive>> = scatter_cache
+                    .borrow()
+                    .as_ref()
+                    .filter(|(cached_hash, _)| inputs_hash.as_ref() == Some(cached_hash))
+                    .map(|(_, scattered)| scattered.clone());
+
+                let mut scattered: Vec<primitive::Primitive> = match cached {
+                    Some(scattered) => scattered,
+                    None => {
+                        let Some(scattered) = scatter.scatter(target, &instances) else {
+                            anyhow::bail!(
+                                "Cannot scatter over a {} as it has no CPU signed distance \
+                                function",
+                                target.shape
+                            );
+                        };
+                        *scatter_cache.borrow_mut() =
+                            inputs_hash.map(|inputs_hash| (inputs_hash, scattered.clone()));
+                        scattered
+                    }
+                };
+                scene_primitives.append(&mut scattered);
+            }
+            evaluator.output_primitive("out", scene_primitives)
+        }
         NodeTemplate::Scene => {
             let render_camera = evaluator.input_camera("render_camera")?;
             let primitives = evaluator.input_primitive("primitives")?;
             let lights = evaluator.input_light("lights")?;
             let atmosphere = evaluator.input_material("atmosphere")?;
+            let atmosphere_density =
+                evaluator.input_combo_box::<scene::AtmosphereDensity>("atmosphere_density")?;
+            let fog_base_height = evaluator.input_float("fog_base_height")?;
+            let fog_falloff = evaluator.input_float("fog_falloff")?;
+            let aerosol_density = evaluator.input_float("aerosol_density")?;
+            let aerosol_falloff = evaluator.input_float("aerosol_falloff")?;
+            let textures = evaluator.input_texture("textures")?;
             evaluator.output_scene(
                 "out",
                 scene::Scene {
@@ -708,18 +1013,23 @@ pub fn evaluate_node(
                     lights: lights,
                     primitives: primitives,
                     atmosphere: atmosphere,
+                    atmosphere_density: atmosphere_density,
+                    fog_base_height: fog_base_height,
+                    fog_falloff: fog_falloff,
+                    aerosol_density: aerosol_density,
+                    aerosol_falloff: aerosol_falloff,
+                    textures: textures,
                 },
             )
         }
         NodeTemplate::Texture => {
+            let mut textures = evaluator.input_texture("textures")?;
             let filepath = evaluator.input_filepath("filepath")?;
-            evaluator.output_texture(
-                "out",
-                textures::Texture {
-                    layers: 1,
-                    filepath: filepath,
-                },
-            )
+            textures.push(textures::Texture {
+                layers: 1,
+                filepath: filepath,
+            });
+            evaluator.output_texture("out", textures)
         }
     }
 }
@@ -11,8 +11,11 @@ pub mod node;
 mod response;
 mod state;
 
-pub use graph::{evaluate_node, Graph};
-use node::{AllNodeTemplates, NodeData};
+pub use graph::{evaluate_node, evaluate_texture_baker, Graph};
+use node::{
+    value_type::{NodeValueType, RangedInput},
+    AllNodeTemplates, NodeData,
+};
 pub use response::NodeGraphResponse;
 pub use state::{NodeGraphEditorState, NodeGraphState};
 
@@ -55,6 +58,22 @@ impl NodeGraph {
         &mut self.user_state
     }
 
+    /// Limit the image indices of the procedural textures to the images
+    /// that are loaded into the texture array of the viewer.
+    pub fn set_image_index_range(&mut self, num_images: usize) {
+        let range = 0..=(num_images.max(1) - 1) as u32;
+        let graph = &mut self.editor_state.graph;
+        for node in graph.nodes.values() {
+            if let Ok(input_id) = node.get_input("image_index") {
+                if let Some(input_param) = graph.inputs.get_mut(input_id) {
+                    if let NodeValueType::UnsignedInteger { ref mut value } = input_param.value {
+                        *value.range_mut() = range.clone();
+                    }
+                }
+            }
+        }
+    }
+
     pub fn show(&mut self, ctx: &egui::Context) -> GraphResponse<NodeGraphResponse, NodeData> {
         egui::CentralPanel::default()
             .show(ctx, |ui| {
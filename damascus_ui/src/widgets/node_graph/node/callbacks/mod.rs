@@ -8,11 +8,15 @@ use egui_node_graph::{InputId, NodeId, OutputId};
 use super::{super::NodeGraphResponse, Graph, NodeValueType, UIInput};
 
 mod light;
+mod material;
 mod primitive;
 mod procedural_texture;
+mod scene;
 pub use light::LightCallbacks;
+pub use material::MaterialCallbacks;
 pub use primitive::PrimitiveCallbacks;
 pub use procedural_texture::ProceduralTextureCallbacks;
+pub use scene::SceneCallbacks;
 
 pub trait NodeCallbacks {
     fn show_input(&self, value_type: &mut NodeValueType) {
@@ -23,6 +27,9 @@ pub trait NodeCallbacks {
             NodeValueType::BVec3 { ref mut value } => {
                 value.ui_data_mut().show();
             }
+            NodeValueType::ColourRamp { ref mut value } => {
+                value.ui_data_mut().show();
+            }
             NodeValueType::ComboBox { ref mut value } => {
                 value.ui_data_mut().show();
             }
@@ -62,6 +69,9 @@ pub trait NodeCallbacks {
             NodeValueType::BVec3 { ref mut value } => {
                 value.ui_data_mut().hide();
             }
+            NodeValueType::ColourRamp { ref mut value } => {
+                value.ui_data_mut().hide();
+            }
             NodeValueType::ComboBox { ref mut value } => {
                 value.ui_data_mut().hide();
             }
@@ -9,6 +9,21 @@ use damascus_core::geometry::{self, primitive};
 
 use super::{super::NodeGraphResponse, Graph, NodeCallbacks, NodeValueType, UIInput};
 
+const CELL_VARIATION_INPUTS: [&str; 12] = [
+    "cell_scale_variation",
+    "cell_scale_seed",
+    "cell_rotation_variation",
+    "cell_rotation_seed",
+    "cell_jitter",
+    "cell_jitter_seed",
+    "cell_hue_variation",
+    "cell_hue_seed",
+    "cell_value_variation",
+    "cell_value_seed",
+    "cell_visibility",
+    "cell_visibility_seed",
+];
+
 #[derive(Clone, Copy, serde::Serialize, serde::Deserialize)]
 pub struct PrimitiveCallbacks;
 
@@ -23,8 +38,14 @@ impl NodeCallbacks for PrimitiveCallbacks {
             "bounding_volume",
             "shape",
             "repetition",
+            "cell_variation",
             "hollow",
             "elongate",
+            "twist",
+            "bend",
+            "taper",
+            "blend_type",
+            "blend_kernel",
         ]
         .contains(&input_name.as_str())
         {
@@ -65,6 +86,19 @@ impl NodeCallbacks for PrimitiveCallbacks {
                                 "scale",
                                 "min_square_radius",
                                 "folding_limit",
+                                "fractal_scale",
+                                "first_fold_plane",
+                                "second_fold_plane",
+                                "third_fold_plane",
+                                "fractal_rotation_angles",
+                                "fractal_offset",
+                                "box_folds_per_cycle",
+                                "bulb_iterations_per_cycle",
+                                "julia_constant",
+                                "escape_radius",
+                                "period",
+                                "east_west_exponent",
+                                "north_south_exponent",
                             ]);
                             match input_param.value() {
                                 NodeValueType::ComboBox { ref value } => {
@@ -105,6 +139,13 @@ impl NodeCallbacks for PrimitiveCallbacks {
                                         Ok(primitive::Shapes::Ellipsoid) => {
                                             to_show.push("radii");
                                         }
+                                        Ok(primitive::Shapes::Gyroid)
+                                        | Ok(primitive::Shapes::Neovius)
+                                        | Ok(primitive::Shapes::SchwarzD)
+                                        | Ok(primitive::Shapes::SchwarzP) => {
+                                            to_show.push("period");
+                                            to_show.push("thickness");
+                                        }
                                         Ok(primitive::Shapes::HexagonalPrism) => {
                                             to_show.push("height");
                                             to_show.push("depth");
@@ -114,12 +155,32 @@ impl NodeCallbacks for PrimitiveCallbacks {
                                             to_show.push("height");
                                             to_show.push("thickness");
                                         }
+                                        Ok(primitive::Shapes::HybridFractal) => {
+                                            to_show.push("scale");
+                                            to_show.push("iterations");
+                                            to_show.push("min_square_radius");
+                                            to_show.push("folding_limit");
+                                            to_show.push("power");
+                                            to_show.push("max_square_radius");
+                                            to_show.push("box_folds_per_cycle");
+                                            to_show.push("bulb_iterations_per_cycle");
+                                        }
                                         Ok(primitive::Shapes::InfiniteCone) => {
                                             to_show.push("angle");
                                         }
                                         Ok(primitive::Shapes::InfiniteCylinder) => {
                                             to_show.push("radius");
                                         }
+                                        Ok(primitive::Shapes::KaleidoscopicIFS) => {
+                                            to_show.push("radial_extent");
+                                            to_show.push("iterations");
+                                            to_show.push("fractal_scale");
+                                            to_show.push("first_fold_plane");
+                                            to_show.push("second_fold_plane");
+                                            to_show.push("third_fold_plane");
+                                            to_show.push("fractal_rotation_angles");
+                                            to_show.push("fractal_offset");
+                                        }
                                         Ok(primitive::Shapes::Link) => {
                                             to_show.push("ring_radius");
                                             to_show.push("tube_radius");
@@ -136,12 +197,23 @@ impl NodeCallbacks for PrimitiveCallbacks {
                                             to_show.push("iterations");
                                             to_show.push("max_square_radius");
                                         }
+                                        Ok(primitive::Shapes::MengerSponge)
+                                        | Ok(primitive::Shapes::SierpinskiOctahedron)
+                                        | Ok(primitive::Shapes::SierpinskiTetrahedron) => {
+                                            to_show.push("radial_extent");
+                                            to_show.push("iterations");
+                                        }
                                         Ok(primitive::Shapes::Octahedron) => {
                                             to_show.push("radial_extent");
                                         }
                                         Ok(primitive::Shapes::Plane) => {
                                             to_show.push("normal");
                                         }
+                                        Ok(primitive::Shapes::QuaternionJulia) => {
+                                            to_show.push("julia_constant");
+                                            to_show.push("iterations");
+                                            to_show.push("escape_radius");
+                                        }
                                         Ok(primitive::Shapes::RectangularPrism) => {
                                             to_show.push("width");
                                             to_show.push("height");
@@ -166,6 +238,11 @@ impl NodeCallbacks for PrimitiveCallbacks {
                                         Ok(primitive::Shapes::Sphere) => {
                                             to_show.push("radius");
                                         }
+                                        Ok(primitive::Shapes::Superellipsoid) => {
+                                            to_show.push("radii");
+                                            to_show.push("east_west_exponent");
+                                            to_show.push("north_south_exponent");
+                                        }
                                         Ok(primitive::Shapes::Torus) => {
                                             to_show.push("ring_radius");
                                             to_show.push("tube_radius");
@@ -182,23 +259,56 @@ impl NodeCallbacks for PrimitiveCallbacks {
                         }
                         "repetition" => match input_param.value() {
                             NodeValueType::ComboBox { ref value } => {
+                                let polar_inputs = [
+                                    "polar_segments",
+                                    "polar_axis",
+                                    "polar_radius",
+                                    "polar_mirror",
+                                ];
                                 match value.as_enum::<geometry::Repetition>() {
                                     Ok(geometry::Repetition::Finite) => {
                                         to_show.push("negative_repetitions");
                                         to_show.push("positive_repetitions");
                                         to_show.push("spacing");
+                                        to_hide.extend(polar_inputs);
                                     }
                                     Ok(geometry::Repetition::Infinite) => {
                                         to_hide.push("negative_repetitions");
                                         to_hide.push("positive_repetitions");
                                         to_show.push("spacing");
+                                        to_hide.extend(polar_inputs);
+                                    }
+                                    Ok(geometry::Repetition::Polar) => {
+                                        to_hide.push("negative_repetitions");
+                                        to_hide.push("positive_repetitions");
+                                        to_hide.push("spacing");
+                                        to_show.extend(polar_inputs);
                                     }
                                     _ => {
                                         to_hide.push("negative_repetitions");
                                         to_hide.push("positive_repetitions");
                                         to_hide.push("spacing");
+                                        to_hide.extend(polar_inputs);
                                     }
                                 }
+                                if let Ok(geometry::Repetition::None) =
+                                    value.as_enum::<geometry::Repetition>()
+                                {
+                                    to_hide.push("cell_variation");
+                                    to_hide.extend(CELL_VARIATION_INPUTS);
+                                } else {
+                                    to_show.push("cell_variation");
+                                }
+                            }
+                            _ => {}
+                        },
+                        "cell_variation" => match input_param.value() {
+                            NodeValueType::Bool { ref value } => {
+                                if *value.value() {
+                                    to_show.extend(CELL_VARIATION_INPUTS);
+                                } else {
+                                    to_hide.extend(CELL_VARIATION_INPUTS);
+                                }
                             }
                             _ => {}
                         },
@@ -222,13 +332,81 @@ impl NodeCallbacks for PrimitiveCallbacks {
                             }
                             _ => {}
                         },
+                        "twist" => match input_param.value() {
+                            NodeValueType::Bool { ref value } => {
+                                if *value.value() {
+                                    to_show.push("twist_axis");
+                                    to_show.push("twist_rate");
+                                } else {
+                                    to_hide.push("twist_axis");
+                                    to_hide.push("twist_rate");
+                                }
+                            }
+                            _ => {}
+                        },
+                        "bend" => match input_param.value() {
+                            NodeValueType::Bool { ref value } => {
+                                if *value.value() {
+                                    to_show.push("bend_axis");
+                                    to_show.push("bend_radius");
+                                } else {
+                                    to_hide.push("bend_axis");
+                                    to_hide.push("bend_radius");
+                                }
+                            }
+                            _ => {}
+                        },
+                        "taper" => match input_param.value() {
+                            NodeValueType::Bool { ref value } => {
+                                if *value.value() {
+                                    to_show.push("taper_axis");
+                                    to_show.push("taper_rate");
+                                } else {
+                                    to_hide.push("taper_axis");
+                                    to_hide.push("taper_rate");
+                                }
+                            }
+                            _ => {}
+                        },
+                        "blend_type" => match input_param.value() {
+                            NodeValueType::ComboBox { ref value } => {
+                                match value.as_enum::<geometry::BlendType>() {
+                                    Ok(geometry::BlendType::Groove)
+                                    | Ok(geometry::BlendType::Tongue) => {
+                                        to_show.push("blend_width");
+                                    }
+                                    _ => {
+                                        to_hide.push("blend_width");
+                                    }
+                                }
+                            }
+                            _ => {}
+                        },
+                        "blend_kernel" => match input_param.value() {
+                            NodeValueType::ComboBox { ref value } => {
+                                match value.as_enum::<geometry::BlendKernel>() {
+                                    Ok(geometry::BlendKernel::Stairs)
+                                    | Ok(geometry::BlendKernel::Columns) => {
+                                        to_show.push("blend_steps");
+                                    }
+                                    _ => {
+                                        to_hide.push("blend_steps");
+                                    }
+                                }
+                            }
+                            _ => {}
+                        },
                         "bounding_volume" => match input_param.value() {
                             NodeValueType::Bool { ref value } => {
                                 if *value.value() {
                                     to_hide.push("blend_type");
+                                    to_hide.push("blend_kernel");
                                     to_hide.push("blend_strength");
+                                    to_hide.push("blend_steps");
+                                    to_hide.push("blend_width");
                                 } else {
                                     to_show.push("blend_type");
+                                    to_show.push("blend_kernel");
                                     to_show.push("blend_strength");
                                 }
                             }
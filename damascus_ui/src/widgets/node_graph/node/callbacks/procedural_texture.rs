@@ -5,7 +5,7 @@
 
 use egui_node_graph::{Node, NodeId};
 
-use damascus_core::materials::ProceduralTextureType;
+use damascus_core::materials::{ProceduralTextureType, TextureProjection};
 
 use super::{
     super::{NodeData, NodeGraphResponse},
@@ -27,6 +27,23 @@ impl ProceduralTextureCallbacks {
         }
         false
     }
+
+    fn uses_triplanar_projection(graph: &Graph, node: &Node<NodeData>) -> bool {
+        if let Ok(input_id) = node.get_input("projection") {
+            if let Some(input_param) = graph.inputs.get(input_id) {
+                match input_param.value() {
+                    NodeValueType::ComboBox { ref value } => {
+                        return matches!(
+                            value.as_enum::<TextureProjection>(),
+                            Ok(TextureProjection::Triplanar)
+                        );
+                    }
+                    _ => {}
+                }
+            }
+        }
+        false
+    }
 }
 
 impl NodeCallbacks for ProceduralTextureCallbacks {
@@ -36,7 +53,14 @@ impl NodeCallbacks for ProceduralTextureCallbacks {
         node_id: NodeId,
         input_name: &String,
     ) -> Vec<NodeGraphResponse> {
-        if !["texture_type", "use_trap_colour"].contains(&input_name.as_str()) {
+        if ![
+            "texture_type",
+            "use_trap_colour",
+            "projection",
+            "use_colour_ramp",
+        ]
+        .contains(&input_name.as_str())
+        {
             return Vec::new();
         }
         if let Some(node) = graph.nodes.get(node_id) {
@@ -71,6 +95,16 @@ impl NodeCallbacks for ProceduralTextureCallbacks {
                                         to_hide.push("high_frequency_scale");
                                         to_hide.push("low_frequency_translation");
                                         to_hide.push("high_frequency_translation");
+                                        to_hide.push("image_index");
+                                        to_hide.push("grid_slices");
+                                        to_hide.push("projection");
+                                        to_hide.push("coordinate_space");
+                                        to_hide.push("warp_strength");
+                                        to_hide.push("warp_octaves");
+                                        to_hide.push("blend_sharpness");
+                                        to_hide.push("voronoi_feature");
+                                        to_hide.push("distance_metric");
+                                        to_hide.push("jitter");
                                     }
                                     Ok(ProceduralTextureType::Checkerboard) => {
                                         to_show.push("scale");
@@ -87,6 +121,9 @@ impl NodeCallbacks for ProceduralTextureCallbacks {
                                         } else {
                                             to_hide.push("hue_rotation_angles");
                                         }
+                                        to_show.push("coordinate_space");
+                                        to_show.push("warp_strength");
+                                        to_show.push("warp_octaves");
 
                                         to_hide.push("octaves");
                                         to_hide.push("lacunarity");
@@ -95,6 +132,13 @@ impl NodeCallbacks for ProceduralTextureCallbacks {
                                         to_hide.push("high_frequency_scale");
                                         to_hide.push("low_frequency_translation");
                                         to_hide.push("high_frequency_translation");
+                                        to_hide.push("image_index");
+                                        to_hide.push("grid_slices");
+                                        to_hide.push("projection");
+                                        to_hide.push("blend_sharpness");
+                                        to_hide.push("voronoi_feature");
+                                        to_hide.push("distance_metric");
+                                        to_hide.push("jitter");
                                     }
                                     Ok(ProceduralTextureType::FBMNoise)
                                     | Ok(ProceduralTextureType::TurbulenceNoise) => {
@@ -119,6 +163,123 @@ impl NodeCallbacks for ProceduralTextureCallbacks {
                                         } else {
                                             to_hide.push("hue_rotation_angles");
                                         }
+                                        to_show.push("coordinate_space");
+                                        to_show.push("warp_strength");
+                                        to_show.push("warp_octaves");
+                                        to_hide.push("image_index");
+                                        to_hide.push("grid_slices");
+                                        to_hide.push("projection");
+                                        to_hide.push("blend_sharpness");
+                                        to_hide.push("voronoi_feature");
+                                        to_hide.push("distance_metric");
+                                        to_hide.push("jitter");
+                                    }
+                                    Ok(ProceduralTextureType::VoronoiNoise) => {
+                                        to_show.push("scale");
+                                        to_show.push("black_point");
+                                        to_show.push("white_point");
+                                        to_show.push("lift");
+                                        to_show.push("gain");
+                                        to_show.push("octaves");
+                                        to_show.push("lacunarity");
+                                        to_show.push("amplitude_gain");
+                                        to_show.push("gamma");
+                                        to_show.push("low_frequency_scale");
+                                        to_show.push("high_frequency_scale");
+                                        to_show.push("low_frequency_translation");
+                                        to_show.push("high_frequency_translation");
+                                        to_show.push("invert");
+                                        to_show.push("use_trap_colour");
+                                        if ProceduralTextureCallbacks::use_trap_colour(graph, node)
+                                        {
+                                            to_show.push("hue_rotation_angles");
+                                        } else {
+                                            to_hide.push("hue_rotation_angles");
+                                        }
+                                        to_show.push("coordinate_space");
+                                        to_show.push("warp_strength");
+                                        to_show.push("warp_octaves");
+                                        to_show.push("voronoi_feature");
+                                        to_show.push("distance_metric");
+                                        to_show.push("jitter");
+                                        to_hide.push("image_index");
+                                        to_hide.push("grid_slices");
+                                        to_hide.push("projection");
+                                        to_hide.push("blend_sharpness");
+                                    }
+                                    Ok(ProceduralTextureType::Image) => {
+                                        to_show.push("scale");
+                                        to_show.push("black_point");
+                                        to_show.push("white_point");
+                                        to_show.push("lift");
+                                        to_show.push("gain");
+                                        to_show.push("gamma");
+                                        to_show.push("invert");
+                                        to_show.push("use_trap_colour");
+                                        if ProceduralTextureCallbacks::use_trap_colour(graph, node)
+                                        {
+                                            to_show.push("hue_rotation_angles");
+                                        } else {
+                                            to_hide.push("hue_rotation_angles");
+                                        }
+                                        to_show.push("coordinate_space");
+                                        to_show.push("warp_strength");
+                                        to_show.push("warp_octaves");
+                                        to_show.push("image_index");
+                                        to_show.push("projection");
+                                        if ProceduralTextureCallbacks::uses_triplanar_projection(
+                                            graph, node,
+                                        ) {
+                                            to_show.push("blend_sharpness");
+                                        } else {
+                                            to_hide.push("blend_sharpness");
+                                        }
+
+                                        to_hide.push("octaves");
+                                        to_hide.push("lacunarity");
+                                        to_hide.push("amplitude_gain");
+                                        to_hide.push("low_frequency_scale");
+                                        to_hide.push("high_frequency_scale");
+                                        to_hide.push("low_frequency_translation");
+                                        to_hide.push("high_frequency_translation");
+                                        to_hide.push("voronoi_feature");
+                                        to_hide.push("distance_metric");
+                                        to_hide.push("jitter");
+                                        to_hide.push("grid_slices");
+                                    }
+                                    Ok(ProceduralTextureType::ImageGrid) => {
+                                        to_show.push("scale");
+                                        to_show.push("black_point");
+                                        to_show.push("white_point");
+                                        to_show.push("lift");
+                                        to_show.push("gain");
+                                        to_show.push("gamma");
+                                        to_show.push("invert");
+                                        to_show.push("use_trap_colour");
+                                        if ProceduralTextureCallbacks::use_trap_colour(graph, node)
+                                        {
+                                            to_show.push("hue_rotation_angles");
+                                        } else {
+                                            to_hide.push("hue_rotation_angles");
+                                        }
+                                        to_show.push("coordinate_space");
+                                        to_show.push("warp_strength");
+                                        to_show.push("warp_octaves");
+                                        to_show.push("image_index");
+                                        to_show.push("grid_slices");
+
+                                        to_hide.push("octaves");
+                                        to_hide.push("lacunarity");
+                                        to_hide.push("amplitude_gain");
+                                        to_hide.push("low_frequency_scale");
+                                        to_hide.push("high_frequency_scale");
+                                        to_hide.push("low_frequency_translation");
+                                        to_hide.push("high_frequency_translation");
+                                        to_hide.push("projection");
+                                        to_hide.push("blend_sharpness");
+                                        to_hide.push("voronoi_feature");
+                                        to_hide.push("distance_metric");
+                                        to_hide.push("jitter");
                                     }
                                     _ => {
                                         to_hide.push("scale");
@@ -142,6 +303,16 @@ impl NodeCallbacks for ProceduralTextureCallbacks {
                                         } else {
                                             to_hide.push("hue_rotation_angles");
                                         }
+                                        to_hide.push("image_index");
+                                        to_hide.push("grid_slices");
+                                        to_hide.push("projection");
+                                        to_hide.push("coordinate_space");
+                                        to_hide.push("warp_strength");
+                                        to_hide.push("warp_octaves");
+                                        to_hide.push("blend_sharpness");
+                                        to_hide.push("voronoi_feature");
+                                        to_hide.push("distance_metric");
+                                        to_hide.push("jitter");
                                     }
                                 }
                             }
@@ -157,6 +328,29 @@ impl NodeCallbacks for ProceduralTextureCallbacks {
                             }
                             _ => {}
                         },
+                        "use_colour_ramp" => match input_param.value() {
+                            NodeValueType::Bool { ref value } => {
+                                if *value.value() {
+                                    to_show.push("colour_ramp");
+                                } else {
+                                    to_hide.push("colour_ramp");
+                                }
+                            }
+                            _ => {}
+                        },
+                        "projection" => match input_param.value() {
+                            NodeValueType::ComboBox { ref value } => {
+                                match value.as_enum::<TextureProjection>() {
+                                    Ok(TextureProjection::Triplanar) => {
+                                        to_show.push("blend_sharpness");
+                                    }
+                                    _ => {
+                                        to_hide.push("blend_sharpness");
+                                    }
+                                }
+                            }
+                            _ => {}
+                        },
                         _ => {}
                     }
                 }
@@ -0,0 +1,75 @@
+// Copyright (c) 2024, Owen Bulka
+// All rights reserved.
+// This source code is licensed under the BSD-style license found in the
+// LICENSE file in the root directory of this source tree.
+
+use egui_node_graph::NodeId;
+
+use damascus_core::scene;
+
+use super::{super::NodeGraphResponse, Graph, NodeCallbacks, NodeValueType};
+
+#[derive(Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct SceneCallbacks;
+
+impl NodeCallbacks for SceneCallbacks {
+    fn input_value_changed(
+        &self,
+        graph: &mut Graph,
+        node_id: NodeId,
+        input_name: &String,
+    ) -> Vec<NodeGraphResponse> {
+        if input_name != "atmosphere_density" {
+            return Vec::new();
+        }
+        if let Some(node) = graph.nodes.get(node_id) {
+            let mut to_hide = vec![];
+            let mut to_show = vec![];
+            if let Ok(input_id) = node.get_input(input_name) {
+                if let Some(input_param) = graph.inputs.get(input_id) {
+                    match input_param.value() {
+                        NodeValueType::ComboBox { ref value } => {
+                            match value.as_enum::<scene::AtmosphereDensity>() {
+                                Ok(scene::AtmosphereDensity::HeightFog) => {
+                                    to_show.push("fog_base_height");
+                                    to_show.push("fog_falloff");
+                                    to_hide.push("aerosol_density");
+                                    to_hide.push("aerosol_falloff");
+                                }
+                                Ok(scene::AtmosphereDensity::LayeredSky) => {
+                                    to_show.push("fog_base_height");
+                                    to_show.push("fog_falloff");
+                                    to_show.push("aerosol_density");
+                                    to_show.push("aerosol_falloff");
+                                }
+                                _ => {
+                                    to_hide.push("fog_base_height");
+                                    to_hide.push("fog_falloff");
+                                    to_hide.push("aerosol_density");
+                                    to_hide.push("aerosol_falloff");
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            for input_name in to_hide.iter() {
+                if let Ok(input_id) = node.get_input(input_name) {
+                    if let Some(input_param) = graph.inputs.get_mut(input_id) {
+                        self.hide_input(&mut input_param.value)
+                    }
+                }
+            }
+            for input_name in to_show.iter() {
+                if let Ok(input_id) = node.get_input(input_name) {
+                    if let Some(input_param) = graph.inputs.get_mut(input_id) {
+                        self.show_input(&mut input_param.value)
+                    }
+                }
+            }
+        }
+        Vec::new()
+    }
+}
@@ -18,6 +18,7 @@ pub enum NodeDataType {
     // Base types
     Bool,
     BVec3,
+    ColourRamp,
     ComboBox,
     Integer,
     UnsignedInteger,
@@ -62,6 +63,7 @@ impl DataTypeTrait<NodeGraphState> for NodeDataType {
         Cow::Borrowed(match self {
             NodeDataType::Bool => "boolean",
             NodeDataType::BVec3 => "3d boolean vector",
+            NodeDataType::ColourRamp => "colour ramp",
             NodeDataType::ComboBox => "combo box",
             NodeDataType::Integer => "integer",
             NodeDataType::UnsignedInteger => "unsigned integer",
@@ -3,13 +3,17 @@
 // This source code is licensed under the BSD-style license found in the
 // LICENSE file in the root directory of this source tree.
 
-use std::borrow::Cow;
+use std::{borrow::Cow, cell::RefCell};
 
 use egui_node_graph;
 use indoc::indoc;
 use strum::{EnumIter, IntoEnumIterator};
 
-use damascus_core::{geometry, lights, materials, renderers::ray_marcher, scene, textures};
+use damascus_core::{
+    geometry, lights, materials,
+    renderers::{ray_marcher, texture_baker},
+    scene, textures,
+};
 
 use super::{Graph, NodeGraphResponse, NodeGraphState};
 
@@ -18,13 +22,16 @@ mod data_type;
 mod node_data;
 pub mod value_type;
 
-use callbacks::{LightCallbacks, NodeCallbacks, PrimitiveCallbacks, ProceduralTextureCallbacks};
+use callbacks::{
+    LightCallbacks, MaterialCallbacks, NodeCallbacks, PrimitiveCallbacks,
+    ProceduralTextureCallbacks, SceneCallbacks,
+};
 pub use data_type::NodeDataType;
 pub use node_data::NodeData;
 use value_type::{
-    BVec3, Bool, Camera, Collapsible, Colour, ComboBox, Filepath, Float, Lights, Mat4, Material,
-    NodeValueType, Primitives, ProceduralTexture, RangedInput, Scene, Texture, UIData, UIInput,
-    UVec3, UnsignedInteger, Vec3, Vec4,
+    BVec3, Bool, Camera, Collapsible, Colour, ColourRamp, ComboBox, Filepath, Float, Lights, Mat4,
+    Material, NodeValueType, Primitives, ProceduralTexture, RangedInput, Scene, Texture, UIData,
+    UIInput, UVec3, UnsignedInteger, Vec2, Vec3, Vec4,
 };
 
 /// NodeTemplate is a mechanism to define node templates. It's what the graph
@@ -41,6 +48,7 @@ pub enum NodeTemplate {
     Primitive,
     ProceduralTexture,
     RayMarcher,
+    Scatter,
     Scene,
     Texture,
 }
@@ -54,12 +62,16 @@ impl NodeCallbacks for NodeTemplate {
     ) -> Vec<NodeGraphResponse> {
         match self {
             NodeTemplate::Light => LightCallbacks.input_value_changed(graph, node_id, input_name),
+            NodeTemplate::Material => {
+                MaterialCallbacks.input_value_changed(graph, node_id, input_name)
+            }
             NodeTemplate::Primitive => {
                 PrimitiveCallbacks.input_value_changed(graph, node_id, input_name)
             }
             NodeTemplate::ProceduralTexture => {
                 ProceduralTextureCallbacks.input_value_changed(graph, node_id, input_name)
             }
+            NodeTemplate::Scene => SceneCallbacks.input_value_changed(graph, node_id, input_name),
             _ => Vec::new(),
         }
     }
@@ -105,6 +117,7 @@ impl egui_node_graph::NodeTemplateTrait for NodeTemplate {
             NodeTemplate::Primitive => "primitive",
             NodeTemplate::ProceduralTexture => "procedural texture",
             NodeTemplate::RayMarcher => "ray marcher",
+            NodeTemplate::Scatter => "scatter",
             NodeTemplate::Scene => "scene",
             NodeTemplate::Texture => "texture",
         })
@@ -117,7 +130,10 @@ impl egui_node_graph::NodeTemplateTrait for NodeTemplate {
     }
 
     fn user_data(&self, _user_state: &mut Self::UserState) -> Self::NodeData {
-        NodeData { template: *self }
+        NodeData {
+            template: *self,
+            scatter_cache: RefCell::default(),
+        }
     }
 
     fn build_node(
@@ -151,6 +167,16 @@ impl egui_node_graph::NodeTemplateTrait for NodeTemplate {
                 true,
             );
         };
+        let input_colour_ramp = |graph: &mut Graph, name: &str, default: ColourRamp| {
+            graph.add_input_param(
+                node_id,
+                name.to_string(),
+                NodeDataType::ColourRamp,
+                NodeValueType::ColourRamp { value: default },
+                egui_node_graph::InputParamKind::ConstantOnly,
+                true,
+            );
+        };
         let input_combo_box = |graph: &mut Graph, name: &str, default: ComboBox| {
             graph.add_input_param(
                 node_id,
@@ -211,16 +237,16 @@ impl egui_node_graph::NodeTemplateTrait for NodeTemplate {
                 true,
             );
         };
-        // let input_vector2 = |graph: &mut Graph, name: &str, default: Vec2| {
-        //     graph.add_input_param(
-        //         node_id,
-        //         name.to_string(),
-        //         NodeDataType::Vec2,
-        //         NodeValueType::Vec2 { value: default },
-        //         egui_node_graph::InputParamKind::ConstantOnly,
-        //         true,
-        //     );
-        // };
+        let input_vector2 = |graph: &mut Graph, name: &str, default: Vec2| {
+            graph.add_input_param(
+                node_id,
+                name.to_string(),
+                NodeDataType::Vec2,
+                NodeValueType::Vec2 { value: default },
+                egui_node_graph::InputParamKind::ConstantOnly,
+                true,
+            );
+        };
         let input_vector3 = |graph: &mut Graph, name: &str, default: Vec3| {
             graph.add_input_param(
                 node_id,
@@ -335,7 +361,7 @@ impl egui_node_graph::NodeTemplateTrait for NodeTemplate {
                 true,
             );
         };
-        let _input_texture = |graph: &mut Graph, name: &str, default: Texture| {
+        let input_texture = |graph: &mut Graph, name: &str, default: Texture| {
             graph.add_input_param(
                 node_id,
                 name.to_string(),
@@ -395,15 +421,25 @@ impl egui_node_graph::NodeTemplateTrait for NodeTemplate {
                     Vec3::from_vec3(glam::Vec3::ZERO)
                         .with_ui_data(UIData::default().with_tooltip("The rotation of this axis.")),
                 );
+                input_vector3(
+                    graph,
+                    "scale",
+                    Vec3::from_vec3(glam::Vec3::ONE)
+                        .with_ui_data(UIData::default().with_tooltip(indoc! {
+                            "The scale of this axis along each of its axes.\n
+                            Non-uniform scales are supported, but the further
+                            they are from uniform, the more steps will be
+                            needed to render primitives using this axis."
+                        }))
+                        .with_range(0.01..=f32::MAX),
+                );
                 input_float(
                     graph,
                     "uniform_scale",
                     Float::new(1.)
-                        .with_ui_data(UIData::default().with_tooltip(indoc! {
-                            "The uniform scale of this axis.\n
-                            We use uniform scale because the signed distance 
-                            fields cannot have their individual axes scaled."
-                        }))
+                        .with_ui_data(UIData::default().with_tooltip(
+                            "The uniform scale of this axis, which multiplies the scale.",
+                        ))
                         .with_range(0.01..=10.0),
                 );
                 output_matrix4(graph, "out");
@@ -655,6 +691,58 @@ impl egui_node_graph::NodeTemplateTrait for NodeTemplate {
                             "Texture that affects the specular roughness of this material."
                         })),
                 );
+                input_float(
+                    graph,
+                    "specular_anisotropy",
+                    Float::new(default_material.specular_anisotropy)
+                        .with_ui_data(UIData::default().with_tooltip(indoc! {
+                            "The amount that specular highlights are stretched along
+                            the tangent of the surface, as with brushed metal."
+                        }))
+                        .with_range(0.0..=1.),
+                );
+                input_float(
+                    graph,
+                    "specular_rotation",
+                    Float::new(default_material.specular_rotation)
+                        .with_ui_data(UIData::default().with_tooltip(indoc! {
+                            "The rotation, in degrees, of the tangent about the
+                            surface normal."
+                        }))
+                        .with_range(-180.0..=180.),
+                );
+                input_combo_box(
+                    graph,
+                    "tangent_type",
+                    ComboBox::from_enum::<materials::TangentType>(default_material.tangent_type)
+                        .with_ui_data(UIData::default().with_tooltip(indoc! {
+                            "How the tangent used by anisotropic specular
+                            reflections is computed.\n
+                            \tLocalAxis: The tangent_axis, in the primitive's
+                            local space, projected onto the surface.\n
+                            \tTextureGradient: The gradient of the tangent_texture,
+                            projected onto the surface."
+                        })),
+                );
+                input_vector3(
+                    graph,
+                    "tangent_axis",
+                    Vec3::from_vec3(default_material.tangent_axis).with_ui_data(
+                        UIData::default().with_tooltip(indoc! {
+                            "The axis, in the primitive's local space, that
+                            the tangent will follow."
+                        }),
+                    ),
+                );
+                input_procedural_texture(
+                    graph,
+                    "tangent_texture",
+                    ProceduralTexture::new(default_material.tangent_texture).with_ui_data(
+                        UIData::default().with_tooltip(indoc! {
+                            "Texture whose gradient the tangent will follow."
+                        }),
+                    ),
+                );
                 input_vector3(
                     graph,
                     "specular_colour",
@@ -673,6 +761,38 @@ impl egui_node_graph::NodeTemplateTrait for NodeTemplate {
                         }),
                     ),
                 );
+                input_float(
+                    graph,
+                    "film_thickness",
+                    Float::new(default_material.film_thickness)
+                        .with_ui_data(UIData::default().with_tooltip(indoc! {
+                            "The thickness, in nanometres, of a thin film coating
+                            the material. Light reflected off of the film will
+                            interfere, creating iridescence like that of soap
+                            bubbles and oil slicks. A thickness of 0 disables
+                            the film."
+                        }))
+                        .with_range(0.0..=1000.),
+                );
+                input_procedural_texture(
+                    graph,
+                    "film_thickness_texture",
+                    ProceduralTexture::new(default_material.film_thickness_texture).with_ui_data(
+                        UIData::default().with_tooltip(indoc! {
+                            "Texture that affects the film thickness of this material."
+                        }),
+                    ),
+                );
+                input_float(
+                    graph,
+                    "film_refractive_index",
+                    Float::new(default_material.film_refractive_index)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip("The index of refraction of the thin film."),
+                        )
+                        .with_range(1.0..=3.),
+                );
                 input_float(
                     graph,
                     "transmissive_probability",
@@ -780,6 +900,21 @@ impl egui_node_graph::NodeTemplateTrait for NodeTemplate {
                         }),
                     ),
                 );
+                input_float(
+                    graph,
+                    "abbe_number",
+                    Float::new(default_material.abbe_number)
+                        .with_ui_data(UIData::default().with_tooltip(indoc! {
+                            "The Abbe number of the material, which determines how
+                            much the refractive index varies with wavelength.
+                            Lower values disperse light more, for example
+                            diamond is around 55 and flint glass around 30.
+                            A value of 0 disables dispersion.\n
+                            Only used when spectral rendering is enabled on
+                            the ray marcher."
+                        }))
+                        .with_range(0.0..=100.),
+                );
                 input_float(
                     graph,
                     "scattering_coefficient",
@@ -806,6 +941,31 @@ impl egui_node_graph::NodeTemplateTrait for NodeTemplate {
                             "Texture that affects the scattering colour of this material."
                         })),
                 );
+                input_float(
+                    graph,
+                    "density",
+                    Float::new(default_material.density)
+                        .with_ui_data(UIData::default().with_tooltip(indoc! {
+                            "The density of the medium inside the material, which
+                            scales both its extinction and scattering."
+                        }))
+                        .with_range(0.0..=10.),
+                );
+                input_procedural_texture(
+                    graph,
+                    "density_texture",
+                    ProceduralTexture::new(default_material.density_texture).with_ui_data(
+                        UIData::default().with_tooltip(indoc! {
+                            "Texture that varies the density of the medium inside
+                            this material, such as noise for clouds and smoke or an
+                            image grid for a volume. The texture is clamped to
+                            [0, 1] and scales the density.
+
+                            Light is only tracked through the medium if the ray
+                            can enter it, so primitives must be transmissive."
+                        }),
+                    ),
+                );
                 output_material(graph, "out");
             }
             NodeTemplate::Primitive => {
@@ -1199,6 +1359,190 @@ impl egui_node_graph::NodeTemplateTrait for NodeTemplate {
                         .with_range(0.01..=2.),
                 );
 
+                // Triply Periodic Minimal Surface Dimensions
+                input_float(
+                    graph,
+                    "period",
+                    Float::new(1.)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip(indoc! {
+                                    "The distance over which the surface repeats.
+                                    The surface is infinite, so make this primitive
+                                    the child of a bounding volume to limit its extent."
+                                })
+                                .with_hidden(),
+                        )
+                        .with_range(0.01..=10.),
+                );
+
+                // Superellipsoid Dimensions
+                input_float(
+                    graph,
+                    "east_west_exponent",
+                    Float::new(0.5)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip(indoc! {
+                                    "The squareness of the cross sections in the
+                                    xz-plane. 1 is round, less than 1 is boxy, and
+                                    greater than 1 is pinched."
+                                })
+                                .with_hidden(),
+                        )
+                        .with_range(0.01..=4.),
+                );
+                input_float(
+                    graph,
+                    "north_south_exponent",
+                    Float::new(0.5)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip(indoc! {
+                                    "The squareness of the cross sections along the
+                                    y-axis. 1 is round, less than 1 is boxy, and
+                                    greater than 1 is pinched."
+                                })
+                                .with_hidden(),
+                        )
+                        .with_range(0.01..=4.),
+                );
+
+                // Hybrid Fractal Dimensions
+                input_uint(
+                    graph,
+                    "box_folds_per_cycle",
+                    UnsignedInteger::new(2)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip(indoc! {
+                                    "The number of mandelbox iterations to compute at
+                                    the start of each cycle of the sequence."
+                                })
+                                .with_hidden(),
+                        )
+                        .with_range(0..=10),
+                );
+                input_uint(
+                    graph,
+                    "bulb_iterations_per_cycle",
+                    UnsignedInteger::new(1)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip(indoc! {
+                                    "The number of mandelbulb iterations to compute
+                                    after the mandelbox iterations in each cycle of
+                                    the sequence."
+                                })
+                                .with_hidden(),
+                        )
+                        .with_range(0..=10),
+                );
+
+                // Quaternion Julia Dimensions
+                input_vector4(
+                    graph,
+                    "julia_constant",
+                    Vec4::from_vec4(glam::Vec4::new(-0.2, 0.6, 0.2, 0.2)).with_ui_data(
+                        UIData::default()
+                            .with_tooltip(indoc! {
+                                "The quaternion constant (real, i, j, k) added on each
+                                iteration, which determines the shape of the set."
+                            })
+                            .with_hidden(),
+                    ),
+                );
+                input_float(
+                    graph,
+                    "escape_radius",
+                    Float::new(4.)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip(
+                                    "Stop iterating once the position is this far from the origin.",
+                                )
+                                .with_hidden(),
+                        )
+                        .with_range(2.0..=16.),
+                );
+
+                // Kaleidoscopic IFS Dimensions
+                input_float(
+                    graph,
+                    "fractal_scale",
+                    Float::new(2.)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip(indoc! {
+                                    "The amount to scale the position about the
+                                    offset after each fold.",
+                                })
+                                .with_hidden(),
+                        )
+                        .with_range(1.0..=4.),
+                );
+                input_vector3(
+                    graph,
+                    "first_fold_plane",
+                    Vec3::from_vec3(default_primitive.fold_planes.x_axis).with_ui_data(
+                        UIData::default()
+                            .with_tooltip(indoc! {
+                                "The normal of the first plane, through the origin,
+                                to fold the position across on each iteration.
+                                A zero vector disables this fold."
+                            })
+                            .with_hidden(),
+                    ),
+                );
+                input_vector3(
+                    graph,
+                    "second_fold_plane",
+                    Vec3::from_vec3(default_primitive.fold_planes.y_axis).with_ui_data(
+                        UIData::default()
+                            .with_tooltip(indoc! {
+                                "The normal of the second plane, through the origin,
+                                to fold the position across on each iteration.
+                                A zero vector disables this fold."
+                            })
+                            .with_hidden(),
+                    ),
+                );
+                input_vector3(
+                    graph,
+                    "third_fold_plane",
+                    Vec3::from_vec3(default_primitive.fold_planes.z_axis).with_ui_data(
+                        UIData::default()
+                            .with_tooltip(indoc! {
+                                "The normal of the third plane, through the origin,
+                                to fold the position across on each iteration.
+                                A zero vector disables this fold."
+                            })
+                            .with_hidden(),
+                    ),
+                );
+                input_vector3(
+                    graph,
+                    "fractal_rotation_angles",
+                    Vec3::from_vec3(default_primitive.fractal_rotation_angles).with_ui_data(
+                        UIData::default()
+                            .with_tooltip(
+                                "The rotation, in degrees, to apply after folding on each iteration.",
+                            )
+                            .with_hidden(),
+                    ),
+                );
+                input_vector3(
+                    graph,
+                    "fractal_offset",
+                    Vec3::from_vec3(default_primitive.fractal_offset).with_ui_data(
+                        UIData::default()
+                            .with_tooltip(
+                                "The point to scale the position about on each iteration.",
+                            )
+                            .with_hidden(),
+                    ),
+                );
+
                 input_matrix4(
                     graph,
                     "world_matrix",
@@ -1259,44 +1603,281 @@ impl egui_node_graph::NodeTemplateTrait for NodeTemplate {
                             .with_hidden(),
                     ),
                 );
-                input_bool(
+                input_uint(
                     graph,
-                    "bounding_volume",
-                    Bool::new(default_primitive.bounding_volume).with_ui_data(
-                        UIData::default().with_tooltip(indoc! {
-                            "If enabled, this object will act as a bounding volume
-                            for all its children. This means that until a ray hits
-                            the bounding volume, none of the child object's signed
-                            distance fields will be computed. This can vastly
-                            improve performance, especially when many complex
-                            objects are far from the camera. This option does
-                            not always play well with lighting effects that depend
-                            on the number of iterations in the computation such
-                            as 'ambient occlusion' and 'softened shadows' due
-                            to the variation near the surface of the bounding object."
-                        }),
-                    ),
+                    "polar_segments",
+                    UnsignedInteger::new(default_primitive.polar_segments)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip("The number of segments to repeat the object in.")
+                                .with_hidden(),
+                        )
+                        .with_range(1..=64),
                 );
                 input_combo_box(
                     graph,
-                    "blend_type",
-                    ComboBox::from_enum::<geometry::BlendType>(default_primitive.blend_type)
-                        .with_ui_data(UIData::default().with_tooltip(indoc! {
-                            "The type of interaction this object will have with its children.\n
-                            \tUnion: All objects will appear as normal.\n
-                            \tSubtraction: This object will be subtracted from all of its\n
-                            \t\tchildren, leaving holes.\n
-                            \tIntersection: Only the region where this object and its\n
-                            \t\tchildren overlap will remain.\n
-                            \tSmooth Union: All children will smoothly blend together\n
-                            \t\twith this object according to the 'blend strength'.\n
-                            \tSmooth Subtraction:This object will be subtracted from all\n
-                            \t\tof its children,  leaving holes that are smoothed\n
-                            \t\taccording to the 'blend strength'.\n
+                    "polar_axis",
+                    ComboBox::from_enum::<geometry::Axis>(default_primitive.polar_axis)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip(indoc! {
+                                    "The axis to repeat the object around. The object
+                                    is offset along the next axis (x to y, y to z,
+                                    z to x)."
+                                })
+                                .with_hidden(),
+                        ),
+                );
+                input_float(
+                    graph,
+                    "polar_radius",
+                    Float::new(default_primitive.polar_radius)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip("The distance to offset the object from the axis.")
+                                .with_hidden(),
+                        )
+                        .with_range(0.0..=10.),
+                );
+                input_bool(
+                    graph,
+                    "polar_mirror",
+                    Bool::new(default_primitive.polar_mirror).with_ui_data(
+                        UIData::default()
+                            .with_tooltip(indoc! {
+                                "Mirror every other segment. Use an even number of
+                                segments to avoid a seam."
+                            })
+                            .with_hidden(),
+                    ),
+                );
+                input_bool(
+                    graph,
+                    "cell_variation",
+                    Bool::new(default_primitive.cell_variation).with_ui_data(
+                        UIData::default()
+                            .with_tooltip(indoc! {
+                                "Randomly vary the object in each repetition cell.
+                                Keep the variations small enough that each copy
+                                stays within its cell."
+                            })
+                            .with_hidden(),
+                    ),
+                );
+                input_float(
+                    graph,
+                    "cell_scale_variation",
+                    Float::new(default_primitive.cell_scale_variation)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip(indoc! {
+                                    "The fraction by which the scale of each cell's copy
+                                    can randomly grow or shrink."
+                                })
+                                .with_hidden(),
+                        )
+                        .with_range(0.0..=0.99),
+                );
+                input_uint(
+                    graph,
+                    "cell_scale_seed",
+                    UnsignedInteger::new(default_primitive.cell_scale_seed)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip("The seed used to randomise the scale of each cell.")
+                                .with_hidden(),
+                        )
+                        .with_range(0..=1000),
+                );
+                input_vector3(
+                    graph,
+                    "cell_rotation_variation",
+                    Vec3::from_vec3(default_primitive.cell_rotation_variation).with_ui_data(
+                        UIData::default()
+                            .with_tooltip(indoc! {
+                                "The maximum angle, in degrees, by which each cell's
+                                copy can randomly rotate around the x, y, and z axes."
+                            })
+                            .with_hidden(),
+                    ),
+                );
+                input_uint(
+                    graph,
+                    "cell_rotation_seed",
+                    UnsignedInteger::new(default_primitive.cell_rotation_seed)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip(
+                                    "The seed used to randomise the rotation of each cell.",
+                                )
+                                .with_hidden(),
+                        )
+                        .with_range(0..=1000),
+                );
+                input_vector3(
+                    graph,
+                    "cell_jitter",
+                    Vec3::from_vec3(default_primitive.cell_jitter).with_ui_data(
+                        UIData::default()
+                            .with_tooltip(indoc! {
+                                "The maximum distance each cell's copy can randomly
+                                be offset along the x, y, and z axes."
+                            })
+                            .with_hidden(),
+                    ),
+                );
+                input_uint(
+                    graph,
+                    "cell_jitter_seed",
+                    UnsignedInteger::new(default_primitive.cell_jitter_seed)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip("The seed used to randomise the offset of each cell.")
+                                .with_hidden(),
+                        )
+                        .with_range(0..=1000),
+                );
+                input_float(
+                    graph,
+                    "cell_hue_variation",
+                    Float::new(default_primitive.cell_hue_variation)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip(indoc! {
+                                    "The fraction of a full turn by which the hue of the
+                                    diffuse colour can randomly shift in each cell."
+                                })
+                                .with_hidden(),
+                        )
+                        .with_range(0.0..=1.),
+                );
+                input_uint(
+                    graph,
+                    "cell_hue_seed",
+                    UnsignedInteger::new(default_primitive.cell_hue_seed)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip("The seed used to randomise the hue of each cell.")
+                                .with_hidden(),
+                        )
+                        .with_range(0..=1000),
+                );
+                input_float(
+                    graph,
+                    "cell_value_variation",
+                    Float::new(default_primitive.cell_value_variation)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip(indoc! {
+                                    "The fraction by which the brightness of the diffuse
+                                    colour can randomly change in each cell."
+                                })
+                                .with_hidden(),
+                        )
+                        .with_range(0.0..=1.),
+                );
+                input_uint(
+                    graph,
+                    "cell_value_seed",
+                    UnsignedInteger::new(default_primitive.cell_value_seed)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip("The seed used to randomise the value of each cell.")
+                                .with_hidden(),
+                        )
+                        .with_range(0..=1000),
+                );
+                input_float(
+                    graph,
+                    "cell_visibility",
+                    Float::new(default_primitive.cell_visibility)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip(indoc! {
+                                    "The probability that the copy in each cell is
+                                    visible."
+                                })
+                                .with_hidden(),
+                        )
+                        .with_range(0.0..=1.),
+                );
+                input_uint(
+                    graph,
+                    "cell_visibility_seed",
+                    UnsignedInteger::new(default_primitive.cell_visibility_seed)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip(
+                                    "The seed used to randomise the visibility of each cell.",
+                                )
+                                .with_hidden(),
+                        )
+                        .with_range(0..=1000),
+                );
+                input_bool(
+                    graph,
+                    "bounding_volume",
+                    Bool::new(default_primitive.bounding_volume).with_ui_data(
+                        UIData::default().with_tooltip(indoc! {
+                            "If enabled, this object will act as a bounding volume
+                            for all its children. This means that until a ray hits
+                            the bounding volume, none of the child object's signed
+                            distance fields will be computed. This can vastly
+                            improve performance, especially when many complex
+                            objects are far from the camera. This option does
+                            not always play well with lighting effects that depend
+                            on the number of iterations in the computation such
+                            as 'ambient occlusion' and 'softened shadows' due
+                            to the variation near the surface of the bounding object."
+                        }),
+                    ),
+                );
+                input_combo_box(
+                    graph,
+                    "blend_type",
+                    ComboBox::from_enum::<geometry::BlendType>(default_primitive.blend_type)
+                        .with_ui_data(UIData::default().with_tooltip(indoc! {
+                            "The type of interaction this object will have with its children.\n
+                            \tUnion: All objects will appear as normal.\n
+                            \tSubtraction: This object will be subtracted from all of its\n
+                            \t\tchildren, leaving holes.\n
+                            \tIntersection: Only the region where this object and its\n
+                            \t\tchildren overlap will remain.\n
+                            \tSmooth Union: All children will smoothly blend together\n
+                            \t\twith this object according to the 'blend strength'.\n
+                            \tSmooth Subtraction:This object will be subtracted from all\n
+                            \t\tof its children,  leaving holes that are smoothed\n
+                            \t\taccording to the 'blend strength'.\n
                             \tSmooth Intersection: Only the region where this object\n
                             \t\tand its children overlap will remain, and the remaining\n
                             \t\tregions will be smoothed according to the 'blend\n
-                            \t\tstrength'.",
+                            \t\tstrength'.\n
+                            \tExclusive Or: Only the regions where either this object\n
+                            \t\tor its children, but not both, are will remain.\n
+                            \tGroove: The shape of the children will be cut into this\n
+                            \t\tobject as a groove 'blend strength' deep and 'blend\n
+                            \t\twidth' wide.\n
+                            \tTongue: The shape of the children will be raised from\n
+                            \t\tthis object as a tongue 'blend strength' high and\n
+                            \t\t'blend width' wide.\n
+                            \tEngrave: The outline of the children will be engraved\n
+                            \t\tinto this object 'blend strength' deep.",
+                        })),
+                );
+                input_combo_box(
+                    graph,
+                    "blend_kernel",
+                    ComboBox::from_enum::<geometry::BlendKernel>(default_primitive.blend_kernel)
+                        .with_ui_data(UIData::default().with_tooltip(indoc! {
+                            "The shape of the blended region in unions, subtractions,
+                            and intersections.\n
+                            \tPolynomial: A smooth, rounded blend.\n
+                            \tExponential: A smooth blend that also affects regions\n
+                            \t\tfar from where the objects meet.\n
+                            \tCircular: A circular fillet.\n
+                            \tChamfer: A 45 degree chamfer.\n
+                            \tStairs: A staircase with 'blend steps' steps.\n
+                            \tColumns: A row of 'blend steps' columns."
                         })),
                 );
                 input_float(
@@ -1307,6 +1888,28 @@ impl egui_node_graph::NodeTemplateTrait for NodeTemplate {
                             .with_tooltip("The amount to blend with this primitive's children."),
                     ),
                 );
+                input_uint(
+                    graph,
+                    "blend_steps",
+                    UnsignedInteger::new(default_primitive.blend_steps)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip("The number of stairs or columns in the blend.")
+                                .with_hidden(),
+                        )
+                        .with_range(1..=16),
+                );
+                input_float(
+                    graph,
+                    "blend_width",
+                    Float::new(default_primitive.blend_width)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip("The width of grooves and tongues.")
+                                .with_hidden(),
+                        )
+                        .with_range(0.0..=1.),
+                );
                 input_bool_vector3(
                     graph,
                     "mirror",
@@ -1354,10 +1957,169 @@ impl egui_node_graph::NodeTemplateTrait for NodeTemplate {
                             .with_hidden(),
                     ),
                 );
+                input_bool(
+                    graph,
+                    "twist",
+                    Bool::new(default_primitive.twist).with_ui_data(
+                        UIData::default().with_tooltip("Enable the twisting of the object."),
+                    ),
+                );
+                input_combo_box(
+                    graph,
+                    "twist_axis",
+                    ComboBox::from_enum::<geometry::Axis>(default_primitive.twist_axis)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip("The axis to twist the object around.")
+                                .with_hidden(),
+                        ),
+                );
+                input_float(
+                    graph,
+                    "twist_rate",
+                    Float::new(default_primitive.twist_rate)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip(indoc! {
+                                    "The angle, in degrees, to twist the object by
+                                    per unit distance along the twist axis."
+                                })
+                                .with_hidden(),
+                        )
+                        .with_range(-360.0..=360.),
+                );
+                input_bool(
+                    graph,
+                    "bend",
+                    Bool::new(default_primitive.bend).with_ui_data(
+                        UIData::default().with_tooltip("Enable the bending of the object."),
+                    ),
+                );
+                input_combo_box(
+                    graph,
+                    "bend_axis",
+                    ComboBox::from_enum::<geometry::Axis>(default_primitive.bend_axis)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip(indoc! {
+                                    "The axis to bend the object around. The next axis
+                                    (x to y, y to z, z to x) will follow the arc."
+                                })
+                                .with_hidden(),
+                        ),
+                );
+                input_float(
+                    graph,
+                    "bend_radius",
+                    Float::new(default_primitive.bend_radius)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip(indoc! {
+                                    "The radius of the arc the object is bent along.
+                                    Negative values bend in the opposite direction."
+                                })
+                                .with_hidden(),
+                        )
+                        .with_range(-10.0..=10.),
+                );
+                input_bool(
+                    graph,
+                    "taper",
+                    Bool::new(default_primitive.taper).with_ui_data(
+                        UIData::default().with_tooltip("Enable the tapering of the object."),
+                    ),
+                );
+                input_combo_box(
+                    graph,
+                    "taper_axis",
+                    ComboBox::from_enum::<geometry::Axis>(default_primitive.taper_axis)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip("The axis to taper the object along.")
+                                .with_hidden(),
+                        ),
+                );
+                input_float(
+                    graph,
+                    "taper_rate",
+                    Float::new(default_primitive.taper_rate)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip(indoc! {
+                                    "The amount the cross section of the object is
+                                    scaled by per unit distance along the taper axis."
+                                })
+                                .with_hidden(),
+                        )
+                        .with_range(-2.0..=2.),
+                );
+                input_procedural_texture(
+                    graph,
+                    "displacement_texture",
+                    ProceduralTexture::new(default_primitive.displacement_texture).with_ui_data(
+                        UIData::default()
+                            .with_tooltip("Texture that displaces the surface along its normal."),
+                    ),
+                );
+                input_float(
+                    graph,
+                    "displacement_amplitude",
+                    Float::new(default_primitive.displacement_amplitude)
+                        .with_ui_data(UIData::default().with_tooltip(indoc! {
+                            "The distance to displace the surface by where the
+                            displacement texture is one. Zero disables
+                            displacement."
+                        }))
+                        .with_range(-1.0..=1.),
+                );
+                input_float(
+                    graph,
+                    "displacement_offset",
+                    Float::new(default_primitive.displacement_offset)
+                        .with_ui_data(UIData::default().with_tooltip(indoc! {
+                            "The amount to add to the displacement texture before
+                            it is scaled by the amplitude. An offset of -0.5
+                            centres a texture in the range 0-1 on the surface."
+                        }))
+                        .with_range(-1.0..=1.),
+                );
+                input_float(
+                    graph,
+                    "lipschitz_bound",
+                    Float::new(default_primitive.lipschitz_bound)
+                        .with_ui_data(UIData::default().with_tooltip(indoc! {
+                            "Displaced surfaces are no longer exact distance
+                            fields, so the step size is divided by this value.
+                            Increase it if the displaced surface has holes or
+                            artifacts, at the cost of performance."
+                        }))
+                        .with_range(1.0..=10.),
+                );
                 output_primitive(graph, "out");
             }
             NodeTemplate::ProceduralTexture => {
                 let default_procedural_texture = materials::ProceduralTexture::default();
+                input_procedural_texture(
+                    graph,
+                    "texture",
+                    ProceduralTexture::new(materials::ProceduralTexture::default()).with_ui_data(
+                        UIData::default().with_tooltip(indoc! {
+                            "The texture to blend this texture with. If
+                            disconnected this texture is applied directly to
+                            the colour of the material."
+                        }),
+                    ),
+                );
+                input_procedural_texture(
+                    graph,
+                    "mask",
+                    ProceduralTexture::new(materials::ProceduralTexture::default()).with_ui_data(
+                        UIData::default().with_tooltip(indoc! {
+                            "A texture used to mix between the input texture
+                            and the blended result."
+                        }),
+                    ),
+                );
                 input_combo_box(
                     graph,
                     "texture_type",
@@ -1541,6 +2303,270 @@ impl egui_node_graph::NodeTemplateTrait for NodeTemplate {
                             .with_hidden(),
                     ),
                 );
+                input_uint(
+                    graph,
+                    "image_index",
+                    UnsignedInteger::new(default_procedural_texture.image_index)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip(indoc! {
+                                    "The index of the image in the textures of the
+                                    scene, in the order that the texture nodes are
+                                    chained. The range follows the number of images
+                                    in the scene last viewed."
+                                })
+                                .with_hidden(),
+                        )
+                        .with_range(0..=0),
+                );
+                input_uint(
+                    graph,
+                    "grid_slices",
+                    UnsignedInteger::new(default_procedural_texture.grid_slices)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip(indoc! {
+                                    "The number of slices in the volume, which are
+                                    stacked vertically in the image from top to
+                                    bottom. The volume spans the scale of the
+                                    texture in each direction about the origin."
+                                })
+                                .with_hidden(),
+                        )
+                        .with_range(1..=512),
+                );
+                input_combo_box(
+                    graph,
+                    "projection",
+                    ComboBox::from_enum::<materials::TextureProjection>(
+                        default_procedural_texture.projection,
+                    )
+                    .with_ui_data(
+                        UIData::default()
+                            .with_tooltip(indoc! {
+                                "How the image is projected onto the surface.\n
+                                \tTriplanar: Project along each axis and blend by
+                                the surface normal.\n
+                                \tPlanar: Project along the z-axis.\n
+                                \tSpherical: Wrap a latlong image around the origin.\n
+                                \tCylindrical: Wrap the image around the y-axis."
+                            })
+                            .with_hidden(),
+                    ),
+                );
+                input_combo_box(
+                    graph,
+                    "coordinate_space",
+                    ComboBox::from_enum::<materials::CoordinateSpace>(
+                        default_procedural_texture.coordinate_space,
+                    )
+                    .with_ui_data(
+                        UIData::default()
+                            .with_tooltip(indoc! {
+                                "The space to evaluate the texture in.\n
+                                \tObject: Move with the primitive.\n
+                                \tWorld: Stay fixed as the primitive moves.\n
+                                \tCamera: Move with the camera.\n
+                                \tSpherical: The azimuth and polar angles about
+                                the primitive's origin, normalized to [0, 1], and
+                                the distance from the origin."
+                            })
+                            .with_hidden(),
+                    ),
+                );
+                input_float(
+                    graph,
+                    "warp_strength",
+                    Float::new(default_procedural_texture.warp_strength)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip(indoc! {
+                                    "The distance, relative to the scale of the
+                                    texture, that noise offsets the position the
+                                    texture is evaluated at. This creates swirling,
+                                    flowing patterns."
+                                })
+                                .with_hidden(),
+                        )
+                        .with_range(0.0..=4.),
+                );
+                input_uint(
+                    graph,
+                    "warp_octaves",
+                    UnsignedInteger::new(default_procedural_texture.warp_octaves)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip(indoc! {
+                                    "The number of frequencies to superimpose in
+                                    the noise that warps the texture."
+                                })
+                                .with_hidden(),
+                        )
+                        .with_range(1..=8),
+                );
+                input_float(
+                    graph,
+                    "blend_sharpness",
+                    Float::new(default_procedural_texture.blend_sharpness)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip(indoc! {
+                                    "The sharpness of the transition between the
+                                    projections of a triplanar texture."
+                                })
+                                .with_hidden(),
+                        )
+                        .with_range(1.0..=64.),
+                );
+                input_combo_box(
+                    graph,
+                    "voronoi_feature",
+                    ComboBox::from_enum::<materials::VoronoiFeature>(
+                        default_procedural_texture.voronoi_feature,
+                    )
+                    .with_ui_data(
+                        UIData::default()
+                            .with_tooltip(indoc! {
+                                "The value of the cellular noise.\n
+                                \tF1: The distance to the closest feature point.\n
+                                \tF2: The distance to the second closest feature point.\n
+                                \tF2MinusF1: The difference of the two, which is zero
+                                on the cell borders.\n
+                                \tCellID: A random value per cell."
+                            })
+                            .with_hidden(),
+                    ),
+                );
+                input_combo_box(
+                    graph,
+                    "distance_metric",
+                    ComboBox::from_enum::<materials::DistanceMetric>(
+                        default_procedural_texture.distance_metric,
+                    )
+                    .with_ui_data(
+                        UIData::default()
+                            .with_tooltip(indoc! {
+                                "The metric used to measure the distance to the
+                                feature points, which determines the shape of
+                                the cells."
+                            })
+                            .with_hidden(),
+                    ),
+                );
+                input_float(
+                    graph,
+                    "jitter",
+                    Float::new(default_procedural_texture.jitter)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip(indoc! {
+                                    "The amount the feature points are randomly
+                                    displaced from the centres of their cells. Zero
+                                    gives a regular grid."
+                                })
+                                .with_hidden(),
+                        )
+                        .with_range(0.0..=1.),
+                );
+                input_combo_box(
+                    graph,
+                    "blend_mode",
+                    ComboBox::from_enum::<materials::TextureBlendMode>(
+                        default_procedural_texture.blend_mode,
+                    )
+                    .with_ui_data(UIData::default().with_tooltip(indoc! {
+                        "How this texture is combined with the input texture.\n
+                        \tMultiply: Multiply the input by this texture.\n
+                        \tAdd: Add this texture to the input.\n
+                        \tScreen: Invert, multiply, and invert again, which
+                        brightens the input.\n
+                        \tOverlay: Multiply the dark areas of the input and
+                        screen the light areas.\n
+                        \tMinimum: The minimum of the two textures.\n
+                        \tMaximum: The maximum of the two textures.\n
+                        \tMix: Replace the input with this texture, which mixes
+                        between them when used with a mask."
+                    })),
+                );
+                input_bool(
+                    graph,
+                    "use_colour_ramp",
+                    Bool::new(default_procedural_texture.use_colour_ramp).with_ui_data(
+                        UIData::default().with_tooltip(indoc! {
+                            "Remap the luminance of the blended texture to a
+                            colour ramp."
+                        }),
+                    ),
+                );
+                input_colour_ramp(
+                    graph,
+                    "colour_ramp",
+                    ColourRamp::new(default_procedural_texture.colour_ramp).with_ui_data(
+                        UIData::default()
+                            .with_tooltip(indoc! {
+                                "The gradient that the luminance of the texture
+                                is remapped to. The position of each stop is the
+                                luminance it is mapped from."
+                            })
+                            .with_hidden(),
+                    ),
+                );
+                let default_texture_baker = texture_baker::TextureBaker::default();
+                input_combo_box(
+                    graph,
+                    "bake_projection",
+                    ComboBox::from_enum::<texture_baker::BakeProjection>(
+                        default_texture_baker.projection,
+                    )
+                    .with_ui_data(UIData::default().with_tooltip(indoc! {
+                        "The surface to evaluate the texture on when baking.\n
+                        Planar: The xy-plane, centred at the origin.\n
+                        LatLong: A sphere centred at the origin, unwrapped
+                        with an equirectangular projection.\n
+                        Triplanar: The surface of the bake primitive, as
+                        seen from the six faces of a cube around it."
+                    })),
+                );
+                input_primitive(
+                    graph,
+                    "bake_primitive",
+                    Primitives::new(vec![]).with_ui_data(UIData::default().with_tooltip(indoc! {
+                        "The primitive whose surface is baked when using the
+                        triplanar bake projection. Only the topmost primitive
+                        is used, without its children or siblings."
+                    })),
+                );
+                input_float(
+                    graph,
+                    "bake_size",
+                    Float::new(default_texture_baker.size)
+                        .with_ui_data(UIData::default().with_tooltip(indoc! {
+                            "The half-width of the plane, the radius of the
+                            sphere, or the half-width of the cube around the
+                            primitive to bake."
+                        }))
+                        .with_range(0.01..=100.),
+                );
+                input_uint(
+                    graph,
+                    "bake_width",
+                    UnsignedInteger::new(default_texture_baker.resolution.x)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip("The width of the baked image in pixels."),
+                        )
+                        .with_range(1..=8192),
+                );
+                input_uint(
+                    graph,
+                    "bake_height",
+                    UnsignedInteger::new(default_texture_baker.resolution.y)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip("The height of the baked image in pixels."),
+                        )
+                        .with_range(1..=8192),
+                );
 
                 output_procedural_texture(graph, "out");
             }
@@ -1695,6 +2721,44 @@ impl egui_node_graph::NodeTemplateTrait for NodeTemplate {
                         }),
                     ),
                 );
+                input_bool(
+                    graph,
+                    "spectral_rendering",
+                    Bool::new(default_ray_marcher.spectral_rendering).with_ui_data(
+                        UIData::default().with_tooltip(indoc! {
+                            "Trace each path with a single wavelength of light,
+                            rather than RGB. This allows refractive materials
+                            with an abbe_number to disperse light, at the cost
+                            of more noise."
+                        }),
+                    ),
+                );
+                input_float(
+                    graph,
+                    "noise_threshold",
+                    Float::new(default_ray_marcher.noise_threshold)
+                        .with_ui_data(UIData::default().with_tooltip(indoc! {
+                            "Pixels stop sampling once the estimated relative
+                            error of their luminance is below this threshold,
+                            and the samples of each frame go to the noisiest
+                            pixels. The render stops once every pixel has
+                            converged. Adaptive sampling is disabled if this
+                            is 0."
+                        }))
+                        .with_range(0.0..=0.1),
+                );
+                input_uint(
+                    graph,
+                    "max_samples",
+                    UnsignedInteger::new(default_ray_marcher.max_samples)
+                        .with_ui_data(UIData::default().with_tooltip(indoc! {
+                            "The maximum number of paths to trace through each
+                            pixel. The render stops once every pixel has
+                            reached it, or converged. There is no limit if
+                            this is 0."
+                        }))
+                        .with_range(0..=100000),
+                );
                 input_combo_box(
                     graph,
                     "output_aov",
@@ -1704,11 +2768,115 @@ impl egui_node_graph::NodeTemplateTrait for NodeTemplate {
                             average number of bounces in the red channel,
                             average number of steps in the green channel,
                             and the distance travelled in the blue channel.
-                            Each is displayed as a fraction of the maximums."
+                            Each is displayed as a fraction of the maximums.
+                            The sample count AOV has the number of paths
+                            traced through each pixel, as a fraction of the
+                            max samples, or of the paths per pixel if there
+                            is no maximum."
                         })),
                 );
                 output_ray_marcher(graph, "out");
             }
+            NodeTemplate::Scatter => {
+                let default_scatter = geometry::scatter::Scatter::default();
+                input_primitive(
+                    graph,
+                    "siblings",
+                    Primitives::new(vec![]).with_ui_data(UIData::default().with_tooltip(indoc! {
+                        "The siblings of the scattered primitives.\n
+                        These will be passed through unchanged."
+                    })),
+                );
+                input_primitive(
+                    graph,
+                    "target",
+                    Primitives::new(vec![]).with_ui_data(UIData::default().with_tooltip(indoc! {
+                        "The primitive to scatter over the surface of.\n
+                        Only the first primitive is used, and its
+                        children, repetition, deformations, and
+                        displacement are ignored."
+                    })),
+                );
+                input_primitive(
+                    graph,
+                    "instances",
+                    Primitives::new(vec![]).with_ui_data(UIData::default().with_tooltip(indoc! {
+                        "The primitives to place at each scattered point,
+                        along with their descendants."
+                    })),
+                );
+                input_uint(
+                    graph,
+                    "count",
+                    UnsignedInteger::new(default_scatter.count)
+                        .with_ui_data(UIData::default().with_tooltip(indoc! {
+                            "The number of points to scatter. Fewer may be
+                            placed if the minimum spacing cannot be met."
+                        }))
+                        .with_range(1..=1000),
+                );
+                input_uint(
+                    graph,
+                    "seed",
+                    UnsignedInteger::new(default_scatter.seed)
+                        .with_ui_data(
+                            UIData::default().with_tooltip("The seed of the random placement."),
+                        )
+                        .with_range(0..=1000),
+                );
+                input_float(
+                    graph,
+                    "minimum_spacing",
+                    Float::new(default_scatter.minimum_spacing)
+                        .with_ui_data(
+                            UIData::default().with_tooltip(
+                                "The minimum distance between any two scattered points.",
+                            ),
+                        )
+                        .with_range(0.0..=10.),
+                );
+                input_float(
+                    graph,
+                    "search_radius",
+                    Float::new(default_scatter.search_radius)
+                        .with_ui_data(UIData::default().with_tooltip(indoc! {
+                            "Half the width of the cube, centred on the
+                            target, within which points are scattered."
+                        }))
+                        .with_range(0.01..=100.),
+                );
+                input_bool(
+                    graph,
+                    "align_to_normal",
+                    Bool::new(default_scatter.align_to_normal).with_ui_data(
+                        UIData::default().with_tooltip(indoc! {
+                            "If enabled, the y-axis of each instance will be
+                            aligned to the surface normal of the target."
+                        }),
+                    ),
+                );
+                input_vector2(
+                    graph,
+                    "scale_range",
+                    Vec2::new(default_scatter.scale_range).with_ui_data(
+                        UIData::default().with_tooltip(
+                            "The range of uniform scales to randomly apply to each instance.",
+                        ),
+                    ),
+                );
+                input_vector3(
+                    graph,
+                    "rotation_range",
+                    Vec3::from_vec3(default_scatter.rotation_range).with_ui_data(
+                        UIData::default().with_tooltip(indoc! {
+                            "The maximum rotation, in degrees, to randomly
+                            apply about each axis of each instance, in
+                            either direction."
+                        }),
+                    ),
+                );
+                output_primitive(graph, "out");
+            }
             NodeTemplate::Scene => {
                 let default_scene = scene::Scene::default();
                 input_camera(
@@ -1739,10 +2907,95 @@ impl egui_node_graph::NodeTemplateTrait for NodeTemplate {
                         "The material to apply to the atmosphere (smoke, extinction, hdri, etc.).",
                     )),
                 );
+                input_combo_box(
+                    graph,
+                    "atmosphere_density",
+                    ComboBox::from_enum::<scene::AtmosphereDensity>(
+                        default_scene.atmosphere_density,
+                    )
+                    .with_ui_data(UIData::default().with_tooltip(indoc! {
+                        "How the density of the atmosphere varies throughout the
+                        scene.\n
+                        Constant: The atmosphere is equally dense everywhere.\n
+                        HeightFog: The density falls off exponentially above the
+                        base height.\n
+                        LayeredSky: As with the height fog, with an additional
+                        layer of aerosols that scatter all wavelengths equally
+                        and fall off at their own rate."
+                    })),
+                );
+                input_float(
+                    graph,
+                    "fog_base_height",
+                    Float::new(default_scene.fog_base_height).with_ui_data(
+                        UIData::default()
+                            .with_tooltip(indoc! {
+                                "The height below which the atmosphere has the full
+                                density of its material."
+                            })
+                            .with_hidden(),
+                    ),
+                );
+                input_float(
+                    graph,
+                    "fog_falloff",
+                    Float::new(default_scene.fog_falloff)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip(indoc! {
+                                    "The rate at which the density of the atmosphere
+                                    decreases with height above the base height."
+                                })
+                                .with_hidden(),
+                        )
+                        .with_range(0.0..=10.),
+                );
+                input_float(
+                    graph,
+                    "aerosol_density",
+                    Float::new(default_scene.aerosol_density)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip(indoc! {
+                                    "The scattering coefficient of the aerosols at
+                                    the base height."
+                                })
+                                .with_hidden(),
+                        )
+                        .with_range(0.0..=1.),
+                );
+                input_float(
+                    graph,
+                    "aerosol_falloff",
+                    Float::new(default_scene.aerosol_falloff)
+                        .with_ui_data(
+                            UIData::default()
+                                .with_tooltip(indoc! {
+                                    "The rate at which the density of the aerosols
+                                    decreases with height above the base height."
+                                })
+                                .with_hidden(),
+                        )
+                        .with_range(0.0..=10.),
+                );
+                input_texture(
+                    graph,
+                    "textures",
+                    Texture::new(default_scene.textures).with_ui_data(
+                        UIData::default()
+                            .with_tooltip("The images that image textures can sample."),
+                    ),
+                );
                 output_scene(graph, "out");
             }
             NodeTemplate::Texture => {
                 let default_texture = textures::Texture::default();
+                input_texture(
+                    graph,
+                    "textures",
+                    Texture::new(vec![])
+                        .with_ui_data(UIData::default().with_tooltip("Chain other textures.")),
+                );
                 input_filepath(
                     graph,
                     "filepath",
@@ -3,8 +3,13 @@
 // This source code is licensed under the BSD-style license found in the
 // LICENSE file in the root directory of this source tree.
 
+use std::cell::RefCell;
+
 use eframe::egui;
 use egui_node_graph::{Graph, NodeDataTrait, NodeId, NodeResponse, UserResponseTrait};
+use serde_hashkey::{Key, OrderedFloatPolicy};
+
+use damascus_core::geometry::primitive::Primitive;
 
 use super::{NodeDataType, NodeGraphResponse, NodeGraphState, NodeTemplate, NodeValueType};
 
@@ -14,6 +19,11 @@ use super::{NodeDataType, NodeGraphResponse, NodeGraphState, NodeTemplate, NodeV
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct NodeData {
     pub template: NodeTemplate,
+    /// The primitives last scattered by a scatter node, with the hash of
+    /// the inputs they were scattered from, as scattering is too slow to
+    /// repeat every time the graph is evaluated.
+    #[serde(skip)]
+    pub scatter_cache: RefCell<Option<(Key<OrderedFloatPolicy>, Vec<Primitive>)>>,
 }
 
 impl NodeDataTrait for NodeData {
@@ -67,6 +77,17 @@ impl NodeDataTrait for NodeData {
             }
         }
 
+        if self.template == NodeTemplate::ProceduralTexture
+            && ui
+                .button("🔥 Bake")
+                .on_hover_text("Bake the texture to an EXR or PNG image.")
+                .clicked()
+        {
+            responses.push(NodeResponse::User(
+                NodeGraphResponse::BakeProceduralTexture(node_id),
+            ));
+        }
+
         responses
     }
 }
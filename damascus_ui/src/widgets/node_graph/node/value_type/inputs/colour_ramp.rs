@@ -0,0 +1,133 @@
+// Copyright (c) 2024, Owen Bulka
+// All rights reserved.
+// This source code is licensed under the BSD-style license found in the
+// LICENSE file in the root directory of this source tree.
+
+use eframe::egui;
+use glam;
+use strum::IntoEnumIterator;
+
+use damascus_core::materials;
+
+use super::{UIData, UIInput};
+
+// The number of segments used to draw the preview of the gradient
+const PREVIEW_SEGMENTS: usize = 64;
+
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ColourRamp {
+    value: materials::ColourRamp,
+    ui_data: UIData,
+}
+
+impl ColourRamp {
+    fn show_preview(&self, ui: &mut egui::Ui) {
+        let (rect, _response) = ui.allocate_exact_size(
+            egui::vec2(ui.available_width(), ui.spacing().interact_size.y),
+            egui::Sense::hover(),
+        );
+        let segment_width: f32 = rect.width() / PREVIEW_SEGMENTS as f32;
+        for segment in 0..PREVIEW_SEGMENTS {
+            let colour: glam::Vec3 = self
+                .value
+                .colour_at((segment as f32 + 0.5) / PREVIEW_SEGMENTS as f32);
+            ui.painter().rect_filled(
+                egui::Rect::from_min_size(
+                    egui::pos2(rect.min.x + segment as f32 * segment_width, rect.min.y),
+                    egui::vec2(segment_width, rect.height()),
+                ),
+                0.,
+                egui::Rgba::from_rgb(colour.x, colour.y, colour.z),
+            );
+        }
+    }
+}
+
+impl UIInput<materials::ColourRamp> for ColourRamp {
+    fn new(value: materials::ColourRamp) -> Self {
+        Self {
+            value: value,
+            ..Default::default()
+        }
+    }
+
+    fn show_ui(&mut self, ui: &mut egui::Ui, label: &str) -> bool {
+        let mut has_changed = false;
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                self.create_parameter_label(ui, label);
+                egui::ComboBox::from_id_salt(label)
+                    .selected_text(format!("{}", self.value.interpolation))
+                    .show_ui(ui, |ui| {
+                        for interpolation in materials::RampInterpolation::iter() {
+                            has_changed |= ui
+                                .selectable_value(
+                                    &mut self.value.interpolation,
+                                    interpolation,
+                                    format!("{}", interpolation),
+                                )
+                                .changed();
+                        }
+                    });
+                if ui.button("+").on_hover_text("Add a stop.").clicked() {
+                    let stops: Vec<materials::ColourStop> = self.value.sorted_stops();
+                    // Insert the new stop in the largest gap between stops
+                    let mut position: f32 = 0.5;
+                    let mut largest_gap: f32 = 0.;
+                    for (lower, upper) in stops.iter().zip(stops.iter().skip(1)) {
+                        if upper.position - lower.position > largest_gap {
+                            largest_gap = upper.position - lower.position;
+                            position = 0.5 * (lower.position + upper.position);
+                        }
+                    }
+                    self.value.stops.push(materials::ColourStop::new(
+                        position,
+                        self.value.colour_at(position),
+                    ));
+                    has_changed = true;
+                }
+            });
+
+            self.show_preview(ui);
+
+            let mut stop_to_remove: Option<usize> = None;
+            for (index, stop) in self.value.stops.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    has_changed |= ui
+                        .add(
+                            egui::DragValue::new(&mut stop.position)
+                                .speed(0.01)
+                                .range(0.0..=1.),
+                        )
+                        .changed();
+                    let mut colour: [f32; 3] = stop.colour.to_array();
+                    if ui.color_edit_button_rgb(&mut colour).changed() {
+                        stop.colour = glam::Vec3::from_array(colour);
+                        has_changed = true;
+                    }
+                    if ui.button("🗙").on_hover_text("Remove the stop.").clicked() {
+                        stop_to_remove = Some(index);
+                    }
+                });
+            }
+            if let Some(index) = stop_to_remove {
+                self.value.stops.remove(index);
+                has_changed = true;
+            }
+        });
+        has_changed
+    }
+
+    fn value(&self) -> &materials::ColourRamp {
+        &self.value
+    }
+
+    fn ui_data(&self) -> &UIData {
+        &self.ui_data
+    }
+
+    fn ui_data_mut(&mut self) -> &mut UIData {
+        &mut self.ui_data
+    }
+}
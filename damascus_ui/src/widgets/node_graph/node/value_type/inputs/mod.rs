@@ -12,6 +12,7 @@ use super::UIData;
 pub mod boolean;
 pub mod boolean_vec3;
 pub mod camera;
+pub mod colour_ramp;
 pub mod combo_box;
 pub mod filepath;
 pub mod float;
@@ -173,3 +174,11 @@ pub fn create_drag_value_ui<T: eframe::emath::Numeric>(
 ) -> egui::Response {
     ui.add(egui::DragValue::new(value))
 }
+
+pub fn create_ranged_drag_value_ui<T: eframe::emath::Numeric>(
+    ui: &mut egui::Ui,
+    value: &mut T,
+    range: &RangeInclusive<T>,
+) -> egui::Response {
+    ui.add(egui::DragValue::new(value).range(range.clone()))
+}
@@ -7,31 +7,22 @@ use damascus_core::textures;
 
 use super::{UIData, UIInput};
 
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
 #[serde(default)]
 pub struct Texture {
-    value: textures::Texture,
+    value: Vec<textures::Texture>,
     ui_data: UIData,
 }
 
-impl Default for Texture {
-    fn default() -> Self {
-        Self {
-            value: textures::Texture::default(),
-            ui_data: UIData::default(),
-        }
-    }
-}
-
-impl UIInput<textures::Texture> for Texture {
-    fn new(value: textures::Texture) -> Self {
+impl UIInput<Vec<textures::Texture>> for Texture {
+    fn new(value: Vec<textures::Texture>) -> Self {
         Self {
             value: value,
             ..Default::default()
         }
     }
 
-    fn value(&self) -> &textures::Texture {
+    fn value(&self) -> &Vec<textures::Texture> {
         &self.value
     }
 
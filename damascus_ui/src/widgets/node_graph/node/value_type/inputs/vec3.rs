@@ -3,18 +3,33 @@
 // This source code is licensed under the BSD-style license found in the
 // LICENSE file in the root directory of this source tree.
 
+use core::ops::RangeInclusive;
+
 use eframe::egui;
 use glam;
 
-use super::{create_drag_value_ui, Collapsible, Colour, UIData, UIInput};
+use super::{create_ranged_drag_value_ui, Collapsible, Colour, UIData, UIInput};
 
-#[derive(Clone, PartialEq, Debug, Default, serde::Serialize, serde::Deserialize)]
+#[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
 #[serde(default)]
 pub struct Vec3 {
     value: [f32; 3],
     ui_data: UIData,
     collapsed: bool,
     pub is_colour: bool,
+    pub range: RangeInclusive<f32>,
+}
+
+impl Default for Vec3 {
+    fn default() -> Self {
+        Self {
+            value: [0.; 3],
+            ui_data: UIData::default(),
+            collapsed: false,
+            is_colour: false,
+            range: f32::MIN..=f32::MAX,
+        }
+    }
 }
 
 impl Vec3 {
@@ -28,6 +43,12 @@ impl Vec3 {
     pub fn as_vec3(&self) -> glam::Vec3 {
         glam::Vec3::from_array(self.value)
     }
+
+    /// Clamp each component of the vector to a range while it is dragged.
+    pub fn with_range(mut self, range: RangeInclusive<f32>) -> Self {
+        self.range = range;
+        self
+    }
 }
 
 impl UIInput<[f32; 3]> for Vec3 {
@@ -42,13 +63,16 @@ impl UIInput<[f32; 3]> for Vec3 {
         let mut has_changed = false;
         ui.horizontal(|ui| {
             self.create_parameter_label(ui, label);
-            has_changed |= create_drag_value_ui(ui, &mut self.value[0]).changed();
+            has_changed |=
+                create_ranged_drag_value_ui(ui, &mut self.value[0], &self.range).changed();
             if self.collapsed() {
                 self.value[1] = self.value[0];
                 self.value[2] = self.value[0];
             } else {
-                has_changed |= create_drag_value_ui(ui, &mut self.value[1]).changed();
-                has_changed |= create_drag_value_ui(ui, &mut self.value[2]).changed();
+                has_changed |=
+                    create_ranged_drag_value_ui(ui, &mut self.value[1], &self.range).changed();
+                has_changed |=
+                    create_ranged_drag_value_ui(ui, &mut self.value[2], &self.range).changed();
             }
             if self.is_colour && !self.collapsed() {
                 has_changed |= ui.color_edit_button_rgb(&mut self.value).changed();
@@ -25,9 +25,10 @@ use super::{
 
 mod inputs;
 pub use inputs::{
-    boolean::Bool, boolean_vec3::BVec3, camera::Camera, combo_box::ComboBox, filepath::Filepath,
-    float::Float, integer::Integer, lights::Lights, mat3::Mat3, mat4::Mat4, material::Material,
-    primitives::Primitives, procedural_texture::ProceduralTexture, scene::Scene, texture::Texture,
+    boolean::Bool, boolean_vec3::BVec3, camera::Camera, colour_ramp::ColourRamp,
+    combo_box::ComboBox, filepath::Filepath, float::Float, integer::Integer, lights::Lights,
+    mat3::Mat3, mat4::Mat4, material::Material, primitives::Primitives,
+    procedural_texture::ProceduralTexture, scene::Scene, texture::Texture,
     unsigned_integer::UnsignedInteger, unsigned_integer_vec3::UVec3, vec2::Vec2, vec3::Vec3,
     vec4::Vec4, Collapsible, Colour, RangedInput, UIInput,
 };
@@ -46,6 +47,7 @@ pub enum NodeValueType {
     // Base types
     Bool { value: Bool },
     BVec3 { value: BVec3 },
+    ColourRamp { value: ColourRamp },
     ComboBox { value: ComboBox },
     Filepath { value: Filepath },
     Integer { value: Integer },
@@ -125,6 +127,15 @@ impl NodeValueType {
         }
     }
 
+    /// Tries to downcast this value type to a colour ramp
+    pub fn try_to_colour_ramp(self) -> anyhow::Result<materials::ColourRamp> {
+        if let NodeValueType::ColourRamp { value } = self {
+            Ok(value.value().clone())
+        } else {
+            anyhow::bail!("Invalid cast from {:?} to ColourRamp", self)
+        }
+    }
+
     /// Tries to downcast this value type to an enum
     pub fn try_to_enum<E: IntoEnumIterator + Display + FromStr>(self) -> anyhow::Result<E> {
         if let NodeValueType::ComboBox { value } = self {
@@ -218,7 +229,7 @@ impl NodeValueType {
     /// Tries to downcast this value type to a material
     pub fn try_to_material(self) -> anyhow::Result<materials::Material> {
         if let NodeValueType::Material { value } = self {
-            Ok(*value.value())
+            Ok(value.value().clone())
         } else {
             anyhow::bail!("Invalid cast from {:?} to Material", self)
         }
@@ -236,7 +247,7 @@ impl NodeValueType {
     /// Tries to downcast this value type to a material
     pub fn try_to_procedural_texture(self) -> anyhow::Result<materials::ProceduralTexture> {
         if let NodeValueType::ProceduralTexture { value } = self {
-            Ok(*value.value())
+            Ok(value.value().clone())
         } else {
             anyhow::bail!("Invalid cast from {:?} to ProceduralTexture", self)
         }
@@ -261,7 +272,7 @@ impl NodeValueType {
     }
 
     /// Tries to downcast this value type to a texture
-    pub fn try_to_texture(self) -> anyhow::Result<textures::Texture> {
+    pub fn try_to_texture(self) -> anyhow::Result<Vec<textures::Texture>> {
         if let NodeValueType::Texture { value } = self {
             Ok(value.value().clone())
         } else {
@@ -293,6 +304,7 @@ impl WidgetValueTrait for NodeValueType {
         let value_changed = match self {
             NodeValueType::Bool { value } => value.create_ui(ui, param_name),
             NodeValueType::BVec3 { value } => value.create_ui(ui, param_name),
+            NodeValueType::ColourRamp { value } => value.create_ui(ui, param_name),
             NodeValueType::ComboBox { value } => value.create_ui(ui, param_name),
             NodeValueType::Integer { value } => RangedInput::create_ui(value, ui, param_name),
             NodeValueType::UnsignedInteger { value } => {
@@ -18,6 +18,7 @@ pub enum NodeGraphResponse {
     InputValueChanged(NodeId, NodeTemplate, String),
     CheckPreprocessorDirectives,
     ReconstructRenderPipeline,
+    BakeProceduralTexture(NodeId),
 }
 
 impl UserResponseTrait for NodeGraphResponse {}
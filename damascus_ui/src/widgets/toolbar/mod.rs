@@ -8,13 +8,14 @@ use std::io::{BufReader, Read, Write};
 
 use eframe::egui;
 use egui_modal;
+use strum::IntoEnumIterator;
 
 use damascus_core::scene::Scene;
 
 use super::{
     dialog,
     node_graph::{NodeGraph, NodeGraphResponse},
-    viewport::Viewport,
+    viewport::{RayMarcherBackend, Viewport},
 };
 use crate::{app::Context, MAX_BUFFER_SIZE};
 
@@ -251,6 +252,75 @@ pub fn show_toolbar(
                                 )
                                 .changed();
                         });
+                        ui.horizontal(|ui| {
+                            ui.label("max texture stack size");
+                            ray_marcher_pipeline_reconstruction_required |= ui
+                                .add(
+                                    egui::DragValue::new(
+                                        &mut viewport
+                                            .settings
+                                            .ray_marcher_view
+                                            .max_texture_stack_size,
+                                    )
+                                    .range(
+                                        1..=Scene::max_texture_stack_size_in_buffer(
+                                            MAX_BUFFER_SIZE,
+                                        ),
+                                    ),
+                                )
+                                .changed();
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("max colour ramp stops");
+                            ray_marcher_pipeline_reconstruction_required |= ui
+                                .add(
+                                    egui::DragValue::new(
+                                        &mut viewport
+                                            .settings
+                                            .ray_marcher_view
+                                            .max_colour_ramp_stops,
+                                    )
+                                    .range(
+                                        1..=Scene::max_colour_ramp_stops_in_buffer(MAX_BUFFER_SIZE),
+                                    ),
+                                )
+                                .changed();
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("backend");
+                            egui::ComboBox::from_id_salt("ray marcher backend")
+                                .selected_text(format!(
+                                    "{}",
+                                    viewport.settings.ray_marcher_view.backend
+                                ))
+                                .show_ui(ui, |ui| {
+                                    for backend in RayMarcherBackend::iter() {
+                                        ray_marcher_pipeline_reconstruction_required |= ui
+                                            .selectable_value(
+                                                &mut viewport.settings.ray_marcher_view.backend,
+                                                backend,
+                                                format!("{}", backend),
+                                            )
+                                            .changed();
+                                    }
+                                });
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("samples per frame");
+                            ray_marcher_pipeline_reconstruction_required |= ui
+                                .add_enabled(
+                                    viewport.settings.ray_marcher_view.backend
+                                        == RayMarcherBackend::Compute,
+                                    egui::DragValue::new(
+                                        &mut viewport.settings.ray_marcher_view.samples_per_frame,
+                                    )
+                                    .range(1..=64),
+                                )
+                                .on_hover_text(
+                                    "the number of paths traced per pixel each frame by the compute backend",
+                                )
+                                .changed();
+                        });
                     });
                 });
             });
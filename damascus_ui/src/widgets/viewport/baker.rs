@@ -0,0 +1,243 @@
+// Copyright (c) 2024, Owen Bulka
+// All rights reserved.
+// This source code is licensed under the BSD-style license found in the
+// LICENSE file in the root directory of this source tree.
+
+use std::{borrow::Cow, sync::mpsc};
+
+use eframe::{
+    egui_wgpu::{self, wgpu},
+    wgpu::util::DeviceExt,
+};
+use image::Rgba32FImage;
+
+use damascus_core::{
+    renderers::texture_baker::TextureBaker,
+    shaders::{
+        ray_marcher::RayMarcherPreprocessorDirectives,
+        texture_baker::{directives_for_texture_baker, texture_baker_shader},
+        WORKGROUP_SIZE,
+    },
+    DualDevice,
+};
+
+use super::{
+    settings::RayMarcherViewSettings,
+    views::{
+        resources::{Buffer, TextureView, TextureViewBindGroup},
+        RayMarcherView, View,
+    },
+};
+
+fn with_compute_visibility(buffers: Vec<Buffer>) -> Vec<Buffer> {
+    buffers
+        .into_iter()
+        .map(|buffer| Buffer {
+            buffer: buffer.buffer,
+            visibility: wgpu::ShaderStages::COMPUTE,
+        })
+        .collect()
+}
+
+fn create_bake_binding(
+    device: &wgpu::Device,
+    parameter_buffer: &wgpu::Buffer,
+    output_buffer: &wgpu::Buffer,
+) -> (wgpu::BindGroupLayout, wgpu::BindGroup) {
+    let bake_bind_group_layout =
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("texture baker bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+    let bake_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("texture baker bind group"),
+        layout: &bake_bind_group_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: parameter_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: output_buffer.as_entire_binding(),
+            },
+        ],
+    });
+
+    (bake_bind_group_layout, bake_bind_group)
+}
+
+/// Evaluate a procedural texture on the GPU and read back the result.
+/// The scene the texture is evaluated in is uploaded exactly as the ray
+/// marcher would upload it, so the baked image matches the viewer. Image
+/// textures index the images of the scene in the ray marcher's viewer,
+/// which it has already loaded.
+pub fn bake_texture(
+    render_state: &egui_wgpu::RenderState,
+    settings: &RayMarcherViewSettings,
+    texture_baker: &TextureBaker,
+    viewer: Option<&RayMarcherView>,
+) -> anyhow::Result<Rgba32FImage> {
+    let device = &render_state.device;
+    let queue = &render_state.queue;
+
+    // The whole image is written to, and read back from, a single buffer
+    let resolution: glam::UVec2 = texture_baker.image_resolution();
+    let output_size = (texture_baker.num_pixels() * 4 * size_of::<f32>()) as wgpu::BufferAddress;
+    let limits: wgpu::Limits = device.limits();
+    let max_output_size: wgpu::BufferAddress =
+        (limits.max_storage_buffer_binding_size as wgpu::BufferAddress).min(limits.max_buffer_size);
+    if output_size > max_output_size {
+        anyhow::bail!(
+            "Cannot bake a {}x{} image, as this device can only bake {} pixels at once",
+            resolution.x,
+            resolution.y,
+            max_output_size / (4 * size_of::<f32>()) as wgpu::BufferAddress,
+        );
+    }
+
+    let directives = directives_for_texture_baker(texture_baker);
+    if directives.contains(&RayMarcherPreprocessorDirectives::EnableImageTexture) {
+        match viewer {
+            Some(viewer) if viewer.images_loaded() => {}
+            Some(_) => anyhow::bail!("The images of the viewed scene are still loading"),
+            None => anyhow::bail!(
+                "Image textures can only be baked while the ray marcher views the scene with \
+                their images"
+            ),
+        }
+    }
+
+    let mut view = RayMarcherView::default();
+    view.renderer.scene = texture_baker.scene();
+
+    let uniform_buffers: Vec<Buffer> =
+        with_compute_visibility(view.create_uniform_buffers(device, settings));
+    let storage_buffers: Vec<Buffer> =
+        with_compute_visibility(view.create_storage_buffers(device, settings));
+    let texture_views: Vec<TextureView> = viewer
+        .unwrap_or(&view)
+        .create_texture_views(device)
+        .into_iter()
+        .map(|texture_view| TextureView {
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ..texture_view
+        })
+        .collect();
+
+    let (uniform_bind_group_layout, uniform_bind_group) =
+        RayMarcherView::create_uniform_binding(device, &uniform_buffers);
+    let (storage_bind_group_layout, storage_bind_group) =
+        RayMarcherView::create_storage_binding(device, &storage_buffers);
+    let (texture_bind_group_layout, texture_bind_group) =
+        RayMarcherView::create_texture_binding(device, &texture_views);
+    let texture_bind_group = TextureViewBindGroup {
+        bind_group: texture_bind_group,
+        bind_group_layout: texture_bind_group_layout,
+        texture_views: texture_views,
+    };
+    texture_bind_group.write(queue);
+
+    let parameter_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("texture baker parameter buffer"),
+        contents: bytemuck::cast_slice(&[texture_baker.as_std430()]),
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+    let output_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("texture baker output buffer"),
+        size: output_size,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+    let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("texture baker readback buffer"),
+        size: output_size,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let (bake_bind_group_layout, bake_bind_group) =
+        create_bake_binding(device, &parameter_buffer, &output_buffer);
+
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("texture baker pipeline layout"),
+        bind_group_layouts: &[
+            &uniform_bind_group_layout,
+            &storage_bind_group_layout,
+            &texture_bind_group.bind_group_layout,
+            &bake_bind_group_layout,
+        ],
+        push_constant_ranges: &[],
+    });
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("texture baker shader"),
+        source: wgpu::ShaderSource::Wgsl(Cow::Owned(texture_baker_shader(&directives))),
+    });
+    let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("texture baker pipeline"),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: Some("cs_main"),
+        compilation_options: wgpu::PipelineCompilationOptions::default(),
+        cache: None,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("texture baker encoder"),
+    });
+    {
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("texture baker pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&compute_pipeline);
+        compute_pass.set_bind_group(0, &uniform_bind_group, &[]);
+        compute_pass.set_bind_group(1, &storage_bind_group, &[]);
+        compute_pass.set_bind_group(2, &texture_bind_group.bind_group, &[]);
+        compute_pass.set_bind_group(3, &bake_bind_group, &[]);
+        compute_pass.dispatch_workgroups(
+            resolution.x.div_ceil(WORKGROUP_SIZE),
+            resolution.y.div_ceil(WORKGROUP_SIZE),
+            1,
+        );
+    }
+    encoder.copy_buffer_to_buffer(&output_buffer, 0, &readback_buffer, 0, output_size);
+    queue.submit(Some(encoder.finish()));
+
+    let (sender, receiver) = mpsc::channel();
+    readback_buffer
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+    device.poll(wgpu::Maintain::Wait);
+    receiver.recv()??;
+
+    let pixels: Vec<f32> =
+        bytemuck::cast_slice(&readback_buffer.slice(..).get_mapped_range()).to_vec();
+    readback_buffer.unmap();
+
+    Rgba32FImage::from_raw(resolution.x, resolution.y, pixels)
+        .ok_or_else(|| anyhow::Error::msg("The baked texture has the wrong dimensions"))
+}
@@ -3,12 +3,17 @@
 // This source code is licensed under the BSD-style license found in the
 // LICENSE file in the root directory of this source tree.
 
+use std::path::Path;
+
 use eframe::{egui, egui_wgpu};
 
+use damascus_core::renderers::texture_baker::TextureBaker;
+
+mod baker;
 mod settings;
 pub mod views;
 
-pub use settings::ViewportSettings;
+pub use settings::{RayMarcherBackend, ViewportSettings};
 pub use views::Views;
 
 use views::{CompositorView, RayMarcherView, View};
@@ -78,6 +83,27 @@ impl Viewport {
         self.view.reconstruct_pipeline(render_state, &self.settings);
     }
 
+    pub fn bake_texture(
+        &self,
+        render_state: &egui_wgpu::RenderState,
+        texture_baker: &TextureBaker,
+        file_path: &Path,
+    ) -> anyhow::Result<()> {
+        let viewer: Option<&RayMarcherView> = match &self.view {
+            Views::RayMarcher { view } => Some(view),
+            _ => None,
+        };
+        views::resources::save_image(
+            baker::bake_texture(
+                render_state,
+                &self.settings.ray_marcher_view,
+                texture_baker,
+                viewer,
+            )?,
+            file_path,
+        )
+    }
+
     fn set_button_backgrounds_transparent(ui: &mut egui::Ui) {
         let style: &mut egui::Style = ui.style_mut();
         style.visuals.widgets.inactive.weak_bg_fill = egui::Color32::TRANSPARENT;
@@ -3,6 +3,8 @@
 // This source code is licensed under the BSD-style license found in the
 // LICENSE file in the root directory of this source tree.
 
+use strum::{Display, EnumIter, EnumString};
+
 use damascus_core::{
     shaders::{compositor::CompositorCompilerSettings, ray_marcher::RayMarcherCompilerSettings},
     Settings,
@@ -31,11 +33,36 @@ impl Default for CompositorViewSettings {
 
 impl Settings for CompositorViewSettings {}
 
+/// Whether the ray marcher renders in the fragment shader that draws
+/// the viewport, or in a compute shader whose result the viewport
+/// displays.
+#[derive(
+    Debug,
+    Default,
+    Display,
+    Copy,
+    Clone,
+    EnumIter,
+    EnumString,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum RayMarcherBackend {
+    #[default]
+    Fragment,
+    Compute,
+}
+
 #[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
 #[serde(default)]
 pub struct RayMarcherViewSettings {
     pub max_primitives: usize,
     pub max_lights: usize,
+    pub max_texture_stack_size: usize,
+    pub max_colour_ramp_stops: usize,
+    pub backend: RayMarcherBackend,
+    pub samples_per_frame: u32,
 }
 
 impl Default for RayMarcherViewSettings {
@@ -43,6 +70,10 @@ impl Default for RayMarcherViewSettings {
         Self {
             max_primitives: 1024,
             max_lights: 1024,
+            max_texture_stack_size: 1024,
+            max_colour_ramp_stops: 1024,
+            backend: RayMarcherBackend::Fragment,
+            samples_per_frame: 1,
         }
     }
 }
@@ -0,0 +1,175 @@
+// Copyright (c) 2024, Owen Bulka
+// All rights reserved.
+// This source code is licensed under the BSD-style license found in the
+// LICENSE file in the root directory of this source tree.
+
+use std::{borrow::Cow, collections::HashSet, sync::mpsc};
+
+use eframe::egui_wgpu::{self, wgpu};
+
+use damascus_core::{
+    renderers::ray_marcher::AdaptiveSamplingCounters,
+    shaders::ray_marcher::{ray_march_shader, RayMarcherPreprocessorDirectives},
+};
+
+use super::resources::RenderResources;
+
+// The binding of the adaptive sampling buffer in the ray marcher's
+// storage bind group, which is the only storage buffer it writes to
+pub const ADAPTIVE_SAMPLING_BINDING: u32 = 7;
+
+enum Readback {
+    Idle,
+    // The counters of a render have been copied in a frame which may not
+    // have been submitted yet, so they cannot be mapped until the next
+    Copied(u32),
+    Mapping(u32, mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>),
+}
+
+/// The pass that counts the unconverged pixels in each tile of the
+/// viewport before the ray marcher renders a frame, so that the samples
+/// of the frame can be shared out between the tiles by their error. The
+/// counters are read back, a few frames late, to find when the render
+/// has converged.
+pub struct AdaptiveSamplingResources {
+    pub preprocessor_directives: HashSet<RayMarcherPreprocessorDirectives>,
+    count_tiles_pipeline: wgpu::ComputePipeline,
+    readback_buffer: wgpu::Buffer,
+    readback: Readback,
+}
+
+impl AdaptiveSamplingResources {
+    pub fn new(
+        render_state: &egui_wgpu::RenderState,
+        render_resources: &RenderResources,
+        preprocessor_directives: &HashSet<RayMarcherPreprocessorDirectives>,
+    ) -> Self {
+        let device = &render_state.device;
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("adaptive sampling pipeline layout"),
+            bind_group_layouts: &render_resources.bind_group_layouts(),
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("adaptive sampling shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(ray_march_shader(preprocessor_directives))),
+        });
+        let count_tiles_pipeline =
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("adaptive sampling pipeline"),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: Some("cs_count_tiles"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                cache: None,
+            });
+
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("adaptive sampling readback buffer"),
+            size: AdaptiveSamplingCounters::SIZE,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            preprocessor_directives: preprocessor_directives.clone(),
+            count_tiles_pipeline,
+            readback_buffer,
+            readback: Readback::Idle,
+        }
+    }
+
+    /// Clear the counters, then count the unconverged pixels of each tile
+    /// into them, copying them to be read back if requested. This must
+    /// happen before the ray marcher renders the frame.
+    pub fn count_tiles(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        render_resources: &RenderResources,
+        num_tiles: glam::UVec2,
+        read_back: bool,
+    ) {
+        let Some(adaptive_sampling_buffer) =
+            render_resources
+                .storage_bind_group
+                .as_ref()
+                .and_then(|storage_bind_group| {
+                    storage_bind_group
+                        .buffers
+                        .get(ADAPTIVE_SAMPLING_BINDING as usize)
+                })
+        else {
+            return;
+        };
+
+        encoder.clear_buffer(
+            &adaptive_sampling_buffer.buffer,
+            0,
+            Some(AdaptiveSamplingCounters::SIZE),
+        );
+        render_resources.dispatch(encoder, &self.count_tiles_pipeline, num_tiles);
+
+        if read_back {
+            encoder.copy_buffer_to_buffer(
+                &adaptive_sampling_buffer.buffer,
+                0,
+                &self.readback_buffer,
+                0,
+                AdaptiveSamplingCounters::SIZE,
+            );
+        }
+    }
+
+    /// Request that the counters of this frame are read back, which is
+    /// only possible once the last ones have been. Returns whether they
+    /// should be copied.
+    pub fn request_readback(&mut self, render_id: u32) -> bool {
+        if !matches!(self.readback, Readback::Idle) {
+            return false;
+        }
+        self.readback = Readback::Copied(render_id);
+        true
+    }
+
+    /// Map the counters copied in an earlier frame, which has been
+    /// submitted by now, and return them once they have been mapped, as
+    /// long as they were copied during the current render.
+    pub fn read_counters(
+        &mut self,
+        device: &wgpu::Device,
+        render_id: u32,
+    ) -> Option<AdaptiveSamplingCounters> {
+        match std::mem::replace(&mut self.readback, Readback::Idle) {
+            Readback::Idle => None,
+            Readback::Copied(copied_render_id) => {
+                let (sender, receiver) = mpsc::channel();
+                self.readback_buffer
+                    .slice(..)
+                    .map_async(wgpu::MapMode::Read, move |result| {
+                        let _ = sender.send(result);
+                    });
+                self.readback = Readback::Mapping(copied_render_id, receiver);
+                None
+            }
+            Readback::Mapping(copied_render_id, receiver) => {
+                device.poll(wgpu::Maintain::Poll);
+                match receiver.try_recv() {
+                    Ok(Ok(())) => {
+                        let counters = AdaptiveSamplingCounters::from_bytes(
+                            &self.readback_buffer.slice(..).get_mapped_range(),
+                        );
+                        self.readback_buffer.unmap();
+                        (copied_render_id == render_id).then_some(counters)
+                    }
+                    Err(mpsc::TryRecvError::Empty) => {
+                        self.readback = Readback::Mapping(copied_render_id, receiver);
+                        None
+                    }
+                    // Failing to map the counters only delays the next readback
+                    _ => None,
+                }
+            }
+        }
+    }
+}
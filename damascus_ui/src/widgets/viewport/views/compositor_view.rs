@@ -214,7 +214,7 @@ impl
         vec![TextureView {
             texture: texture,
             texture_view: texture_view,
-            texture_data: texture_data,
+            texture_data: bytemuck::cast_slice(texture_data.as_raw().as_slice()).to_vec(),
             visibility: wgpu::ShaderStages::FRAGMENT,
             view_dimension: wgpu::TextureViewDimension::D2,
             size: size,
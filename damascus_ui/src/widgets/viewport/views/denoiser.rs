@@ -0,0 +1,493 @@
+// Copyright (c) 2024, Owen Bulka
+// All rights reserved.
+// This source code is licensed under the BSD-style license found in the
+// LICENSE file in the root directory of this source tree.
+
+use std::{borrow::Cow, collections::HashSet};
+
+use eframe::{
+    egui,
+    egui_wgpu::{self, wgpu},
+};
+
+use damascus_core::{
+    renderers::denoiser::{Denoiser, Std430GPUDenoiser, MAX_DENOISER_ITERATIONS},
+    shaders::{
+        denoiser::denoiser_shader, ray_marcher::RayMarcherPreprocessorDirectives, WORKGROUP_SIZE,
+    },
+};
+
+use super::resources::RenderResources;
+
+// The bind group the denoiser's resources are bound to, which replaces
+// the ray marcher's storage textures
+const DENOISER_BIND_GROUP: u32 = 3;
+
+fn texture_bind_group_layout_entry(
+    binding: u32,
+    visibility: wgpu::ShaderStages,
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding: binding,
+        visibility: visibility,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+fn storage_texture_bind_group_layout_entry(
+    binding: u32,
+    access: wgpu::StorageTextureAccess,
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding: binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::StorageTexture {
+            access: access,
+            format: wgpu::TextureFormat::Rgba32Float,
+            view_dimension: wgpu::TextureViewDimension::D2,
+        },
+        count: None,
+    }
+}
+
+/// The textures the denoiser reads and writes, which have the
+/// resolution of the viewport.
+struct DenoiserTextures {
+    resolution: glam::UVec2,
+    // The iterations of the filter alternate between writing to each
+    ping_pong_textures: Vec<wgpu::Texture>,
+    // The ray marcher's frame, drawn here so that it has been rendered
+    // before it is filtered, rather than while painting
+    frame_texture_view: wgpu::TextureView,
+    iteration_bind_groups: Vec<wgpu::BindGroup>,
+    display_bind_groups: Vec<wgpu::BindGroup>,
+    frame_display_bind_group: wgpu::BindGroup,
+}
+
+/// The pipelines and textures that denoise the progressive render of the
+/// ray marcher, guided by the first hits the ray marcher accumulates
+/// alongside it. They share the ray marcher's uniform bind group, so
+/// that they know the resolution of the render.
+pub struct DenoiserResources {
+    pub preprocessor_directives: HashSet<RayMarcherPreprocessorDirectives>,
+    target_format: wgpu::TextureFormat,
+    filter_pipeline: wgpu::ComputePipeline,
+    display_pipeline: wgpu::RenderPipeline,
+    filter_bind_group_layout: wgpu::BindGroupLayout,
+    display_bind_group_layout: wgpu::BindGroupLayout,
+    parameter_buffers: Vec<wgpu::Buffer>,
+    textures: Option<DenoiserTextures>,
+}
+
+impl DenoiserResources {
+    pub fn new(
+        render_state: &egui_wgpu::RenderState,
+        render_resources: &RenderResources,
+        preprocessor_directives: &HashSet<RayMarcherPreprocessorDirectives>,
+    ) -> Self {
+        let device = &render_state.device;
+
+        let filter_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("denoiser filter bind group layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    texture_bind_group_layout_entry(1, wgpu::ShaderStages::COMPUTE),
+                    storage_texture_bind_group_layout_entry(
+                        2,
+                        wgpu::StorageTextureAccess::WriteOnly,
+                    ),
+                    texture_bind_group_layout_entry(3, wgpu::ShaderStages::COMPUTE),
+                    texture_bind_group_layout_entry(4, wgpu::ShaderStages::COMPUTE),
+                ],
+            });
+        let display_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("denoiser display bind group layout"),
+                entries: &[texture_bind_group_layout_entry(
+                    5,
+                    wgpu::ShaderStages::FRAGMENT,
+                )],
+            });
+
+        let mut filter_bind_group_layouts: Vec<&wgpu::BindGroupLayout> =
+            render_resources.bind_group_layouts();
+        filter_bind_group_layouts.truncate(DENOISER_BIND_GROUP as usize);
+        let mut display_bind_group_layouts: Vec<&wgpu::BindGroupLayout> =
+            filter_bind_group_layouts.clone();
+        filter_bind_group_layouts.push(&filter_bind_group_layout);
+        display_bind_group_layouts.push(&display_bind_group_layout);
+
+        let filter_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("denoiser filter pipeline layout"),
+                bind_group_layouts: &filter_bind_group_layouts,
+                push_constant_ranges: &[],
+            });
+        let display_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("denoiser display pipeline layout"),
+                bind_group_layouts: &display_bind_group_layouts,
+                push_constant_ranges: &[],
+            });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("denoiser shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(denoiser_shader(preprocessor_directives))),
+        });
+
+        let filter_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("denoiser filter pipeline"),
+            layout: Some(&filter_pipeline_layout),
+            module: &shader,
+            entry_point: Some("cs_filter"),
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            cache: None,
+        });
+        let display_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("denoiser display pipeline"),
+            layout: Some(&display_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(render_state.target_format.into())],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..wgpu::PrimitiveState::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let parameter_buffers: Vec<wgpu::Buffer> = (0..MAX_DENOISER_ITERATIONS)
+            .map(|_| {
+                device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("denoiser parameter buffer"),
+                    size: size_of::<Std430GPUDenoiser>() as wgpu::BufferAddress,
+                    usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
+                    mapped_at_creation: false,
+                })
+            })
+            .collect();
+
+        Self {
+            preprocessor_directives: preprocessor_directives.clone(),
+            target_format: render_state.target_format,
+            filter_pipeline: filter_pipeline,
+            display_pipeline: display_pipeline,
+            filter_bind_group_layout: filter_bind_group_layout,
+            display_bind_group_layout: display_bind_group_layout,
+            parameter_buffers: parameter_buffers,
+            textures: None,
+        }
+    }
+
+    /// Recreate the textures if the resolution of the viewport has
+    /// changed. The first iteration of the filter reads the progressive
+    /// rendering texture of the ray marcher, and every iteration reads
+    /// its guides.
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        resolution: glam::UVec2,
+        render_resources: &RenderResources,
+    ) {
+        let resolution: glam::UVec2 = resolution.max(glam::UVec2::ONE);
+        if self
+            .textures
+            .as_ref()
+            .is_some_and(|textures| textures.resolution == resolution)
+        {
+            return;
+        }
+        let Some(storage_texture_bind_group) = &render_resources.storage_texture_bind_group else {
+            return;
+        };
+        let progressive_rendering_texture_view: &wgpu::TextureView =
+            &storage_texture_bind_group.storage_texture_views[0].texture_view;
+        let normal_depth_texture_view: &wgpu::TextureView =
+            &storage_texture_bind_group.storage_texture_views[2].texture_view;
+        let albedo_texture_view: &wgpu::TextureView =
+            &storage_texture_bind_group.storage_texture_views[3].texture_view;
+
+        let texture_descriptor = wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width: resolution.x,
+                height: resolution.y,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba32Float,
+            usage: wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::STORAGE_BINDING
+                | wgpu::TextureUsages::TEXTURE_BINDING,
+            label: Some("denoiser ping pong texture"),
+            view_formats: &[],
+        };
+
+        let ping_pong_textures: Vec<wgpu::Texture> = (0..2)
+            .map(|_| device.create_texture(&texture_descriptor))
+            .collect();
+        let ping_pong_texture_views: Vec<wgpu::TextureView> = ping_pong_textures
+            .iter()
+            .map(|texture| texture.create_view(&Default::default()))
+            .collect();
+
+        let iteration_bind_groups: Vec<wgpu::BindGroup> = (0..MAX_DENOISER_ITERATIONS as usize)
+            .map(|iteration| {
+                let input_texture_view: &wgpu::TextureView = if iteration == 0 {
+                    progressive_rendering_texture_view
+                } else {
+                    &ping_pong_texture_views[(iteration + 1) % 2]
+                };
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("denoiser filter bind group"),
+                    layout: &self.filter_bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: self.parameter_buffers[iteration].as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::TextureView(input_texture_view),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: wgpu::BindingResource::TextureView(
+                                &ping_pong_texture_views[iteration % 2],
+                            ),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 3,
+                            resource: wgpu::BindingResource::TextureView(normal_depth_texture_view),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 4,
+                            resource: wgpu::BindingResource::TextureView(albedo_texture_view),
+                        },
+                    ],
+                })
+            })
+            .collect();
+
+        let frame_texture_view: wgpu::TextureView = device
+            .create_texture(&wgpu::TextureDescriptor {
+                format: self.target_format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                label: Some("denoiser frame texture"),
+                ..texture_descriptor
+            })
+            .create_view(&Default::default());
+
+        let display_bind_group = |texture_view: &wgpu::TextureView| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("denoiser display bind group"),
+                layout: &self.display_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::TextureView(texture_view),
+                }],
+            })
+        };
+        let display_bind_groups: Vec<wgpu::BindGroup> = ping_pong_texture_views
+            .iter()
+            .map(&display_bind_group)
+            .collect();
+        let frame_display_bind_group: wgpu::BindGroup = display_bind_group(&frame_texture_view);
+
+        self.textures = Some(DenoiserTextures {
+            resolution,
+            ping_pong_textures,
+            frame_texture_view,
+            iteration_bind_groups,
+            display_bind_groups,
+            frame_display_bind_group,
+        });
+    }
+
+    fn scene_bind_groups<'a>(render_resources: &'a RenderResources) -> Vec<&'a wgpu::BindGroup> {
+        let mut bind_groups: Vec<&wgpu::BindGroup> = vec![];
+        if let Some(uniform_bind_group) = &render_resources.uniform_bind_group {
+            bind_groups.push(&uniform_bind_group.bind_group);
+        }
+        if let Some(storage_bind_group) = &render_resources.storage_bind_group {
+            bind_groups.push(&storage_bind_group.bind_group);
+        }
+        if let Some(texture_bind_group) = &render_resources.texture_bind_group {
+            bind_groups.push(&texture_bind_group.bind_group);
+        }
+        bind_groups
+    }
+
+    /// Draw the ray marcher's frame, which renders it when using the
+    /// fragment backend, so that it can be filtered before painting. This
+    /// must happen after the ray marcher's compute pass, when using the
+    /// compute backend, and before denoising.
+    pub fn render_frame(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        render_resources: &RenderResources,
+    ) {
+        let Some(textures) = &self.textures else {
+            return;
+        };
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("denoiser frame pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &textures.frame_texture_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_resources.paint(&mut render_pass);
+    }
+
+    /// Run each iteration of the filter over the progressive render. This
+    /// must happen after the frame has been rendered, and before painting.
+    pub fn denoise(
+        &self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        render_resources: &RenderResources,
+        denoiser: &Denoiser,
+    ) {
+        let Some(textures) = &self.textures else {
+            return;
+        };
+
+        for iteration in 0..denoiser.num_iterations() {
+            queue.write_buffer(
+                &self.parameter_buffers[iteration as usize],
+                0,
+                bytemuck::cast_slice(&[denoiser.iteration_parameters(iteration)]),
+            );
+        }
+
+        let workgroups: glam::UVec2 = (textures.resolution + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE;
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("denoiser pass"),
+            timestamp_writes: None,
+        });
+        for (index, bind_group) in Self::scene_bind_groups(render_resources)
+            .into_iter()
+            .enumerate()
+        {
+            compute_pass.set_bind_group(index as u32, bind_group, &[]);
+        }
+
+        compute_pass.set_pipeline(&self.filter_pipeline);
+        for iteration in 0..denoiser.num_iterations() as usize {
+            compute_pass.set_bind_group(
+                DENOISER_BIND_GROUP,
+                &textures.iteration_bind_groups[iteration],
+                &[],
+            );
+            compute_pass.dispatch_workgroups(workgroups.x, workgroups.y, 1);
+        }
+    }
+
+    /// Draw the denoised render in place of the ray marcher's. In the
+    /// split view the ray marcher's frame is drawn first, and only the
+    /// right half is drawn over, so that the noisy render shows on the
+    /// left.
+    pub fn paint(
+        &self,
+        info: &egui::PaintCallbackInfo,
+        render_pass: &mut wgpu::RenderPass<'_>,
+        render_resources: &RenderResources,
+        denoiser: &Denoiser,
+    ) {
+        let Some(textures) = &self.textures else {
+            return;
+        };
+
+        render_pass.set_pipeline(&self.display_pipeline);
+        for (index, bind_group) in Self::scene_bind_groups(render_resources)
+            .into_iter()
+            .enumerate()
+        {
+            render_pass.set_bind_group(index as u32, bind_group, &[]);
+        }
+
+        if denoiser.split_view {
+            render_pass.set_bind_group(
+                DENOISER_BIND_GROUP,
+                &textures.frame_display_bind_group,
+                &[],
+            );
+            render_pass.draw(0..4, 0..1);
+
+            let viewport = info.viewport_in_pixels();
+            let clip_rect = info.clip_rect_in_pixels();
+            let left: i32 = (viewport.left_px + viewport.width_px / 2).max(clip_rect.left_px);
+            let right: i32 = clip_rect.left_px + clip_rect.width_px;
+            if right <= left {
+                return;
+            }
+            render_pass.set_scissor_rect(
+                left as u32,
+                clip_rect.top_px as u32,
+                (right - left) as u32,
+                clip_rect.height_px as u32,
+            );
+        }
+
+        render_pass.set_bind_group(
+            DENOISER_BIND_GROUP,
+            &textures.display_bind_groups[Self::output_index(denoiser)],
+            &[],
+        );
+        render_pass.draw(0..4, 0..1);
+    }
+
+    /// The texture the last iteration of the filter wrote to, and its
+    /// resolution.
+    pub fn denoised_texture(&self, denoiser: &Denoiser) -> Option<(&wgpu::Texture, glam::UVec2)> {
+        self.textures.as_ref().map(|textures| {
+            (
+                &textures.ping_pong_textures[Self::output_index(denoiser)],
+                textures.resolution,
+            )
+        })
+    }
+
+    fn output_index(denoiser: &Denoiser) -> usize {
+        (denoiser.num_iterations() as usize + 1) % 2
+    }
+}
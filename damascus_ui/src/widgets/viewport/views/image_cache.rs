@@ -0,0 +1,74 @@
+// Copyright (c) 2024, Owen Bulka
+// All rights reserved.
+// This source code is licensed under the BSD-style license found in the
+// LICENSE file in the root directory of this source tree.
+
+use std::{collections::HashMap, sync::mpsc, thread};
+
+use image::{ImageReader, Rgba32FImage};
+
+enum CachedImage {
+    Loading(mpsc::Receiver<Option<Rgba32FImage>>),
+    // Images that fail to load are cached as None so they are not retried
+    Loaded(Option<Rgba32FImage>),
+}
+
+/// Decodes the images of image textures on background threads, so that
+/// large images do not stall the interface, and keeps them so that each
+/// file is only decoded once.
+#[derive(Default)]
+pub struct ImageCache {
+    images: HashMap<String, CachedImage>,
+}
+
+impl ImageCache {
+    /// Start loading the images that are not cached yet, collect those
+    /// that have finished loading, and drop those no longer requested.
+    ///
+    /// Returns the number of requested images that have finished
+    /// loading, which changes whenever the textures need to be uploaded.
+    pub fn load(&mut self, filepaths: &[String]) -> usize {
+        self.images
+            .retain(|filepath, _| filepaths.contains(filepath));
+
+        for filepath in filepaths {
+            if self.images.contains_key(filepath) {
+                continue;
+            }
+            let (sender, receiver) = mpsc::channel();
+            let image_filepath: String = filepath.clone();
+            thread::spawn(move || {
+                let image: Option<Rgba32FImage> = ImageReader::open(&image_filepath)
+                    .ok()
+                    .and_then(|reader| reader.decode().ok())
+                    .map(|image| image.to_rgba32f());
+                let _ = sender.send(image);
+            });
+            self.images
+                .insert(filepath.clone(), CachedImage::Loading(receiver));
+        }
+
+        let mut num_loaded: usize = 0;
+        for cached_image in self.images.values_mut() {
+            if let CachedImage::Loading(receiver) = cached_image {
+                match receiver.try_recv() {
+                    Ok(image) => *cached_image = CachedImage::Loaded(image),
+                    Err(mpsc::TryRecvError::Disconnected) => {
+                        *cached_image = CachedImage::Loaded(None)
+                    }
+                    Err(mpsc::TryRecvError::Empty) => continue,
+                }
+            }
+            num_loaded += 1;
+        }
+        num_loaded
+    }
+
+    /// The decoded image, if it has finished loading successfully.
+    pub fn get(&self, filepath: &str) -> Option<&Rgba32FImage> {
+        match self.images.get(filepath) {
+            Some(CachedImage::Loaded(image)) => image.as_ref(),
+            _ => None,
+        }
+    }
+}
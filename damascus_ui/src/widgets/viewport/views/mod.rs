@@ -22,7 +22,10 @@ use super::settings::{self, ViewportCompilerSettings, ViewportSettings};
 
 use crate::icons::Icons;
 
+mod adaptive_sampling;
 mod compositor_view;
+mod denoiser;
+mod image_cache;
 mod ray_marcher_view;
 pub mod resources;
 
@@ -127,6 +130,17 @@ pub trait View<
         })
     }
 
+    /// Create a compute pipeline from the same shader as the render
+    /// pipeline, for views that render in a compute shader and only
+    /// display the result in the fragment shader.
+    fn create_compute_pipeline(
+        &self,
+        _device: &wgpu::Device,
+        _bind_group_layouts: Vec<&wgpu::BindGroupLayout>,
+    ) -> Option<wgpu::ComputePipeline> {
+        None
+    }
+
     fn construct_pipeline(&mut self, render_state: &egui_wgpu::RenderState, settings: &V) {
         let device = &render_state.device;
 
@@ -184,6 +198,7 @@ pub trait View<
 
         let mut render_resources = RenderResources {
             render_pipeline: None,
+            compute_pipeline: None,
             uniform_bind_group: uniform_bind_group,
             storage_bind_group: storage_bind_group,
             texture_bind_group: texture_bind_group,
@@ -195,6 +210,8 @@ pub trait View<
             render_state.target_format,
             render_resources.bind_group_layouts(),
         ));
+        render_resources.compute_pipeline =
+            self.create_compute_pipeline(device, render_resources.bind_group_layouts());
 
         render_state
             .renderer
@@ -232,12 +249,14 @@ pub trait View<
 
             let device = &render_state.device;
 
-            // Create the updated pipeline
+            // Create the updated pipelines
             render_resources.render_pipeline = Some(self.create_render_pipeline(
                 device,
                 render_state.target_format,
                 render_resources.bind_group_layouts(),
             ));
+            render_resources.compute_pipeline =
+                self.create_compute_pipeline(device, render_resources.bind_group_layouts());
         }
     }
 
@@ -3,7 +3,7 @@
 // This source code is licensed under the BSD-style license found in the
 // LICENSE file in the root directory of this source tree.
 
-use std::{collections::HashSet, ops::BitOr, time::SystemTime};
+use std::{borrow::Cow, collections::HashSet, ops::BitOr, time::SystemTime};
 
 use eframe::{
     egui,
@@ -12,18 +12,30 @@ use eframe::{
     wgpu::util::DeviceExt,
 };
 use glam;
+use half::f16;
+use image::{
+    imageops::{self, FilterType},
+    Rgba32FImage,
+};
 use serde_hashkey::{to_key_with_ordered_float, Key, OrderedFloatPolicy};
 
 use damascus_core::{
     geometry::{
+        bvh::Std430GPUBVHNode,
         camera::{Camera, Std430GPUCamera},
         primitive::{Primitive, Std430GPUPrimitive},
     },
     lights::{Light, Lights, Std430GPULight},
-    materials::{Material, ProceduralTexture, Std430GPUMaterial},
-    renderers::ray_marcher::{
-        GPURayMarcher, RayMarcher, RayMarcherRenderState, Std430GPURayMarcher,
-        Std430GPURayMarcherRenderState,
+    materials::{
+        Material, ProceduralTexture, Std430GPUColourStop, Std430GPUMaterial,
+        Std430GPUProceduralTexture, TextureBuffers,
+    },
+    renderers::{
+        denoiser::{Denoiser, MAX_DENOISER_ITERATIONS},
+        ray_marcher::{
+            AdaptiveSamplingCounters, GPURayMarcher, RayMarcher, RayMarcherRenderState,
+            Std430GPURayMarcher, Std430GPURayMarcherRenderState,
+        },
     },
     scene::{Scene, Std430GPUSceneParameters},
     shaders::{
@@ -34,12 +46,21 @@ use damascus_core::{
 };
 
 use super::{
-    resources::{Buffer, StorageTextureView},
-    settings::RayMarcherViewSettings,
+    adaptive_sampling::{AdaptiveSamplingResources, ADAPTIVE_SAMPLING_BINDING},
+    denoiser::DenoiserResources,
+    image_cache::ImageCache,
+    resources::{
+        read_texture, save_image, BindingResource, Buffer, StorageTextureView, TextureView,
+    },
+    settings::{RayMarcherBackend, RayMarcherViewSettings},
     RenderResources, View,
 };
 
-use crate::MAX_TEXTURE_DIMENSION;
+use crate::{widgets::dialog, MAX_TEXTURE_DIMENSION};
+
+// The resources are shared by the fragment and compute backends
+const RESOURCE_VISIBILITY: wgpu::ShaderStages =
+    wgpu::ShaderStages::FRAGMENT.union(wgpu::ShaderStages::COMPUTE);
 
 struct RayMarcherViewCallback {
     render_parameters: Std430GPURayMarcher,
@@ -50,6 +71,13 @@ struct RayMarcherViewCallback {
     lights: Vec<Std430GPULight>,
     atmosphere: Std430GPUMaterial,
     emissive_primitive_indices: Vec<u32>,
+    texture_stack: Vec<Std430GPUProceduralTexture>,
+    colour_ramp_stops: Vec<Std430GPUColourStop>,
+    bvh_nodes: Vec<Std430GPUBVHNode>,
+    workgroups: Option<glam::UVec2>,
+    tiles: Option<glam::UVec2>,
+    read_back_counters: bool,
+    denoiser: Option<Denoiser>,
 }
 
 impl egui_wgpu::CallbackTrait for RayMarcherViewCallback {
@@ -58,11 +86,11 @@ impl egui_wgpu::CallbackTrait for RayMarcherViewCallback {
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         _screen_descriptor: &egui_wgpu::ScreenDescriptor,
-        _encoder: &mut wgpu::CommandEncoder,
+        encoder: &mut wgpu::CommandEncoder,
         resources: &mut egui_wgpu::CallbackResources,
     ) -> Vec<wgpu::CommandBuffer> {
-        let resources: &RenderResources = resources.get().unwrap();
-        resources.prepare(
+        let render_resources: &RenderResources = resources.get().unwrap();
+        render_resources.prepare(
             device,
             queue,
             vec![
@@ -76,19 +104,48 @@ impl egui_wgpu::CallbackTrait for RayMarcherViewCallback {
                 bytemuck::cast_slice(self.lights.as_slice()),
                 bytemuck::cast_slice(&[self.atmosphere]),
                 bytemuck::cast_slice(self.emissive_primitive_indices.as_slice()),
+                bytemuck::cast_slice(self.texture_stack.as_slice()),
+                bytemuck::cast_slice(self.colour_ramp_stops.as_slice()),
+                bytemuck::cast_slice(self.bvh_nodes.as_slice()),
             ],
         );
+        if let (Some(tiles), Some(adaptive_sampling_resources)) =
+            (self.tiles, resources.get::<AdaptiveSamplingResources>())
+        {
+            adaptive_sampling_resources.count_tiles(
+                encoder,
+                render_resources,
+                tiles,
+                self.read_back_counters,
+            );
+        }
+        if let Some(workgroups) = self.workgroups {
+            render_resources.compute(encoder, workgroups);
+        }
+        if let (Some(denoiser), Some(denoiser_resources)) =
+            (&self.denoiser, resources.get::<DenoiserResources>())
+        {
+            denoiser_resources.render_frame(encoder, render_resources);
+            denoiser_resources.denoise(queue, encoder, render_resources, denoiser);
+        }
         Vec::new()
     }
 
     fn paint(
         &self,
-        _info: egui::PaintCallbackInfo,
+        info: egui::PaintCallbackInfo,
         render_pass: &mut wgpu::RenderPass<'static>,
         resources: &egui_wgpu::CallbackResources,
     ) {
-        let resources: &RenderResources = resources.get().unwrap();
-        resources.paint(render_pass);
+        let render_resources: &RenderResources = resources.get().unwrap();
+        // The denoiser has already rendered the frame
+        if let (Some(denoiser), Some(denoiser_resources)) =
+            (&self.denoiser, resources.get::<DenoiserResources>())
+        {
+            denoiser_resources.paint(&info, render_pass, render_resources, denoiser);
+        } else {
+            render_resources.paint(render_pass);
+        }
     }
 }
 
@@ -102,6 +159,14 @@ pub struct RayMarcherView {
     recompile_hash: Key<OrderedFloatPolicy>,
     reconstruct_hash: Key<OrderedFloatPolicy>,
     preprocessor_directives: HashSet<RayMarcherPreprocessorDirectives>,
+    backend: RayMarcherBackend,
+    denoiser: Denoiser,
+    image_cache: ImageCache,
+    loaded_images: usize,
+    converged: bool,
+    // Identifies the current render, so that adaptive sampling counters
+    // read back from before it was reset are ignored
+    render_id: u32,
 }
 
 impl Default for RayMarcherView {
@@ -116,6 +181,12 @@ impl Default for RayMarcherView {
             recompile_hash: Key::<OrderedFloatPolicy>::Unit,
             reconstruct_hash: Key::<OrderedFloatPolicy>::Unit,
             preprocessor_directives: HashSet::<RayMarcherPreprocessorDirectives>::new(),
+            backend: RayMarcherBackend::Fragment,
+            denoiser: Denoiser::default(),
+            image_cache: ImageCache::default(),
+            loaded_images: 0,
+            converged: false,
+            render_id: 0,
         }
     }
 }
@@ -149,7 +220,17 @@ impl
     }
 
     fn set_reconstruct_hash(&mut self, settings: &RayMarcherViewSettings) -> bool {
-        if let Ok(reconstruct_hash) = to_key_with_ordered_float(&settings) {
+        // The backend is needed when the shader is recompiled, which
+        // happens without the settings
+        self.backend = settings.backend;
+
+        // The image textures are uploaded when the pipeline is constructed,
+        // and again as each of their images finishes loading
+        if let Ok(reconstruct_hash) = to_key_with_ordered_float(&(
+            settings,
+            &self.renderer().scene.textures,
+            self.loaded_images,
+        )) {
             if reconstruct_hash != self.reconstruct_hash {
                 self.reconstruct_hash = reconstruct_hash;
                 return true;
@@ -172,6 +253,38 @@ impl
         shaders::ray_marcher::ray_march_shader(self.current_preprocessor_directives())
     }
 
+    fn create_compute_pipeline(
+        &self,
+        device: &wgpu::Device,
+        bind_group_layouts: Vec<&wgpu::BindGroupLayout>,
+    ) -> Option<wgpu::ComputePipeline> {
+        if self.backend != RayMarcherBackend::Compute {
+            return None;
+        }
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("ray marcher compute pipeline layout"),
+            bind_group_layouts: &bind_group_layouts,
+            push_constant_ranges: &[],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("ray marcher compute shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(self.get_shader())),
+        });
+
+        Some(
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("ray marcher compute pipeline"),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: Some("cs_main"),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                cache: None,
+            }),
+        )
+    }
+
     fn create_uniform_buffers(
         &self,
         device: &wgpu::Device,
@@ -184,7 +297,7 @@ impl
                     contents: bytemuck::cast_slice(&[self.renderer().as_std430()]),
                     usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
                 }),
-                visibility: wgpu::ShaderStages::FRAGMENT,
+                visibility: RESOURCE_VISIBILITY,
             },
             Buffer {
                 buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -195,7 +308,7 @@ impl
                         .scene_parameters(settings.max_primitives, settings.max_lights)]),
                     usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
                 }),
-                visibility: wgpu::ShaderStages::FRAGMENT,
+                visibility: RESOURCE_VISIBILITY,
             },
             Buffer {
                 buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -203,7 +316,7 @@ impl
                     contents: bytemuck::cast_slice(&[self.render_state.as_std430()]),
                     usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
                 }),
-                visibility: wgpu::ShaderStages::FRAGMENT,
+                visibility: RESOURCE_VISIBILITY,
             },
             Buffer {
                 buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -215,7 +328,7 @@ impl
                         .as_std430()]),
                     usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
                 }),
-                visibility: wgpu::ShaderStages::FRAGMENT.bitor(wgpu::ShaderStages::VERTEX),
+                visibility: RESOURCE_VISIBILITY.bitor(wgpu::ShaderStages::VERTEX),
             },
         ]
     }
@@ -225,16 +338,22 @@ impl
         device: &wgpu::Device,
         settings: &RayMarcherViewSettings,
     ) -> Vec<Buffer> {
+        let mut texture_buffers = TextureBuffers::new(
+            settings.max_texture_stack_size,
+            settings.max_colour_ramp_stops,
+        );
         let primitives: Vec<Std430GPUPrimitive> = self
             .renderer
             .scene
-            .create_gpu_primitives(settings.max_primitives);
+            .create_gpu_primitives(settings.max_primitives, &mut texture_buffers);
+        let atmosphere: Std430GPUMaterial = self.renderer.scene.atmosphere(&mut texture_buffers);
         let lights: Vec<Std430GPULight> =
             self.renderer.scene.create_gpu_lights(settings.max_lights);
         let emissive_primitive_indices: Vec<u32> = self
             .renderer
             .scene
             .emissive_primitive_indices(settings.max_primitives);
+        let bvh_nodes: Vec<Std430GPUBVHNode> = self.create_gpu_bvh_nodes(settings);
         vec![
             Buffer {
                 buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -251,7 +370,7 @@ impl
                     .concat(),
                     usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::STORAGE,
                 }),
-                visibility: wgpu::ShaderStages::FRAGMENT,
+                visibility: RESOURCE_VISIBILITY,
             },
             Buffer {
                 buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -264,15 +383,15 @@ impl
                     .concat(),
                     usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::STORAGE,
                 }),
-                visibility: wgpu::ShaderStages::FRAGMENT,
+                visibility: RESOURCE_VISIBILITY,
             },
             Buffer {
                 buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
                     label: Some("ray marcher render globals buffer"),
-                    contents: bytemuck::cast_slice(&[self.renderer().scene.atmosphere()]),
+                    contents: bytemuck::cast_slice(&[atmosphere]),
                     usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::STORAGE,
                 }),
-                visibility: wgpu::ShaderStages::FRAGMENT,
+                visibility: RESOURCE_VISIBILITY,
             },
             Buffer {
                 buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -289,36 +408,256 @@ impl
                     .concat(),
                     usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::STORAGE,
                 }),
-                visibility: wgpu::ShaderStages::FRAGMENT,
+                visibility: RESOURCE_VISIBILITY,
+            },
+            Buffer {
+                buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("ray marcher texture stack buffer"),
+                    contents: &[
+                        bytemuck::cast_slice(texture_buffers.texture_stack.as_slice()),
+                        vec![
+                            0;
+                            (settings.max_texture_stack_size - texture_buffers.texture_stack.len())
+                                * size_of::<Std430GPUProceduralTexture>()
+                        ]
+                        .as_slice(),
+                    ]
+                    .concat(),
+                    usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::STORAGE,
+                }),
+                visibility: RESOURCE_VISIBILITY,
+            },
+            Buffer {
+                buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("ray marcher colour ramp stops buffer"),
+                    contents: &[
+                        bytemuck::cast_slice(texture_buffers.colour_ramp_stops.as_slice()),
+                        vec![
+                            0;
+                            (settings.max_colour_ramp_stops
+                                - texture_buffers.colour_ramp_stops.len())
+                                * size_of::<Std430GPUColourStop>()
+                        ]
+                        .as_slice(),
+                    ]
+                    .concat(),
+                    usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::STORAGE,
+                }),
+                visibility: RESOURCE_VISIBILITY,
+            },
+            Buffer {
+                buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("ray marcher bvh nodes buffer"),
+                    contents: &[
+                        bytemuck::cast_slice(bvh_nodes.as_slice()),
+                        vec![
+                            0;
+                            (Scene::max_bvh_nodes(settings.max_primitives) - bvh_nodes.len())
+                                * size_of::<Std430GPUBVHNode>()
+                        ]
+                        .as_slice(),
+                    ]
+                    .concat(),
+                    usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::STORAGE,
+                }),
+                visibility: RESOURCE_VISIBILITY,
+            },
+            Buffer {
+                buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("ray marcher adaptive sampling buffer"),
+                    size: AdaptiveSamplingCounters::buffer_size(glam::UVec2::splat(
+                        MAX_TEXTURE_DIMENSION,
+                    )),
+                    usage: wgpu::BufferUsages::COPY_DST
+                        | wgpu::BufferUsages::COPY_SRC
+                        | wgpu::BufferUsages::STORAGE,
+                    mapped_at_creation: false,
+                }),
+                visibility: RESOURCE_VISIBILITY,
             },
         ]
     }
 
+    fn storage_bind_group_layout_entry(
+        binding: u32,
+        buffer: &Buffer,
+    ) -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: buffer.visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage {
+                    read_only: binding != ADAPTIVE_SAMPLING_BINDING,
+                },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }
+    }
+
+    fn create_texture_views(&self, device: &wgpu::Device) -> Vec<TextureView> {
+        let images: Vec<Option<&Rgba32FImage>> = self
+            .renderer()
+            .scene
+            .textures
+            .iter()
+            .map(|texture| self.image_cache.get(&texture.filepath))
+            .collect();
+
+        // Every layer of a texture array must have the same dimensions
+        let (width, height) = images
+            .iter()
+            .flatten()
+            .fold((1, 1), |(width, height), image| {
+                (width.max(image.width()), height.max(image.height()))
+            });
+        let width: u32 = width.min(MAX_TEXTURE_DIMENSION);
+        let height: u32 = height.min(MAX_TEXTURE_DIMENSION);
+        let layers: u32 = images.len().max(1) as u32;
+
+        // Images that fail to load, or are still loading, are left black
+        let mut texture_data = Rgba32FImage::new(width, height * layers);
+        for (layer, image) in images.into_iter().enumerate() {
+            if let Some(image) = image {
+                let layer_data: Rgba32FImage = if image.dimensions() == (width, height) {
+                    image.clone()
+                } else {
+                    imageops::resize(image, width, height, FilterType::Triangle)
+                };
+                imageops::replace(
+                    &mut texture_data,
+                    &layer_data,
+                    0,
+                    (layer as u32 * height) as i64,
+                );
+            }
+        }
+
+        let size = wgpu::Extent3d {
+            width: width,
+            height: height,
+            depth_or_array_layers: layers,
+        };
+        let texture_descriptor = wgpu::TextureDescriptor {
+            size: size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            // Half precision keeps the range of high dynamic range images
+            // in half the memory
+            format: wgpu::TextureFormat::Rgba16Float,
+            usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+            label: Some("ray marcher image textures"),
+            view_formats: &[],
+        };
+        let texture: wgpu::Texture = device.create_texture(&texture_descriptor);
+        let texture_view: wgpu::TextureView = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        vec![TextureView {
+            texture: texture,
+            texture_view: texture_view,
+            texture_data: bytemuck::cast_slice(
+                texture_data
+                    .as_raw()
+                    .iter()
+                    .map(|channel| f16::from_f32(*channel))
+                    .collect::<Vec<f16>>()
+                    .as_slice(),
+            )
+            .to_vec(),
+            visibility: RESOURCE_VISIBILITY,
+            view_dimension: wgpu::TextureViewDimension::D2Array,
+            size: size,
+        }]
+    }
+
     fn create_storage_texture_views(&self, device: &wgpu::Device) -> Vec<StorageTextureView> {
+        // The textures match the viewport, and are recreated when it is
+        // resized
+        let resolution: glam::UVec2 = self.render_state.resolution.max(glam::UVec2::ONE);
         let texture_descriptor = wgpu::TextureDescriptor {
             size: wgpu::Extent3d {
-                width: MAX_TEXTURE_DIMENSION,
-                height: MAX_TEXTURE_DIMENSION,
+                width: resolution.x,
+                height: resolution.y,
                 depth_or_array_layers: 1,
             },
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             format: wgpu::TextureFormat::Rgba32Float,
-            usage: wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::STORAGE_BINDING,
+            // The denoiser reads the progressive render as a texture
+            usage: wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::STORAGE_BINDING
+                | wgpu::TextureUsages::TEXTURE_BINDING,
             label: Some("ray marcher progressive rendering texture"),
             view_formats: &[],
         };
 
-        vec![StorageTextureView {
-            texture_view: device
-                .create_texture(&texture_descriptor)
-                .create_view(&Default::default()),
-            visibility: wgpu::ShaderStages::FRAGMENT,
-            access: wgpu::StorageTextureAccess::ReadWrite,
-            format: texture_descriptor.format,
-            view_dimension: wgpu::TextureViewDimension::D2,
-        }]
+        // The per-pixel sample counts and luminance statistics used to
+        // decide when a pixel has converged. These stay in full precision
+        // as half floats cannot count past 2048 samples
+        let statistics_texture_descriptor = wgpu::TextureDescriptor {
+            label: Some("ray marcher pixel statistics texture"),
+            ..texture_descriptor.clone()
+        };
+
+        // The guides of the denoiser, which it reads as textures, only
+        // need to be precise enough to find edges
+        let normal_depth_texture_descriptor = wgpu::TextureDescriptor {
+            format: wgpu::TextureFormat::Rgba16Float,
+            label: Some("ray marcher normal depth texture"),
+            ..texture_descriptor.clone()
+        };
+        let albedo_texture_descriptor = wgpu::TextureDescriptor {
+            format: wgpu::TextureFormat::Rgba16Float,
+            label: Some("ray marcher albedo texture"),
+            ..texture_descriptor.clone()
+        };
+
+        let texture: wgpu::Texture = device.create_texture(&texture_descriptor);
+        let statistics_texture: wgpu::Texture =
+            device.create_texture(&statistics_texture_descriptor);
+        let normal_depth_texture: wgpu::Texture =
+            device.create_texture(&normal_depth_texture_descriptor);
+        let albedo_texture: wgpu::Texture = device.create_texture(&albedo_texture_descriptor);
+
+        vec![
+            StorageTextureView {
+                texture_view: texture.create_view(&Default::default()),
+                texture: texture,
+                visibility: RESOURCE_VISIBILITY,
+                access: wgpu::StorageTextureAccess::ReadWrite,
+                format: texture_descriptor.format,
+                view_dimension: wgpu::TextureViewDimension::D2,
+            },
+            StorageTextureView {
+                texture_view: statistics_texture.create_view(&Default::default()),
+                texture: statistics_texture,
+                visibility: RESOURCE_VISIBILITY,
+                access: wgpu::StorageTextureAccess::ReadWrite,
+                format: statistics_texture_descriptor.format,
+                view_dimension: wgpu::TextureViewDimension::D2,
+            },
+            StorageTextureView {
+                texture_view: normal_depth_texture.create_view(&Default::default()),
+                texture: normal_depth_texture,
+                visibility: RESOURCE_VISIBILITY,
+                access: wgpu::StorageTextureAccess::ReadWrite,
+                format: normal_depth_texture_descriptor.format,
+                view_dimension: wgpu::TextureViewDimension::D2,
+            },
+            StorageTextureView {
+                texture_view: albedo_texture.create_view(&Default::default()),
+                texture: albedo_texture,
+                visibility: RESOURCE_VISIBILITY,
+                access: wgpu::StorageTextureAccess::ReadWrite,
+                format: albedo_texture_descriptor.format,
+                view_dimension: wgpu::TextureViewDimension::D2,
+            },
+        ]
     }
 
     fn disable(&mut self) {
@@ -350,11 +689,23 @@ impl
 
     fn reset(&mut self) {
         self.render_state.paths_rendered_per_pixel = 0;
+        self.converged = false;
+        self.render_id = self.render_id.wrapping_add(1);
     }
 
     fn show_controls(&mut self, render_state: &egui_wgpu::RenderState, ui: &mut egui::Ui) -> bool {
         self.show_restart_pause_play_buttons(render_state, ui);
-        ui.add(egui::Label::new(&self.stats_text).truncate());
+        ui.horizontal(|ui| {
+            self.show_denoiser_controls(ui);
+            if ui
+                .add_enabled(self.enabled(), egui::Button::new("export"))
+                .on_hover_text("save the render to an image, denoised if the denoiser is on")
+                .clicked()
+            {
+                self.export_render(render_state, ui.ctx());
+            }
+            ui.add(egui::Label::new(&self.stats_text).truncate());
+        });
         false
     }
 
@@ -368,8 +719,9 @@ impl
     ) -> Option<epaint::PaintCallback> {
         let (rect, response) = ui.allocate_at_least(available_size, egui::Sense::drag());
 
-        self.render_state.resolution = glam::UVec2::new(rect.width() as u32, rect.height() as u32)
+        let resolution: glam::UVec2 = glam::UVec2::new(rect.width() as u32, rect.height() as u32)
             .min(glam::UVec2::splat(MAX_TEXTURE_DIMENSION));
+        self.render_state.resolution = resolution;
 
         self.stats_text = format!(
             "{:} paths per pixel @ {:.2} fps @ {:.0}x{:.0}",
@@ -383,6 +735,9 @@ impl
             self.stats_text += " - viewer disabled, activate a node to enable it";
             return None;
         }
+        if self.converged {
+            self.stats_text += " - converged";
+        }
 
         ui.ctx().request_repaint();
 
@@ -394,12 +749,40 @@ impl
 
         self.update_camera(ui, &rect, &response);
 
+        let filepaths: Vec<String> = self
+            .renderer
+            .scene
+            .textures
+            .iter()
+            .map(|texture| texture.filepath.clone())
+            .collect();
+        self.loaded_images = self.image_cache.load(&filepaths);
+
         let _data_changed: bool = self.reconstruct_if_hash_changed(render_state, settings)
             || self.recompile_if_hash_changed(render_state, compiler_settings);
 
+        self.resize_storage_textures(render_state);
+
+        if self.denoiser.enabled {
+            self.update_denoiser_resources(render_state);
+        }
+
+        let read_back_counters: bool = self.renderer.adaptive_sampling()
+            && self.update_adaptive_sampling_resources(render_state);
+
+        // The compute backend can trace several paths per pixel each frame
+        self.render_state.compute_backend = self.backend == RayMarcherBackend::Compute;
+        self.render_state.samples_per_frame = if self.render_state.compute_backend {
+            settings.samples_per_frame.max(1)
+        } else {
+            1
+        };
+
         let mut paths_rendered: u32 = 0;
 
-        if self.paused() {
+        // Once every pixel has converged there is nothing left to render
+        let rendering: bool = !self.paused() && !self.converged;
+        if !rendering {
             self.render_state.previous_frame_time = SystemTime::now();
             self.render_state.frame_counter = 1;
         } else {
@@ -418,9 +801,28 @@ impl
                 self.render_state.frame_counter += 1;
             }
 
-            paths_rendered = 1;
+            paths_rendered = self.render_state.samples_per_frame;
         }
 
+        // Dispatch a workgroup per tile of the viewport, when rendering
+        let tiles: Option<glam::UVec2> = (paths_rendered > 0)
+            .then(|| AdaptiveSamplingCounters::num_tiles(self.render_state.resolution));
+        let workgroups: Option<glam::UVec2> = tiles.filter(|_| self.render_state.compute_backend);
+
+        // The shader only knows to stop accumulating when it is paused
+        let mut gpu_render_state: RayMarcherRenderState = self.render_state.clone();
+        gpu_render_state.paused = !rendering;
+
+        let mut texture_buffers = TextureBuffers::new(
+            settings.max_texture_stack_size,
+            settings.max_colour_ramp_stops,
+        );
+        let primitives: Vec<Std430GPUPrimitive> = self
+            .renderer
+            .scene
+            .create_gpu_primitives(settings.max_primitives, &mut texture_buffers);
+        let atmosphere: Std430GPUMaterial = self.renderer.scene.atmosphere(&mut texture_buffers);
+
         let callback = Some(egui_wgpu::Callback::new_paint_callback(
             rect,
             RayMarcherViewCallback {
@@ -429,18 +831,26 @@ impl
                     .renderer()
                     .scene
                     .scene_parameters(settings.max_primitives, settings.max_lights),
-                render_state: self.render_state.as_std430(),
+                render_state: gpu_render_state.as_std430(),
                 render_camera: self.renderer().scene.render_camera.as_std430(),
-                primitives: self
-                    .renderer
-                    .scene
-                    .create_gpu_primitives(settings.max_primitives),
+                primitives: primitives,
                 lights: self.renderer.scene.create_gpu_lights(settings.max_lights),
-                atmosphere: self.renderer().scene.atmosphere(),
+                atmosphere: atmosphere,
                 emissive_primitive_indices: self
                     .renderer
                     .scene
                     .emissive_primitive_indices(settings.max_primitives),
+                texture_stack: texture_buffers.texture_stack,
+                colour_ramp_stops: texture_buffers.colour_ramp_stops,
+                bvh_nodes: self.create_gpu_bvh_nodes(settings),
+                workgroups: workgroups,
+                tiles: tiles.filter(|_| self.renderer.adaptive_sampling()),
+                read_back_counters,
+                denoiser: if self.denoiser.enabled {
+                    Some(self.denoiser)
+                } else {
+                    None
+                },
             },
         ));
 
@@ -451,6 +861,221 @@ impl
 }
 
 impl RayMarcherView {
+    /// Build the bounding volume hierarchy with leaves matching the
+    /// primitives the compiled shader evaluates together.
+    fn create_gpu_bvh_nodes(&self, settings: &RayMarcherViewSettings) -> Vec<Std430GPUBVHNode> {
+        self.renderer.scene.create_gpu_bvh_nodes(
+            settings.max_primitives,
+            self.current_preprocessor_directives()
+                .contains(&RayMarcherPreprocessorDirectives::EnableChildInteractions),
+        )
+    }
+
+    /// Recreate the storage textures, and restart the render, if the
+    /// resolution of the viewport has changed. The layout of the bind
+    /// group is unchanged, so the pipelines do not need recompiling.
+    fn resize_storage_textures(&mut self, render_state: &egui_wgpu::RenderState) {
+        let resolution: glam::UVec2 = self.render_state.resolution.max(glam::UVec2::ONE);
+        let device = &render_state.device;
+        let mut renderer = render_state.renderer.write();
+        let Some(storage_texture_bind_group) = renderer
+            .callback_resources
+            .get_mut::<RenderResources>()
+            .and_then(|render_resources| render_resources.storage_texture_bind_group.as_mut())
+        else {
+            return;
+        };
+        let texture: &wgpu::Texture = &storage_texture_bind_group.storage_texture_views[0].texture;
+        if glam::UVec2::new(texture.width(), texture.height()) == resolution {
+            return;
+        }
+
+        let storage_texture_views: Vec<StorageTextureView> =
+            self.create_storage_texture_views(device);
+        let bind_group_entries: Vec<wgpu::BindGroupEntry<'_>> = storage_texture_views
+            .iter()
+            .enumerate()
+            .map(|(binding, storage_texture_view)| wgpu::BindGroupEntry {
+                binding: binding as u32,
+                resource: storage_texture_view.as_resource(),
+            })
+            .collect();
+        storage_texture_bind_group.bind_group =
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("storage texture bind group"),
+                layout: &storage_texture_bind_group.bind_group_layout,
+                entries: &bind_group_entries,
+            });
+        storage_texture_bind_group.storage_texture_views = storage_texture_views;
+
+        // The new textures have none of the pixels rendered so far
+        self.reset();
+    }
+
+    /// Create the denoiser's resources if they are missing, or were
+    /// compiled with other preprocessor directives than the ray marcher,
+    /// and match their textures to the resolution of the viewport.
+    fn update_denoiser_resources(&self, render_state: &egui_wgpu::RenderState) {
+        let mut renderer = render_state.renderer.write();
+        let callback_resources = &mut renderer.callback_resources;
+
+        let denoiser_resources: Option<DenoiserResources> = callback_resources
+            .remove::<DenoiserResources>()
+            .filter(|denoiser_resources| {
+                denoiser_resources.preprocessor_directives
+                    == *self.current_preprocessor_directives()
+            });
+        let Some(render_resources) = callback_resources.get::<RenderResources>() else {
+            return;
+        };
+        let mut denoiser_resources: DenoiserResources = denoiser_resources.unwrap_or_else(|| {
+            DenoiserResources::new(
+                render_state,
+                render_resources,
+                self.current_preprocessor_directives(),
+            )
+        });
+        denoiser_resources.resize(
+            &render_state.device,
+            self.render_state.resolution,
+            render_resources,
+        );
+
+        callback_resources.insert(denoiser_resources);
+    }
+
+    /// Create the adaptive sampling resources if they are missing, or were
+    /// compiled with other preprocessor directives than the ray marcher,
+    /// and read back the counters of an earlier frame to find whether the
+    /// render has converged. Returns whether the counters of this frame
+    /// should be read back.
+    fn update_adaptive_sampling_resources(
+        &mut self,
+        render_state: &egui_wgpu::RenderState,
+    ) -> bool {
+        let mut renderer = render_state.renderer.write();
+        let callback_resources = &mut renderer.callback_resources;
+
+        let adaptive_sampling_resources: Option<AdaptiveSamplingResources> = callback_resources
+            .remove::<AdaptiveSamplingResources>()
+            .filter(|adaptive_sampling_resources| {
+                adaptive_sampling_resources.preprocessor_directives
+                    == *self.current_preprocessor_directives()
+            });
+        let Some(render_resources) = callback_resources.get::<RenderResources>() else {
+            return false;
+        };
+        let mut adaptive_sampling_resources: AdaptiveSamplingResources =
+            adaptive_sampling_resources.unwrap_or_else(|| {
+                AdaptiveSamplingResources::new(
+                    render_state,
+                    render_resources,
+                    self.current_preprocessor_directives(),
+                )
+            });
+
+        if let Some(counters) =
+            adaptive_sampling_resources.read_counters(&render_state.device, self.render_id)
+        {
+            self.converged = counters.converged().unwrap_or(false);
+        }
+        let read_back: bool = !self.paused()
+            && !self.converged
+            && adaptive_sampling_resources.request_readback(self.render_id);
+
+        callback_resources.insert(adaptive_sampling_resources);
+        read_back
+    }
+
+    fn show_denoiser_controls(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.denoiser.enabled, "denoise")
+            .on_hover_text(
+                "filter the noise out of the render, guided by the normals, depth, and colour of \
+                the surfaces the camera sees",
+            );
+        ui.add_enabled_ui(self.denoiser.enabled, |ui| {
+            ui.checkbox(&mut self.denoiser.split_view, "split")
+                .on_hover_text(
+                    "show the noisy render on the left, and the denoised render on the right",
+                );
+            ui.add(
+                egui::DragValue::new(&mut self.denoiser.iterations)
+                    .range(1..=MAX_DENOISER_ITERATIONS)
+                    .prefix("iterations: "),
+            )
+            .on_hover_text(
+                "the number of times the filter is applied, each twice as wide as the last",
+            );
+            ui.add(
+                egui::DragValue::new(&mut self.denoiser.colour_phi)
+                    .range(0.0..=10.)
+                    .speed(0.01)
+                    .prefix("strength: "),
+            )
+            .on_hover_text(
+                "how different in colour neighbouring pixels can be and still be blurred together",
+            );
+        });
+    }
+
+    /// Read back the render, or the denoised render if the denoiser is
+    /// on, at the resolution of the viewport.
+    fn read_render(&self, render_state: &egui_wgpu::RenderState) -> anyhow::Result<Rgba32FImage> {
+        let renderer = render_state.renderer.read();
+
+        if self.denoiser.enabled {
+            if let Some((texture, resolution)) = renderer
+                .callback_resources
+                .get::<DenoiserResources>()
+                .and_then(|denoiser_resources| denoiser_resources.denoised_texture(&self.denoiser))
+            {
+                return read_texture(render_state, texture, resolution);
+            }
+        }
+
+        let Some(storage_texture_bind_group) = renderer
+            .callback_resources
+            .get::<RenderResources>()
+            .and_then(|render_resources| render_resources.storage_texture_bind_group.as_ref())
+        else {
+            anyhow::bail!("There is no render to export");
+        };
+        read_texture(
+            render_state,
+            &storage_texture_bind_group.storage_texture_views[0].texture,
+            self.render_state.resolution.max(glam::UVec2::ONE),
+        )
+    }
+
+    fn export_render(&self, render_state: &egui_wgpu::RenderState, ctx: &egui::Context) {
+        let modal = egui_modal::Modal::new(ctx, "dialog_modal");
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("export render to file")
+            .add_filter("OpenEXR", &["exr"])
+            .add_filter("PNG", &["png"])
+            .save_file()
+        else {
+            return;
+        };
+        match self
+            .read_render(render_state)
+            .and_then(|image| save_image(image, &path))
+        {
+            Ok(()) => dialog::success(
+                &modal,
+                "Success",
+                &format!("Render exported to {:}", path.display()),
+            ),
+            Err(error) => dialog::error(&modal, "Render Export Error", &error.to_string()),
+        }
+    }
+
+    /// Whether every image of the scene's image textures has finished
+    /// loading, or failed to.
+    pub fn images_loaded(&self) -> bool {
+        self.loaded_images >= self.renderer.scene.textures.len()
+    }
+
     pub fn disable_camera_controls(&mut self) {
         self.camera_controls_enabled = false;
     }
@@ -3,8 +3,10 @@
 // This source code is licensed under the BSD-style license found in the
 // LICENSE file in the root directory of this source tree.
 
-use eframe::egui_wgpu::wgpu;
-use image::Rgba32FImage;
+use std::{path::Path, sync::mpsc};
+
+use eframe::egui_wgpu::{self, wgpu};
+use image::{imageops, DynamicImage, Rgba32FImage};
 
 pub trait BindingResource {
     fn as_resource(&self) -> wgpu::BindingResource<'_>;
@@ -24,7 +26,8 @@ impl BindingResource for Buffer {
 pub struct TextureView {
     pub texture: wgpu::Texture,
     pub texture_view: wgpu::TextureView,
-    pub texture_data: Rgba32FImage,
+    // The texels in the format of the texture
+    pub texture_data: Vec<u8>,
     pub visibility: wgpu::ShaderStages,
     pub view_dimension: wgpu::TextureViewDimension,
     pub size: wgpu::Extent3d,
@@ -37,6 +40,7 @@ impl BindingResource for TextureView {
 }
 
 pub struct StorageTextureView {
+    pub texture: wgpu::Texture,
     pub texture_view: wgpu::TextureView,
     pub visibility: wgpu::ShaderStages,
     pub access: wgpu::StorageTextureAccess,
@@ -80,11 +84,16 @@ impl TextureViewBindGroup {
                     origin: wgpu::Origin3d::ZERO,
                     aspect: wgpu::TextureAspect::All,
                 },
-                bytemuck::cast_slice(texture_view.texture_data.as_raw().as_slice()),
+                &texture_view.texture_data,
                 wgpu::TexelCopyBufferLayout {
                     offset: 0,
-                    bytes_per_row: Some(16 * texture_view.texture_data.width()),
-                    rows_per_image: Some(texture_view.texture_data.height()),
+                    bytes_per_row: texture_view
+                        .texture
+                        .format()
+                        .block_copy_size(None)
+                        .map(|texel_size| texel_size * texture_view.size.width),
+                    // Array layers are stacked vertically in the texture data
+                    rows_per_image: Some(texture_view.size.height),
                 },
                 texture_view.size,
             );
@@ -100,6 +109,7 @@ pub struct StorageTextureViewBindGroup {
 
 pub struct RenderResources {
     pub render_pipeline: Option<wgpu::RenderPipeline>,
+    pub compute_pipeline: Option<wgpu::ComputePipeline>,
     pub uniform_bind_group: Option<BufferBindGroup>,
     pub storage_bind_group: Option<BufferBindGroup>,
     pub texture_bind_group: Option<TextureViewBindGroup>,
@@ -127,6 +137,49 @@ impl RenderResources {
         // self.storage_texture_bind_group.write(queue, storage_texture_data);
     }
 
+    /// Dispatch the compute pipeline, if there is one, over a grid of
+    /// workgroups. This must happen before painting so that the render
+    /// pipeline can display the result.
+    pub fn compute(&self, encoder: &mut wgpu::CommandEncoder, workgroups: glam::UVec2) {
+        if let Some(compute_pipeline) = &self.compute_pipeline {
+            self.dispatch(encoder, compute_pipeline, workgroups);
+        }
+    }
+
+    /// Dispatch a compute pipeline that shares the bind groups of these
+    /// resources over a grid of workgroups.
+    pub fn dispatch(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        compute_pipeline: &wgpu::ComputePipeline,
+        workgroups: glam::UVec2,
+    ) {
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("compute pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(compute_pipeline);
+
+        let mut bind_group: u32 = 0;
+        if let Some(uniform_bind_group) = &self.uniform_bind_group {
+            compute_pass.set_bind_group(bind_group, &uniform_bind_group.bind_group, &[]);
+            bind_group += 1
+        }
+        if let Some(storage_bind_group) = &self.storage_bind_group {
+            compute_pass.set_bind_group(bind_group, &storage_bind_group.bind_group, &[]);
+            bind_group += 1
+        }
+        if let Some(texture_bind_group) = &self.texture_bind_group {
+            compute_pass.set_bind_group(bind_group, &texture_bind_group.bind_group, &[]);
+            bind_group += 1
+        }
+        if let Some(storage_texture_bind_group) = &self.storage_texture_bind_group {
+            compute_pass.set_bind_group(bind_group, &storage_texture_bind_group.bind_group, &[]);
+        }
+
+        compute_pass.dispatch_workgroups(workgroups.x, workgroups.y, 1);
+    }
+
     pub fn paint(&self, render_pass: &mut wgpu::RenderPass<'_>) {
         if let Some(render_pipeline) = &self.render_pipeline {
             render_pass.set_pipeline(&render_pipeline);
@@ -171,3 +224,135 @@ impl RenderResources {
         bind_group_layouts
     }
 }
+
+/// Copy the bottom left corner of an rgba32float texture back from the
+/// GPU. The rows of the texture start at the bottom of the image, so
+/// they are flipped to match the way the image is displayed.
+pub fn read_texture(
+    render_state: &egui_wgpu::RenderState,
+    texture: &wgpu::Texture,
+    resolution: glam::UVec2,
+) -> anyhow::Result<Rgba32FImage> {
+    let device = &render_state.device;
+    let queue = &render_state.queue;
+
+    // Each row of the copy must be aligned, so it may be padded
+    let bytes_per_pixel = 4 * size_of::<f32>() as u32;
+    let padded_bytes_per_row: u32 =
+        (resolution.x * bytes_per_pixel).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+    let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("texture readback buffer"),
+        size: (padded_bytes_per_row * resolution.y) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("texture readback encoder"),
+    });
+    encoder.copy_texture_to_buffer(
+        wgpu::TexelCopyTextureInfo {
+            texture: texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        wgpu::TexelCopyBufferInfo {
+            buffer: &readback_buffer,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(resolution.y),
+            },
+        },
+        wgpu::Extent3d {
+            width: resolution.x,
+            height: resolution.y,
+            depth_or_array_layers: 1,
+        },
+    );
+    queue.submit(Some(encoder.finish()));
+
+    let (sender, receiver) = mpsc::channel();
+    readback_buffer
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+    device.poll(wgpu::Maintain::Wait);
+    receiver.recv()??;
+
+    let pixels: Vec<f32> = readback_buffer
+        .slice(..)
+        .get_mapped_range()
+        .chunks(padded_bytes_per_row as usize)
+        .flat_map(|row| {
+            bytemuck::cast_slice::<u8, f32>(&row[..(resolution.x * bytes_per_pixel) as usize])
+                .to_vec()
+        })
+        .collect();
+    readback_buffer.unmap();
+
+    let image = Rgba32FImage::from_raw(resolution.x, resolution.y, pixels)
+        .ok_or_else(|| anyhow::Error::msg("The texture has the wrong dimensions"))?;
+    Ok(imageops::flip_vertical(&image))
+}
+
+/// Save an image, as a 32-bit float EXR or a 16-bit PNG depending on
+/// the extension of the file path.
+pub fn save_image(image: Rgba32FImage, file_path: &Path) -> anyhow::Result<()> {
+    match file_path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_lowercase())
+        .as_deref()
+    {
+        Some("exr") => image.save(file_path)?,
+        Some("png") => DynamicImage::ImageRgba32F(image)
+            .to_rgba16()
+            .save(file_path)?,
+        _ => anyhow::bail!("Images must be saved as .exr or .png files"),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient() -> Rgba32FImage {
+        Rgba32FImage::from_fn(4, 3, |x, y| {
+            image::Rgba([x as f32 / 3., y as f32 / 2., 0.25, 1.])
+        })
+    }
+
+    #[test]
+    fn test_save_image() {
+        let directory = std::env::temp_dir().join(format!("damascus_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        // EXRs keep the full precision, whatever the case of the extension
+        let exr_path = directory.join("render.EXR");
+        save_image(gradient(), &exr_path).unwrap();
+        assert_eq!(image::open(&exr_path).unwrap().to_rgba32f(), gradient());
+
+        // PNGs are quantized to 16 bits per channel
+        let png_path = directory.join("render.png");
+        save_image(gradient(), &png_path).unwrap();
+        let png: DynamicImage = image::open(&png_path).unwrap();
+        assert_eq!(png.color(), image::ColorType::Rgba16);
+        for (saved, original) in png.to_rgba32f().pixels().zip(gradient().pixels()) {
+            for (saved, original) in saved.0.iter().zip(original.0) {
+                assert!((saved - original).abs() <= 0.5 / u16::MAX as f32);
+            }
+        }
+
+        for file_name in ["render.jpg", "render"] {
+            let file_path = directory.join(file_name);
+            assert!(save_image(gradient(), &file_path).is_err());
+            assert!(!file_path.exists());
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}